@@ -0,0 +1,32 @@
+use rubiks_trainer::db::{AlgDB, AlgEntry, AlgSet};
+
+pub const SETS: &[(&str, &str)] = &[
+    ("sune", include_str!("../../default_algs/2x2/cll/sune")),
+    ("antisune", include_str!("../../default_algs/2x2/cll/antisune")),
+    ("T", include_str!("../../default_algs/2x2/cll/T")),
+    ("U", include_str!("../../default_algs/2x2/cll/U")),
+    ("L", include_str!("../../default_algs/2x2/cll/L")),
+    ("pi", include_str!("../../default_algs/2x2/cll/pi")),
+    ("H", include_str!("../../default_algs/2x2/cll/H")),
+];
+
+// Every built-in set copied into `groups` groups, like an alg directory with all of ZBLL and more
+pub fn large_db(groups: usize) -> AlgDB {
+    let entries = (0..groups)
+        .map(|group| {
+            let sets = SETS.iter()
+                .map(|(name, text)| {
+                    let mut algset = AlgSet::from_text(name.to_string(), text).unwrap();
+                    algset.path = format!("group {}/{}", group, name);
+                    AlgEntry::Algs(name.to_string(), algset)
+                })
+                .collect();
+            AlgEntry::Group(format!("group {}", group), sets)
+        })
+        .collect();
+    AlgDB { entries, diagnostics: Vec::new() }
+}
+
+pub fn algsets(db: &AlgDB) -> Vec<&AlgSet> {
+    db.matching("**")
+}
@@ -0,0 +1,22 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ratatui::{backend::TestBackend, Terminal};
+use rubiks_trainer::app::App;
+use rubiks_trainer::config::Config;
+
+mod common;
+
+fn setup_tree(c: &mut Criterion) {
+    let db = common::large_db(100);
+    let config = Config::default();
+    let mut app = App::new(&db, &config);
+    app.enable(&common::algsets(&db));
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    c.bench_function("render setup tree of large db", |b| {
+        b.iter(|| {
+            terminal.draw(|frame| app.draw(frame)).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, setup_tree);
+criterion_main!(benches);
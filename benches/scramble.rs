@@ -0,0 +1,48 @@
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+use rubiks_trainer::cube::CubeState;
+use rubiks_trainer::db::{AlgDB, AlgSet};
+use rubiks_trainer::scramble::{get_scramble, get_scramble_avoiding, Variation};
+
+mod common;
+
+fn loading(c: &mut Criterion) {
+    // All the built-in sets as one file, 50 times over
+    let text = common::SETS.iter().map(|(_, text)| *text).collect::<Vec<_>>().join("\n").repeat(50);
+    c.bench_function("parse large alg file", |b| b.iter(|| AlgSet::from_text("large".to_string(), black_box(&text)).unwrap()));
+
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("default_algs");
+    c.bench_function("load default alg directory", |b| b.iter(|| AlgDB::load(black_box(dir.clone())).unwrap()));
+}
+
+fn scrambles(c: &mut Criterion) {
+    let db = common::large_db(100);
+    let algsets = common::algsets(&db);
+    let variation = Variation::default();
+    let mut rng = StdRng::seed_from_u64(0);
+    c.bench_function("scramble from large db", |b| b.iter(|| get_scramble(black_box(&algsets), variation, &mut rng)));
+
+    let recent: Vec<_> = (0..5).map(|_| get_scramble(&algsets, variation, &mut rng).unwrap()).collect();
+    c.bench_function("scramble from large db avoiding recent cases", |b| {
+        b.iter(|| get_scramble_avoiding(black_box(&algsets), variation, &recent, &mut rng))
+    });
+}
+
+fn cube(c: &mut Criterion) {
+    let db = common::large_db(1);
+    let algsets = common::algsets(&db);
+    let scramble = AlgSet::parse_scramble(&get_scramble(&algsets, Variation::default(), &mut StdRng::seed_from_u64(0)).unwrap().text).unwrap();
+    c.bench_function("apply scramble to cube", |b| {
+        b.iter(|| {
+            let mut state = CubeState::solved();
+            state.apply(black_box(&scramble));
+            state
+        })
+    });
+}
+
+criterion_group!(benches, loading, scrambles, cube);
+criterion_main!(benches);
@@ -1,38 +1,136 @@
-use std::{collections::HashMap, io};
+use std::{collections::{HashMap, HashSet}, io, path::PathBuf, time::{Duration, Instant}};
 
 type Identifier = u32;
 
-const START_BUTTON_ID: u32 = 6969;
+// Alg set rows are numbered from 0, the buttons count down from the top so they never share an id
+const START_BUTTON_ID: u32 = u32::MAX;
+const INSPECTION_BUTTON_ID: u32 = u32::MAX - 1;
+const SRS_BUTTON_ID: u32 = u32::MAX - 2;
+const MIRROR_BUTTON_ID: u32 = u32::MAX - 3;
+const RECAP_BUTTON_ID: u32 = u32::MAX - 4;
+const EXAM_BUTTON_ID: u32 = u32::MAX - 5;
+const QUICK_BUTTON_ID: u32 = u32::MAX - 6;
+const RANDOM_STATE_BUTTON_ID: u32 = u32::MAX - 7;
+
+// Why a session couldn't start
+const NO_SETS: &str = "No alg sets enabled";
+const NO_CASES: &str = "No cases in the selected sets";
 
-use color_eyre::owo_colors::OwoColorize;
-use rand::{rng, seq::IndexedRandom};
 use ratatui::{
-    buffer::Buffer, crossterm::event::{self, Event, KeyCode, KeyEvent}, layout::{Constraint, Flex, Layout, Rect}, style::{Style, Stylize}, text::{Text, ToText}, widgets::Widget, DefaultTerminal, Frame
+    crossterm::{event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags}, execute, terminal}, layout::{Constraint, Flex, Layout, Rect}, style::{Style, Stylize}, text::Line, widgets::{Block, Clear, Paragraph}, DefaultTerminal, Frame
 };
+use rand::{rngs::StdRng, SeedableRng};
 use tui_tree_widget::{Tree, TreeItem, TreeState};
 
-use crate::db::{AlgDB, AlgEntry, AlgSet, Movement};
+use crate::config::Config;
+use crate::cube::CubeState;
+use crate::db::{AlgDB, AlgEntry, AlgSet, DiagnosticKind, Movement};
+use crate::debug_page::DebugPage;
+use crate::diagnostics_page::DiagnosticsPage;
+use crate::history::History;
+use crate::profile;
+use crate::live::{Live, LiveServer};
+use crate::lock::InstanceLock;
+use crate::review_page::ReviewPage;
+use crate::srs::Scheduler;
+use crate::tour::Tour;
+use crate::smart_cube::SmartCube;
+use crate::stackmat::{Packet, Stackmat};
+use crate::stats_page::StatsPage;
+use crate::train::TrainPage;
+use crate::triggers::TriggerLog;
+use crate::triggers_page::TriggersPage;
 
 #[derive(Debug)]
 pub struct App<'a> {
-    pub page: AppPage<'a>,
+    pub pages: Vec<AppPage<'a>>,
     pub db: &'a AlgDB,
+    pub config: &'a Config,
+    pub tour: Option<Tour>,
+    pub history: History,
+    pub srs: Scheduler,
+    pub triggers: TriggerLog,
+    // Whether the terminal reports key releases, needed to start the timer on release
+    pub release_events: bool,
+    // Alg file picked to open in the editor. The app exits so the caller can suspend the terminal
+    // and reload the alg sets, then carry the session over with `into_reload`
+    pub edit: Option<PathBuf>,
+    // Held while this session owns the data files
+    pub lock: Option<InstanceLock>,
+    // Nothing is saved, another session owns the data files
+    pub read_only: bool,
+    // Started with --no-save, nothing was loaded and nothing is saved
+    pub no_save: bool,
     pub exit: bool,
+    // Every random choice of the session, seeded with --seed to get the same scrambles again
+    pub rng: StdRng,
+    // A Stackmat timer on the line in, starting and stopping the timer instead of the timer key
+    pub stackmat: Option<Stackmat>,
+    // A smart cube reporting its turns, timing solves without touching the keyboard
+    pub smart_cube: Option<SmartCube>,
+    // Where dashboards follow the session from, while it holds the lock
+    pub live: Option<LiveServer>,
+}
+
+// The Setup toggles a training session starts with
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Modes {
+    pub inspection: bool,
+    pub srs: bool,
+    pub mirror: bool,
+    pub random_state: bool,
+    pub recap: bool,
+    pub exam: bool,
+    // Minutes a quick session lasts, None for no time limit
+    pub quick: Option<u64>,
+}
+
+impl Modes {
+    fn new(config: &Config) -> Modes {
+        let training = &config.training;
+        Modes { inspection: training.inspection, srs: training.srs, mirror: training.mirror, random_state: training.random_state, recap: training.recap, exam: training.exam, quick: None }
+    }
+}
+
+// What a session keeps when the alg sets are reloaded after editing a file
+#[derive(Debug)]
+pub struct Reload {
+    pub tour: Option<Tour>,
+    pub history: History,
+    pub srs: Scheduler,
+    pub triggers: TriggerLog,
+    rng: StdRng,
+    lock: Option<InstanceLock>,
+    stackmat: Option<Stackmat>,
+    smart_cube: Option<SmartCube>,
+    live: Option<LiveServer>,
+    read_only: bool,
+    no_save: bool,
+    // Paths of the enabled alg sets
+    enabled: Vec<String>,
+    opened: HashSet<Vec<Identifier>>,
+    selected: Vec<Identifier>,
+    modes: Modes,
+    // Shown in Setup, like the outcome of the edit
+    pub notice: Option<String>,
 }
 
 impl<'a> App<'a> {
-    pub fn new(db: &'a AlgDB) -> App<'a> {
-        fn parse_entries<'a>(entries: &'a [AlgEntry], id: &mut u32, algset_map: &mut HashMap<Identifier, AlgInfo<'a>>) {
+    pub fn new(db: &'a AlgDB, config: &'a Config) -> App<'a> {
+        fn parse_entries<'a>(entries: &'a [AlgEntry], id: &mut u32, groups: &[Identifier], algset_map: &mut HashMap<Identifier, AlgInfo<'a>>) {
             for entry in entries {
                 match entry {
                     AlgEntry::Group(_name, entries) => {
+                        let mut inner = groups.to_vec();
+                        inner.push(*id);
                         *id += 1;
-                        parse_entries(entries, id, algset_map);
+                        parse_entries(entries, id, &inner, algset_map);
                     }
                     AlgEntry::Algs(_name, algs) => {
                         let info = AlgInfo {
                             algset: algs,
                             enabled: false,
+                            groups: groups.to_vec(),
                         };
                         algset_map.insert(*id, info);
                     }
@@ -43,46 +141,274 @@ impl<'a> App<'a> {
 
         let mut algset_map: HashMap<Identifier, AlgInfo> = HashMap::new();
         let mut id: u32 = 0;
-        parse_entries(&db.entries, &mut id, &mut algset_map);
+        parse_entries(&db.entries, &mut id, &[], &mut algset_map);
 
         let mut state = TreeState::default();
         state.select(vec![0]);
+        // Aliases are only worth a look on the diagnostics page
+        let skipped = db.diagnostics.iter().filter(|diagnostic| diagnostic.kind != DiagnosticKind::Alias).count();
 
         let page = AppPage::Setup {
-            state,
+            state: Box::new(state),
             algset_map,
             db,
+            modes: Modes::new(config),
+            visual: None,
+            search: Search::default(),
+            notice: (skipped > 0).then(|| format!("{} files or lines of the alg directory were skipped, press ! to see them", skipped)),
         };
 
         App {
             db,
-            page: page,
+            config,
+            pages: vec![page],
+            tour: None,
+            history: History::default(),
+            srs: Scheduler::default(),
+            triggers: TriggerLog::default(),
+            release_events: false,
+            edit: None,
+            lock: None,
+            read_only: false,
+            no_save: false,
             exit: false,
+            rng: StdRng::from_os_rng(),
+            stackmat: None,
+            smart_cube: None,
+            live: None,
+        }
+    }
+
+    // Stops saving anything, when another session has the data files
+    pub fn detach(&mut self) {
+        self.history.detach();
+        self.srs.detach();
+        self.triggers.detach();
+        self.read_only = true;
+    }
+
+    pub fn into_reload(self) -> Reload {
+        let enabled = self.enabled();
+        let mut reload = Reload {
+            tour: self.tour,
+            history: self.history,
+            srs: self.srs,
+            triggers: self.triggers,
+            rng: self.rng,
+            lock: self.lock,
+            stackmat: self.stackmat,
+            smart_cube: self.smart_cube,
+            live: self.live,
+            read_only: self.read_only,
+            no_save: self.no_save,
+            enabled,
+            opened: HashSet::new(),
+            selected: Vec::new(),
+            modes: Modes::new(self.config),
+            notice: None,
+        };
+        if let Some(AppPage::Setup { state, modes, .. }) = self.pages.into_iter().next() {
+            reload.opened = state.opened().clone();
+            reload.selected = state.selected().to_vec();
+            reload.modes = modes;
+        }
+        reload
+    }
+
+    // Picks the session back up in Setup. Sets that are gone from the reloaded files stay disabled
+    pub fn reload(&mut self, reload: Reload) {
+        self.tour = reload.tour;
+        self.history = reload.history;
+        self.srs = reload.srs;
+        self.triggers = reload.triggers;
+        self.rng = reload.rng;
+        self.lock = reload.lock;
+        self.stackmat = reload.stackmat;
+        self.smart_cube = reload.smart_cube;
+        self.live = reload.live;
+        self.read_only = reload.read_only;
+        self.no_save = reload.no_save;
+        if let Some(AppPage::Setup { state, algset_map, modes, notice, .. }) = self.pages.first_mut() {
+            for info in algset_map.values_mut() {
+                info.enabled = reload.enabled.contains(&info.algset.path);
+            }
+            for opened in reload.opened {
+                state.open(opened);
+            }
+            state.select(reload.selected);
+            *modes = reload.modes;
+            *notice = reload.notice;
+        }
+    }
+
+    pub fn enable(&mut self, algsets: &[&AlgSet]) {
+        if let Some(AppPage::Setup { algset_map, .. }) = self.pages.first_mut() {
+            for info in algset_map.values_mut() {
+                if algsets.iter().any(|algset| std::ptr::eq(*algset, info.algset)) {
+                    info.enabled = true;
+                }
+            }
         }
     }
 
-    pub fn run(&mut self, terminal: &mut DefaultTerminal) {
+    // Paths of the alg sets enabled in Setup
+    pub fn enabled(&self) -> Vec<String> {
+        let Some(AppPage::Setup { algset_map, .. }) = self.pages.first() else {
+            return Vec::new();
+        };
+        let mut paths: Vec<String> = algset_map.values().filter(|info| info.enabled).map(|info| info.algset.path.clone()).collect();
+        paths.sort();
+        paths
+    }
+
+    // Makes the next session a quick one of this many minutes, as if toggled in Setup
+    pub fn quick(&mut self, minutes: u64) {
+        if let Some(AppPage::Setup { modes, .. }) = self.pages.first_mut() {
+            modes.quick = Some(minutes);
+        }
+    }
+
+    // Starts training as if Start was selected in Setup, fails with why when there is nothing to train
+    pub fn start(&mut self) -> Result<(), &'static str> {
+        let Some(AppPage::Setup { algset_map, modes, .. }) = self.pages.first() else {
+            return Err(NO_SETS);
+        };
+        let algs: Vec<&'a AlgSet> = algset_map.values().filter(|info| info.enabled).map(|info| info.algset).collect();
+        if algs.is_empty() {
+            return Err(NO_SETS);
+        }
+        let page = TrainPage::new(algs, *modes, self).ok_or(NO_CASES)?;
+        self.pages.push(AppPage::Train(Box::new(page)));
+        Ok(())
+    }
+
+    // Fails when the terminal can't be drawn to or read from, the caller restores it before reporting
+    pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        self.release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if self.release_events {
+            execute!(io::stdout(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+
         loop {
-            terminal.draw(|frame| self.draw(frame)).unwrap();
+            if let Some(lock) = &mut self.lock && !lock.beat() {
+                self.lock = None;
+                self.live = None;
+                self.detach();
+            }
+            if let Some(live) = &mut self.live {
+                live.publish(&self.pages.last().map(AppPage::live).unwrap_or_default());
+            }
+            let packets = self.stackmat.as_ref().map(Stackmat::packets).unwrap_or_default();
+            for packet in packets {
+                let mut page = self.pages.pop().unwrap();
+                page.handle_stackmat(self, packet);
+                self.pages.push(page);
+            }
+            let turns = self.smart_cube.as_mut().map(SmartCube::turns).unwrap_or_default();
+            for (movement, state) in turns {
+                let mut page = self.pages.pop().unwrap();
+                page.handle_turn(self, movement, &state);
+                self.pages.push(page);
+            }
+            // A solve can end without a turn, when the cube is left a U turn off
+            if self.smart_cube.is_some() {
+                let mut page = self.pages.pop().unwrap();
+                page.handle_cube_wait(self);
+                self.pages.push(page);
+            }
+            let mut page = self.pages.pop().unwrap();
+            page.handle_generator(self);
+            self.pages.push(page);
+            terminal.draw(|frame| self.draw(frame))?;
 
-            if let Event::Key(key) = event::read().unwrap() {
-                unsafe {
-                    let ptr = self as *mut App<'a>;
-                    self.page.handle_key(ptr.as_mut().unwrap(), key);
+            // Redraw regularly even without input so timed elements update
+            if !event::poll(Duration::from_millis(50))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Release {
+                    let mut page = self.pages.pop().unwrap();
+                    page.handle_release(self, key);
+                    self.pages.push(page);
+                    continue;
+                }
+                if let Some(tour) = &mut self.tour {
+                    let keys = &self.config.keys;
+                    match key.code {
+                        code if keys.tour.matches(code) => {
+                            if !tour.advance() {
+                                self.tour = None;
+                            }
+                            continue;
+                        }
+                        code if keys.cancel.matches(code) => {
+                            self.tour = None;
+                            continue;
+                        }
+                        _ => {}
+                    }
                 }
+                self.press(key);
             }
             if self.exit {
                 break;
             }
         }
+
+        if self.release_events {
+            execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+        }
+        Ok(())
+    }
+
+    // Hands a key press to the page on top and goes where it says
+    fn press(&mut self, key: KeyEvent) {
+        let mut page = self.pages.pop().unwrap();
+        match page.handle_key(self, key) {
+            Nav::Stay => self.pages.push(page),
+            Nav::Push(new_page) => {
+                self.pages.push(page);
+                self.pages.push(new_page);
+            }
+            Nav::Replace(new_page) => self.pages.push(new_page),
+            Nav::Pop => {
+                if self.pages.is_empty() {
+                    self.exit = true;
+                }
+            }
+            Nav::Quit => {
+                self.pages.push(page);
+                self.exit = true;
+            }
+            Nav::Edit(file) => {
+                self.pages.push(page);
+                self.edit = Some(file);
+                self.exit = true;
+            }
+        }
     }
 
     pub fn draw(&mut self, frame: &mut Frame) {
-        self.page.draw(frame);
+        // Modal pages are drawn on top of the page below them
+        let base = self.pages.iter().rposition(|page| !page.is_modal()).unwrap_or(0);
+        for page in self.pages[base..].iter_mut() {
+            page.draw(frame, self.config);
+        }
+        if let Some(tour) = &self.tour {
+            tour.draw(frame, self.config);
+        }
+        if self.read_only {
+            let [line] = Layout::vertical([Constraint::Length(1)]).areas(frame.area());
+            let text = Paragraph::new(" Read-only: another session owns the data files, nothing is saved ").right_aligned().yellow();
+            frame.render_widget(text, line);
+        } else if self.no_save {
+            let [line] = Layout::vertical([Constraint::Length(1)]).areas(frame.area());
+            frame.render_widget(Paragraph::new(" Quick practice: nothing is saved ").right_aligned().yellow(), line);
+        }
     }
 }
 
-fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
+pub(crate) fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
         .flex(Flex::Center)
         .areas(area);
@@ -90,163 +416,615 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     area
 }
 
+fn draw_tree_error(frame: &mut Frame, err: &io::Error) {
+    let text = Paragraph::new(format!("Couldn't show the alg sets: {}", err)).centered().red();
+    frame.render_widget(text, center(frame.area(), Constraint::Percentage(80), Constraint::Length(1)));
+}
+
+// Whether the characters of the query show up in the text in order, ignoring case, so "zbllt3"
+// finds "3x3/zbll/T/3"
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    query.chars().flat_map(char::to_lowercase).all(|c| text.any(|other| other == c))
+}
+
+// Alg sets match the search by their path, groups when any set inside them does
+fn entry_matches(entry: &AlgEntry, query: &str) -> bool {
+    match entry {
+        _ if query.is_empty() => true,
+        AlgEntry::Group(_name, entries) => entries.iter().any(|entry| entry_matches(entry, query)),
+        AlgEntry::Algs(_name, algset) => fuzzy_match(query, &algset.path),
+    }
+}
+
+// Paths of the rows the tree shows, in order. Ids are handed out the same way the tree is built,
+// so children of closed groups and rows the search hides use up ids but aren't shown
+fn visible_rows(entries: &[AlgEntry], id: &mut u32, parent: &[Identifier], opened: &HashSet<Vec<Identifier>>, query: &str, rows: &mut Vec<Vec<Identifier>>) {
+    for entry in entries {
+        let mut path = parent.to_vec();
+        path.push(*id);
+        let shown = entry_matches(entry, query);
+        if shown {
+            rows.push(path.clone());
+        }
+        if let AlgEntry::Group(_name, entries) = entry {
+            *id += 1;
+            let mut children = Vec::new();
+            visible_rows(entries, id, &path, opened, query, &mut children);
+            if shown && opened.contains(&path) {
+                rows.extend(children);
+            }
+        }
+        *id += 1;
+    }
+}
+
+// Paths of the alg sets matching the search, in tree order, whether their groups are open or not
+fn search_matches(entries: &[AlgEntry], id: &mut u32, parent: &[Identifier], query: &str, rows: &mut Vec<Vec<Identifier>>) {
+    for entry in entries {
+        let mut path = parent.to_vec();
+        path.push(*id);
+        match entry {
+            AlgEntry::Group(_name, entries) => {
+                *id += 1;
+                search_matches(entries, id, &path, query, rows);
+            }
+            AlgEntry::Algs(_name, algset) if fuzzy_match(query, &algset.path) => rows.push(path),
+            AlgEntry::Algs(..) => {}
+        }
+        *id += 1;
+    }
+}
+
+// Text typed after "/" in Setup. While it isn't empty, only the alg sets matching it are shown
+#[derive(Debug, Default)]
+pub struct Search {
+    query: String,
+    editing: bool,
+}
+
+impl Search {
+    // Paths of the matches, after opening every group that has one
+    fn open_matches(&self, db: &AlgDB, state: &mut TreeState<Identifier>) -> Vec<Vec<Identifier>> {
+        let mut matches = Vec::new();
+        search_matches(&db.entries, &mut 0, &[], &self.query, &mut matches);
+        for path in matches.iter() {
+            for end in 1..path.len() {
+                state.open(path[..end].to_vec());
+            }
+        }
+        matches
+    }
+
+    // Moves to the next match after the selected row, or the previous one, going around at the ends
+    fn jump(&self, db: &AlgDB, state: &mut TreeState<Identifier>, forward: bool) {
+        let matches = self.open_matches(db, state);
+        let selected = state.selected().to_vec();
+        let next = match forward {
+            true => matches.iter().find(|path| **path > selected).or(matches.first()),
+            false => matches.iter().rev().find(|path| **path < selected).or(matches.last()),
+        };
+        if let Some(path) = next {
+            state.select(path.clone());
+        }
+    }
+
+    // Keeps the selected row while it still matches as the query changes
+    fn update(&self, db: &AlgDB, state: &mut TreeState<Identifier>) {
+        if self.query.is_empty() {
+            return;
+        }
+        let matches = self.open_matches(db, state);
+        let selected = state.selected().to_vec();
+        if let Some(path) = matches.iter().find(|path| **path >= selected).or(matches.first()) {
+            state.select(path.clone());
+        }
+    }
+}
+
+// Ids of the rows between the visual mode anchor and the selected row
+fn visual_range(db: &AlgDB, state: &TreeState<Identifier>, anchor: &[Identifier], search: &Search) -> Vec<Identifier> {
+    let mut rows = Vec::new();
+    visible_rows(&db.entries, &mut 0, &[], state.opened(), &search.query, &mut rows);
+    // A search can leave no rows to select
+    if rows.is_empty() {
+        return Vec::new();
+    }
+    // Past the last alg set when the cursor is on the buttons below the tree
+    let position = |path: &[Identifier]| rows.iter().position(|row| row == path).unwrap_or(rows.len().saturating_sub(1));
+    let (a, b) = (position(anchor), position(state.selected()));
+    rows[a.min(b)..=a.max(b)].iter().filter_map(|row| row.last().copied()).collect()
+}
+
+// Ids of the rows that share the selected row's parent group
+fn siblings(db: &AlgDB, state: &TreeState<Identifier>, search: &Search) -> Vec<Identifier> {
+    let mut rows = Vec::new();
+    visible_rows(&db.entries, &mut 0, &[], state.opened(), &search.query, &mut rows);
+    let selected = state.selected();
+    rows.iter()
+        .filter(|row| row.len() == selected.len() && row[..row.len() - 1] == selected[..selected.len() - 1])
+        .filter_map(|row| row.last().copied())
+        .collect()
+}
+
+// Enables every alg set among the ids and inside the groups among them, or disables them all if they
+// already are
+fn toggle_all(algset_map: &mut HashMap<Identifier, AlgInfo>, ids: &[Identifier]) {
+    let ids: Vec<Identifier> = ids.iter().flat_map(|id| [vec![*id], group_sets(algset_map, *id)].concat()).collect();
+    let enable = ids.iter().filter_map(|id| algset_map.get(id)).any(|info| !info.enabled);
+    for id in ids.iter() {
+        if let Some(info) = algset_map.get_mut(id) {
+            info.enabled = enable;
+        }
+    }
+}
+
+// Ids of the alg sets anywhere inside a group
+fn group_sets(algset_map: &HashMap<Identifier, AlgInfo>, group: Identifier) -> Vec<Identifier> {
+    algset_map.iter().filter(|(_, info)| info.groups.contains(&group)).map(|(id, _)| *id).collect()
+}
+
 #[derive(Debug)]
 pub struct AlgInfo<'a> {
     pub algset: &'a AlgSet,
     pub enabled: bool,
+    // Ids of the groups the set is in, outermost first
+    pub groups: Vec<Identifier>,
 }
 
+// What a page asks the app to do after handling a key
 #[derive(Debug)]
-enum AppPage<'a> {
+pub enum Nav<'a> {
+    Stay,
+    Push(AppPage<'a>),
+    // Takes the place of the current page
+    Replace(AppPage<'a>),
+    Pop,
+    Quit,
+    // Closes the trainer to open the file in the editor, then picks the session back up
+    Edit(PathBuf),
+}
+
+#[derive(Debug)]
+pub enum AppPage<'a> {
     Setup {
-        state: TreeState<Identifier>,
+        // Boxed so the setup page doesn't set the size of every page
+        state: Box<TreeState<Identifier>>,
         db: &'a AlgDB,
         algset_map: HashMap<Identifier, AlgInfo<'a>>,
+        modes: Modes,
+        // Row where visual selection started
+        visual: Option<Vec<Identifier>>,
+        search: Search,
+        notice: Option<String>,
     },
-    Train {
-        algs: Vec<&'a AlgSet>,
-        scrambles: Vec<String>,
-        idx: usize,
-    },
+    Train(Box<TrainPage<'a>>),
+    Stats(StatsPage),
+    Triggers(Box<TriggersPage>),
+    Review(ReviewPage),
+    Debug(DebugPage),
+    Diagnostics(DiagnosticsPage),
+    QuitConfirm,
 }
 
-pub fn get_scramble<'a>(algsets: &'a [&'a AlgSet]) -> String {
-    let mut movements: Vec<&'a [Movement]> = Vec::new();
+impl<'a> AppPage<'a> {
+    pub fn handle_stackmat(&mut self, app: &mut App<'a>, packet: Packet) {
+        if let AppPage::Train(page) = self {
+            page.handle_stackmat(app, packet);
+        }
+    }
 
-    for algset in algsets {
-        for alg in algset.algs.iter() {
-            movements.push(&alg);
+    pub fn handle_turn(&mut self, app: &mut App<'a>, movement: Movement, state: &CubeState) {
+        if let AppPage::Train(page) = self {
+            page.handle_turn(app, movement, state);
         }
     }
 
-    let movements = movements.choose(&mut rng()).unwrap();
-    
-    let mut text = String::new();
-    
-    for (i, movement) in movements.iter().rev().enumerate() {
-        text.push_str(movement.inv().as_text());
-        if i < movements.len()-1 {
-            text.push(' ');
+    pub fn handle_cube_wait(&mut self, app: &mut App<'a>) {
+        if let AppPage::Train(page) = self {
+            page.handle_cube_wait(app);
         }
     }
 
-    text
-}
+    pub fn handle_generator(&mut self, app: &mut App<'a>) {
+        if let AppPage::Train(page) = self {
+            page.handle_generator(app);
+        }
+    }
 
-impl<'a> AppPage<'a> {
-    pub fn handle_key(&mut self, app: &mut App<'a>, key: KeyEvent) {
+    pub fn handle_release(&mut self, app: &App<'a>, key: KeyEvent) {
+        match self {
+            AppPage::Train(page) => page.handle_release(app, key),
+            AppPage::Triggers(page) => page.handle_release(app, key),
+            _ => {}
+        }
+    }
+
+    // What dashboards are shown of the page
+    pub fn live(&self) -> Live {
+        match self {
+            AppPage::Train(page) => page.live(),
+            _ => Live::default(),
+        }
+    }
+
+    pub fn is_modal(&self) -> bool {
+        matches!(self, AppPage::QuitConfirm)
+    }
+
+    pub fn handle_key(&mut self, app: &mut App<'a>, key: KeyEvent) -> Nav<'a> {
+        let keys = &app.config.keys;
         match self {
-            AppPage::Setup { state, algset_map, .. } => {
+            AppPage::Setup { state, db, algset_map, modes, visual, search, notice } => {
+                *notice = None;
+                if search.editing {
+                    match key.code {
+                        code if keys.save.matches(code) => search.editing = false,
+                        code if keys.cancel.matches(code) => *search = Search::default(),
+                        KeyCode::Backspace => {
+                            search.query.pop();
+                            search.update(db, state);
+                        }
+                        KeyCode::Char(c) => {
+                            search.query.push(c);
+                            search.update(db, state);
+                        }
+                        _ => {}
+                    }
+                    return Nav::Stay;
+                }
                 match key.code {
-                    KeyCode::Up | KeyCode::Char('k') => {
+                    code if keys.quit.matches(code) => {
+                        return Nav::Quit;
+                    }
+                    code if keys.back.matches(code) && visual.is_some() => {
+                        *visual = None;
+                    }
+                    code if keys.back.matches(code) && !search.query.is_empty() => {
+                        *search = Search::default();
+                    }
+                    code if keys.leave.matches(code) => {
+                        return Nav::Push(AppPage::QuitConfirm);
+                    }
+                    code if keys.filter.matches(code) => {
+                        // The range's ends may not be among the matches
+                        *visual = None;
+                        search.editing = true;
+                    }
+                    code if keys.next_match.matches(code) && !search.query.is_empty() => {
+                        search.jump(db, state, true);
+                    }
+                    code if keys.previous_match.matches(code) && !search.query.is_empty() => {
+                        search.jump(db, state, false);
+                    }
+                    code if (keys.visual.matches(code) || keys.select.matches(code)) && visual.is_some() => {
+                        let ids = visual_range(db, state, visual.as_ref().unwrap(), search);
+                        toggle_all(algset_map, &ids);
+                        *visual = None;
+                    }
+                    code if keys.visual.matches(code) => {
+                        *visual = Some(state.selected().to_vec());
+                    }
+                    code if keys.siblings.matches(code) => {
+                        toggle_all(algset_map, &siblings(db, state, search));
+                    }
+                    code if keys.all.matches(code) || keys.none.matches(code) => {
+                        let enable = keys.all.matches(code);
+                        for info in algset_map.values_mut() {
+                            info.enabled = enable;
+                        }
+                    }
+                    // Not a configurable key, the page is for tracking down slowdowns
+                    KeyCode::F(12) => {
+                        return Nav::Push(AppPage::Debug(DebugPage::new(db)));
+                    }
+                    code if keys.diagnostics.matches(code) => {
+                        return Nav::Push(AppPage::Diagnostics(DiagnosticsPage::new(&db.diagnostics)));
+                    }
+                    code if keys.triggers.matches(code) => {
+                        return Nav::Push(AppPage::Triggers(Box::new(TriggersPage::new(&app.triggers, app.config))));
+                    }
+                    code if keys.edit.matches(code) => {
+                        let Some(info) = state.selected().last().and_then(|id| algset_map.get(id)) else {
+                            return Nav::Stay;
+                        };
+                        match &info.algset.file {
+                            _ if info.algset.is_locked() => *notice = Some(format!("{} is locked", info.algset.path)),
+                            Some(file) => return Nav::Edit(file.clone()),
+                            None => *notice = Some("The demo sets can't be edited".to_string()),
+                        }
+                    }
+                    code if keys.up.matches(code) => {
                         state.key_up();
                     }
-                    KeyCode::Down | KeyCode::Char('j') => {
+                    code if keys.down.matches(code) => {
                         state.key_down();
                     }
-                    KeyCode::Enter | KeyCode::Char(' ') => {
-                        // state.toggle_selected();
-                        state.toggle_selected();
+                    code if keys.open.matches(code) => {
+                        state.key_right();
+                    }
+                    code if keys.close.matches(code) => {
+                        state.key_left();
+                    }
+                    code if keys.select.matches(code) => {
                         if let Some(identifier) = state.selected().last() {
-                            if let Some(algset) = algset_map.get_mut(identifier) {
+                            if !group_sets(algset_map, *identifier).is_empty() {
+                                toggle_all(algset_map, &[*identifier]);
+                            } else if let Some(algset) = algset_map.get_mut(identifier) {
                                 algset.enabled = !algset.enabled;
                             } else if *identifier == START_BUTTON_ID {
                                 let algs: Vec<&'a AlgSet> = algset_map.values().filter(|info| info.enabled).map(|info| info.algset).collect();
-                                if algs.len() > 0 {
-                                    let scramble = get_scramble(&algs);
-                                    app.page = AppPage::Train {
-                                        algs,
-                                        scrambles: vec![scramble],
-                                        idx: 0,
-                                    };
+                                if !algs.is_empty() {
+                                    match TrainPage::new(algs, *modes, app) {
+                                        Some(page) => return Nav::Push(AppPage::Train(Box::new(page))),
+                                        None => *notice = Some(NO_CASES.to_string()),
+                                    }
                                 }
+                            } else if *identifier == INSPECTION_BUTTON_ID {
+                                modes.inspection = !modes.inspection;
+                            } else if *identifier == SRS_BUTTON_ID {
+                                modes.srs = !modes.srs;
+                            } else if *identifier == MIRROR_BUTTON_ID {
+                                modes.mirror = !modes.mirror;
+                            } else if *identifier == RANDOM_STATE_BUTTON_ID {
+                                modes.random_state = !modes.random_state;
+                            } else if *identifier == RECAP_BUTTON_ID {
+                                modes.recap = !modes.recap;
+                            } else if *identifier == EXAM_BUTTON_ID {
+                                modes.exam = !modes.exam;
+                            } else if *identifier == QUICK_BUTTON_ID {
+                                modes.quick = match modes.quick {
+                                    Some(_) => None,
+                                    None => Some(app.config.training.quick_minutes),
+                                };
                             }
                         }
                     }
-                    KeyCode::Char('q') => {
-                        app.exit = true;
-                    }
                     _ => {}
                 }
             }
-            AppPage::Train {scrambles, idx, algs, ..} => {
+            AppPage::Train(page) => {
+                return page.handle_key(app, key);
+            }
+            AppPage::Stats(page) => {
+                return page.handle_key(app, key);
+            }
+            AppPage::Triggers(page) => {
+                return page.handle_key(app, key);
+            }
+            AppPage::Review(page) => {
+                return page.handle_key(app, key);
+            }
+            AppPage::Debug(page) => {
+                return page.handle_key(app, key);
+            }
+            AppPage::Diagnostics(page) => {
+                return page.handle_key(app, key);
+            }
+            AppPage::QuitConfirm => {
                 match key.code {
-                    KeyCode::Enter | KeyCode::Char(' ' | 'l' | 'j') | KeyCode::Right  => {
-                        if *idx == scrambles.len()-1 {
-                            let mut scramble = "".to_string();
-
-                            while scramble == "".to_string() || scramble == *scrambles.last().unwrap() {
-                                scramble = get_scramble(&algs);
-                            }
-                            
-                            scrambles.push(scramble);
-                            *idx += 1;
-                        } else {
-                            *idx += 1;
-                        }
+                    code if keys.yes.matches(code) || keys.quit.matches(code) => {
+                        return Nav::Quit;
                     }
-                    KeyCode::Char('q') => {
-                        *app = App::new(app.db); 
+                    code if keys.no.matches(code) || keys.back.matches(code) => {
+                        return Nav::Pop;
                     }
-                    KeyCode::Left | KeyCode::Char('h' | 'k') => {
-                        if *idx > 0 {
-                            *idx -= 1;
-                        }
-                    }
-                    _ => {},
+                    _ => {}
                 }
             }
         }
+        Nav::Stay
     }
 
-    pub fn draw(&mut self, frame: &mut Frame) {
+    pub fn draw(&mut self, frame: &mut Frame, config: &Config) {
         match self {
-            AppPage::Setup { state, db, algset_map, .. } => {
-                fn parse_entries<'a>(entries: &'a [AlgEntry], id: &mut u32, algset_map: &mut HashMap<Identifier, AlgInfo<'a>>) -> Vec<TreeItem<'a, Identifier>> {
+            AppPage::Setup { state, db, algset_map, modes, visual, search, notice } => {
+                let selection: HashSet<Identifier> = match visual {
+                    Some(anchor) => visual_range(db, state, anchor, search).into_iter().collect(),
+                    None => HashSet::new(),
+                };
+
+                fn parse_entries<'a>(entries: &'a [AlgEntry], id: &mut u32, algset_map: &mut HashMap<Identifier, AlgInfo<'a>>, selection: &HashSet<Identifier>, query: &str, config: &Config) -> io::Result<Vec<TreeItem<'a, Identifier>>> {
                     let mut ret_items: Vec<TreeItem<Identifier>> = Vec::new();
                     for entry in entries {
+                        let shown = entry_matches(entry, query);
                         match entry {
                             AlgEntry::Group(name, entries) => {
-                                let mut group = TreeItem::new(*id, name.clone(), vec![]).unwrap();
+                                // Groups with some of their sets enabled show how many
+                                let sets = group_sets(algset_map, *id);
+                                let enabled = sets.iter().filter(|set| algset_map[*set].enabled).count();
+                                let text = match enabled {
+                                    0 => Line::from(name.clone()),
+                                    enabled if enabled == sets.len() => Line::from(format!("|-- {}", name)).fg(config.colors.enabled),
+                                    enabled => Line::from(format!("|~~ {} ({}/{})", name, enabled, sets.len())).fg(config.colors.enabled),
+                                };
+                                let text = if selection.contains(id) { text.reversed() } else { text };
+                                let mut group = TreeItem::new(*id, text, vec![])?;
                                 *id += 1;
-                                let items = parse_entries(entries, id, algset_map);
+                                let items = parse_entries(entries, id, algset_map, selection, query, config)?;
                                 for item in items {
-                                    group.add_child(item).unwrap();
+                                    group.add_child(item)?;
+                                }
+                                if shown {
+                                    ret_items.push(group);
                                 }
-                                ret_items.push(group);
                             }
                             AlgEntry::Algs(name, algs) => {
-                                let info = AlgInfo {
-                                    algset: algs,
-                                    enabled: false,
-                                };
-
-                                let mut text = format!("|-- {}", name.clone());
+                                let name = if algs.is_locked() { format!("{} [locked]", name) } else { name.clone() };
+                                let mut text = Line::from(format!("|-- {}", name)).fg(config.colors.enabled);
                                 if !algset_map.get(id).unwrap().enabled {
-                                    text = name.clone();
+                                    text = Line::from(name);
+                                }
+                                if selection.contains(id) {
+                                    text = text.reversed();
                                 }
                                 
                                 let item = TreeItem::new_leaf(*id, text);
-                                ret_items.push(item);
+                                if shown {
+                                    ret_items.push(item);
+                                }
                             }
                         }
                         *id += 1;
                     }
-                    ret_items
+                    Ok(ret_items)
                 }
 
-                let mut entries = parse_entries(&db.entries, &mut 0, algset_map);
+                let started = Instant::now();
+                // A tree that can't be built, with two rows of the same id, is shown as an error rather than a panic
+                let mut entries = match parse_entries(&db.entries, &mut 0, algset_map, &selection, &search.query, config) {
+                    Ok(entries) => entries,
+                    Err(err) => return draw_tree_error(frame, &err),
+                };
+                let inspection_text = if modes.inspection { "Inspection: on" } else { "Inspection: off" };
+                entries.push(TreeItem::new_leaf(INSPECTION_BUTTON_ID, inspection_text));
+                let srs_text = if modes.srs { "Spaced repetition: on" } else { "Spaced repetition: off" };
+                entries.push(TreeItem::new_leaf(SRS_BUTTON_ID, srs_text));
+                let mirror_text = if modes.mirror { "Mirrored (left hand): on" } else { "Mirrored (left hand): off" };
+                entries.push(TreeItem::new_leaf(MIRROR_BUTTON_ID, mirror_text));
+                let random_state_text = if modes.random_state { "Random-state scrambles (2x2 and 3x3 sets): on" } else { "Random-state scrambles (2x2 and 3x3 sets): off" };
+                entries.push(TreeItem::new_leaf(RANDOM_STATE_BUTTON_ID, random_state_text));
+                let recap_text = if modes.recap { "Recap (every case once): on" } else { "Recap (every case once): off" };
+                entries.push(TreeItem::new_leaf(RECAP_BUTTON_ID, recap_text));
+                let exam_text = format!("Exam ({} random cases, graded): {}", config.training.exam_cases, if modes.exam { "on" } else { "off" });
+                entries.push(TreeItem::new_leaf(EXAM_BUTTON_ID, exam_text));
+                let quick_text = match modes.quick {
+                    Some(minutes) => format!("Quick session (due cases, ends after {} min): on", minutes),
+                    None => "Quick session (due cases, time limit): off".to_string(),
+                };
+                entries.push(TreeItem::new_leaf(QUICK_BUTTON_ID, quick_text));
                 let start_button = TreeItem::new_leaf(START_BUTTON_ID, "Start");
                 entries.push(start_button);
 
-                let widget = Tree::new(&entries).unwrap().highlight_symbol("> ");
+                let widget = match Tree::new(&entries) {
+                    Ok(tree) => tree.highlight_symbol("> ").highlight_style(Style::new().fg(config.colors.highlight)),
+                    Err(err) => return draw_tree_error(frame, &err),
+                };
                 frame.render_stateful_widget(widget, frame.area(), state);
+                profile::record("render setup tree", started.elapsed());
+
+                let keys = &config.keys;
+                let [status] = Layout::vertical([Constraint::Length(1)]).flex(Flex::End).areas(frame.area());
+                if visual.is_some() {
+                    let text = format!(" -- VISUAL --  {}/{}: toggle range  {}: cancel", keys.visual.label(), keys.select.label(), keys.back.label());
+                    let text = Paragraph::new(text).bold();
+                    frame.render_widget(text, status);
+                } else if search.editing {
+                    frame.render_widget(Paragraph::new(format!(" /{}_", search.query)).bold(), status);
+                } else if let Some(notice) = notice {
+                    frame.render_widget(Paragraph::new(format!(" {}", notice)).bold(), status);
+                } else if !search.query.is_empty() {
+                    let text = format!(
+                        " /{}  {}/{}: next/previous match  {}: show all",
+                        search.query, keys.next_match.label(), keys.previous_match.label(), keys.back.label(),
+                    );
+                    let text = Paragraph::new(text).bold();
+                    frame.render_widget(text, status);
+                }
             }
-            AppPage::Train {scrambles, idx, ..} => {
-                let text = scrambles[*idx].to_text();
+            AppPage::Train(page) => {
+                page.draw(frame, config);
+            }
+            AppPage::Stats(page) => {
+                page.draw(frame, config);
+            }
+            AppPage::Triggers(page) => {
+                page.draw(frame, config);
+            }
+            AppPage::Review(page) => {
+                page.draw(frame, config);
+            }
+            AppPage::Debug(page) => {
+                page.draw(frame, config);
+            }
+            AppPage::Diagnostics(page) => {
+                page.draw(frame, config);
+            }
+            AppPage::QuitConfirm => {
+                let area = center(frame.area(), Constraint::Length(24), Constraint::Length(3));
+                let paragraph = Paragraph::new(format!("Quit? ({}/{})", config.keys.yes.label(), config.keys.no.label()))
+                    .centered()
+                    .block(Block::bordered());
 
-                text.render(frame.area(), frame.buffer_mut());
+                frame.render_widget(Clear, area);
+                frame.render_widget(paragraph, area);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::KeyModifiers;
+    use ratatui::Terminal;
+
+    use super::*;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.press(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    // Where the Setup tree is and which sets are enabled
+    fn setup(app: &App) -> (HashSet<Vec<Identifier>>, Vec<Identifier>, Vec<String>) {
+        let Some(AppPage::Setup { state, .. }) = app.pages.first() else {
+            panic!("Setup isn't the first page");
+        };
+        (state.opened().clone(), state.selected().to_vec(), app.enabled())
+    }
+
+    #[test]
+    fn back_to_setup() {
+        let db = AlgDB::demo();
+        let config = Config::default();
+        let mut app = App::new(&db, &config);
+        let Some(AppPage::Setup { state, algset_map, .. }) = app.pages.first_mut() else {
+            unreachable!();
+        };
+        let (pbl, info) = algset_map.iter().find(|(_, info)| info.algset.path.ends_with("PBL")).unwrap();
+        let path = info.algset.path.clone();
+        state.open(vec![0]);
+        state.select(vec![0, *pbl]);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.enabled(), vec![path]);
+
+        for back in [KeyCode::Esc, KeyCode::Backspace] {
+            let Some(AppPage::Setup { state, .. }) = app.pages.first_mut() else {
+                unreachable!();
+            };
+            state.select(vec![START_BUTTON_ID]);
+            let before = setup(&app);
+            press(&mut app, KeyCode::Enter);
+            assert!(matches!(app.pages.last(), Some(AppPage::Train(_))));
+
+            press(&mut app, back);
+            assert_eq!(app.pages.len(), 1);
+            assert!(!app.exit);
+            assert_eq!(setup(&app), before);
+        }
+    }
+
+    #[test]
+    fn visual_mode_without_matches() {
+        let db = AlgDB::demo();
+        let config = Config::default();
+        let mut app = App::new(&db, &config);
+        let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+        press(&mut app, KeyCode::Char('v'));
+        press(&mut app, KeyCode::Char('/'));
+        // Searching ends visual mode
+        let Some(AppPage::Setup { visual, .. }) = app.pages.first() else {
+            unreachable!();
+        };
+        assert!(visual.is_none());
+        for c in "zzzzqqq".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        // Nothing is left to select, and drawing the page doesn't panic on the empty range
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('v'));
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert!(app.enabled().is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use std::io;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use btleplug::api::{Central, Characteristic, Manager as _, Peripheral as _, PeripheralProperties, ScanFilter, WriteType};
+use btleplug::platform::{Manager, Peripheral};
+use futures::StreamExt;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::db::Movement;
+use crate::smart_cube::{Decoder, Protocol};
+
+// How long to look for the cube before giving up
+const SCAN_TIMEOUT: Duration = Duration::from_secs(15);
+
+// The characteristics each protocol's cubes send their turns on, and take requests on when they do
+const CHARACTERISTICS: [(Protocol, u128, Option<u128>); 5] = [
+    (Protocol::Giiker, 0x0000aadc_0000_1000_8000_00805f9b34fb, None),
+    (Protocol::Gan2, 0x28be4cb6_cd67_11e9_a32f_0800200c9a66, Some(0x28be4a4a_cd67_11e9_a32f_0800200c9a66)),
+    (Protocol::Gan3, 0x8653000b_43e6_47b7_9cb0_5fc21d4ae340, Some(0x8653000c_43e6_47b7_9cb0_5fc21d4ae340)),
+    (Protocol::Gan4, 0x0000fff6_0000_1000_8000_00805f9b34fb, Some(0x0000fff5_0000_1000_8000_00805f9b34fb)),
+    (Protocol::Moyu32, 0x0783b03e_7735_b5a0_1760_a305d2795cb1, Some(0x0783b03e_7735_b5a0_1760_a305d2795cb2)),
+];
+
+// A cube connected over Bluetooth, followed on a thread of its own that sends each turn until the
+// connection is dropped
+#[derive(Debug)]
+pub struct Connection {
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Connection {
+    // Connects to the first cube whose name starts with `name`, waiting until it's connected
+    pub fn open(name: &str, turns: Sender<Movement>) -> io::Result<Connection> {
+        let name = name.to_string();
+        let (ready, connected) = mpsc::channel();
+        let (stop, stopped) = oneshot::channel();
+        let thread = thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(err) => {
+                    let _ = ready.send(Err(err));
+                    return;
+                }
+            };
+            runtime.block_on(async move {
+                let (cube, state, command, decoder) = match connect(&name).await {
+                    Ok(cube) => cube,
+                    Err(err) => {
+                        let _ = ready.send(Err(err));
+                        return;
+                    }
+                };
+                let _ = ready.send(Ok(()));
+                let _ = follow(&cube, &state, command.as_ref(), decoder, turns, stopped).await;
+                let _ = cube.disconnect().await;
+            });
+        });
+        match connected.recv() {
+            Ok(Ok(())) => Ok(Connection { stop: Some(stop), thread: Some(thread) }),
+            Ok(Err(err)) => Err(err),
+            Err(_) => Err(io::Error::other("the Bluetooth thread stopped")),
+        }
+    }
+}
+
+impl Connection {
+    // Disconnects from the cube, once it's told the thread following it to stop
+    pub fn close(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// The cube's MAC address, which its messages are encrypted with. GAN cubes advertise it at the end
+// of their manufacturer data, which is all there is to go by on macOS. WCU_MY32 cubes are named
+// after the end of theirs
+fn mac_address(properties: &PeripheralProperties, name: &str) -> Option<[u8; 6]> {
+    let advertised = properties.manufacturer_data.iter()
+        .filter(|(id, data)| *id & 0xff == 0x01 && data.len() >= 6)
+        .map(|(_, data)| std::array::from_fn(|idx| data[data.len() - 1 - idx]))
+        .next();
+    let address = Some(properties.address.into_inner()).filter(|address| *address != [0; 6]);
+    let named = || {
+        let end = name.strip_prefix("WCU_MY32_").filter(|end| end.len() == 4)?;
+        let byte = |idx: usize| u8::from_str_radix(end.get(idx..idx + 2)?, 16).ok();
+        Some([0xcf, 0x30, 0x16, 0x00, byte(0)?, byte(2)?])
+    };
+    advertised.or(address).or_else(named)
+}
+
+// The cube, what it sends its turns on and where requests go, and how to read it
+async fn connect(name: &str) -> io::Result<(Peripheral, Characteristic, Option<Characteristic>, Decoder)> {
+    let manager = Manager::new().await.map_err(io::Error::other)?;
+    let adapter = manager.adapters().await.map_err(io::Error::other)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no Bluetooth adapter"))?;
+    adapter.start_scan(ScanFilter::default()).await.map_err(io::Error::other)?;
+    let found = tokio::time::timeout(SCAN_TIMEOUT, async {
+        loop {
+            for peripheral in adapter.peripherals().await.map_err(io::Error::other)? {
+                let Some(properties) = peripheral.properties().await.map_err(io::Error::other)? else {
+                    continue;
+                };
+                if let Some(local) = properties.local_name.clone().filter(|local| local.starts_with(name)) {
+                    return Ok::<_, io::Error>((peripheral, local, properties));
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }).await;
+    let _ = adapter.stop_scan().await;
+    let (cube, local, properties) = found.map_err(|_| io::Error::new(io::ErrorKind::NotFound, format!("no cube named {} found", name)))??;
+
+    cube.connect().await.map_err(io::Error::other)?;
+    cube.discover_services().await.map_err(io::Error::other)?;
+    let characteristics = cube.characteristics();
+    let find = |uuid: Uuid| characteristics.iter().find(|characteristic| characteristic.uuid == uuid).cloned();
+    let (protocol, state, command) = CHARACTERISTICS.iter()
+        .find_map(|(protocol, state, command)| {
+            let state = find(Uuid::from_u128(*state))?;
+            Some((*protocol, state, command.and_then(|command| find(Uuid::from_u128(command)))))
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("{} isn't a Giiker, GAN or MoYu cube the trainer reads, connect it through a bridge command", local)))?;
+    let mac = match protocol {
+        Protocol::Giiker => [0; 6],
+        _ => mac_address(&properties, &local)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("the MAC address of {} can't be read, which its turns are encrypted with", local)))?,
+    };
+    cube.subscribe(&state).await.map_err(io::Error::other)?;
+    Ok((cube, state, command, Decoder::new(protocol, &local, mac)))
+}
+
+// Sends the turns the cube reports until `stopped`, or until nothing takes them anymore
+async fn follow(cube: &Peripheral, state: &Characteristic, command: Option<&Characteristic>, mut decoder: Decoder, turns: Sender<Movement>, mut stopped: oneshot::Receiver<()>) -> io::Result<()> {
+    let mut notifications = cube.notifications().await.map_err(io::Error::other)?;
+    // Where the cube is now, so turns it was left with aren't taken for new ones
+    if command.is_none() && let Ok(value) = cube.read(state).await {
+        decoder.start(value);
+    }
+    if let Some(command) = command && let Some(request) = decoder.request() {
+        cube.write(command, &request, WriteType::WithResponse).await.map_err(io::Error::other)?;
+    }
+    loop {
+        tokio::select! {
+            notification = notifications.next() => {
+                let Some(notification) = notification else {
+                    return Ok(());
+                };
+                if notification.uuid != state.uuid {
+                    continue;
+                }
+                for turn in decoder.turns(&notification.value) {
+                    if turns.send(turn).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            _ = &mut stopped => return Ok(()),
+        }
+    }
+}
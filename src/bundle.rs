@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::{AlgSet, RubiksError};
+
+// A course to share: alg sets in the order to learn them, with the time each case should take.
+// Saved as a single JSON file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    // In the suggested learning order
+    pub sets: Vec<BundleSet>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleSet {
+    // Path inside the bundle, e.g. "pll/G"
+    pub path: String,
+    // Target time for a case, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<f64>,
+    // The alg file, in the plain text format
+    pub algs: String,
+}
+
+impl Bundle {
+    // Paths are kept below the longest directory the sets share
+    pub fn of(name: String, description: String, algsets: &[&AlgSet]) -> Bundle {
+        let prefix = match algsets {
+            [algset] => algset.path.rfind('/').map_or(0, |idx| idx + 1),
+            _ => common_dir(algsets.iter().map(|algset| algset.path.as_str())),
+        };
+        let sets = algsets.iter()
+            .map(|algset| {
+                let mut meta = algset.meta.clone();
+                let target = meta.target.take();
+                meta.order = None;
+                let text = AlgSet { meta, ..(*algset).clone() }.to_text();
+                BundleSet { path: algset.path[prefix..].to_string(), target, algs: text }
+            })
+            .collect();
+        Bundle { name, description, sets }
+    }
+
+    pub fn parse(text: &str) -> Result<Bundle, RubiksError> {
+        let bundle: Bundle = serde_json::from_str(text).map_err(|err| RubiksError::InvalidFile(err.to_string()))?;
+        for set in bundle.sets.iter() {
+            // Paths end up inside the alg directory, they can't leave it. Backslashes and drive letters
+            // are separators and roots on Windows
+            if set.path.contains(['\\', ':']) || set.path.split('/').any(|part| matches!(part, "" | "." | "..")) {
+                return Err(RubiksError::InvalidFile(format!("invalid set path {}", set.path)));
+            }
+        }
+        Ok(bundle)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // The alg files to write and their number of cases, the target and learning order written
+    // into each as "#!" lines
+    pub fn files(&self) -> Result<Vec<(String, String, usize)>, RubiksError> {
+        self.sets.iter().enumerate()
+            .map(|(idx, set)| {
+                let name = set.path.rsplit('/').next().unwrap().to_string();
+                let mut algset = AlgSet::from_text(name, &set.algs)
+                    .map_err(|err| RubiksError::InvalidFile(format!("{}: {}", set.path, err)))?;
+                algset.meta.target = set.target.or(algset.meta.target);
+                algset.meta.order = Some(idx + 1);
+                Ok((set.path.clone(), algset.to_text(), algset.algs.len()))
+            })
+            .collect()
+    }
+}
+
+// Length of the directory part all the paths start with, with its trailing slash
+fn common_dir<'a>(mut paths: impl Iterator<Item = &'a str>) -> usize {
+    let Some(first) = paths.next() else {
+        return 0;
+    };
+    let mut dir: Vec<&str> = first.split('/').collect();
+    dir.pop();
+    for path in paths {
+        let parts: Vec<&str> = path.split('/').collect();
+        let shared = dir.iter().zip(parts.iter()).take_while(|(a, b)| a == b).count();
+        dir.truncate(shared.min(parts.len() - 1));
+    }
+    dir.iter().map(|part| part.len() + 1).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(path: &str) -> String {
+        serde_json::json!({ "name": "course", "sets": [{ "path": path, "algs": "T: R U R' U' R' F R2 U' R' U' R U R' F'\n" }] }).to_string()
+    }
+
+    #[test]
+    fn safe_paths() {
+        for path in ["pll", "pll/G", "3x3/last layer/pll"] {
+            let parsed = Bundle::parse(&bundle(path)).unwrap();
+            let files = parsed.files().unwrap();
+            assert_eq!(files[0].0, path);
+            assert_eq!(files[0].2, 1);
+        }
+    }
+
+    #[test]
+    fn malicious_paths() {
+        let paths = [
+            "", "/etc/passwd", "//server/share", "../outside", "pll/../../outside", "pll/..", "./pll", "pll//G", "pll/",
+            "..\\outside", "pll\\..\\..\\outside", "C:\\Windows\\x", "C:outside", "\\\\server\\share",
+        ];
+        for path in paths {
+            assert!(matches!(Bundle::parse(&bundle(path)), Err(RubiksError::InvalidFile(_))), "{} was accepted", path);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let (mut pll, _) = AlgSet::read_text("3x3/ll/pll".to_string(), "T: R U R' U' R' F R2 U' R' U' R U R' F'\n", None);
+        pll.meta.target = Some(2.5);
+        let (oll, _) = AlgSet::read_text("3x3/ll/oll".to_string(), "Sune: R U R' U R U2 R'\n", None);
+        let text = Bundle::of("LL".to_string(), String::new(), &[&pll, &oll]).to_json();
+        let parsed = Bundle::parse(&text).unwrap();
+        let paths: Vec<&str> = parsed.sets.iter().map(|set| set.path.as_str()).collect();
+        assert_eq!(paths, ["pll", "oll"]);
+        let files = parsed.files().unwrap();
+        assert!(files[0].1.contains("#! target: 2.5"));
+        assert!(files[1].1.contains("#! order: 2"));
+    }
+}
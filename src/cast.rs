@@ -0,0 +1,70 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::session::Solve;
+use crate::timer::{big_text, format_result, format_time};
+
+const WIDTH: usize = 80;
+const HEIGHT: usize = 14;
+// How long each part of a solve stays on screen in the replay, in seconds
+const SCRAMBLE_SECS: f64 = 2.0;
+const RESULT_SECS: f64 = 1.5;
+const FRAME_SECS: f64 = 0.1;
+
+// Replays a finished session as an asciinema cast (https://docs.asciinema.org/manual/asciicast/v2/):
+// for every solve its case and scramble, the timer counting up to its time and the result.
+// `cases` has the case name of each solve
+pub fn session_cast(title: &str, solves: &[Solve], cases: &[String]) -> String {
+    let timestamp = solves.first()
+        .and_then(|solve| solve.date.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let header = json!({"version": 2, "width": WIDTH, "height": HEIGHT, "timestamp": timestamp.as_secs(), "title": title});
+
+    let mut lines = vec![header.to_string()];
+    let mut at = 0.0;
+    let mut frame = |at: f64, screen: String| {
+        // Whole milliseconds, so the sums don't show float noise
+        lines.push(json!([(at * 1000.0).round() / 1000.0, "o", screen]).to_string());
+    };
+    for (idx, (solve, case)) in solves.iter().zip(cases).enumerate() {
+        let results: Vec<String> = solves[..idx].iter().rev().take(5)
+            .map(|solve| format_result(solve.time, solve.penalty))
+            .collect();
+        let screen = |timer: &str| {
+            let heading = format!("{} · solve {}/{}", title, idx + 1, solves.len());
+            let mut rows = vec![heading, String::new(), case.clone(), solve.scramble.clone(), String::new()];
+            rows.extend(big_text(timer).lines().map(str::to_string));
+            rows.extend([String::new(), results.join("  ")]);
+            format!("\x1b[2J\x1b[H{}", rows.join("\r\n"))
+        };
+
+        frame(at, screen("0.00"));
+        at += SCRAMBLE_SECS;
+        let frames = (solve.time.as_secs_f64() / FRAME_SECS).ceil() as usize;
+        for step in 0..frames {
+            let elapsed = step as f64 * FRAME_SECS;
+            frame(at + elapsed, screen(&format_time(Duration::from_secs_f64(elapsed))));
+        }
+        at += solve.time.as_secs_f64();
+        frame(at, screen(&format_result(solve.time, solve.penalty)));
+        at += RESULT_SECS;
+    }
+    // Keeps the last result up until the end
+    if !solves.is_empty() {
+        frame(at, String::new());
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// Name for the cast file of a session, from the date of its first solve. Not in the browser, where
+// there's no clock to fall back on
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_name(solves: &[Solve]) -> String {
+    let date = solves.first().map(|solve| solve.date).unwrap_or(SystemTime::now());
+    let date: chrono::DateTime<chrono::Local> = date.into();
+    format!("session-{}.cast", date.format("%Y-%m-%d"))
+}
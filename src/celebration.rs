@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use rand::{rng, Rng};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect}, style::{Color, Stylize}, text::Line, widgets::{Block, Clear, Paragraph}, Frame
+};
+
+use crate::pb::Record;
+use crate::timer::format_time;
+
+const TOAST_TIME: Duration = Duration::from_secs(3);
+const CONFETTI_TIME: Duration = Duration::from_millis(1500);
+const CONFETTI_COUNT: usize = 80;
+const CONFETTI_COLORS: [Color; 6] = [Color::Red, Color::Yellow, Color::Green, Color::Cyan, Color::Blue, Color::Magenta];
+const CONFETTI_SYMBOLS: [char; 5] = ['*', '+', 'o', '.', '~'];
+
+#[derive(Debug)]
+struct Particle {
+    // Horizontal position as a fraction of the area width
+    x: f32,
+    // Rows per second
+    speed: f32,
+    delay: f32,
+    symbol: char,
+    color: Color,
+}
+
+// A toast listing new PBs, with confetti falling over the screen for a moment
+#[derive(Debug)]
+pub struct Celebration {
+    start: Instant,
+    records: Vec<Record>,
+    particles: Vec<Particle>,
+}
+
+impl Celebration {
+    pub fn new(records: Vec<Record>, confetti: bool) -> Celebration {
+        let mut rng = rng();
+        let count = if confetti { CONFETTI_COUNT } else { 0 };
+        let particles = (0..count)
+            .map(|i| Particle {
+                x: rng.random(),
+                speed: rng.random_range(8.0..24.0),
+                delay: rng.random_range(0.0..0.5),
+                symbol: CONFETTI_SYMBOLS[i % CONFETTI_SYMBOLS.len()],
+                color: CONFETTI_COLORS[i % CONFETTI_COLORS.len()],
+            })
+            .collect();
+
+        Celebration {
+            start: Instant::now(),
+            records,
+            particles,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.start.elapsed() > TOAST_TIME
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let elapsed = self.start.elapsed();
+
+        if elapsed < CONFETTI_TIME {
+            let secs = elapsed.as_secs_f32();
+            let buffer = frame.buffer_mut();
+            for particle in self.particles.iter() {
+                let y = (secs - particle.delay) * particle.speed;
+                if y < 0.0 || y >= area.height as f32 {
+                    continue;
+                }
+                let x = area.x + (particle.x * area.width.saturating_sub(1) as f32) as u16;
+                if let Some(cell) = buffer.cell_mut((x, area.y + y as u16)) {
+                    cell.set_char(particle.symbol).set_fg(particle.color);
+                }
+            }
+        }
+
+        let lines: Vec<Line> = self.records.iter()
+            .map(|record| Line::from(format!("{}: {}", record.as_text(), format_time(record.time))))
+            .collect();
+        let width = lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16 + 4;
+        let [toast] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::End).areas(area);
+        let [toast] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)]).areas(toast);
+
+        let paragraph = Paragraph::new(lines)
+            .block(Block::bordered().title(" New PB! ").yellow().bold());
+        frame.render_widget(Clear, toast);
+        frame.render_widget(paragraph, toast);
+    }
+}
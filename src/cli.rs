@@ -0,0 +1,294 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use crate::sheet::Format;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Terminal Rubik's Cube Trainer")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Config file (defaults to ~/.config/rubiks_trainer/config.toml)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub train: TrainArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the interactive trainer (default)
+    Train(TrainArgs),
+    /// Open the trainer in a window instead of the terminal
+    #[cfg(feature = "gui")]
+    Gui(TrainArgs),
+    /// List the groups and alg sets found in an alg directory
+    List {
+        /// Alg directory
+        dir: Option<PathBuf>,
+    },
+    /// Check that every alg file in a directory parses
+    Validate {
+        /// Alg directory
+        dir: Option<PathBuf>,
+        /// Also check on a cube model that every alg solves a case of the kind its set's mask says
+        #[arg(long)]
+        cases: bool,
+    },
+    /// Compare the cases of two alg directories by what they do to the cube, whatever the names
+    Diff {
+        /// Your alg directory
+        left: PathBuf,
+        /// The alg directory to compare with
+        right: PathBuf,
+    },
+    /// Find algs and cases written more than once in an alg directory, which come up more often
+    #[command(alias = "doctor")]
+    Dedupe {
+        /// Alg directory
+        dir: Option<PathBuf>,
+    },
+    /// Rewrite alg files in another format, keeping their settings and comments
+    Convert {
+        /// Alg files to convert
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        to: AlgFormat,
+        /// Keep the original files (both would be loaded as alg sets if left in the alg directory)
+        #[arg(long)]
+        keep: bool,
+    },
+    /// Print scrambles for alg sets, one per line
+    Scramble {
+        /// Alg set names, paths inside the alg directory or glob patterns (e.g. "2x2/cll/T" '3x3/pll/*'),
+        /// or groups to take all the sets of
+        algsets: Vec<String>,
+        /// Another alg set to draw cases from, like the positional ones
+        #[arg(short, long = "set")]
+        sets: Vec<String>,
+        /// Number of scrambles, the cases only repeating once every one of them came up
+        #[arg(short = 'n', long, default_value_t = 1)]
+        count: usize,
+        /// Seed for the random choices, the same seed gives the same scrambles
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Alg directory
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+        /// Print it as cubing.js Alg JSON
+        #[arg(long)]
+        json: bool,
+        /// Solver made scrambles for 2x2 and 3x3 sets instead of inverse algs
+        #[arg(long)]
+        random_state: bool,
+    },
+    /// Render alg sets as a printable Markdown or HTML sheet
+    Export {
+        /// Glob patterns of the alg sets to include (e.g. '3x3/pll/*')
+        #[arg(required = true)]
+        patterns: Vec<String>,
+        /// Alg directory
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+        #[arg(short, long, value_enum, default_value_t = Format::Markdown)]
+        format: Format,
+        /// Draw each case next to its algs
+        #[arg(long)]
+        diagrams: bool,
+        /// File to write (defaults to printing the sheet)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Share alg sets as a course: one file with the sets, their target times and learning order
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
+    },
+    /// Create alg sets from another timer's data
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
+    /// Prune, deduplicate, compact or export the solve history
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Print today's solves, the last ao12 and the cases due, for status bars
+    Status {
+        /// Print JSON with the streak too, for waybar or scripts
+        #[arg(long)]
+        json: bool,
+    },
+    /// Chart how solve times change with the time of day and how far into a session they are
+    Fatigue {
+        /// Minutes without a solve that end a session
+        #[arg(long, default_value_t = 30)]
+        gap: u64,
+        /// Solves per row of the session chart
+        #[arg(long, default_value_t = 25)]
+        size: usize,
+    },
+    /// Show the live stats of a trainer running elsewhere, e.g. on a second monitor or a stream
+    Dashboard {
+        /// Alg directory, for the case names
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+        /// Minutes without a solve that end a session
+        #[arg(long, default_value_t = 30)]
+        gap: u64,
+    },
+    /// Print the completion script for a shell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the man page, in roff
+    Man,
+}
+
+// Formats alg files can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AlgFormat {
+    Text,
+    Json,
+    Toml,
+    Markdown,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BundleCommand {
+    /// Write a bundle of alg sets, in the order the patterns list them
+    Export {
+        /// Glob patterns of the alg sets to include, the order to learn them in (e.g. '3x3/pll/A*' '3x3/pll/*')
+        #[arg(required = true)]
+        patterns: Vec<String>,
+        /// Name of the course, also the group its sets are imported into
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Alg directory
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+        /// File to write (defaults to <name>.bundle.json)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add the alg sets of a bundle to the alg directory, in a group named after it
+    Import {
+        file: PathBuf,
+        /// Alg directory
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportCommand {
+    /// Turn the sessions of a csTimer export into alg sets, a case per scramble
+    Cstimer {
+        /// File saved with csTimer's "Export to file"
+        file: PathBuf,
+        /// Alg directory, the sets go in a "cstimer" group inside it
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+    /// Turn a CSV alg sheet (AlgDb.net, SpeedCubeDB, spreadsheets) into alg sets, one per group
+    Csv {
+        /// CSV file with a header row
+        file: PathBuf,
+        /// Alg directory
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+        /// Name of the alg set, or of the group holding a set per group (defaults to the file name)
+        #[arg(long)]
+        set: Option<String>,
+        /// Header of the case name column (found by name when not given, e.g. "Case")
+        #[arg(long)]
+        name_column: Option<String>,
+        /// Header of the alg column (e.g. "Algorithm")
+        #[arg(long)]
+        alg_column: Option<String>,
+        /// Header of the group column (e.g. "Subset")
+        #[arg(long)]
+        group_column: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// Delete solves older than some days, after archiving their weekly stats
+    Prune {
+        /// Age in days of the oldest solves to keep
+        #[arg(long)]
+        older_than: u64,
+        /// Only report what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove solves that were recorded twice
+    Dedup {
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Archive finished weeks, sort solves by date and drop repeated weekly snapshots
+    Compact {
+        /// Only report what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Save a day's session as an asciinema cast to share or review
+    Export {
+        /// Cast file to write (defaults to session-<date>.cast)
+        output: Option<PathBuf>,
+        /// Day of the session, YYYY-MM-DD (defaults to the last day with solves)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Alg directory, for the case names
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct TrainArgs {
+    /// Alg directory
+    pub dir: Option<PathBuf>,
+    /// Use the built-in demo alg sets and show a guided tour
+    #[arg(long)]
+    pub demo: bool,
+    /// Enable the alg sets matching a glob pattern (e.g. 'ZBLL/T/*'), can be repeated
+    #[arg(long, value_name = "PATTERN")]
+    pub enable: Vec<String>,
+    /// Enable the alg sets of a preset from the config
+    #[arg(long)]
+    pub preset: Option<String>,
+    /// Skip Setup and start training with the enabled alg sets
+    #[arg(long)]
+    pub start: bool,
+    /// Start a quick session of this many minutes right away: the sets enabled last time (or the
+    /// ones asked for), due cases first, and a summary when the time is up
+    #[arg(long, value_name = "MINUTES")]
+    pub quick: Option<u64>,
+    /// Practice without reading or saving any solves, schedules or enabled sets, e.g. with someone else's alg directory
+    #[arg(long)]
+    pub no_save: bool,
+    /// Seed for the random choices, training the same scramble sequence as anyone using the same seed and alg sets
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl TrainArgs {
+    // The demo and --no-save sessions start empty and keep everything in memory
+    pub fn in_memory(&self) -> bool {
+        self.demo || self.no_save
+    }
+}
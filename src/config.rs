@@ -0,0 +1,686 @@
+use std::{collections::HashMap, fmt, path::{Path, PathBuf}};
+
+use ratatui::{crossterm::event::KeyCode, style::Color};
+use serde::Deserialize;
+
+use crate::cube::{CubeState, Face};
+use crate::db::AlgSet;
+use crate::scramble::{Rotation, Variation};
+use crate::session::Mistake;
+use crate::srs::Grade;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IOError(std::io::Error),
+    ParseError(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::IOError(err) => write!(f, "IO Error: {}", err),
+            ConfigError::ParseError(err) => write!(f, "Invalid config: {}", err),
+            ConfigError::Invalid(err) => write!(f, "Invalid config: {}", err),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub alg_dir: Option<PathBuf>,
+    // Locale used to show dates, e.g. "de_DE". Taken from LANG when not set
+    pub locale: Option<String>,
+    // Group the sets of a flat alg directory by the prefix of their names ("OLL_01" goes in "OLL")
+    pub group_prefixes: bool,
+    pub keys: Keys,
+    pub colors: Colors,
+    pub training: Training,
+    pub history: HistorySettings,
+    pub cube: CubeSettings,
+    // Named lists of alg set patterns to enable with --preset
+    pub presets: HashMap<String, Vec<String>>,
+    pub recovery: Recovery,
+    pub stackmat: StackmatSettings,
+    pub smart_cube: SmartCubeSettings,
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rubiks_trainer").join("config.toml"))
+    }
+
+    // A missing file is not an error, it just means the defaults are used
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = std::fs::read_to_string(path).map_err(ConfigError::IOError)?;
+        let config: Config = toml::from_str(&text).map_err(ConfigError::ParseError)?;
+        config.cube.face_colors().map_err(ConfigError::Invalid)?;
+        config.keys.validate().map_err(ConfigError::Invalid)?;
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Binding(Vec<KeyCode>);
+
+impl Binding {
+    fn new(keys: &[&str]) -> Binding {
+        Binding(keys.iter().map(|key| parse_key(key).unwrap()).collect())
+    }
+
+    pub fn matches(&self, code: KeyCode) -> bool {
+        self.0.contains(&code)
+    }
+
+    // The key to show in hints: the first letter-like one, as those are the quickest to read
+    pub fn label(&self) -> String {
+        let key = self.0.iter().find(|code| matches!(code, KeyCode::Char(c) if *c != ' ')).or(self.0.first());
+        key.map(key_name).unwrap_or_default()
+    }
+}
+
+impl TryFrom<Vec<String>> for Binding {
+    type Error = String;
+
+    fn try_from(keys: Vec<String>) -> Result<Self, Self::Error> {
+        keys.iter()
+            .map(|key| parse_key(key).ok_or(format!("unknown key \"{}\"", key)))
+            .collect::<Result<Vec<KeyCode>, String>>()
+            .map(Binding)
+    }
+}
+
+fn key_name(code: &KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        code => code.to_string(),
+    }
+}
+
+fn parse_key(text: &str) -> Option<KeyCode> {
+    let mut chars = text.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    match text.to_lowercase().as_str() {
+        "space" => Some(KeyCode::Char(' ')),
+        "enter" => Some(KeyCode::Enter),
+        "esc" => Some(KeyCode::Esc),
+        "tab" => Some(KeyCode::Tab),
+        "backspace" => Some(KeyCode::Backspace),
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "left" => Some(KeyCode::Left),
+        "right" => Some(KeyCode::Right),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    pub up: Binding,
+    pub down: Binding,
+    pub select: Binding,
+    pub open: Binding,
+    pub close: Binding,
+    pub visual: Binding,
+    pub siblings: Binding,
+    pub all: Binding,
+    pub none: Binding,
+    pub next: Binding,
+    pub timer: Binding,
+    pub previous: Binding,
+    pub zen: Binding,
+    pub group: Binding,
+    pub big: Binding,
+    pub source: Binding,
+    pub reveal: Binding,
+    pub solution: Binding,
+    pub net: Binding,
+    pub notes: Binding,
+    pub stats: Binding,
+    pub filter: Binding,
+    pub next_match: Binding,
+    pub previous_match: Binding,
+    pub misrecognition: Binding,
+    pub wrong_alg: Binding,
+    pub execution_slip: Binding,
+    pub auf_error: Binding,
+    pub again: Binding,
+    pub hard: Binding,
+    pub good: Binding,
+    pub easy: Binding,
+    // Moving the selected case's next review on the session review page
+    pub later: Binding,
+    pub sooner: Binding,
+    // Reps of the drill on the triggers page
+    pub more: Binding,
+    pub fewer: Binding,
+    pub confirm: Binding,
+    pub mismatch: Binding,
+    // Answers to the quit prompt
+    pub yes: Binding,
+    pub no: Binding,
+    // Ending text input (notes, search, tag filter). Typed letters go into the text, so these
+    // need keys like enter and esc
+    pub save: Binding,
+    pub cancel: Binding,
+    // Next step of the tour, cancel closes it
+    pub tour: Binding,
+    pub triggers: Binding,
+    pub diagnostics: Binding,
+    pub edit: Binding,
+    pub back: Binding,
+    // Leaving Setup, which asks whether to quit. Kept apart from back so a stray Backspace doesn't
+    // bring up the quit prompt
+    pub leave: Binding,
+    pub quit: Binding,
+}
+
+impl Keys {
+    pub fn mistake(&self, code: KeyCode) -> Option<Mistake> {
+        let bindings = [&self.misrecognition, &self.wrong_alg, &self.execution_slip, &self.auf_error];
+        Mistake::ALL.into_iter().zip(bindings).find(|(_, binding)| binding.matches(code)).map(|(mistake, _)| mistake)
+    }
+
+    pub fn grade(&self, code: KeyCode) -> Option<Grade> {
+        let bindings = [&self.again, &self.hard, &self.good, &self.easy];
+        Grade::ALL.into_iter().zip(bindings).find(|(_, binding)| binding.matches(code)).map(|(grade, _)| grade)
+    }
+
+    // The keys each page reads. Text input, the tour and Setup's back (which only ends visual mode
+    // and the search, before leave is looked at) are matched ahead of the page's own keys
+    fn pages(&self) -> [(&'static str, Vec<(&'static str, &Binding)>); 8] {
+        [
+            ("Setup", vec![
+                ("up", &self.up), ("down", &self.down), ("select", &self.select), ("open", &self.open),
+                ("close", &self.close), ("visual", &self.visual), ("siblings", &self.siblings), ("all", &self.all),
+                ("none", &self.none), ("filter", &self.filter), ("next_match", &self.next_match),
+                ("previous_match", &self.previous_match), ("triggers", &self.triggers),
+                ("diagnostics", &self.diagnostics), ("edit", &self.edit), ("leave", &self.leave), ("quit", &self.quit),
+            ]),
+            ("Train", vec![
+                ("next", &self.next), ("previous", &self.previous), ("timer", &self.timer), ("zen", &self.zen),
+                ("group", &self.group), ("big", &self.big), ("source", &self.source), ("reveal", &self.reveal),
+                ("solution", &self.solution), ("net", &self.net), ("notes", &self.notes), ("stats", &self.stats),
+                ("misrecognition", &self.misrecognition), ("wrong_alg", &self.wrong_alg),
+                ("execution_slip", &self.execution_slip), ("auf_error", &self.auf_error), ("again", &self.again),
+                ("hard", &self.hard), ("good", &self.good), ("easy", &self.easy), ("confirm", &self.confirm),
+                ("mismatch", &self.mismatch), ("back", &self.back), ("quit", &self.quit),
+            ]),
+            ("Stats", vec![("filter", &self.filter), ("stats", &self.stats), ("back", &self.back), ("quit", &self.quit)]),
+            ("Triggers", vec![
+                ("up", &self.up), ("down", &self.down), ("more", &self.more), ("fewer", &self.fewer), ("timer", &self.timer),
+                ("triggers", &self.triggers), ("back", &self.back), ("quit", &self.quit),
+            ]),
+            ("Review", vec![
+                ("up", &self.up), ("down", &self.down), ("later", &self.later), ("sooner", &self.sooner),
+                ("select", &self.select), ("back", &self.back), ("quit", &self.quit),
+            ]),
+            ("Diagnostics", vec![
+                ("up", &self.up), ("down", &self.down), ("edit", &self.edit), ("diagnostics", &self.diagnostics),
+                ("back", &self.back), ("quit", &self.quit),
+            ]),
+            ("Quit", vec![("yes", &self.yes), ("no", &self.no), ("back", &self.back), ("quit", &self.quit)]),
+            ("text input", vec![("save", &self.save), ("cancel", &self.cancel)]),
+        ]
+    }
+
+    // Which action a key does on a page mustn't depend on the order the page checks them in
+    pub fn validate(&self) -> Result<(), String> {
+        for (page, bindings) in self.pages() {
+            for (idx, (name, binding)) in bindings.iter().enumerate() {
+                for (other, other_binding) in &bindings[idx + 1..] {
+                    if let Some(code) = binding.0.iter().find(|code| other_binding.matches(**code)) {
+                        return Err(format!("{} is both {} and {} on the {} page", key_name(code), name, other, page));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            up: Binding::new(&["up", "k"]),
+            down: Binding::new(&["down", "j"]),
+            select: Binding::new(&["enter", "space"]),
+            open: Binding::new(&["right", "l"]),
+            close: Binding::new(&["left", "h"]),
+            visual: Binding::new(&["v"]),
+            siblings: Binding::new(&["*"]),
+            all: Binding::new(&["a"]),
+            none: Binding::new(&["A", "d"]),
+            next: Binding::new(&["enter", "l", "j", "right", "n"]),
+            timer: Binding::new(&["space"]),
+            previous: Binding::new(&["left", "h", "k", "p"]),
+            zen: Binding::new(&["z"]),
+            group: Binding::new(&["g"]),
+            big: Binding::new(&["b"]),
+            source: Binding::new(&["w"]),
+            reveal: Binding::new(&["r"]),
+            solution: Binding::new(&["a"]),
+            net: Binding::new(&["c"]),
+            notes: Binding::new(&["t"]),
+            stats: Binding::new(&["s"]),
+            filter: Binding::new(&["f", "/"]),
+            next_match: Binding::new(&["n"]),
+            previous_match: Binding::new(&["N"]),
+            misrecognition: Binding::new(&["1"]),
+            wrong_alg: Binding::new(&["2"]),
+            execution_slip: Binding::new(&["3"]),
+            auf_error: Binding::new(&["4"]),
+            again: Binding::new(&["u"]),
+            hard: Binding::new(&["i"]),
+            good: Binding::new(&["o"]),
+            easy: Binding::new(&["e"]),
+            later: Binding::new(&["+"]),
+            sooner: Binding::new(&["-"]),
+            more: Binding::new(&["+"]),
+            fewer: Binding::new(&["-"]),
+            confirm: Binding::new(&["y"]),
+            mismatch: Binding::new(&["m"]),
+            yes: Binding::new(&["y", "enter"]),
+            no: Binding::new(&["n"]),
+            save: Binding::new(&["enter"]),
+            cancel: Binding::new(&["esc"]),
+            tour: Binding::new(&["tab"]),
+            triggers: Binding::new(&["x"]),
+            diagnostics: Binding::new(&["!"]),
+            edit: Binding::new(&["e"]),
+            back: Binding::new(&["esc", "backspace"]),
+            leave: Binding::new(&["esc"]),
+            quit: Binding::new(&["q"]),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub scramble: Color,
+    pub scramble_alt: Color,
+    pub enabled: Color,
+    pub highlight: Color,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            scramble: Color::Reset,
+            scramble_alt: Color::Cyan,
+            enabled: Color::Green,
+            highlight: Color::Yellow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CubeColor {
+    White,
+    Yellow,
+    Green,
+    Blue,
+    Red,
+    Orange,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    // White opposite yellow, green opposite blue
+    #[default]
+    Western,
+    // White opposite blue, green opposite yellow
+    Japanese,
+}
+
+impl Scheme {
+    // Colors of U R F D L B with white on top and green in front
+    fn colors(&self) -> [CubeColor; 6] {
+        use CubeColor::*;
+        match self {
+            Scheme::Western => [White, Red, Green, Yellow, Orange, Blue],
+            Scheme::Japanese => [White, Red, Green, Blue, Orange, Yellow],
+        }
+    }
+}
+
+// How each cube color is drawn in the terminal
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Palette {
+    pub white: Color,
+    pub yellow: Color,
+    pub green: Color,
+    pub blue: Color,
+    pub red: Color,
+    pub orange: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            white: Color::White,
+            yellow: Color::Yellow,
+            green: Color::Green,
+            blue: Color::Blue,
+            red: Color::Red,
+            orange: Color::Rgb(255, 165, 0),
+        }
+    }
+}
+
+impl Palette {
+    pub fn color(&self, color: CubeColor) -> Color {
+        match color {
+            CubeColor::White => self.white,
+            CubeColor::Yellow => self.yellow,
+            CubeColor::Green => self.green,
+            CubeColor::Blue => self.blue,
+            CubeColor::Red => self.red,
+            CubeColor::Orange => self.orange,
+        }
+    }
+}
+
+// The cube's color scheme and how it is held, so diagrams match the cube in hand
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CubeSettings {
+    pub scheme: Scheme,
+    pub top: CubeColor,
+    pub front: CubeColor,
+    pub palette: Palette,
+}
+
+impl Default for CubeSettings {
+    fn default() -> Self {
+        CubeSettings {
+            scheme: Scheme::Western,
+            top: CubeColor::White,
+            front: CubeColor::Green,
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl CubeSettings {
+    // Cube color on each face (U R F D L B) the way the cube is held. Tries every way of holding
+    // the cube until `top` and `front` end up in place
+    pub fn held_colors(&self) -> Result<[CubeColor; 6], String> {
+        let scheme = self.scheme.colors();
+        for tilt in ["", "x", "x2", "x'", "z", "z'"] {
+            for turn in ["", "y", "y2", "y'"] {
+                let mut state = CubeState::solved();
+                state.apply(&AlgSet::parse_scramble(&format!("{} {}", tilt, turn)).unwrap());
+                // The center on each face tells which face of the scheme is there now
+                let colors = Face::ALL.map(|face| {
+                    let center = state.face(face)[1][1];
+                    scheme[Face::ALL.iter().position(|other| *other == center).unwrap()]
+                });
+                if colors[0] == self.top && colors[2] == self.front {
+                    return Ok(colors);
+                }
+            }
+        }
+        Err(format!("{:?} and {:?} aren't next to each other on the cube", self.top, self.front).to_lowercase())
+    }
+
+    pub fn face_colors(&self) -> Result<FaceColors, String> {
+        self.held_colors().map(|colors| FaceColors(colors.map(|color| self.palette.color(color))))
+    }
+}
+
+// Terminal colors are picked for a dark background, the named ones get their usual RGB values
+// where they are drawn outside the terminal
+pub fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0, 0, 0),
+        Color::Red | Color::LightRed => (220, 30, 30),
+        Color::Green | Color::LightGreen => (20, 180, 60),
+        Color::Yellow | Color::LightYellow => (250, 220, 0),
+        Color::Blue | Color::LightBlue => (20, 80, 220),
+        Color::Magenta | Color::LightMagenta => (200, 50, 200),
+        Color::Cyan | Color::LightCyan => (40, 200, 220),
+        Color::Gray | Color::DarkGray => (160, 160, 160),
+        _ => (255, 255, 255),
+    }
+}
+
+// Terminal color of the stickers on each face (U R F D L B) the way the cube is held
+#[derive(Debug, Clone)]
+pub struct FaceColors(pub [Color; 6]);
+
+impl Default for FaceColors {
+    fn default() -> Self {
+        CubeSettings::default().face_colors().unwrap()
+    }
+}
+
+impl FaceColors {
+    pub fn color(&self, face: Face) -> Color {
+        self.0[Face::ALL.iter().position(|other| *other == face).unwrap()]
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Training {
+    pub zen: bool,
+    pub avoid_repeats: bool,
+    // With `avoid_repeats`, how many of the last scrambles' cases can't come up next
+    pub avoid_last: usize,
+    // Start with scrambles split into memo chunks of `chunk_size` moves
+    pub group: bool,
+    pub chunk_size: usize,
+    // Start with the scramble in block letters, to read it from a distance
+    pub big_scramble: bool,
+    // Color each move of the scramble like the face it turns instead of by chunk
+    pub color_moves: bool,
+    // Start with the alg set and case name shown under the scramble, hide it to test recognition
+    pub show_source: bool,
+    // Hide the scramble this many milliseconds after showing it, 0 disables hiding
+    pub flash_ms: u64,
+    // How long the timer key has to be held before the timer can start
+    pub hold_ms: u64,
+    // Start sessions with a 15 second WCA inspection, can be changed in Setup
+    pub inspection: bool,
+    // Start sessions picking cases by spaced repetition instead of at random, can be changed in Setup
+    pub srs: bool,
+    // Draw the scrambled cube next to the scramble (toggle with c)
+    pub net: bool,
+    // Only draw the last layer, also the default for sets with an OLL, PLL, LL or CLL mask
+    pub last_layer: bool,
+    // Confetti when a solve sets a PB, the toast is always shown
+    pub confetti: bool,
+    // Random U turn after the case is solved and before it is shown, unless an alg file says otherwise
+    pub auf: bool,
+    pub pre_auf: bool,
+    // Scramble from a random whole cube rotation so cases show up from other angles
+    pub rotation: Rotation,
+    // Drop whole cube rotations at the start and end of scrambles, the moves after them renamed
+    pub trim_rotations: bool,
+    // Start sessions with mirrored algs for left hand practice, can be changed in Setup
+    pub mirror: bool,
+    // Start sessions with solver made scrambles for 2x2 and 3x3 sets instead of inverse algs, can be changed in Setup
+    pub random_state: bool,
+    // Start sessions in recap mode, every case of the enabled sets once, can be changed in Setup
+    pub recap: bool,
+    // Start sessions in exam mode, a graded run through `exam_cases` random cases, can be changed in Setup
+    pub exam: bool,
+    // Cases never attempted are first shown with their alg played on the cube and repeated a few times
+    pub first_seen: bool,
+    pub exam_cases: usize,
+    // Length of a quick session started from Setup, --quick gives its own
+    pub quick_minutes: u64,
+    // Seconds a case should take in an exam, for sets without a "#! target:" line
+    pub exam_target_secs: f64,
+    // Run the commands of "#! generator:" lines in alg files, which could do anything
+    pub generators: bool,
+    // Ask to compare the cube with the drawing before each attempt, a mismatch gets a new scramble
+    pub check_scramble: bool,
+    // Show an easy case after this many hard ones instead of hard cases back to back, 0 picks at random
+    pub interleave: usize,
+    // Pick slow and often failed cases more often, by the recent solves in the history
+    pub weighted: bool,
+    // With `weighted`, how often the best case still comes up compared to the worst, from 0 to 1
+    pub exploration: f64,
+    // Only draw from this percentage of each set's cases, the ones going worst, 0 draws from all
+    pub weakest: u32,
+}
+
+impl Training {
+    pub fn variation(&self) -> Variation {
+        Variation { auf: self.auf, pre_auf: self.pre_auf, rotation: self.rotation, mirror: self.mirror, trim_rotations: self.trim_rotations, random_state: self.random_state }
+    }
+}
+
+impl Default for Training {
+    fn default() -> Self {
+        Training {
+            zen: false,
+            avoid_repeats: true,
+            avoid_last: 1,
+            group: false,
+            chunk_size: 4,
+            big_scramble: false,
+            color_moves: false,
+            show_source: false,
+            flash_ms: 0,
+            hold_ms: 300,
+            inspection: false,
+            srs: false,
+            net: true,
+            last_layer: false,
+            confetti: true,
+            auf: false,
+            pre_auf: false,
+            rotation: Rotation::Off,
+            trim_rotations: false,
+            mirror: false,
+            random_state: false,
+            recap: false,
+            exam: false,
+            first_seen: false,
+            exam_cases: 20,
+            quick_minutes: 5,
+            exam_target_secs: 4.0,
+            generators: false,
+            check_scramble: false,
+            interleave: 0,
+            weighted: false,
+            exploration: 0.1,
+            weakest: 0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistorySettings {
+    // Solves older than this many days are dropped once their week is snapshotted, 0 keeps them all
+    pub keep_days: u64,
+    // Hour the day starts at for daily stats and streaks, so late sessions count for the day before
+    pub day_start_hour: u32,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        HistorySettings {
+            keep_days: 0,
+            day_start_hour: 4,
+        }
+    }
+}
+
+// A Stackmat timer plugged into the line in, read through a command recording it
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StackmatSettings {
+    // Writes raw signed 16 bit little endian mono samples, e.g. "arecord -q -t raw -f S16_LE -c 1 -r 44100"
+    pub command: Option<String>,
+    pub sample_rate: u32,
+}
+
+impl Default for StackmatSettings {
+    fn default() -> Self {
+        StackmatSettings {
+            command: None,
+            sample_rate: 44100,
+        }
+    }
+}
+
+// A smart cube, connected over Bluetooth or by a bridge program that prints its turns
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmartCubeSettings {
+    pub command: Option<String>,
+    // Start of the Bluetooth name of a Giiker, GAN or MoYu cube to connect to, e.g. "GiC" or "GAN"
+    pub bluetooth: Option<String>,
+}
+
+// Light practice while recovering from strain: forced rest between timed attempts and a daily cap
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Recovery {
+    pub enabled: bool,
+    pub rest_secs: u64,
+    // Timed attempts allowed per day, 0 means no limit
+    pub daily_attempts: usize,
+}
+
+impl Default for Recovery {
+    fn default() -> Self {
+        Recovery {
+            enabled: false,
+            rest_secs: 60,
+            daily_attempts: 30,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_labels() {
+        assert_eq!(Binding::new(&["up", "k"]).label(), "k");
+        assert_eq!(Binding::new(&["enter", "space"]).label(), "enter");
+        assert_eq!(Binding::new(&["space", "l"]).label(), "l");
+        assert_eq!(Binding::new(&["+"]).label(), "+");
+        assert_eq!(Binding(Vec::new()).label(), "");
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert_eq!(Keys::default().validate(), Ok(()));
+        let keys: Keys = toml::from_str("easy = [\"p\"]").unwrap();
+        assert_eq!(keys.validate(), Err("p is both previous and easy on the Train page".to_string()));
+        // A key can do different things on different pages
+        let keys: Keys = toml::from_str("later = [\"s\"]").unwrap();
+        assert_eq!(keys.validate(), Ok(()));
+    }
+}
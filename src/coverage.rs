@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use crate::cube::{cancel_moves, case_key, orientations, rotate_moves, without_rotations, CubeState};
+use crate::db::{AlgDB, AlgSet, Movement, Puzzle};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CaseRef<'a> {
+    pub algset: &'a AlgSet,
+    pub alg: usize,
+}
+
+impl CaseRef<'_> {
+    // "2x2/cll/T · Case 3"
+    pub fn name(&self) -> String {
+        let name = self.algset.algs[self.alg].name.clone().unwrap_or_else(|| format!("case {}", self.alg + 1));
+        format!("{} · {}", self.algset.path, name)
+    }
+
+    pub fn algs(&self) -> Vec<String> {
+        self.algset.algs[self.alg].all()
+            .map(|moves| moves.iter().map(Movement::as_text).collect::<Vec<_>>().join(" "))
+            .collect()
+    }
+}
+
+// How the cases of two alg directories line up, matching cases by what they do to the cube
+// rather than by name, so differently organized directories can be compared. Cases of puzzles the
+// cube model can't follow are matched by their main alg instead
+#[derive(Debug, Default)]
+pub struct Coverage<'a> {
+    pub only_left: Vec<CaseRef<'a>>,
+    pub only_right: Vec<CaseRef<'a>>,
+    // The same case with algs that aren't all on both sides
+    pub differing: Vec<(CaseRef<'a>, CaseRef<'a>)>,
+    pub shared: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CaseKey {
+    // The case's state on a cube the size of its puzzle, so a 2x2 case is matched by its corners alone
+    State(CubeState),
+    // Puzzles the cube model can't follow, like big cubes or Square-1, by their main alg's `alg_key`
+    Alg(String),
+}
+
+fn key_of(case: &CaseRef, orientations: &[Vec<Movement>]) -> (Puzzle, CaseKey) {
+    let puzzle = case.algset.puzzle();
+    let moves = &case.algset.algs[case.alg].moves;
+    match puzzle.modeled_size() {
+        Some(size) => (puzzle, CaseKey::State(case_key(moves, size))),
+        None => (puzzle, CaseKey::Alg(alg_key(moves, puzzle, orientations))),
+    }
+}
+
+fn cases_by_key(db: &AlgDB) -> Vec<((Puzzle, CaseKey), CaseRef<'_>)> {
+    let orientations = orientations();
+    db.matching("**").into_iter()
+        .flat_map(|algset| (0..algset.algs.len()).map(move |alg| CaseRef { algset, alg }))
+        .map(|case| (key_of(&case, &orientations), case))
+        .collect()
+}
+
+impl<'a> Coverage<'a> {
+    pub fn compare(left: &'a AlgDB, right: &'a AlgDB) -> Coverage<'a> {
+        let left = cases_by_key(left);
+        let right = cases_by_key(right);
+        // A directory can have the same case more than once, like in two overlapping sets
+        let mut right_keys: HashMap<&(Puzzle, CaseKey), Vec<CaseRef>> = HashMap::new();
+        for (key, case) in right.iter() {
+            right_keys.entry(key).or_default().push(*case);
+        }
+        let left_keys: HashMap<&(Puzzle, CaseKey), CaseRef> = left.iter().map(|(key, case)| (key, *case)).collect();
+
+        let mut coverage = Coverage::default();
+        for (key, case) in left.iter() {
+            let Some(others) = right_keys.get(key) else {
+                coverage.only_left.push(*case);
+                continue;
+            };
+            coverage.shared += 1;
+            let algs = case.algs();
+            let same = |other: &CaseRef| {
+                let other_algs = other.algs();
+                algs.iter().all(|alg| other_algs.contains(alg)) && other_algs.iter().all(|alg| algs.contains(alg))
+            };
+            if !others.iter().any(same) {
+                coverage.differing.push((*case, others[0]));
+            }
+        }
+        coverage.only_right = right.iter()
+            .filter(|(key, _)| !left_keys.contains_key(key))
+            .map(|(_, case)| *case)
+            .collect();
+        coverage
+    }
+}
+
+// Algs and cases a directory has more than once, like from sheets pasted into several files. Every
+// copy is drawn on its own, so a duplicated case comes up more often than the others
+#[derive(Debug, Default)]
+pub struct Duplicates<'a> {
+    // Algs that are the same moves once rotations are taken out and whatever angle they're done
+    // from, each copy with the case it's written for and its text there
+    pub algs: Vec<Vec<(CaseRef<'a>, String)>>,
+    // The same case in more than one place, whatever the names, algs or AUF
+    pub cases: Vec<Vec<CaseRef<'a>>>,
+}
+
+// The same text for algs that only differ by rotations, moves that cancel or the way the cube is held
+fn alg_key(moves: &[Movement], puzzle: Puzzle, orientations: &[Vec<Movement>]) -> String {
+    let text = |moves: &[Movement]| moves.iter().map(Movement::as_text).collect::<Vec<_>>().join(" ");
+    if !puzzle.is_cube() {
+        return text(moves);
+    }
+    let moves = cancel_moves(&without_rotations(moves));
+    orientations.iter().map(|rotation| text(&rotate_moves(&moves, rotation))).min().unwrap()
+}
+
+impl<'a> Duplicates<'a> {
+    pub fn find(db: &'a AlgDB) -> Duplicates<'a> {
+        let orientations = orientations();
+        let mut duplicates = Duplicates::default();
+
+        // Kept in the order the algs are found for output that doesn't change between runs
+        let mut alg_groups: HashMap<String, usize> = HashMap::new();
+        for algset in db.matching("**") {
+            let puzzle = algset.puzzle();
+            for (alg, case) in algset.algs.iter().enumerate() {
+                for moves in case.all() {
+                    let copy = (CaseRef { algset, alg }, moves.iter().map(Movement::as_text).collect::<Vec<_>>().join(" "));
+                    let key = alg_key(moves, puzzle, &orientations);
+                    match alg_groups.get(&key) {
+                        Some(idx) => duplicates.algs[*idx].push(copy),
+                        None => {
+                            alg_groups.insert(key, duplicates.algs.len());
+                            duplicates.algs.push(vec![copy]);
+                        }
+                    }
+                }
+            }
+        }
+        duplicates.algs.retain(|copies| copies.len() > 1);
+
+        // Cases are told apart on the cube model, so only for the puzzles it can follow
+        let mut case_groups: HashMap<(Puzzle, CaseKey), usize> = HashMap::new();
+        let cases = db.matching("**").into_iter()
+            .filter(|algset| algset.puzzle().is_modeled())
+            .flat_map(|algset| (0..algset.algs.len()).map(move |alg| CaseRef { algset, alg }));
+        for case in cases {
+            let key = key_of(&case, &orientations);
+            match case_groups.get(&key) {
+                Some(idx) => duplicates.cases[*idx].push(case),
+                None => {
+                    case_groups.insert(key, duplicates.cases.len());
+                    duplicates.cases.push(vec![case]);
+                }
+            }
+        }
+        duplicates.cases.retain(|cases| cases.len() > 1);
+        duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::AlgEntry;
+
+    fn db(sets: &[(&str, &str)]) -> AlgDB {
+        let entries = sets.iter()
+            .map(|(path, text)| {
+                let algset = AlgSet::from_text(path.to_string(), text).unwrap();
+                AlgEntry::Algs(path.to_string(), AlgSet { path: path.to_string(), ..algset })
+            })
+            .collect();
+        AlgDB { entries, diagnostics: Vec::new() }
+    }
+
+    fn names(cases: &[CaseRef]) -> Vec<String> {
+        cases.iter().map(CaseRef::name).collect()
+    }
+
+    #[test]
+    fn cube_cases() {
+        // Matched by the case whatever the name or angle, a different alg for it is a difference
+        let left = db(&[("pll", "T: R U R' U' R' F R2 U' R' U' R U R' F'\nJb: R U R' F' R U R' U' R' F R2 U' R'\n")]);
+        let right = db(&[("last layer", "y2 T-perm: y2 R U R' U' R' F R2 U' R' U' R U R' F'\nH: M2 U M2 U2 M2 U M2\n")]);
+        let coverage = Coverage::compare(&left, &right);
+        assert_eq!(coverage.shared, 1);
+        assert_eq!(names(&coverage.only_left), ["pll · Jb"]);
+        assert_eq!(names(&coverage.only_right), ["last layer · H"]);
+        assert_eq!(coverage.differing.len(), 1);
+    }
+
+    #[test]
+    fn other_puzzles() {
+        // The cube model can't tell these cases apart, so they're matched by their algs
+        let left = db(&[
+            ("cubeshape", "#! puzzle: square-1\nKite: (1,0) / (-1,-1) / (0,1)\nBarrel: (1,0) / (3,0) / (-1,0)\n"),
+            ("l4e", "#! puzzle: pyraminx\nA: R U' R' U\n"),
+            ("parity", "#! puzzle: 4x4\nPLL parity: 2R2 U2 2R2 Uw2 2R2 Uw2\nOLL parity: Rw U2 x Rw U2 Rw U2 Rw' U2 Lw U2 Rw' U2 Rw U2 Rw' U2 Rw'\n"),
+        ]);
+        let right = db(&[
+            ("sq1", "#! puzzle: square-1\nKite: (1,0) / (-1,-1) / (0,1)\n"),
+            ("pyraminx", "#! puzzle: pyraminx\nB: L' U L U'\n"),
+            ("4x4", "#! puzzle: 4x4\nPLL parity: y 2R2 U2 2R2 Uw2 2R2 Uw2\n"),
+        ]);
+        let coverage = Coverage::compare(&left, &right);
+        assert_eq!(coverage.shared, 2);
+        // The rotation doesn't make it another case, but the alg is written differently
+        let differing: Vec<(String, String)> = coverage.differing.iter().map(|(a, b)| (a.name(), b.name())).collect();
+        assert_eq!(differing, [("parity · PLL parity".to_string(), "4x4 · PLL parity".to_string())]);
+        assert_eq!(names(&coverage.only_left), ["cubeshape · Barrel", "l4e · A", "parity · OLL parity"]);
+        assert_eq!(names(&coverage.only_right), ["pyraminx · B"]);
+    }
+}
@@ -0,0 +1,118 @@
+use serde_json::Value;
+
+use crate::csv::file_name;
+use crate::db::{AlgSet, RubiksError};
+
+// A session from a csTimer export ("Export to file" in its settings). The export is a JSON object with
+// a "sessionN" array of solves per session, each solve being [[penalty, time], scramble, comment, date],
+// and the session names in properties.sessionData
+#[derive(Debug)]
+pub struct CsTimerSession {
+    pub name: String,
+    // Every different scramble, in the order they were first used
+    pub scrambles: Vec<String>,
+    pub solves: usize,
+}
+
+impl CsTimerSession {
+    pub fn parse_export(text: &str) -> Result<Vec<CsTimerSession>, RubiksError> {
+        let invalid = |err: &str| RubiksError::InvalidFile(format!("csTimer export: {}", err));
+        let export: Value = serde_json::from_str(text).map_err(|err| invalid(&err.to_string()))?;
+        let export = export.as_object().ok_or_else(|| invalid("not a JSON object"))?;
+
+        // Older exports keep the session names as a JSON string inside the JSON
+        let names = export.get("properties")
+            .and_then(|properties| properties.get("sessionData"))
+            .map(nested)
+            .unwrap_or(Value::Null);
+
+        let mut sessions = Vec::new();
+        for (key, solves) in export.iter() {
+            let Some(id) = key.strip_prefix("session") else {
+                continue;
+            };
+            let solves = nested(solves);
+            let solves = solves.as_array().ok_or_else(|| invalid(&format!("{} is not a list of solves", key)))?;
+
+            let mut scrambles: Vec<String> = Vec::new();
+            for solve in solves {
+                let scramble = solve.get(1).and_then(Value::as_str).unwrap_or("");
+                let scramble = scramble.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !scramble.is_empty() && !scrambles.contains(&scramble) {
+                    scrambles.push(scramble);
+                }
+            }
+            let name = names.get(id)
+                .and_then(|session| session.get("name"))
+                .map(|name| match name {
+                    Value::String(name) => name.clone(),
+                    name => name.to_string(),
+                })
+                .unwrap_or_else(|| format!("Session {}", id));
+            sessions.push(CsTimerSession { name, scrambles, solves: solves.len() });
+        }
+        Ok(sessions)
+    }
+
+    // The session as an alg file: a case per scramble, solved by its inverse. None when a scramble
+    // isn't for a 3x3 (or 2x2) the trainer can read, like megaminx or 4x4 layer moves
+    pub fn alg_file(&self) -> Option<String> {
+        let mut lines = vec![format!("# Imported from the csTimer session \"{}\" ({} solves)", self.name, self.solves)];
+        for scramble in self.scrambles.iter() {
+            let moves = AlgSet::parse_scramble(scramble).ok()?;
+            let alg: Vec<String> = moves.iter().rev().map(|movement| movement.inv().as_text()).collect();
+            lines.push(alg.join(" "));
+        }
+        Some(lines.join("\n") + "\n")
+    }
+
+    pub fn file_name(&self) -> String {
+        file_name(&self.name)
+    }
+}
+
+fn nested(value: &Value) -> Value {
+    match value {
+        Value::String(text) => serde_json::from_str(text).unwrap_or(Value::Null),
+        value => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = include_str!("../tests/fixtures/cstimer_export.json");
+
+    #[test]
+    fn import_export() {
+        let sessions = CsTimerSession::parse_export(EXPORT).unwrap();
+        let names: Vec<&str> = sessions.iter().map(|session| session.name.as_str()).collect();
+        assert_eq!(names, ["1", "..", "Megaminx", "OLL/PLL: 2-look"]);
+
+        // Repeated scrambles are one case, and the DNF still counts as a solve
+        let pll = &sessions[0];
+        assert_eq!((pll.scrambles.len(), pll.solves), (2, 3));
+        let text = pll.alg_file().unwrap();
+        let (algset, skipped) = AlgSet::read_text("pll".to_string(), &text, None);
+        assert!(skipped.is_empty());
+        assert_eq!(algset.algs.len(), 2);
+        assert_eq!(algset.notes, ["Imported from the csTimer session \"1\" (3 solves)"]);
+
+        // Spacing doesn't make a scramble different
+        assert_eq!(sessions[1].scrambles, ["R U R' U R U2 R'", "R U2 R' U' R U' R'"]);
+        // Megaminx scrambles can't be trained
+        assert_eq!(sessions[2].alg_file(), None);
+        assert_eq!(sessions[3].solves, 0);
+    }
+
+    #[test]
+    fn session_file_names() {
+        let sessions = CsTimerSession::parse_export(EXPORT).unwrap();
+        let files: Vec<String> = sessions.iter().map(CsTimerSession::file_name).collect();
+        // A session can't be named after the directory or its parent, or reach into another one
+        assert_eq!(files, ["1", "-..", "Megaminx", "OLL-PLL- 2-look"]);
+        assert!(CsTimerSession::parse_export("[]").is_err());
+        assert!(CsTimerSession::parse_export("{\"session1\": 3}").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use crate::db::{AlgSet, RubiksError};
+
+// Alg sheets exported as CSV (AlgDb.net, SpeedCubeDB, spreadsheets). Columns are found by their
+// header unless given: the case name, the alg and optionally the group the case goes in
+#[derive(Debug, Default)]
+pub struct Columns {
+    pub name: Option<String>,
+    pub alg: Option<String>,
+    pub group: Option<String>,
+}
+
+const NAME_HEADERS: &[&str] = &["name", "case", "case name"];
+const ALG_HEADERS: &[&str] = &["alg", "algorithm", "algs", "solution"];
+const GROUP_HEADERS: &[&str] = &["group", "set", "subset", "category"];
+
+#[derive(Debug)]
+pub struct SheetCase {
+    pub name: String,
+    // The first alg listed for the case, then the others
+    pub algs: Vec<String>,
+}
+
+// Cases of one group, in sheet order. The group is empty when the sheet has no group column
+#[derive(Debug)]
+pub struct SheetGroup {
+    pub name: String,
+    pub cases: Vec<SheetCase>,
+}
+
+#[derive(Debug, Default)]
+pub struct Sheet {
+    pub groups: Vec<SheetGroup>,
+    // Rows whose alg doesn't parse, by line number
+    pub skipped: Vec<usize>,
+}
+
+// Splits a line into fields, with "" for a quote inside a quoted field
+fn fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+fn column(header: &[String], given: &Option<String>, known: &[&str]) -> Option<usize> {
+    let matches = |field: &String, name: &str| field.to_lowercase() == name.to_lowercase();
+    match given {
+        Some(given) => header.iter().position(|field| matches(field, given)),
+        None => header.iter().position(|field| known.iter().any(|name| matches(field, name))),
+    }
+}
+
+impl Sheet {
+    pub fn parse(text: &str, columns: &Columns) -> Result<Sheet, RubiksError> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let header = lines.next().map(|(_, line)| fields(line)).unwrap_or_default();
+        let missing = |what: &str| RubiksError::InvalidFile(format!("no {} column in the CSV header: {}", what, header.join(", ")));
+        let alg_column = column(&header, &columns.alg, ALG_HEADERS).ok_or_else(|| missing("alg"))?;
+        let name_column = column(&header, &columns.name, NAME_HEADERS);
+        if columns.name.is_some() && name_column.is_none() {
+            return Err(missing("name"));
+        }
+        let group_column = column(&header, &columns.group, GROUP_HEADERS);
+        if columns.group.is_some() && group_column.is_none() {
+            return Err(missing("group"));
+        }
+
+        let mut sheet = Sheet::default();
+        for (number, line) in lines {
+            let row = fields(line);
+            let field = |idx: Option<usize>| idx.and_then(|idx| row.get(idx)).cloned().unwrap_or_default();
+            let alg = field(Some(alg_column)).replace('’', "'");
+            if alg.is_empty() || AlgSet::parse_scramble(&alg).is_err() {
+                sheet.skipped.push(number + 1);
+                continue;
+            }
+
+            let group_name = field(group_column);
+            let group = match sheet.groups.iter().position(|group| group.name == group_name) {
+                Some(idx) => &mut sheet.groups[idx],
+                None => {
+                    sheet.groups.push(SheetGroup { name: group_name, cases: Vec::new() });
+                    sheet.groups.last_mut().unwrap()
+                }
+            };
+            // Rows without a name are cases of their own, named rows with the same name are alternatives
+            let name = field(name_column);
+            match group.cases.iter_mut().find(|case| !name.is_empty() && case.name == name) {
+                Some(case) => case.algs.push(alg),
+                None => group.cases.push(SheetCase { name, algs: vec![alg] }),
+            }
+        }
+        Ok(sheet)
+    }
+}
+
+impl SheetGroup {
+    // The group as an alg file, with "name: alg" lines and the other algs for a case on ALT: lines
+    pub fn alg_file(&self, source: &str) -> String {
+        let mut lines = vec![format!("# Imported from {}", source)];
+        for case in self.cases.iter() {
+            // ':' ends the name and '#' starts a comment in alg files
+            let name: String = case.name.chars().map(|c| if c == ':' || c == '#' { '-' } else { c }).collect();
+            let mut algs = case.algs.iter();
+            let first = algs.next().unwrap();
+            lines.push(if name.is_empty() { first.clone() } else { format!("{}: {}", name, first) });
+            lines.extend(algs.map(|alg| format!("ALT: {}", alg)));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+// Name for an alg file or group directory, without characters that don't belong in a path. Names
+// like ".." that would be the directory itself or its parent get a '-' in front
+pub fn file_name(name: &str) -> String {
+    let name: String = name.chars().map(|c| if matches!(c, '/' | '\\' | ':') || c.is_control() { '-' } else { c }).collect();
+    if name.trim().chars().all(|c| c == '.') { format!("-{}", name) } else { name }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_columns() {
+        // Columns in any order and case, with a quoted field holding a comma and a row without an alg
+        let text = "Algorithm,Case Name,Notes\nR U R' U R U2 R',Sune,\"fast, easy\"\n,Empty,\nR U2 R' U' R U' R',Antisune,\nR U R' U' R' F R2 U' R' U' R U R' F',Sune,\n";
+        let sheet = Sheet::parse(text, &Columns::default()).unwrap();
+        assert_eq!(sheet.skipped, [3]);
+        assert_eq!(sheet.groups.len(), 1);
+        let group = &sheet.groups[0];
+        assert_eq!(group.name, "");
+        let cases: Vec<(&str, usize)> = group.cases.iter().map(|case| (case.name.as_str(), case.algs.len())).collect();
+        assert_eq!(cases, [("Sune", 2), ("Antisune", 1)]);
+        assert_eq!(group.alg_file("oll.csv"), "# Imported from oll.csv\nSune: R U R' U R U2 R'\nALT: R U R' U' R' F R2 U' R' U' R U R' F'\nAntisune: R U2 R' U' R U' R'\n");
+
+        assert!(Sheet::parse("name,notes\nSune,x\n", &Columns::default()).is_err());
+        let columns = Columns { alg: Some("moves".to_string()), ..Columns::default() };
+        let sheet = Sheet::parse("moves\nR U R'\n", &columns).unwrap();
+        assert_eq!(sheet.groups[0].cases[0].algs, ["R U R'"]);
+    }
+
+    #[test]
+    fn group_column() {
+        let text = "subset,name,alg\nT,T1,R U R'\nU,U1,R U2 R'\nT,T2,R' U' R\n";
+        let sheet = Sheet::parse(text, &Columns::default()).unwrap();
+        let groups: Vec<(&str, Vec<&str>)> = sheet.groups.iter()
+            .map(|group| (group.name.as_str(), group.cases.iter().map(|case| case.name.as_str()).collect()))
+            .collect();
+        assert_eq!(groups, [("T", vec!["T1", "T2"]), ("U", vec!["U1"])]);
+        // A group asked for by name has to be there
+        let columns = Columns { group: Some("category".to_string()), ..Columns::default() };
+        assert!(Sheet::parse(text, &columns).is_err());
+        assert_eq!(file_name("A/B"), "A-B");
+        assert_eq!(file_name("C:\\x"), "C--x");
+        for (name, safe) in [("..", "-.."), (".", "-."), ("", "-"), (" . ", "- . "), ("..x", "..x")] {
+            assert_eq!(file_name(name), safe);
+        }
+    }
+}
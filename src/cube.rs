@@ -0,0 +1,752 @@
+use crate::db::{Mask, Movement};
+
+type Vec3 = [i8; 3];
+
+// The faces double as sticker colors: a sticker is named after the face it is on when solved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Face {
+    U,
+    R,
+    F,
+    D,
+    L,
+    B,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::U, Face::R, Face::F, Face::D, Face::L, Face::B];
+
+    // x points right, y up and z to the front
+    fn normal(&self) -> Vec3 {
+        match self {
+            Face::U => [0, 1, 0],
+            Face::R => [1, 0, 0],
+            Face::F => [0, 0, 1],
+            Face::D => [0, -1, 0],
+            Face::L => [-1, 0, 0],
+            Face::B => [0, 0, -1],
+        }
+    }
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Face::U => "U",
+            Face::R => "R",
+            Face::F => "F",
+            Face::D => "D",
+            Face::L => "L",
+            Face::B => "B",
+        }
+    }
+}
+
+// Which layers along the turning axis a move takes, counted from the face it is named after
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layers {
+    Outer,
+    Middle,
+    Wide,
+    All,
+    // Layers of a bigger cube, from 1 for the face's own. The 3x3 model takes the ones it has
+    Range(u8, u8),
+}
+
+impl Layers {
+    fn contains(&self, depth: i8) -> bool {
+        match self {
+            Layers::Outer => depth == 1,
+            Layers::Middle => depth == 0,
+            Layers::Wide => depth >= 0,
+            Layers::All => true,
+            Layers::Range(from, to) => (*from as i8..=*to as i8).contains(&(2 - depth)),
+        }
+    }
+}
+
+// Axis a move turns clockwise around (looking at it from the outside), the layers it
+// takes and how many quarter turns it makes. None for moves of other puzzles than cubes
+fn turn(movement: &Movement) -> Option<(Vec3, Layers, u8)> {
+    use Movement::*;
+    match movement {
+        Layered { face, from, to, turns } => return Some((face.normal(), Layers::Range(*from, *to), *turns)),
+        Twist { .. } | Square1Turn { .. } | Square1Slash => return None,
+        _ => {}
+    }
+    let turns = match movement {
+        R | U | F | L | B | D | X | Y | Z | M | E | S | RW | UW | FW | LW | BW | DW => 1,
+        R2 | U2 | F2 | L2 | B2 | D2 | X2 | Y2 | Z2 | M2 | E2 | S2 | RW2 | UW2 | FW2 | LW2 | BW2 | DW2 => 2,
+        _ => 3,
+    };
+    let (face, layers) = match movement {
+        R | RP | R2 => (Face::R, Layers::Outer),
+        U | UP | U2 => (Face::U, Layers::Outer),
+        F | FP | F2 => (Face::F, Layers::Outer),
+        L | LP | L2 => (Face::L, Layers::Outer),
+        B | BP | B2 => (Face::B, Layers::Outer),
+        D | DP | D2 => (Face::D, Layers::Outer),
+        // Rotations follow R, U and F, slices follow L, D and F
+        X | XP | X2 => (Face::R, Layers::All),
+        Y | YP | Y2 => (Face::U, Layers::All),
+        Z | ZP | Z2 => (Face::F, Layers::All),
+        M | MP | M2 => (Face::L, Layers::Middle),
+        E | EP | E2 => (Face::D, Layers::Middle),
+        S | SP | S2 => (Face::F, Layers::Middle),
+        RW | RWP | RW2 => (Face::R, Layers::Wide),
+        UW | UWP | UW2 => (Face::U, Layers::Wide),
+        FW | FWP | FW2 => (Face::F, Layers::Wide),
+        LW | LWP | LW2 => (Face::L, Layers::Wide),
+        BW | BWP | BW2 => (Face::B, Layers::Wide),
+        DW | DWP | DW2 => (Face::D, Layers::Wide),
+        Layered { .. } | Twist { .. } | Square1Turn { .. } | Square1Slash => unreachable!(),
+    };
+    Some((face.normal(), layers, turns))
+}
+
+fn is_rotation(movement: &Movement) -> bool {
+    turn(movement).is_some_and(|(_, layers, _)| layers == Layers::All)
+}
+
+// A turn of one outer face, like R or U2, which is all a smart cube can report
+pub fn is_face_turn(movement: &Movement) -> bool {
+    turn(movement).is_some_and(|(_, layers, _)| layers == Layers::Outer)
+}
+
+// The move turning the layers of the face with this normal, the other way around from `turn`
+fn named_move(normal: Vec3, layers: Layers, turns: u8) -> Movement {
+    let face = Face::ALL.into_iter().find(|face| face.normal() == normal).unwrap();
+    if let Layers::Range(from, to) = layers {
+        return Movement::layered(face, from, to, turns);
+    }
+    let name = face.as_text();
+    // Slices and rotations are named after one face of their axis, seen from the other face they turn backwards
+    let (name, turns) = match (layers, face) {
+        (Layers::Outer, _) => (name.to_string(), turns),
+        (Layers::Wide, _) => (name.to_lowercase(), turns),
+        (Layers::Middle, Face::L | Face::R) => ("M".to_string(), if face == Face::L { turns } else { 4 - turns }),
+        (Layers::Middle, Face::D | Face::U) => ("E".to_string(), if face == Face::D { turns } else { 4 - turns }),
+        (Layers::Middle, Face::F | Face::B) => ("S".to_string(), if face == Face::F { turns } else { 4 - turns }),
+        (Layers::All, Face::R | Face::L) => ("x".to_string(), if face == Face::R { turns } else { 4 - turns }),
+        (Layers::All, Face::U | Face::D) => ("y".to_string(), if face == Face::U { turns } else { 4 - turns }),
+        (Layers::All, Face::F | Face::B) => ("z".to_string(), if face == Face::F { turns } else { 4 - turns }),
+        (Layers::Range(..), _) => unreachable!(),
+    };
+    let suffix = match turns {
+        1 => "",
+        2 => "2",
+        _ => "'",
+    };
+    Movement::from_text(&format!("{}{}", name, suffix)).unwrap()
+}
+
+// Moves that do the same as `rotation movements rotation'` without any rotation to perform:
+// what `movements` would do to the cube if it was held after `rotation`
+pub fn rotate_moves(movements: &[Movement], rotation: &[Movement]) -> Vec<Movement> {
+    let relabel = |mut axis: Vec3| {
+        for (rotation_axis, _, turns) in rotation.iter().rev().filter_map(turn) {
+            for _ in 0..4 - turns {
+                axis = rotate(axis, rotation_axis);
+            }
+        }
+        axis
+    };
+
+    movements.iter()
+        .map(|movement| match turn(movement) {
+            Some((axis, layers, turns)) => named_move(relabel(axis), layers, turns),
+            None => movement.clone(),
+        })
+        .collect()
+}
+
+pub fn has_rotation(movements: &[Movement]) -> bool {
+    movements.iter().any(is_rotation)
+}
+
+// Moves that solve the same as an alg with rotations, without them: moves after a rotation are
+// turned into the ones they end up being, and turns of the same layers next to each other merged
+pub fn without_rotations(movements: &[Movement]) -> Vec<Movement> {
+    let mut rotation = Vec::new();
+    let mut result: Vec<(Vec3, Layers, u8)> = Vec::new();
+    for movement in movements {
+        if is_rotation(movement) {
+            rotation.push(movement.clone());
+            continue;
+        }
+        let Some((axis, layers, turns)) = turn(&rotate_moves(std::slice::from_ref(movement), &rotation)[0]) else {
+            continue;
+        };
+        push_turn(&mut result, (axis, layers, turns));
+    }
+    result.into_iter().map(|(axis, layers, turns)| named_move(axis, layers, turns)).collect()
+}
+
+// Adds a turn, merged into the last one when it turns the same layers, and both gone when they cancel out
+fn push_turn(turns: &mut Vec<(Vec3, Layers, u8)>, (axis, layers, amount): (Vec3, Layers, u8)) {
+    match turns.last_mut() {
+        Some(last) if last.0 == axis && last.1 == layers => {
+            last.2 = (last.2 + amount) % 4;
+            if last.2 == 0 {
+                turns.pop();
+            }
+        }
+        _ => turns.push((axis, layers, amount)),
+    }
+}
+
+// Moves with the ones next to each other on the same layers merged, like `U U'` gone and `R R2`
+// made `R'`. Rotations are kept, and moves of other puzzles are left as they are
+pub fn cancel_moves(movements: &[Movement]) -> Vec<Movement> {
+    let mut result = Vec::new();
+    let mut turns: Vec<(Vec3, Layers, u8)> = Vec::new();
+    for movement in movements {
+        match turn(movement) {
+            Some(next) => push_turn(&mut turns, next),
+            None => {
+                result.extend(turns.drain(..).map(|(axis, layers, turns)| named_move(axis, layers, turns)));
+                result.push(movement.clone());
+            }
+        }
+    }
+    result.extend(turns.into_iter().map(|(axis, layers, turns)| named_move(axis, layers, turns)));
+    result
+}
+
+// Moves without the rotations at the start and end: moves after a leading rotation are turned into
+// the ones they end up being, and a trailing rotation only changes how the cube is held after
+pub fn trim_rotations(movements: &[Movement]) -> Vec<Movement> {
+    let leading = movements.iter().take_while(|movement| is_rotation(movement)).count();
+    let mut rest = rotate_moves(&movements[leading..], &movements[..leading]);
+    let trailing = rest.iter().rev().take_while(|movement| is_rotation(movement)).count();
+    rest.truncate(rest.len() - trailing);
+    rest
+}
+
+// Mirrors moves across the M slice for the other hand: R and L swap and every turn goes the other way
+pub fn mirror_moves(movements: &[Movement]) -> Vec<Movement> {
+    movements.iter()
+        .map(|movement| match turn(movement) {
+            Some((axis, layers, turns)) => named_move([-axis[0], axis[1], axis[2]], layers, 4 - turns),
+            None => movement.clone(),
+        })
+        .collect()
+}
+
+fn dot(a: Vec3, b: Vec3) -> i8 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// Quarter turn of v clockwise around the unit axis a: -(a × v) + a (a · v)
+fn rotate(v: Vec3, a: Vec3) -> Vec3 {
+    let cross = [
+        a[1] * v[2] - a[2] * v[1],
+        a[2] * v[0] - a[0] * v[2],
+        a[0] * v[1] - a[1] * v[0],
+    ];
+    let d = dot(a, v);
+    [a[0] * d - cross[0], a[1] * d - cross[1], a[2] * d - cross[2]]
+}
+
+// Position of the piece a facelet is on and the direction the facelet faces.
+// Facelets are numbered face by face (U R F D L B), row by row as seen from outside the face,
+// with U and D read as if the cube was tilted to look at them from the front
+fn facelet(idx: usize) -> (Vec3, Vec3) {
+    let face = Face::ALL[idx / 9];
+    let (row, col) = ((idx % 9 / 3) as i8, (idx % 3) as i8);
+    let position = match face {
+        Face::U => [col - 1, 1, row - 1],
+        Face::R => [1, 1 - row, 1 - col],
+        Face::F => [col - 1, 1 - row, 1],
+        Face::D => [col - 1, -1, 1 - row],
+        Face::L => [-1, 1 - row, col - 1],
+        Face::B => [1 - col, 1 - row, -1],
+    };
+    (position, face.normal())
+}
+
+// The inverse of `facelet`
+fn facelet_index(position: Vec3, normal: Vec3) -> usize {
+    let face = Face::ALL.iter().position(|face| face.normal() == normal).unwrap();
+    let [x, y, z] = position;
+    let (row, col) = match Face::ALL[face] {
+        Face::U => (z + 1, x + 1),
+        Face::R => (1 - y, 1 - z),
+        Face::F => (1 - y, x + 1),
+        Face::D => (1 - z, x + 1),
+        Face::L => (1 - y, z + 1),
+        Face::B => (1 - y, 1 - x),
+    };
+    face * 9 + row as usize * 3 + col as usize
+}
+
+// A 3x3 cube as its 54 facelets. 2x2 algs work on it too, the corners behave the same
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CubeState {
+    pub facelets: [Face; 54],
+}
+
+impl Default for CubeState {
+    fn default() -> Self {
+        CubeState::solved()
+    }
+}
+
+impl CubeState {
+    pub fn solved() -> CubeState {
+        CubeState {
+            facelets: std::array::from_fn(|idx| Face::ALL[idx / 9]),
+        }
+    }
+
+    // Every face a single color, in any orientation
+    pub fn is_solved(&self) -> bool {
+        self.facelets.chunks(9).all(|face| face.iter().all(|facelet| *facelet == face[0]))
+    }
+
+    // A 2x2 is solved once its corners are, on the model
+    pub fn is_solved_2x2(&self) -> bool {
+        Face::ALL.iter().all(|face| {
+            let stickers = self.stickers(*face, 2).concat();
+            stickers.iter().all(|sticker| *sticker == stickers[0])
+        })
+    }
+
+    // The state as a cube this size sees it: a 2x2 only has the corners, the other facelets
+    // are all taken as U so they never tell two states apart
+    pub fn for_size(&self, size: usize) -> CubeState {
+        if size != 2 {
+            return self.clone();
+        }
+        CubeState {
+            facelets: std::array::from_fn(|idx| if facelet(idx).0.contains(&0) { Face::U } else { self.facelets[idx] }),
+        }
+    }
+
+    // The facelets of a face, row by row
+    pub fn face(&self, face: Face) -> [[Face; 3]; 3] {
+        let start = Face::ALL.iter().position(|other| *other == face).unwrap() * 9;
+        std::array::from_fn(|row| std::array::from_fn(|col| self.facelets[start + row * 3 + col]))
+    }
+
+    // The facelets of a face of a cube this size, row by row: a 2x2's are the corners
+    pub fn stickers(&self, face: Face, size: usize) -> Vec<Vec<Face>> {
+        let picked: &[usize] = if size == 2 { &[0, 2] } else { &[0, 1, 2] };
+        let facelets = self.face(face);
+        picked.iter().map(|row| picked.iter().map(|col| facelets[*row][*col]).collect()).collect()
+    }
+
+    // The sticker on `face` of the piece at `position`, x right, y up and z to the front from -1 to 1
+    pub fn sticker(&self, position: [i8; 3], face: Face) -> Face {
+        self.facelets[facelet_index(position, face.normal())]
+    }
+
+    // Moves of other puzzles than cubes leave it as it is
+    pub fn apply(&mut self, movements: &[Movement]) {
+        for (axis, layers, turns) in movements.iter().filter_map(turn) {
+            for _ in 0..turns {
+                self.quarter_turn(axis, layers);
+            }
+        }
+    }
+
+    fn center(&self, normal: Vec3) -> Face {
+        self.facelets[facelet_index(normal, normal)]
+    }
+
+    // Every facelet `keep` picks has the color of its face's center
+    fn solved_where(&self, keep: impl Fn(Vec3, Vec3) -> bool) -> bool {
+        (0..54).all(|idx| {
+            let (position, normal) = facelet(idx);
+            !keep(position, normal) || self.facelets[idx] == self.center(normal)
+        })
+    }
+
+    // Corners of the layer facing away from `top` are solved relative to each other.
+    // 2x2 sets have no centers to compare to, so only the corners are looked at
+    fn corners_solved(&self, top: Vec3) -> bool {
+        let bottom: Vec<(Vec3, Face)> = (0..54)
+            .filter(|idx| {
+                let (position, _) = facelet(*idx);
+                dot(position, top) == -1 && !position.contains(&0)
+            })
+            .map(|idx| (facelet(idx).1, self.facelets[idx]))
+            .collect();
+        bottom.iter().all(|(normal, color)| {
+            bottom.iter().all(|(other, other_color)| other != normal || other_color == color)
+        })
+    }
+
+    // Whether the pieces a set with this mask shouldn't touch are still solved, with the
+    // cube held in any orientation. Cross algs start from anything so they always pass
+    pub fn keeps(&self, mask: Mask) -> bool {
+        Face::ALL.iter().any(|face| {
+            let top = face.normal();
+            match mask {
+                Mask::Cross => true,
+                // The cross edges and the bottom center
+                Mask::F2L => self.solved_where(|position, _| {
+                    dot(position, top) == -1 && position.contains(&0)
+                }),
+                Mask::OLL | Mask::LL => self.solved_where(|position, _| dot(position, top) < 1),
+                // PLL only moves pieces around, the top stays one color
+                Mask::PLL => self.solved_where(|position, normal| dot(position, top) < 1 || normal == top),
+                Mask::CLL => self.corners_solved(top),
+            }
+        })
+    }
+
+    fn quarter_turn(&mut self, axis: Vec3, layers: Layers) {
+        let old = self.facelets;
+        for (idx, color) in old.iter().enumerate() {
+            let (position, normal) = facelet(idx);
+            if layers.contains(dot(position, axis)) {
+                self.facelets[facelet_index(rotate(position, axis), rotate(normal, axis))] = *color;
+            }
+        }
+    }
+}
+
+// What is wrong with an alg for a cube this size, if anything. The alg's inverse is the scramble
+// the trainer shows, so that has to be a case of the kind the set's mask says. A 2x2 has no edges or
+// centers, so whatever the mask its algs only have to keep the bottom corners together
+pub fn check_alg(alg: &[Movement], mask: Option<Mask>, size: usize) -> Option<String> {
+    let inverse: Vec<Movement> = alg.iter().rev().map(Movement::inv).collect();
+    let mut state = CubeState::solved();
+    state.apply(&inverse);
+
+    if (size == 2 && state.is_solved_2x2()) || state.is_solved() {
+        return Some("does nothing to a solved cube".to_string());
+    }
+    let kept = |mask: Mask| if size == 2 && mask != Mask::Cross { Mask::CLL } else { mask };
+    if let Some(mask) = mask && !state.keeps(kept(mask)) {
+        return Some(format!("moves pieces a {} alg shouldn't touch", mask.as_text()));
+    }
+    None
+}
+
+// The 24 ways of holding the cube, as rotations from the usual one
+pub fn orientations() -> Vec<Vec<Movement>> {
+    use Movement::*;
+    let tilts = [vec![], vec![X], vec![X2], vec![XP], vec![Z], vec![ZP]];
+    let turns = [vec![], vec![Y], vec![Y2], vec![YP]];
+    tilts.iter().flat_map(|tilt| turns.iter().map(move |turn| [tilt.clone(), turn.clone()].concat())).collect()
+}
+
+// The case an alg solves as a single state, the smallest of the ones `same_case` allows for, so
+// many cases can be matched up through a map instead of comparing every pair
+pub fn case_key(alg: &[Movement], size: usize) -> CubeState {
+    let inverse: Vec<Movement> = alg.iter().rev().map(Movement::inv).collect();
+    let orientations = orientations();
+    let mut key: Option<CubeState> = None;
+    for before in 0..4 {
+        for after in 0..4 {
+            let mut state = CubeState::solved();
+            state.apply(&vec![Movement::U; before]);
+            state.apply(&inverse);
+            state.apply(&vec![Movement::U; after]);
+            for rotation in orientations.iter() {
+                let mut rotated = state.clone();
+                rotated.apply(rotation);
+                let rotated = rotated.for_size(size);
+                if key.as_ref().is_none_or(|key| rotated < *key) {
+                    key = Some(rotated);
+                }
+            }
+        }
+    }
+    key.unwrap()
+}
+
+// Whether two algs solve the same case on a cube this size, up to U turns before and after them and
+// the way the cube is held
+pub fn same_case(a: &[Movement], b: &[Movement], size: usize) -> bool {
+    let case = |alg: &[Movement], before: usize, after: usize| {
+        let mut state = CubeState::solved();
+        state.apply(&vec![Movement::U; before]);
+        state.apply(&alg.iter().rev().map(Movement::inv).collect::<Vec<_>>());
+        state.apply(&vec![Movement::U; after]);
+        state
+    };
+
+    let target = case(a, 0, 0).for_size(size);
+    let orientations = orientations();
+    (0..4).any(|before| (0..4).any(|after| {
+        let state = case(b, before, after);
+        orientations.iter().any(|rotation| {
+            let mut rotated = state.clone();
+            rotated.apply(rotation);
+            rotated.for_size(size) == target
+        })
+    }))
+}
+
+// Whether two states are the same cube held another way
+pub fn same_state(a: &CubeState, b: &CubeState) -> bool {
+    orientations().iter().any(|rotation| {
+        let mut rotated = a.clone();
+        rotated.apply(rotation);
+        rotated == *b
+    })
+}
+
+// Moves with slices and wide moves made the face turns and rotation that do the same, like M as
+// R L' x', the way a cube that only tells its faces apart sees them
+fn by_faces(movements: &[Movement]) -> Vec<Movement> {
+    let mut result = Vec::new();
+    for movement in movements {
+        let Some((axis, layers, turns)) = turn(movement).filter(|(_, layers, _)| !matches!(layers, Layers::Outer | Layers::All)) else {
+            result.push(movement.clone());
+            continue;
+        };
+        let opposite = axis.map(|coord| -coord);
+        if layers.contains(0) {
+            // The whole cube turned, with the outer layers the move leaves out turned back
+            if !layers.contains(1) {
+                result.push(named_move(axis, Layers::Outer, 4 - turns));
+            }
+            if !layers.contains(-1) {
+                result.push(named_move(opposite, Layers::Outer, turns));
+            }
+            result.push(named_move(axis, Layers::All, turns));
+        } else {
+            if layers.contains(1) {
+                result.push(named_move(axis, Layers::Outer, turns));
+            }
+            if layers.contains(-1) {
+                result.push(named_move(opposite, Layers::Outer, 4 - turns));
+            }
+        }
+    }
+    result
+}
+
+// Turns split into runs around one axis, whose order doesn't matter, with each layer's turns
+// in a run added up and the ones that cancel out gone
+fn parallel_runs(movements: &[Movement]) -> Vec<Vec<(Vec3, Layers, u8)>> {
+    let mut runs: Vec<Vec<(Vec3, Layers, u8)>> = Vec::new();
+    for (axis, layers, turns) in movements.iter().filter_map(turn) {
+        let parallel = |other: &Vec3| *other == axis || *other == axis.map(|coord| -coord);
+        let Some(run) = runs.last_mut().filter(|run| run.iter().all(|(other, ..)| parallel(other))) else {
+            runs.push(vec![(axis, layers, turns)]);
+            continue;
+        };
+        match run.iter().position(|(other, other_layers, _)| *other == axis && *other_layers == layers) {
+            Some(idx) => {
+                run[idx].2 = (run[idx].2 + turns) % 4;
+                if run[idx].2 == 0 {
+                    run.remove(idx);
+                }
+            }
+            None => run.push((axis, layers, turns)),
+        }
+        if run.is_empty() {
+            runs.pop();
+        }
+    }
+    runs
+}
+
+// Whether turns done on a cube, like a smart cube reports them, follow an alg: however the cube was
+// held, with the alg's slices and wide moves made face turns and its rotations done away with,
+// turns of opposite faces next to each other taken in any order and U turns at either end left out
+pub fn same_turns(alg: &[Movement], turned: &[Movement]) -> bool {
+    let without_auf = |moves: &[Movement]| {
+        let mut runs = parallel_runs(moves);
+        let is_auf = |(axis, layers, _): &(Vec3, Layers, u8)| *axis == Face::U.normal() && *layers == Layers::Outer;
+        if let Some(last) = runs.last_mut() {
+            last.retain(|turn| !is_auf(turn));
+            if last.is_empty() {
+                runs.pop();
+            }
+        }
+        if let Some(first) = runs.first_mut() {
+            first.retain(|turn| !is_auf(turn));
+            if first.is_empty() {
+                runs.remove(0);
+            }
+        }
+        runs.into_iter()
+            .flat_map(|run| {
+                let mut names: Vec<String> = run.into_iter().map(|(axis, layers, turns)| named_move(axis, layers, turns).as_text()).collect();
+                names.sort();
+                names
+            })
+            .collect::<Vec<_>>()
+    };
+    let alg = without_auf(&without_rotations(&by_faces(alg)));
+    orientations().iter().any(|rotation| without_auf(&rotate_moves(turned, rotation)) == alg)
+}
+
+// Moves that solve a scrambled cube of this size with one of a case's algs, the way the trainer plays
+// them for a new case: the alg renamed for however the scramble left the cube held, with the U turns
+// before and after it that the scramble's AUFs call for. When no alg fits, like for a generated
+// scramble, the scramble undone
+pub fn solving_moves(scramble: &[Movement], algs: &[Vec<Movement>], size: usize) -> Vec<Movement> {
+    let mut scrambled = CubeState::solved();
+    scrambled.apply(scramble);
+    let solved = |state: &CubeState| if size == 2 { state.is_solved_2x2() } else { state.is_solved() };
+    let orientations = orientations();
+    for alg in algs {
+        let alg = without_rotations(alg);
+        // The alg as it's written first, with the fewest AUFs
+        for rotation in orientations.iter() {
+            for (before, after) in (0..4).flat_map(|before| (0..4).map(move |after| (before, after))) {
+                let moves = rotate_moves(&[vec![Movement::U; before], alg.clone(), vec![Movement::U; after]].concat(), rotation);
+                let mut state = scrambled.clone();
+                state.apply(&moves);
+                if solved(&state) {
+                    return cancel_moves(&moves);
+                }
+            }
+        }
+    }
+    let inverse: Vec<Movement> = scramble.iter().rev().map(Movement::inv).collect();
+    cancel_moves(&without_rotations(&inverse))
+}
+
+// Whether a state is a U turn away from solved, like a solve that got everything but the AUF
+pub fn off_by_u(state: &CubeState) -> bool {
+    !state.is_solved() && [Movement::U, Movement::U2, Movement::UP].iter().any(|movement| {
+        let mut turned = state.clone();
+        turned.apply(std::slice::from_ref(movement));
+        turned.is_solved()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{AlgSet, moves_text};
+
+    fn cancel(text: &str) -> String {
+        moves_text(&cancel_moves(&AlgSet::parse_scramble(text).unwrap()))
+    }
+
+    #[test]
+    fn trimmed_rotations() {
+        let trim = |text: &str| moves_text(&trim_rotations(&AlgSet::parse_scramble(text).unwrap()));
+        assert_eq!(trim("x R y"), "R");
+        assert_eq!(trim("y R U"), "B U");
+        assert_eq!(trim("R U x"), "R U");
+        assert_eq!(trim("x y2 z'"), "");
+        // Rotations in the middle stay
+        assert_eq!(trim("R x U"), "R x U");
+        // The cube ends up the same, only held another way
+        for text in ["x R y", "y R U", "z' R U R' x2 y", "y2 M' U2 M"] {
+            let (mut a, mut b) = (CubeState::solved(), CubeState::solved());
+            a.apply(&AlgSet::parse_scramble(text).unwrap());
+            b.apply(&trim_rotations(&AlgSet::parse_scramble(text).unwrap()));
+            assert!(same_state(&a, &b), "{}", text);
+        }
+    }
+
+    #[test]
+    fn cancelling_moves() {
+        assert_eq!(cancel("R U U' R'"), "");
+        assert_eq!(cancel("R R2"), "R'");
+        assert_eq!(cancel("U2 U2 F"), "F");
+        assert_eq!(cancel("R U R' U'"), "R U R' U'");
+        // Only moves right next to each other are merged
+        assert_eq!(cancel("R L R'"), "R L R'");
+        assert_eq!(cancel("r r"), "r2");
+        // Rotations are kept and keep moves on either side apart
+        assert_eq!(cancel("R x R'"), "R x R'");
+    }
+
+    #[test]
+    fn cancelling_keeps_the_state() {
+        let moves = AlgSet::parse_scramble("U R R2 U' y U2 F F' D U D' x M M").unwrap();
+        let mut state = CubeState::solved();
+        state.apply(&moves);
+        let mut cancelled = CubeState::solved();
+        cancelled.apply(&cancel_moves(&moves));
+        assert!(state == cancelled);
+    }
+
+    fn solve(scramble: &str, algs: &[&str], size: usize) -> String {
+        let scramble = AlgSet::parse_scramble(scramble).unwrap();
+        let algs: Vec<Vec<Movement>> = algs.iter().map(|alg| AlgSet::parse_scramble(alg).unwrap()).collect();
+        let moves = solving_moves(&scramble, &algs, size);
+        let mut state = CubeState::solved();
+        state.apply(&scramble);
+        state.apply(&moves);
+        assert!(if size == 2 { state.is_solved_2x2() } else { state.is_solved() });
+        moves_text(&moves)
+    }
+
+    #[test]
+    fn solving_with_the_alg() {
+        // Sune, as its inverse scrambles it
+        let sune = "R U R' U R U2 R'";
+        assert_eq!(solve("R U2 R' U' R U' R'", &[sune], 3), sune);
+        // With AUFs before and after
+        assert_eq!(solve("U R U2 R' U' R U' R' U2", &[sune], 3), "U2 R U R' U R U2 R' U'");
+        // Set up from the side, the last layer only needs AUFs to be the alg's case
+        assert_eq!(solve("F U2 F' U' F U' F'", &[sune], 3), "U' R U R' U R U2 R' U");
+        // A case off the U face gets the alg renamed
+        assert_eq!(solve("R F2 R' F' R F' R'", &[sune], 3), "R F R' F R F2 R'");
+        // The second alg when the first is for another case
+        assert_eq!(solve("R U2 R' U' R U' R'", &["R U R' U'", sune], 3), sune);
+    }
+
+    #[test]
+    fn solving_without_an_alg() {
+        // A scramble no alg fits is undone
+        assert_eq!(solve("R U F", &["R U R' U'"], 3), "F' U' R'");
+        // Rotations are left out and the moves after them renamed
+        let moves = solve("R y U F", &[], 3);
+        assert_eq!(moves.split(' ').count(), 3);
+        assert!(!moves.contains('y'));
+    }
+
+    #[test]
+    fn solving_2x2() {
+        // Turning both sides of a 2x2 turns the whole cube, so the alg is found from another side
+        let moves = solve("U R U' R' R L'", &["R U R' U'"], 2);
+        assert_eq!(moves.split(' ').count(), 4);
+    }
+
+    #[test]
+    fn cases_on_a_2x2() {
+        let parse = |text: &str| AlgSet::parse_scramble(text).unwrap();
+        let sune = parse("R U R' U R U2 R'");
+        // Only moves edges, which a 2x2 doesn't have
+        let edges = parse("R2 U2 R2 U2 R2 U2");
+        let alt = [edges.clone(), sune.clone()].concat();
+        assert!(same_case(&sune, &alt, 2));
+        assert!(!same_case(&sune, &alt, 3));
+        assert_eq!(case_key(&sune, 2), case_key(&alt, 2));
+        assert_ne!(case_key(&sune, 3), case_key(&alt, 3));
+        assert!(!same_case(&sune, &parse("R U R' U'"), 2));
+
+        assert_eq!(check_alg(&edges, None, 2), Some("does nothing to a solved cube".to_string()));
+        assert_eq!(check_alg(&alt, Some(Mask::OLL), 2), None);
+        assert!(check_alg(&alt, Some(Mask::OLL), 3).is_some());
+        assert!(check_alg(&parse("R U R' F"), Some(Mask::OLL), 2).is_some());
+    }
+
+    #[test]
+    fn turns_on_a_smart_cube() {
+        let parse = |text: &str| AlgSet::parse_scramble(text).unwrap();
+        let sune = parse("R U R' U R U2 R'");
+        assert!(same_turns(&sune, &parse("R U R' U R U2 R'")));
+        // Held another way, with an AUF after it
+        assert!(same_turns(&sune, &parse("F U F' U F U2 F' U")));
+        assert!(!same_turns(&sune, &parse("R U R' U R U R'")));
+
+        // The U perm's slices come from the cube as the faces on either side, the cube held a turn further
+        let u_perm = parse("M2 U M U2 M' U M2");
+        let turned = parse("R2 L2 D R L' F2 R' L D R2 L2");
+        let mut alg = CubeState::solved();
+        alg.apply(&u_perm);
+        let mut cube = CubeState::solved();
+        cube.apply(&turned);
+        assert!(same_state(&alg, &cube));
+        assert!(same_turns(&u_perm, &turned));
+        // However the cube reported two faces turned at once
+        assert!(same_turns(&u_perm, &parse("L2 R2 D L' R F2 R' L D R2 L2")));
+        assert!(!same_turns(&u_perm, &parse("R2 L2 D R L' F2 R' L U R2 L2")));
+
+        // Wide moves the same way
+        assert!(same_turns(&parse("r U R' U' r' F R F'"), &parse("L F R' F' L' F R F'")));
+    }
+}
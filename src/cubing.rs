@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::{twelfths, AlgSet, Movement, Notation, RubiksError, MAX_REPEAT};
+
+// The Alg JSON structure of cubing.js, so algs and scrambles can be passed to tools built on it:
+// {"type": "alg", "nodes": [{"type": "move", "family": "R", "amount": -1}, ...]}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "alg")]
+pub struct AlgJson {
+    pub nodes: Vec<NodeJson>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NodeJson {
+    Move {
+        family: String,
+        amount: i32,
+        #[serde(rename = "innerLayer", default, skip_serializing_if = "Option::is_none")]
+        inner_layer: Option<u32>,
+        #[serde(rename = "outerLayer", default, skip_serializing_if = "Option::is_none")]
+        outer_layer: Option<u32>,
+    },
+    // Written only for Square-1 turns, "(1,-1)" being a grouping of the moves U_SQ_ 1 and D_SQ_ -1
+    Grouping {
+        alg: AlgJson,
+        amount: i32,
+    },
+    Commutator {
+        #[serde(rename = "A")]
+        a: AlgJson,
+        #[serde(rename = "B")]
+        b: AlgJson,
+    },
+    Conjugate {
+        #[serde(rename = "A")]
+        a: AlgJson,
+        #[serde(rename = "B")]
+        b: AlgJson,
+    },
+    Pause,
+    Newline,
+    LineComment {
+        comment: String,
+    },
+}
+
+fn inverse(movements: &[Movement]) -> Vec<Movement> {
+    movements.iter().rev().map(Movement::inv).collect()
+}
+
+// Square-1 moves as cubing.js names them: the layers turned in twelfths and the slash
+const SQUARE1_TOP: &str = "U_SQ_";
+const SQUARE1_BOTTOM: &str = "D_SQ_";
+const SQUARE1_SLASH: &str = "_SLASH_";
+
+fn simple_move(family: &str, amount: i32) -> NodeJson {
+    NodeJson::Move { family: family.to_string(), amount, inner_layer: None, outer_layer: None }
+}
+
+// A grouping of Square-1 layer turns is a single move, None when the grouping is anything else
+fn square1_turn(alg: &AlgJson, amount: i32) -> Option<Movement> {
+    let (mut top, mut bottom) = (0, 0);
+    for node in alg.nodes.iter() {
+        match node {
+            NodeJson::Move { family, amount, .. } if family == SQUARE1_TOP => top += amount,
+            NodeJson::Move { family, amount, .. } if family == SQUARE1_BOTTOM => bottom += amount,
+            _ => return None,
+        }
+    }
+    Some(Movement::Square1Turn { top: twelfths(top.saturating_mul(amount)), bottom: twelfths(bottom.saturating_mul(amount)) })
+}
+
+// A grouping can't be repeated more often than one in an alg file
+fn repeat(movements: Vec<Movement>, amount: i32) -> Result<Vec<Movement>, RubiksError> {
+    let times = amount.unsigned_abs() as usize;
+    if times > MAX_REPEAT {
+        return Err(RubiksError::InvalidMovement(format!("grouping repeated {} times", amount)));
+    }
+    let movements = if amount < 0 { inverse(&movements) } else { movements };
+    Ok(movements.iter().cycle().take(movements.len() * times).cloned().collect())
+}
+
+impl AlgJson {
+    pub fn from_moves(movements: &[Movement]) -> AlgJson {
+        let nodes = movements.iter()
+            .map(|movement| {
+                match movement {
+                    Movement::Square1Turn { top, bottom } => {
+                        let alg = AlgJson { nodes: vec![simple_move(SQUARE1_TOP, i32::from(*top)), simple_move(SQUARE1_BOTTOM, i32::from(*bottom))] };
+                        return NodeJson::Grouping { alg, amount: 1 };
+                    }
+                    Movement::Square1Slash => return simple_move(SQUARE1_SLASH, 1),
+                    _ => {}
+                }
+                let text = movement.as_text();
+                // Big cube moves have their layers in front, "2-3Rw" turns layers 2 (outer) to 3 (inner)
+                let (layers, text) = text.split_at(text.find(|c: char| !c.is_ascii_digit() && c != '-').unwrap_or(0));
+                let (outer_layer, inner_layer) = match layers.split_once('-') {
+                    Some((outer, inner)) => (outer.parse().ok(), inner.parse().ok()),
+                    None => (None, layers.parse().ok()),
+                };
+                // The amount ends the move, like the "2'" of a megaminx "BR2'"
+                let (text, sign) = match text.strip_suffix('\'') {
+                    Some(text) => (text, -1),
+                    None => (text, 1),
+                };
+                let family = text.trim_end_matches(|c: char| c.is_ascii_digit());
+                let amount = sign * text[family.len()..].parse().unwrap_or(1);
+                NodeJson::Move { family: family.to_string(), amount, inner_layer, outer_layer }
+            })
+            .collect();
+        AlgJson { nodes }
+    }
+
+    // Scrambles are stored as text
+    pub fn from_text(text: &str, notation: &dyn Notation) -> Result<AlgJson, RubiksError> {
+        Ok(AlgJson::from_moves(&AlgSet::parse_moves(text, notation)?))
+    }
+
+    // Groupings, commutators and conjugates are expanded
+    pub fn moves(&self) -> Result<Vec<Movement>, RubiksError> {
+        let mut movements = Vec::new();
+        for node in self.nodes.iter() {
+            match node {
+                NodeJson::Move { family, amount, .. } if family == SQUARE1_SLASH => {
+                    if amount.rem_euclid(2) == 1 {
+                        movements.push(Movement::Square1Slash);
+                    }
+                }
+                NodeJson::Move { family, amount, .. } if family == SQUARE1_TOP || family == SQUARE1_BOTTOM => {
+                    let (top, bottom) = if family == SQUARE1_TOP { (*amount, 0) } else { (0, *amount) };
+                    let turn = Movement::Square1Turn { top: twelfths(top), bottom: twelfths(bottom) };
+                    if turn != (Movement::Square1Turn { top: 0, bottom: 0 }) {
+                        movements.push(turn);
+                    }
+                }
+                NodeJson::Move { family, amount, inner_layer, outer_layer } => {
+                    let suffix = match amount.rem_euclid(4) {
+                        0 => continue,
+                        1 => "",
+                        2 => "2",
+                        _ => "'",
+                    };
+                    let layers = match (outer_layer, inner_layer) {
+                        (None, None) => String::new(),
+                        (None, Some(inner)) => inner.to_string(),
+                        (Some(outer), Some(inner)) => format!("{}-{}", outer, inner),
+                        (Some(_), None) => return Err(RubiksError::InvalidMovement(family.clone())),
+                    };
+                    let text = format!("{}{}{}", layers, family, suffix);
+                    movements.push(Movement::from_text(&text).ok_or(RubiksError::InvalidMovement(text))?);
+                }
+                NodeJson::Grouping { alg, amount } => match square1_turn(alg, *amount) {
+                    Some(Movement::Square1Turn { top: 0, bottom: 0 }) => {}
+                    Some(turn) => movements.push(turn),
+                    None => movements.extend(repeat(alg.moves()?, *amount)?),
+                },
+                NodeJson::Commutator { a, b } => {
+                    let (a, b) = (a.moves()?, b.moves()?);
+                    movements.extend([a.clone(), b.clone(), inverse(&a), inverse(&b)].concat());
+                }
+                NodeJson::Conjugate { a, b } => {
+                    let a = a.moves()?;
+                    movements.extend([a.clone(), b.moves()?, inverse(&a)].concat());
+                }
+                NodeJson::Pause | NodeJson::Newline | NodeJson::LineComment { .. } => {}
+            }
+        }
+        Ok(movements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{moves_text, CubeNotation, Puzzle};
+
+    fn round_trip(text: &str, notation: &dyn Notation) -> String {
+        let json = serde_json::to_string(&AlgJson::from_text(text, notation).unwrap()).unwrap();
+        let alg: AlgJson = serde_json::from_str(&json).unwrap();
+        moves_text(&alg.moves().unwrap())
+    }
+
+    #[test]
+    fn moves_as_json() {
+        let json = serde_json::to_value(AlgJson::from_text("R U' x2 3Rw 2-3Lw'", &CubeNotation).unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!({"type": "alg", "nodes": [
+            {"type": "move", "family": "R", "amount": 1},
+            {"type": "move", "family": "U", "amount": -1},
+            {"type": "move", "family": "x", "amount": 2},
+            {"type": "move", "family": "Rw", "amount": 1, "innerLayer": 3},
+            {"type": "move", "family": "Lw", "amount": -1, "innerLayer": 3, "outerLayer": 2},
+        ]}));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        for text in ["R U R' U R U2 R'", "M2 U M U2 M' U M2", "r U R' U' r' F R F'", "x y' z2 E S'", "3Rw 2-3Lw' 2R2"] {
+            assert_eq!(round_trip(text, &CubeNotation), text);
+        }
+    }
+
+    #[test]
+    fn groupings() {
+        let alg: AlgJson = serde_json::from_str(r#"{"type": "alg", "nodes": [
+            {"type": "grouping", "amount": -2, "alg": {"type": "alg", "nodes": [{"type": "move", "family": "R", "amount": 1}, {"type": "move", "family": "U", "amount": 1}]}},
+            {"type": "pause"},
+            {"type": "commutator", "A": {"type": "alg", "nodes": [{"type": "move", "family": "R", "amount": 1}]}, "B": {"type": "alg", "nodes": [{"type": "move", "family": "U", "amount": 1}]}},
+            {"type": "conjugate", "A": {"type": "alg", "nodes": [{"type": "move", "family": "F", "amount": 1}]}, "B": {"type": "alg", "nodes": [{"type": "move", "family": "U", "amount": 4}]}}
+        ]}"#).unwrap();
+        assert_eq!(moves_text(&alg.moves().unwrap()), "U' R' U' R' R U R' U' F F'");
+    }
+
+    #[test]
+    fn square1_as_json() {
+        let notation = Puzzle::Square1.notation();
+        let json = serde_json::to_value(AlgJson::from_text("(1,0) / (-1,6)", notation).unwrap()).unwrap();
+        let tuple = |top: i32, bottom: i32| serde_json::json!({"type": "grouping", "amount": 1, "alg": {"type": "alg", "nodes": [
+            {"type": "move", "family": "U_SQ_", "amount": top},
+            {"type": "move", "family": "D_SQ_", "amount": bottom},
+        ]}});
+        assert_eq!(json, serde_json::json!({"type": "alg", "nodes": [
+            tuple(1, 0),
+            {"type": "move", "family": "_SLASH_", "amount": 1},
+            tuple(-1, 6),
+        ]}));
+        assert_eq!(round_trip("/ (3,0) / (1,0) / (0,-3) /", notation), "/ (3,0) / (1,0) / (0,-3) /");
+
+        // Layer turns written on their own, and a slash done twice is none
+        let alg: AlgJson = serde_json::from_str(r#"{"type": "alg", "nodes": [
+            {"type": "move", "family": "U_SQ_", "amount": 13},
+            {"type": "move", "family": "_SLASH_", "amount": 2},
+            {"type": "move", "family": "D_SQ_", "amount": -2},
+            {"type": "move", "family": "_SLASH_", "amount": -1}
+        ]}"#).unwrap();
+        assert_eq!(moves_text(&alg.moves().unwrap()), "(1,0) (0,-2) /");
+    }
+
+    #[test]
+    fn huge_groupings() {
+        // Repeated no more often than an alg file allows, rather than filling the memory
+        let grouping = |amount: i32| AlgJson { nodes: vec![NodeJson::Grouping {
+            alg: AlgJson { nodes: vec![NodeJson::Move { family: "R".to_string(), amount: 1, inner_layer: None, outer_layer: None }] },
+            amount,
+        }] };
+        assert_eq!(grouping(99).moves().unwrap().len(), 99);
+        assert_eq!(grouping(-99).moves().unwrap().len(), 99);
+        assert!(grouping(100).moves().is_err());
+        assert!(grouping(2_000_000_000).moves().is_err());
+        assert!(grouping(i32::MIN).moves().is_err());
+        let alg: Result<AlgJson, _> = serde_json::from_str(r#"{"type": "alg", "nodes": [{"type": "grouping", "amount": 2000000000, "alg": {"type": "alg", "nodes": [{"type": "move", "family": "R", "amount": 1}]}}]}"#);
+        assert!(alg.unwrap().moves().is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::{fs, io, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+
+use chrono::{DateTime, Local};
+use ratatui::{
+    crossterm::event::{self, Event, KeyEventKind}, layout::{Constraint, Layout}, style::Stylize, text::Line, widgets::{Block, Paragraph}, DefaultTerminal, Frame
+};
+
+use crate::config::Config;
+use crate::db::AlgDB;
+use crate::history::History;
+use crate::live::LiveClient;
+use crate::lock::InstanceLock;
+use crate::session::Solve;
+use crate::srs::Scheduler;
+use crate::stats::{average_of, best, mean};
+use crate::status::Status;
+use crate::timer::{big_text, format_result, format_time};
+
+const RECENT: usize = 12;
+
+// Live stats of the trainer running in another terminal, for a second monitor or a stream
+// overlay. The case and timer come from the trainer's live socket, found through its lock file,
+// and the stats from the history and scheduler it saves
+pub struct Dashboard<'a> {
+    path: &'a Path,
+    lock_path: Option<PathBuf>,
+    db: &'a AlgDB,
+    config: &'a Config,
+    // A break this long between solves starts a new session
+    gap: Duration,
+    history: History,
+    srs: Scheduler,
+    // When the history and scheduler files were last read
+    modified: Option<SystemTime>,
+    srs_modified: Option<SystemTime>,
+    error: Option<String>,
+    live: Option<LiveClient>,
+}
+
+// When the file at `path` was changed, if that's since `modified`
+fn changed(path: &Path, modified: Option<SystemTime>) -> Option<Option<SystemTime>> {
+    let now = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    (now.is_none() || now != modified).then_some(now)
+}
+
+fn format_average(average: Option<Option<Duration>>) -> String {
+    match average {
+        None => "-".to_string(),
+        Some(None) => "DNF".to_string(),
+        Some(Some(time)) => format_time(time),
+    }
+}
+
+impl<'a> Dashboard<'a> {
+    pub fn new(path: &'a Path, lock_path: Option<PathBuf>, db: &'a AlgDB, config: &'a Config, gap: Duration) -> Dashboard<'a> {
+        Dashboard {
+            path,
+            lock_path,
+            db,
+            config,
+            gap,
+            history: History::default(),
+            srs: Scheduler::default(),
+            modified: None,
+            srs_modified: None,
+            error: None,
+            live: None,
+        }
+    }
+
+    // Reads the history and the scheduler again when their files changed, and follows the
+    // trainer's live state while it runs
+    fn refresh(&mut self) {
+        if let Some(modified) = changed(self.path, self.modified) {
+            match History::load(self.path) {
+                Ok(history) => {
+                    self.history = history;
+                    self.modified = modified;
+                    self.error = None;
+                }
+                // The trainer may be writing it, the next refresh tries again
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+        if let Some(path) = Scheduler::default_path()
+            && let Some(modified) = changed(&path, self.srs_modified)
+            && let Ok(srs) = Scheduler::load(&path)
+        {
+            self.srs = srs;
+            self.srs_modified = modified;
+        }
+
+        if self.live.as_mut().is_some_and(|live| !live.update()) {
+            self.live = None;
+        }
+        if self.live.is_none() {
+            let port = self.lock_path.as_deref().and_then(InstanceLock::holder).and_then(|owner| owner.port);
+            self.live = port.and_then(|port| LiveClient::connect(port).ok());
+        }
+    }
+
+    pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            self.refresh();
+            terminal.draw(|frame| self.draw(frame))?;
+            // A running timer is counted up on screen
+            let running = self.live.as_ref().is_some_and(|live| live.running().is_some());
+            if !event::poll(Duration::from_millis(if running { 50 } else { 1000 }))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? && key.kind == KeyEventKind::Press {
+                let keys = &self.config.keys;
+                if keys.quit.matches(key.code) || keys.back.matches(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn case_name(&self, solve: &Solve) -> String {
+        let alg = self.db.find(&solve.algset).and_then(|algset| algset.algs.get(solve.alg));
+        match alg.and_then(|alg| alg.name.clone()) {
+            Some(name) => format!("{} · {}", solve.algset, name),
+            None => format!("{} case {}", solve.algset, solve.alg + 1),
+        }
+    }
+
+    // The solves since the last long break
+    fn session(&self) -> &[Solve] {
+        let solves = &self.history.solves;
+        let start = (1..solves.len()).rev()
+            .find(|idx| solves[*idx].date.duration_since(solves[idx - 1].date).is_ok_and(|gap| gap > self.gap))
+            .unwrap_or(0);
+        let session = &solves[start..];
+        // A session that ended a while ago isn't live anymore
+        match session.last() {
+            Some(last) if last.date.elapsed().is_ok_and(|since| since > self.gap) => &[],
+            _ => session,
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let session = self.session();
+        let results: Vec<Option<Duration>> = session.iter().map(Solve::result).collect();
+        let status = Status::new(&self.history, &self.srs, self.config.history.day_start_hour);
+
+        let live = self.live.as_ref();
+        let title = match (&self.error, self.modified) {
+            (Some(err), _) => format!(" Dashboard · {} ", err),
+            (None, _) if live.is_some() => " Dashboard · live ".to_string(),
+            (None, Some(modified)) => format!(" Dashboard · updated {} ", DateTime::<Local>::from(modified).format("%H:%M:%S")),
+            (None, None) => " Dashboard · no solves yet ".to_string(),
+        };
+        let block = Block::bordered().title(title).title_bottom(format!(" {}: quit ", self.config.keys.quit.label()));
+        let area = block.inner(frame.area());
+        frame.render_widget(block, frame.area());
+
+        let [last_area, stats_area, recent_area] = Layout::vertical([
+            Constraint::Length(7),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+
+        let case = live.and_then(|live| live.live.case.clone());
+        if let Some(running) = live.and_then(LiveClient::running) {
+            let time = big_text(&format_time(running));
+            let now = Paragraph::new(format!("{}\n\n{}", time, case.clone().unwrap_or_default())).centered();
+            frame.render_widget(now, last_area);
+        } else if let Some(solve) = session.last() {
+            let time = big_text(&format_result(solve.time, solve.penalty));
+            let last = Paragraph::new(format!("{}\n\n{}", time, self.case_name(solve))).centered();
+            frame.render_widget(last, last_area);
+        } else {
+            frame.render_widget(Paragraph::new("Waiting for the next session").centered().dim(), last_area);
+        }
+
+        let mut stats = vec![
+            Line::from(format!("Session: {} solves  mean {}  best {}  ao5 {}  ao12 {}",
+                session.len(),
+                mean(&results).map(format_time).unwrap_or("-".to_string()),
+                best(&results).map(format_time).unwrap_or("-".to_string()),
+                format_average(average_of(&results, 5)),
+                format_average(average_of(&results, 12)))),
+            Line::from(status.text),
+        ];
+        if let Some(case) = case {
+            stats.push(Line::from(format!("Training: {}", case)).bold());
+        }
+        frame.render_widget(Paragraph::new(stats).centered(), stats_area);
+
+        let recent: Vec<Line> = session.iter().rev().take(RECENT)
+            .map(|solve| Line::from(format!("{:>8}  {}", format_result(solve.time, solve.penalty), self.case_name(solve))))
+            .collect();
+        frame.render_widget(Paragraph::new(recent).block(Block::new().title(" Last solves ")), recent_area);
+    }
+}
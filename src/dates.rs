@@ -0,0 +1,48 @@
+use std::{collections::BTreeSet, time::SystemTime};
+
+use chrono::{DateTime, Local, Locale, NaiveDate, TimeDelta};
+
+// Dates are stored in UTC and only turned into local time to be shown or grouped by day
+
+// The local day a moment counts towards. Days start at `day_start_hour` instead of midnight,
+// so a late night session still counts for the day it started on
+pub fn day_of(date: SystemTime, day_start_hour: u32) -> NaiveDate {
+    let local: DateTime<Local> = date.into();
+    (local - TimeDelta::hours(day_start_hour as i64)).date_naive()
+}
+
+// Not in the browser, which has no clock to read
+#[cfg(not(target_arch = "wasm32"))]
+pub fn today(day_start_hour: u32) -> NaiveDate {
+    day_of(SystemTime::now(), day_start_hour)
+}
+
+// The configured locale, else the one from the environment, else en_US
+pub fn locale(setting: Option<&str>) -> Locale {
+    setting.map(str::to_string)
+        .or(std::env::var("LC_ALL").ok())
+        .or(std::env::var("LC_TIME").ok())
+        .or(std::env::var("LANG").ok())
+        .and_then(|name| {
+            // "de_DE.UTF-8" -> "de_DE"
+            let name = name.split(['.', '@']).next().unwrap_or_default();
+            Locale::try_from(name).ok()
+        })
+        .unwrap_or(Locale::en_US)
+}
+
+pub fn format_date(date: NaiveDate, locale: Locale) -> String {
+    date.format_localized("%x", locale).to_string()
+}
+
+// Days in a row with solves, up to today. A streak that reached yesterday still counts
+// since today's practice may not have happened yet
+pub fn streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = if days.contains(&today) { today } else { today - TimeDelta::days(1) };
+    let mut count = 0;
+    while days.contains(&day) {
+        count += 1;
+        day -= TimeDelta::days(1);
+    }
+    count
+}
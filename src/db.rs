@@ -1,31 +1,211 @@
-use rand::{prelude::IndexedRandom, rng};
-use std::{fs::{self, ReadDir}, path::PathBuf};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, io, path::{Path, PathBuf}};
 
-#[derive(Debug, Clone)]
+use crate::cube::Face;
+use crate::markdown;
+
+// Pieces of an alg's text: moves, the parentheses grouping them and the brackets, commas and
+// colons of commutators and conjugates. Closing ones come with what follows them, like the "3"
+// of "(R U R' U')3"
+#[derive(Debug)]
+enum AlgToken {
+    Move(String),
+    Open,
+    Close(String),
+    OpenBracket,
+    CloseBracket(String),
+    Comma,
+    Colon,
+}
+
+impl AlgToken {
+    fn text(&self) -> String {
+        match self {
+            AlgToken::Move(text) => text.clone(),
+            AlgToken::Open => "(".to_string(),
+            AlgToken::Close(suffix) => format!("){}", suffix),
+            AlgToken::OpenBracket => "[".to_string(),
+            AlgToken::CloseBracket(suffix) => format!("]{}", suffix),
+            AlgToken::Comma => ",".to_string(),
+            AlgToken::Colon => ":".to_string(),
+        }
+    }
+}
+
+fn alg_tokens(text: &str) -> Vec<AlgToken> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if ("()[],:".contains(c) || c.is_whitespace()) && !word.is_empty() {
+            tokens.push(AlgToken::Move(std::mem::take(&mut word)));
+        }
+        let mut suffix = || {
+            let mut suffix = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '\'' | '’' | '′')) {
+                suffix.push(c);
+            }
+            suffix
+        };
+        match c {
+            '(' => tokens.push(AlgToken::Open),
+            ')' => tokens.push(AlgToken::Close(suffix())),
+            '[' => tokens.push(AlgToken::OpenBracket),
+            ']' => tokens.push(AlgToken::CloseBracket(suffix())),
+            ',' => tokens.push(AlgToken::Comma),
+            ':' => tokens.push(AlgToken::Colon),
+            c if c.is_whitespace() => {}
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(AlgToken::Move(word));
+    }
+    tokens
+}
+
+fn inverse(moves: &[Movement]) -> Vec<Movement> {
+    moves.iter().rev().map(Movement::inv).collect()
+}
+
+fn unexpected(token: Option<&AlgToken>, open: &str, close: &str) -> RubiksError {
+    match token {
+        None => RubiksError::InvalidMovement(format!("{} without {}", open, close)),
+        Some(token) => RubiksError::InvalidMovement(format!("stray {}", token.text())),
+    }
+}
+
+// The moves from `pos` up to the end or the token ending the part of a group they're in, which is
+// returned too. Groups in parentheses and brackets inside are expanded: "[A, B]" is A B A' B' and
+// "[A: B]" is A B A'. `depth` is how many groups the moves are in
+fn parse_sequence<'t>(tokens: &'t [AlgToken], pos: &mut usize, notation: &dyn Notation, depth: usize) -> Result<(Vec<Movement>, Option<&'t AlgToken>), RubiksError> {
+    let mut moves = Vec::new();
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        if matches!(token, AlgToken::Open | AlgToken::OpenBracket) && depth >= MAX_DEPTH {
+            return Err(RubiksError::InvalidMovement(format!("groups nested more than {} deep", MAX_DEPTH)));
+        }
+        match token {
+            AlgToken::Move(text) => moves.push(notation.parse(text).ok_or_else(|| RubiksError::InvalidMovement(text.clone()))?),
+            AlgToken::Open => match parse_sequence(tokens, pos, notation, depth + 1)? {
+                (group, Some(AlgToken::Close(suffix))) => moves.extend(repeated(group, suffix)?),
+                (_, end) => return Err(unexpected(end, "(", ")")),
+            },
+            AlgToken::OpenBracket => {
+                let (first, separator) = parse_sequence(tokens, pos, notation, depth + 1)?;
+                let commutator = match separator {
+                    Some(AlgToken::Comma) => true,
+                    Some(AlgToken::Colon) => false,
+                    Some(AlgToken::CloseBracket(_)) => return Err(RubiksError::InvalidMovement("[ ] without , or :".to_string())),
+                    end => return Err(unexpected(end, "[", "]")),
+                };
+                let (second, suffix) = match parse_sequence(tokens, pos, notation, depth + 1)? {
+                    (second, Some(AlgToken::CloseBracket(suffix))) => (second, suffix),
+                    (_, end) => return Err(unexpected(end, "[", "]")),
+                };
+                let mut group = first.clone();
+                group.extend(second.iter().cloned());
+                group.extend(inverse(&first));
+                if commutator {
+                    group.extend(inverse(&second));
+                }
+                moves.extend(repeated(group, suffix)?);
+            }
+            _ => return Ok((moves, Some(token))),
+        }
+    }
+    Ok((moves, None))
+}
+
+// Most times a group can be repeated, more is a typo rather than an alg
+pub(crate) const MAX_REPEAT: usize = 99;
+// Most groups inside each other, deeper is no alg anyone writes and would only run the parser out of stack
+const MAX_DEPTH: usize = 8;
+
+// A group as many times as the digits after it say, undone when they end in a prime
+fn repeated(group: Vec<Movement>, suffix: &str) -> Result<Vec<Movement>, RubiksError> {
+    let digits: String = suffix.chars().filter(char::is_ascii_digit).collect();
+    let times = match digits.is_empty() {
+        true => 1,
+        false => digits.parse::<usize>().ok().filter(|times| (1..=MAX_REPEAT).contains(times))
+            .ok_or_else(|| RubiksError::InvalidMovement(format!("){}", suffix)))?,
+    };
+    let group = match suffix.chars().filter(|c| !c.is_ascii_digit()).count() % 2 {
+        1 => inverse(&group),
+        _ => group,
+    };
+    Ok((0..times).flat_map(|_| group.iter().cloned()).collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Movement {
     R,
     U,
     F,
     L,
     B,
+    D,
     X,
     Y,
+    Z,
+    M,
+    E,
+    S,
+    RW,
+    UW,
+    FW,
+    LW,
+    BW,
+    DW,
 
     RP,
     UP,
     FP,
     LP,
     BP,
+    DP,
     XP,
     YP,
+    ZP,
+    MP,
+    EP,
+    SP,
+    RWP,
+    UWP,
+    FWP,
+    LWP,
+    BWP,
+    DWP,
 
     R2,
     U2,
     F2,
     L2,
     B2,
+    D2,
     X2,
     Y2,
+    Z2,
+    M2,
+    E2,
+    S2,
+    RW2,
+    UW2,
+    FW2,
+    LW2,
+    BW2,
+    DW2,
+
+    // Big cube moves turning layers `from` to `to` counted from the face, like 3Rw (1 to 3),
+    // 2-3Lw or 3R (3 to 3). Never the layers of a face or wide move, those are the moves above
+    Layered { face: Face, from: u8, to: u8, turns: u8 },
+
+    // A move of a puzzle other than a cube, the family being its place in the puzzle's notation
+    Twist { puzzle: Puzzle, family: u8, turns: u8 },
+
+    // Square-1 moves: "(x,y)" turns the top x and the bottom y twelfths clockwise, "/" the right half
+    Square1Turn { top: i8, bottom: i8 },
+    Square1Slash,
 }
 impl Movement {
     pub fn inv(&self) -> Movement {
@@ -35,135 +215,1127 @@ impl Movement {
             Movement::F => Movement::FP,
             Movement::L => Movement::LP,
             Movement::B => Movement::BP,
+            Movement::D => Movement::DP,
             Movement::X => Movement::XP,
             Movement::Y => Movement::YP,
+            Movement::Z => Movement::ZP,
+            Movement::M => Movement::MP,
+            Movement::E => Movement::EP,
+            Movement::S => Movement::SP,
+            Movement::RW => Movement::RWP,
+            Movement::UW => Movement::UWP,
+            Movement::FW => Movement::FWP,
+            Movement::LW => Movement::LWP,
+            Movement::BW => Movement::BWP,
+            Movement::DW => Movement::DWP,
 
             Movement::RP => Movement::R,
             Movement::UP => Movement::U,
             Movement::FP => Movement::F,
             Movement::LP => Movement::L,
             Movement::BP => Movement::B,
+            Movement::DP => Movement::D,
             Movement::XP => Movement::X,
             Movement::YP => Movement::Y,
+            Movement::ZP => Movement::Z,
+            Movement::MP => Movement::M,
+            Movement::EP => Movement::E,
+            Movement::SP => Movement::S,
+            Movement::RWP => Movement::RW,
+            Movement::UWP => Movement::UW,
+            Movement::FWP => Movement::FW,
+            Movement::LWP => Movement::LW,
+            Movement::BWP => Movement::BW,
+            Movement::DWP => Movement::DW,
 
             Movement::R2 => Movement::R2,
             Movement::U2 => Movement::U2,
             Movement::F2 => Movement::F2,
             Movement::L2 => Movement::L2,
             Movement::B2 => Movement::B2,
+            Movement::D2 => Movement::D2,
             Movement::X2 => Movement::X2,
             Movement::Y2 => Movement::Y2,
+            Movement::Z2 => Movement::Z2,
+            Movement::M2 => Movement::M2,
+            Movement::E2 => Movement::E2,
+            Movement::S2 => Movement::S2,
+            Movement::RW2 => Movement::RW2,
+            Movement::UW2 => Movement::UW2,
+            Movement::FW2 => Movement::FW2,
+            Movement::LW2 => Movement::LW2,
+            Movement::BW2 => Movement::BW2,
+            Movement::DW2 => Movement::DW2,
+
+            Movement::Layered { face, from, to, turns } => Movement::Layered { face: *face, from: *from, to: *to, turns: 4 - turns },
+            Movement::Twist { puzzle, .. } => puzzle.notation().inverse(self),
+            Movement::Square1Turn { top, bottom } => Movement::Square1Turn { top: twelfths(-(*top as i32)), bottom: twelfths(-(*bottom as i32)) },
+            Movement::Square1Slash => Movement::Square1Slash,
         }
     }
 
+    // Reads the spellings alg sheets use besides the usual ones too: "U3" turns like "U'",
+    // "X" is x and "rw" is r. Big cube moves have their layers in front, like "3Rw" or "2-3Lw'"
     pub fn from_text(text: &str) -> Option<Movement> {
+        Movement::canonical(text)
+            .or_else(|| Movement::canonical(&normalize_move(text)?))
+            .or_else(|| layered_move(text))
+    }
+
+    // The move turning layers `from` to `to` of a face, as a face or wide move when it is one
+    pub fn layered(face: Face, from: u8, to: u8, turns: u8) -> Movement {
+        let family = match (from, to) {
+            (1, 1) => face.as_text().to_string(),
+            (1, 2) => face.as_text().to_lowercase(),
+            _ => return Movement::Layered { face, from, to, turns },
+        };
+        Movement::canonical(&format!("{}{}", family, turn_suffix(turns))).unwrap()
+    }
+
+    fn canonical(text: &str) -> Option<Movement> {
         match text {
             "R" => Some(Movement::R),
             "U" => Some(Movement::U),
             "F" => Some(Movement::F),
             "L" => Some(Movement::L),
             "B" => Some(Movement::B),
+            "D" => Some(Movement::D),
             "x" => Some(Movement::X),
             "y" => Some(Movement::Y),
+            "z" => Some(Movement::Z),
+            "M" => Some(Movement::M),
+            "E" => Some(Movement::E),
+            "S" => Some(Movement::S),
+            "r" | "Rw" => Some(Movement::RW),
+            "u" | "Uw" => Some(Movement::UW),
+            "f" | "Fw" => Some(Movement::FW),
+            "l" | "Lw" => Some(Movement::LW),
+            "b" | "Bw" => Some(Movement::BW),
+            "d" | "Dw" => Some(Movement::DW),
 
             "R'" => Some(Movement::RP),
             "U'" => Some(Movement::UP),
             "F'" => Some(Movement::FP),
             "L'" => Some(Movement::LP),
             "B'" => Some(Movement::BP),
+            "D'" => Some(Movement::DP),
             "x'" => Some(Movement::XP),
             "y'" => Some(Movement::YP),
+            "z'" => Some(Movement::ZP),
+            "M'" => Some(Movement::MP),
+            "E'" => Some(Movement::EP),
+            "S'" => Some(Movement::SP),
+            "r'" | "Rw'" => Some(Movement::RWP),
+            "u'" | "Uw'" => Some(Movement::UWP),
+            "f'" | "Fw'" => Some(Movement::FWP),
+            "l'" | "Lw'" => Some(Movement::LWP),
+            "b'" | "Bw'" => Some(Movement::BWP),
+            "d'" | "Dw'" => Some(Movement::DWP),
 
             "R2" => Some(Movement::R2),
             "U2" => Some(Movement::U2),
             "F2" => Some(Movement::F2),
             "L2" => Some(Movement::L2),
             "B2" => Some(Movement::B2),
+            "D2" => Some(Movement::D2),
             "x2" => Some(Movement::X2),
             "y2" => Some(Movement::Y2),
+            "z2" => Some(Movement::Z2),
+            "M2" => Some(Movement::M2),
+            "E2" => Some(Movement::E2),
+            "S2" => Some(Movement::S2),
+            "r2" | "Rw2" => Some(Movement::RW2),
+            "u2" | "Uw2" => Some(Movement::UW2),
+            "f2" | "Fw2" => Some(Movement::FW2),
+            "l2" | "Lw2" => Some(Movement::LW2),
+            "b2" | "Bw2" => Some(Movement::BW2),
+            "d2" | "Dw2" => Some(Movement::DW2),
 
             "R2'" => Some(Movement::R2),
             "U2'" => Some(Movement::U2),
             "F2'" => Some(Movement::F2),
             "L2'" => Some(Movement::L2),
             "B2'" => Some(Movement::B2),
+            "D2'" => Some(Movement::D2),
             "x2'" => Some(Movement::X2),
             "y2'" => Some(Movement::Y2),
+            "z2'" => Some(Movement::Z2),
+            "M2'" => Some(Movement::M2),
+            "E2'" => Some(Movement::E2),
+            "S2'" => Some(Movement::S2),
+            "r2'" | "Rw2'" => Some(Movement::RW2),
+            "u2'" | "Uw2'" => Some(Movement::UW2),
+            "f2'" | "Fw2'" => Some(Movement::FW2),
+            "l2'" | "Lw2'" => Some(Movement::LW2),
+            "b2'" | "Bw2'" => Some(Movement::BW2),
+            "d2'" | "Dw2'" => Some(Movement::DW2),
             _ => None,
         }
     }
 
-    pub fn as_text(&self) -> &'static str {
-        match self {
+    pub fn as_text(&self) -> String {
+        let text = match self {
+            Movement::Layered { face, from, to, turns } => {
+                let layers = match *from == 1 || from == to {
+                    true => to.to_string(),
+                    false => format!("{}-{}", from, to),
+                };
+                let wide = if from == to { "" } else { "w" };
+                return format!("{}{}{}{}", layers, face.as_text(), wide, turn_suffix(*turns));
+            }
+            Movement::Twist { puzzle, .. } => return puzzle.notation().text(self),
+            Movement::Square1Turn { top, bottom } => return format!("({},{})", top, bottom),
+            Movement::Square1Slash => "/",
             Movement::R => "R",
             Movement::U => "U",
             Movement::F => "F",
             Movement::L => "L",
             Movement::B => "B",
+            Movement::D => "D",
             Movement::X => "x",
             Movement::Y => "y",
+            Movement::Z => "z",
+            Movement::M => "M",
+            Movement::E => "E",
+            Movement::S => "S",
+            Movement::RW => "r",
+            Movement::UW => "u",
+            Movement::FW => "f",
+            Movement::LW => "l",
+            Movement::BW => "b",
+            Movement::DW => "d",
 
             Movement::RP => "R'",
             Movement::UP => "U'",
             Movement::FP => "F'",
             Movement::LP => "L'",
             Movement::BP => "B'",
+            Movement::DP => "D'",
             Movement::XP => "x'",
             Movement::YP => "y'",
+            Movement::ZP => "z'",
+            Movement::MP => "M'",
+            Movement::EP => "E'",
+            Movement::SP => "S'",
+            Movement::RWP => "r'",
+            Movement::UWP => "u'",
+            Movement::FWP => "f'",
+            Movement::LWP => "l'",
+            Movement::BWP => "b'",
+            Movement::DWP => "d'",
 
             Movement::R2 => "R2",
             Movement::U2 => "U2",
             Movement::F2 => "F2",
             Movement::L2 => "L2",
             Movement::B2 => "B2",
+            Movement::D2 => "D2",
             Movement::X2 => "x2",
             Movement::Y2 => "y2",
+            Movement::Z2 => "z2",
+            Movement::M2 => "M2",
+            Movement::E2 => "E2",
+            Movement::S2 => "S2",
+            Movement::RW2 => "r2",
+            Movement::UW2 => "u2",
+            Movement::FW2 => "f2",
+            Movement::LW2 => "l2",
+            Movement::BW2 => "b2",
+            Movement::DW2 => "d2",
+        };
+        text.to_string()
+    }
+}
+
+// A move token written some other way than the trainer writes it, as the trainer would, e.g.
+// "U3" as "U'", "R2'" as "R2" and "Rw'2" as "r2". None when it isn't one
+fn normalize_move(text: &str) -> Option<String> {
+    let text = text.replace(['’', '′', '`'], "'");
+    let mut chars = text.chars();
+    let face = match chars.next()? {
+        face @ ('X' | 'Y' | 'Z') => face.to_ascii_lowercase(),
+        face => face,
+    };
+    let wide = chars.as_str().starts_with('w');
+    if wide {
+        chars.next();
+    }
+    let family = match (face, wide) {
+        ('R' | 'U' | 'F' | 'L' | 'B' | 'D', true) => face.to_ascii_lowercase(),
+        ('r' | 'u' | 'f' | 'l' | 'b' | 'd', true) => face,
+        (_, true) => return None,
+        _ => face,
+    };
+    Some(format!("{}{}", family, turn_suffix(turns_of(chars.as_str(), 4)?)))
+}
+
+// A big cube move: layer numbers, then a face and 'w' when it takes the layers up to the face
+fn layered_move(text: &str) -> Option<Movement> {
+    let text = text.replace(['’', '′', '`'], "'");
+    let number = |text: &str| -> Option<(u8, usize)> {
+        let end = text.find(|c: char| !c.is_ascii_digit())?;
+        Some((text[..end].parse().ok()?, end))
+    };
+    let (first, end) = number(&text)?;
+    let (range, rest) = match text[end..].strip_prefix('-') {
+        Some(rest) => {
+            let (last, end) = number(rest)?;
+            (Some(last), &rest[end..])
         }
+        None => (None, &text[end..]),
+    };
+    let mut chars = rest.chars();
+    let face = match chars.next()? {
+        'U' => Face::U,
+        'R' => Face::R,
+        'F' => Face::F,
+        'D' => Face::D,
+        'L' => Face::L,
+        'B' => Face::B,
+        _ => return None,
+    };
+    let wide = chars.as_str().starts_with('w');
+    if wide {
+        chars.next();
+    }
+    let (from, to) = match (range, wide) {
+        (None, false) => (first, first),
+        (None, true) => (1, first),
+        (Some(last), true) => (first, last),
+        // "2-3R" takes more than one layer, so it has to be written as a wide move
+        (Some(_), false) => return None,
+    };
+    if from == 0 || from > to {
+        return None;
     }
+    Some(Movement::layered(face, from, to, turns_of(chars.as_str(), 4)?))
 }
 
-#[derive(Debug)]
-enum RubiksError {
-    IOError(std::io::Error),
+// Turns clockwise an amount like "3", "2'" or "'2" makes on a face `order` turns bring back,
+// None for a whole turn
+fn turns_of(amount: &str, order: u8) -> Option<u8> {
+    if !amount.chars().all(|c| c.is_ascii_digit() || c == '\'') {
+        return None;
+    }
+    let digits: String = amount.chars().filter(char::is_ascii_digit).collect();
+    let primes = amount.chars().filter(|c| *c == '\'').count();
+    let mut turns = if digits.is_empty() { 1 } else { (digits.parse::<u32>().ok()? % order as u32) as u8 };
+    if primes % 2 == 1 {
+        turns = (order - turns) % order;
+    }
+    // Turning all the way around is no move
+    (turns != 0).then_some(turns)
+}
+
+fn turn_suffix(turns: u8) -> &'static str {
+    match turns {
+        1 => "",
+        2 => "2",
+        _ => "'",
+    }
+}
+
+// How a puzzle's moves are written: reading them from an alg file, writing them back and undoing
+// them, which is all scrambles made by inverting an alg need
+pub trait Notation {
+    // None when the puzzle has no such move
+    fn parse(&self, text: &str) -> Option<Movement>;
+    fn text(&self, movement: &Movement) -> String;
+    fn inverse(&self, movement: &Movement) -> Movement;
+
+    // A whole alg, for puzzles whose moves use the parentheses and commas that group cube moves
+    fn parse_alg(&self, _text: &str) -> Option<Result<Vec<Movement>, RubiksError>> {
+        None
+    }
+}
+
+// Cubes of any size, the moves `Movement` has variants for
+pub struct CubeNotation;
+
+impl Notation for CubeNotation {
+    fn parse(&self, text: &str) -> Option<Movement> {
+        Movement::from_text(text)
+    }
+
+    fn text(&self, movement: &Movement) -> String {
+        movement.as_text()
+    }
+
+    fn inverse(&self, movement: &Movement) -> Movement {
+        movement.inv()
+    }
+}
+
+// Puzzles whose moves are only told apart by name: each family of moves with the number of
+// turns that brings it back, written with the amount after it like "U'" or "BR2'"
+pub struct TwistNotation {
+    puzzle: Puzzle,
+    families: &'static [(&'static str, u8)],
+}
+
+static PYRAMINX: TwistNotation = TwistNotation {
+    puzzle: Puzzle::Pyraminx,
+    families: &[("U", 3), ("L", 3), ("R", 3), ("B", 3), ("u", 3), ("l", 3), ("r", 3), ("b", 3), ("y", 3)],
+};
+
+static SKEWB: TwistNotation = TwistNotation {
+    puzzle: Puzzle::Skewb,
+    families: &[("U", 3), ("L", 3), ("R", 3), ("B", 3), ("F", 3), ("x", 4), ("y", 4), ("z", 4)],
+};
+
+// Faces as in Megaminx PLL sheets, plus the R++ and D++ of WCA scrambles
+static MEGAMINX: TwistNotation = TwistNotation {
+    puzzle: Puzzle::Megaminx,
+    families: &[
+        ("U", 5), ("F", 5), ("R", 5), ("L", 5), ("BR", 5), ("BL", 5), ("DR", 5), ("DL", 5), ("B", 5), ("D", 5), ("y", 5),
+        ("R++", 5), ("D++", 5),
+    ],
+};
+
+// Square-1 algs like "/ (3,0) / (1,0) / (0,-3) /", also written without spaces or parentheses
+// ("1,0/-1,-1/"). Parentheses and commas are part of the moves, so algs can't be grouped
+pub struct Square1Notation;
+
+// A layer turn in twelfths as it's written, from -5 to 6
+pub(crate) fn twelfths(turns: i32) -> i8 {
+    match turns.rem_euclid(12) {
+        turns if turns > 6 => (turns - 12) as i8,
+        turns => turns as i8,
+    }
+}
+
+impl Square1Notation {
+    fn moves(text: &str) -> Result<Vec<Movement>, RubiksError> {
+        let invalid = |text: &str| RubiksError::InvalidMovement(text.trim().to_string());
+        let mut moves = Vec::new();
+        let mut rest = text.trim_start();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('/') {
+                moves.push(Movement::Square1Slash);
+                rest = after.trim_start();
+                continue;
+            }
+            let (turn, after) = match rest.strip_prefix('(') {
+                Some(inside) => inside.split_once(')').ok_or_else(|| invalid(rest))?,
+                None => rest.split_at(rest.find(|c: char| c == '/' || c.is_whitespace()).unwrap_or(rest.len())),
+            };
+            let (top, bottom) = turn.split_once(',').ok_or_else(|| invalid(turn))?;
+            let top: i32 = top.trim().parse().map_err(|_| invalid(turn))?;
+            let bottom: i32 = bottom.trim().parse().map_err(|_| invalid(turn))?;
+            let (top, bottom) = (twelfths(top), twelfths(bottom));
+            // "(0,0)" is no move at all
+            if (top, bottom) != (0, 0) {
+                moves.push(Movement::Square1Turn { top, bottom });
+            }
+            rest = after.trim_start();
+        }
+        Ok(moves)
+    }
+}
+
+impl Notation for Square1Notation {
+    fn parse(&self, text: &str) -> Option<Movement> {
+        match Square1Notation::moves(text).ok()?.as_slice() {
+            [movement] => Some(movement.clone()),
+            _ => None,
+        }
+    }
+
+    fn text(&self, movement: &Movement) -> String {
+        movement.as_text()
+    }
+
+    // A turn goes back the other way, a slash undoes itself
+    fn inverse(&self, movement: &Movement) -> Movement {
+        movement.inv()
+    }
+
+    fn parse_alg(&self, text: &str) -> Option<Result<Vec<Movement>, RubiksError>> {
+        Some(Square1Notation::moves(text))
+    }
+}
+
+impl Notation for TwistNotation {
+    fn parse(&self, text: &str) -> Option<Movement> {
+        let text = text.replace(['’', '′', '`'], "'");
+        // The longest name that fits, so "BR2" isn't read as B
+        let (family, turns) = self.families.iter().enumerate()
+            .filter_map(|(family, (name, order))| {
+                // Two fifths of the way, clockwise or back
+                let turns = match name.strip_suffix("++") {
+                    Some(base) => match text.strip_prefix(base)? {
+                        "++" => 2,
+                        "--" => order - 2,
+                        _ => return None,
+                    },
+                    None => turns_of(text.strip_prefix(name)?, *order)?,
+                };
+                Some((family, turns))
+            })
+            .max_by_key(|(family, _)| self.families[*family].0.len())?;
+        Some(Movement::Twist { puzzle: self.puzzle, family: family as u8, turns })
+    }
+
+    fn text(&self, movement: &Movement) -> String {
+        let Movement::Twist { family, turns, .. } = movement else {
+            return movement.as_text();
+        };
+        let (name, order) = self.families[*family as usize];
+        if let Some(base) = name.strip_suffix("++") {
+            return format!("{}{}", base, if *turns == 2 { "++" } else { "--" });
+        }
+        // Turning back is shorter past halfway: a megaminx face turned 3 fifths is "2'"
+        let suffix = match (*turns, order - turns) {
+            (1, _) => String::new(),
+            (_, 1) => "'".to_string(),
+            (turns, back) if turns <= back => turns.to_string(),
+            (_, back) => format!("{}'", back),
+        };
+        format!("{}{}", name, suffix)
+    }
+
+    fn inverse(&self, movement: &Movement) -> Movement {
+        match movement {
+            Movement::Twist { puzzle, family, turns } => {
+                let order = self.families[*family as usize].1;
+                Movement::Twist { puzzle: *puzzle, family: *family, turns: order - turns }
+            }
+            movement => movement.inv(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RubiksError {
+    #[error("IO Error: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("Invalid movement: {0}")]
     InvalidMovement(String),
+    #[error("Invalid metadata: {0}")]
+    InvalidMetadata(String),
+    // A .json or .toml alg file that doesn't have the expected structure
+    #[error("Invalid file: {0}")]
+    InvalidFile(String),
+    // The alg directory itself can't be read
+    #[error("Can't read the alg directory {0}: {1}")]
+    AlgDir(PathBuf, #[source] std::io::Error),
+}
+
+// Names that aren't valid UTF-8 get replacement characters instead of failing to load
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+// Where a file sits inside the alg directory, which its puzzle is read from. Directories above
+// the alg directory don't count, a file outside it only has its name
+fn inside(path: &Path, root: &Path) -> String {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match absolute(path).strip_prefix(absolute(root)) {
+        Ok(relative) => relative.iter().map(|segment| segment.to_string_lossy()).collect::<Vec<_>>().join("/"),
+        Err(_) => file_name(path),
+    }
+}
+
+// Every file the alg directory is read through. The browser has no files, there they fail
+#[cfg(not(target_arch = "wasm32"))]
+fn read_file(path: &Path) -> io::Result<String> {
+    std::fs::read_to_string(path)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_file(_path: &Path) -> io::Result<String> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+// The paths in a directory, each one failing on its own when it can't be listed
+#[cfg(not(target_arch = "wasm32"))]
+fn read_dir(path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+    Ok(std::fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_dir(_path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrainingMode {
+    Timed,
+    Recognition,
+    Drill,
+}
+
+impl TrainingMode {
+    pub fn from_text(text: &str) -> Option<TrainingMode> {
+        match text {
+            "timed" => Some(TrainingMode::Timed),
+            "recognition" => Some(TrainingMode::Recognition),
+            "drill" => Some(TrainingMode::Drill),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            TrainingMode::Timed => "timed",
+            TrainingMode::Recognition => "recognition",
+            TrainingMode::Drill => "drill",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mask {
+    Cross,
+    F2L,
+    OLL,
+    PLL,
+    LL,
+    CLL,
+}
+
+impl Mask {
+    pub fn from_text(text: &str) -> Option<Mask> {
+        match text {
+            "cross" => Some(Mask::Cross),
+            "f2l" => Some(Mask::F2L),
+            "oll" => Some(Mask::OLL),
+            "pll" => Some(Mask::PLL),
+            "ll" => Some(Mask::LL),
+            "cll" => Some(Mask::CLL),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Mask::Cross => "cross",
+            Mask::F2L => "f2l",
+            Mask::OLL => "oll",
+            Mask::PLL => "pll",
+            Mask::LL => "ll",
+            Mask::CLL => "cll",
+        }
+    }
+}
+
+// How scrambles are made from a set's cases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrambleSource {
+    // The inverse of an alg, as it is
+    Inverse,
+    // The inverse of an alg with a random U turn on both sides
+    Auf,
+    // The scrambles written on "SCRAMBLE:" lines under each case, for cases whose inverse gives the alg away
+    Listed,
+}
+
+impl ScrambleSource {
+    pub fn from_text(text: &str) -> Option<ScrambleSource> {
+        match text {
+            "inverse" => Some(ScrambleSource::Inverse),
+            "auf" => Some(ScrambleSource::Auf),
+            "listed" => Some(ScrambleSource::Listed),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            ScrambleSource::Inverse => "inverse",
+            ScrambleSource::Auf => "auf",
+            ScrambleSource::Listed => "listed",
+        }
+    }
+}
+
+// The puzzle a set's algs are for, sets without one are for the 3x3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Puzzle {
+    // An NxN cube, like Cube(4) for the 4x4
+    Cube(u8),
+    Pyraminx,
+    Skewb,
+    Megaminx,
+    Square1,
+}
+
+impl Puzzle {
+    pub fn from_text(text: &str) -> Option<Puzzle> {
+        match text {
+            "pyraminx" => return Some(Puzzle::Pyraminx),
+            "skewb" => return Some(Puzzle::Skewb),
+            "megaminx" => return Some(Puzzle::Megaminx),
+            "square-1" | "square1" | "sq1" => return Some(Puzzle::Square1),
+            _ => {}
+        }
+        let (size, other) = text.split_once('x')?;
+        match size.parse() {
+            Ok(size @ 2..=7) if size.to_string() == other => Some(Puzzle::Cube(size)),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> String {
+        match self {
+            Puzzle::Cube(size) => format!("{}x{}", size, size),
+            Puzzle::Pyraminx => "pyraminx".to_string(),
+            Puzzle::Skewb => "skewb".to_string(),
+            Puzzle::Megaminx => "megaminx".to_string(),
+            Puzzle::Square1 => "square-1".to_string(),
+        }
+    }
+
+    pub fn notation(&self) -> &'static dyn Notation {
+        match self {
+            Puzzle::Cube(_) => &CubeNotation,
+            Puzzle::Pyraminx => &PYRAMINX,
+            Puzzle::Skewb => &SKEWB,
+            Puzzle::Megaminx => &MEGAMINX,
+            Puzzle::Square1 => &Square1Notation,
+        }
+    }
+
+    // Only cube scrambles can get an AUF, be mirrored or be rotated
+    pub fn is_cube(&self) -> bool {
+        matches!(self, Puzzle::Cube(_))
+    }
+
+    // A puzzle named in a set's path, by a directory like "2x2/" or a file name ending like "eg-1.2x2.txt".
+    // The name closest to the file wins
+    pub fn in_path(path: &str) -> Option<Puzzle> {
+        path.rsplit('/').find_map(|segment| {
+            Puzzle::from_text(segment).or_else(|| segment.rsplit(['.', '_', '-', ' ']).find_map(Puzzle::from_text))
+        })
+    }
+
+    // Stickers along a face's edge when the trainer's cube model can follow the algs, to draw
+    // and check them: a 2x2 is the corners of the 3x3 model
+    pub fn modeled_size(&self) -> Option<usize> {
+        match self {
+            Puzzle::Cube(size @ 2..=3) => Some(*size as usize),
+            _ => None,
+        }
+    }
+
+    pub fn is_modeled(&self) -> bool {
+        self.modeled_size().is_some()
+    }
+}
+
+impl TryFrom<String> for Puzzle {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Puzzle, String> {
+        Puzzle::from_text(&text).ok_or_else(|| format!("unknown puzzle {}", text))
+    }
+}
+
+impl From<Puzzle> for String {
+    fn from(puzzle: Puzzle) -> String {
+        puzzle.as_text()
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" | "true" => Some(true),
+        "off" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+// Set-wide settings declared in an alg file with "#! key: value" lines
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SetMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<TrainingMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auf: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<Mask>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reps: Option<usize>,
+    // Curated algs that edits and batch transforms must leave alone unless unlocked first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scrambles: Option<ScrambleSource>,
+    // Seconds a case should take, what exams grade times against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<f64>,
+    // Where the set comes in the suggested learning order, sets with one are listed first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<usize>,
+    // Shell command printing a scramble for a case, run from the alg file's directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+    // What the algs are for when it isn't the 3x3, like "4x4" for parity algs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub puzzle: Option<Puzzle>,
+}
+
+impl SetMeta {
+    // Combines the metadata of several sets, keeping only the settings they don't disagree on
+    pub fn merge(metas: &[&SetMeta]) -> SetMeta {
+        fn agreed<T: PartialEq>(values: impl Iterator<Item = Option<T>>) -> Option<T> {
+            let mut result = None;
+            for value in values.flatten() {
+                match &result {
+                    None => result = Some(value),
+                    Some(prev) if *prev != value => return None,
+                    Some(_) => {}
+                }
+            }
+            result
+        }
+
+        SetMeta {
+            mode: agreed(metas.iter().map(|meta| meta.mode)),
+            auf: agreed(metas.iter().map(|meta| meta.auf)),
+            mask: agreed(metas.iter().map(|meta| meta.mask)),
+            reps: agreed(metas.iter().map(|meta| meta.reps)),
+            locked: agreed(metas.iter().map(|meta| meta.locked)),
+            scrambles: agreed(metas.iter().map(|meta| meta.scrambles)),
+            target: agreed(metas.iter().map(|meta| meta.target)),
+            order: agreed(metas.iter().map(|meta| meta.order)),
+            generator: agreed(metas.iter().map(|meta| meta.generator.clone())),
+            puzzle: agreed(metas.iter().map(|meta| meta.puzzle)),
+        }
+    }
+
+    pub(crate) fn parse_line(&mut self, line: &str) -> Result<(), RubiksError> {
+        let invalid = || RubiksError::InvalidMetadata(line.trim().to_string());
+        let (key, value) = line.split_once(':').ok_or_else(invalid)?;
+        let value = value.trim();
+
+        match key.trim() {
+            "mode" => self.mode = Some(TrainingMode::from_text(value).ok_or_else(invalid)?),
+            "auf" => self.auf = Some(parse_switch(value).ok_or_else(invalid)?),
+            "locked" => self.locked = Some(parse_switch(value).ok_or_else(invalid)?),
+            "mask" => self.mask = Some(Mask::from_text(value).ok_or_else(invalid)?),
+            "scrambles" => self.scrambles = Some(ScrambleSource::from_text(value).ok_or_else(invalid)?),
+            "reps" => self.reps = Some(value.parse().map_err(|_| invalid())?),
+            "generator" if !value.is_empty() => self.generator = Some(value.to_string()),
+            "puzzle" => self.puzzle = Some(Puzzle::from_text(value).ok_or_else(invalid)?),
+            "order" => self.order = Some(value.parse().map_err(|_| invalid())?),
+            "target" => self.target = Some(value.parse().ok().filter(|target: &f64| *target > 0.0).ok_or_else(invalid)?),
+            _ => return Err(invalid()),
+        }
+
+        Ok(())
+    }
+
+    // The settings as "key: value" lines, the way parse_line reads them
+    pub fn lines(&self) -> Vec<String> {
+        let switch = |value: bool| if value { "on" } else { "off" };
+        let mut lines = Vec::new();
+        lines.extend(self.puzzle.map(|puzzle| format!("puzzle: {}", puzzle.as_text())));
+        lines.extend(self.mode.map(|mode| format!("mode: {}", mode.as_text())));
+        lines.extend(self.reps.map(|reps| format!("reps: {}", reps)));
+        lines.extend(self.auf.map(|auf| format!("auf: {}", switch(auf))));
+        lines.extend(self.mask.map(|mask| format!("mask: {}", mask.as_text())));
+        lines.extend(self.scrambles.map(|scrambles| format!("scrambles: {}", scrambles.as_text())));
+        lines.extend(self.target.map(|target| format!("target: {}", target)));
+        lines.extend(self.order.map(|order| format!("order: {}", order)));
+        lines.extend(self.generator.as_ref().map(|generator| format!("generator: {}", generator)));
+        lines.extend(self.locked.map(|locked| format!("locked: {}", switch(locked))));
+        lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines().is_empty()
+    }
+}
+
+// A case and how to solve it, from a line like "T-perm: R U R' U' R' F R2 U' R' U' R U R' F' @easy *2"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alg {
+    pub name: Option<String>,
+    pub moves: Vec<Movement>,
+    // Other algs for the case, written on "ALT:" lines after it
+    pub alternatives: Vec<Vec<Movement>>,
+    // Scrambles for the case written on "SCRAMBLE:" lines after it, used by "#! scrambles: listed" sets
+    pub scrambles: Vec<Vec<Movement>>,
+    pub tags: Vec<String>,
+    // How often the case comes up compared to the others in its set, "*2" for twice as often
+    pub weight: f64,
+    // Comments written after the case in its file
+    pub notes: Vec<String>,
+}
+
+// The case name before a colon and the rest of the line. A colon inside brackets is a conjugate's
+fn split_name(line: &str) -> (Option<&str>, &str) {
+    match line.find(':').filter(|idx| !line[..*idx].contains('[')) {
+        Some(idx) => (Some(&line[..idx]), &line[idx + 1..]),
+        None => (None, line),
+    }
+}
+
+impl Alg {
+    fn parse(line: &str, notation: &dyn Notation) -> Result<Alg, RubiksError> {
+        let (name, rest) = split_name(line);
+        let name = name.map(|name| name.trim().to_string());
+
+        let mut tags = Vec::new();
+        let mut weight = 1.0;
+        let mut moves = Vec::new();
+        for token in rest.split(' ') {
+            if let Some(tag) = token.strip_prefix('@') {
+                tags.push(tag.to_string());
+            } else if let Some(value) = token.strip_prefix('*') {
+                weight = value.parse().ok().filter(|weight: &f64| weight.is_finite() && *weight >= 0.0)
+                    .ok_or_else(|| RubiksError::InvalidMetadata(token.to_string()))?;
+            } else {
+                moves.push(token);
+            }
+        }
+
+        Ok(Alg {
+            name,
+            moves: AlgSet::parse_moves(&moves.join(" "), notation)?,
+            alternatives: Vec::new(),
+            scrambles: Vec::new(),
+            tags,
+            weight,
+            notes: Vec::new(),
+        })
+    }
+
+    // The alg followed by its alternatives
+    pub fn all(&self) -> impl Iterator<Item = &Vec<Movement>> {
+        std::iter::once(&self.moves).chain(self.alternatives.iter())
+    }
+
+    // The case line of a text alg file, "name: moves @tag *weight"
+    fn line(&self) -> String {
+        let mut words = vec![moves_text(&self.moves)];
+        words.extend(self.tags.iter().map(|tag| format!("@{}", tag)));
+        if self.weight != 1.0 {
+            words.push(format!("*{}", self.weight));
+        }
+        match &self.name {
+            Some(name) => format!("{}: {}", name, words.join(" ")),
+            None => words.join(" "),
+        }
+    }
+}
+
+pub fn moves_text(movements: &[Movement]) -> String {
+    let texts: Vec<String> = movements.iter().map(Movement::as_text).collect();
+    texts.join(" ")
+}
+
+// Structured alg files (.json or .toml), for algs generated by other tools. A file is an alg set,
+// or a group when it has "groups":
+// {"name": "PLL", "meta": {"mask": "pll"}, "cases": [{"name": "T-perm", "algs": ["R U R' U' ..."], "tags": ["easy"], "weight": 2}]}
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EntryFile {
+    // Defaults to the file name for the top entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<SetMeta>,
+    #[serde(default)]
+    cases: Vec<CaseFile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<EntryFile>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CaseFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    // The first one is the main alg, the rest alternatives
+    algs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    scrambles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+}
+
+impl EntryFile {
+    fn parse(path: &Path, text: &str) -> Result<EntryFile, RubiksError> {
+        let invalid = |err: String| RubiksError::InvalidFile(format!("{}: {}", path.display(), err));
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(text).map_err(|err| invalid(err.to_string())),
+            _ => serde_json::from_str(text).map_err(|err| invalid(err.to_string())),
+        }
+    }
+
+    // The alg set as a structured file, named after the file it's written to
+    fn of(algset: &AlgSet) -> EntryFile {
+        EntryFile {
+            name: None,
+            notes: algset.notes.clone(),
+            meta: (!algset.meta.is_empty()).then(|| algset.meta.clone()),
+            cases: algset.algs.iter().map(CaseFile::of).collect(),
+            groups: None,
+        }
+    }
+
+    // `puzzle` is the one the file's location names, for sets without their own
+    fn into_entry(self, name: String, puzzle: Option<Puzzle>) -> Result<AlgEntry, RubiksError> {
+        let name = self.name.unwrap_or(name);
+        let puzzle = Puzzle::in_path(&name).or(puzzle);
+        let Some(groups) = self.groups else {
+            let notation = self.meta.as_ref().and_then(|meta| meta.puzzle).or(puzzle).unwrap_or(Puzzle::Cube(3)).notation();
+            let algs = self.cases.into_iter().map(|case| case.into_alg(notation)).collect::<Result<_, _>>()?;
+            let alg_set = AlgSet { name: name.clone(), path: name.clone(), algs, meta: self.meta.unwrap_or_default(), enabled: true, file: None, notes: self.notes };
+            return Ok(AlgEntry::Algs(name, alg_set));
+        };
+        if !self.cases.is_empty() || self.meta.is_some() || !self.notes.is_empty() {
+            return Err(RubiksError::InvalidFile(format!("{} has groups, it can't also have cases or meta", name)));
+        }
+
+        let entries = groups.into_iter()
+            .map(|entry| {
+                let child = entry.name.clone().ok_or_else(|| RubiksError::InvalidFile(format!("an entry in {} has no name", name)))?;
+                entry.into_entry(child, puzzle)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(AlgEntry::Group(name, entries))
+    }
+}
+
+impl CaseFile {
+    fn of(alg: &Alg) -> CaseFile {
+        CaseFile {
+            name: alg.name.clone(),
+            algs: alg.all().map(|moves| moves_text(moves)).collect(),
+            scrambles: alg.scrambles.iter().map(|moves| moves_text(moves)).collect(),
+            tags: alg.tags.clone(),
+            weight: (alg.weight != 1.0).then_some(alg.weight),
+            notes: alg.notes.clone(),
+        }
+    }
+
+    fn into_alg(self, notation: &dyn Notation) -> Result<Alg, RubiksError> {
+        let name = self.name.clone().unwrap_or_else(|| format!("\"{}\"", self.algs.join(" / ")));
+        let mut algs = self.algs.iter()
+            .map(|alg| AlgSet::parse_moves(&alg.replace('’', "'"), notation));
+        let moves = algs.next().ok_or_else(|| RubiksError::InvalidFile(format!("case {} has no algs", name)))??;
+        let weight = self.weight.unwrap_or(1.0);
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(RubiksError::InvalidFile(format!("case {} has a negative or infinite weight", name)));
+        }
+
+        Ok(Alg {
+            name: self.name,
+            moves,
+            alternatives: algs.collect::<Result<_, _>>()?,
+            scrambles: self.scrambles.iter().map(|scramble| AlgSet::parse_moves(&scramble.replace('’', "'"), notation)).collect::<Result<_, _>>()?,
+            tags: self.tags,
+            weight,
+            notes: self.notes,
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct AlgSet {
     pub name: String,
-    pub algs: Vec<Vec<Movement>>,
+    // Path inside the db, e.g. "2x2/cll/T"
+    pub path: String,
+    // One alg per case
+    pub algs: Vec<Alg>,
+    pub meta: SetMeta,
     pub enabled: bool,
+    // File the set was loaded from, None for the built-in demo sets
+    pub file: Option<PathBuf>,
+    // Comments in the file before the first case
+    pub notes: Vec<String>,
 }
 
 impl AlgSet {
+    // Groups in parentheses can be nested and repeated or inverted by what follows them, like
+    // "(R U R' U')3" or "(R U)2'", and commutators "[R, U]" and conjugates "[F: [R, U]]" are
+    // written out too
     pub fn parse_scramble(text: &str) -> Result<Vec<Movement>, RubiksError> {
-        let mut scramble: Vec<Movement> = Vec::new();
+        AlgSet::parse_moves(text, &CubeNotation)
+    }
+
+    // Moves in another puzzle's notation, grouped the same way
+    pub fn parse_moves(text: &str, notation: &dyn Notation) -> Result<Vec<Movement>, RubiksError> {
+        if let Some(moves) = notation.parse_alg(text) {
+            return moves;
+        }
+        let tokens = alg_tokens(text);
+        match parse_sequence(&tokens, &mut 0, notation, 0)? {
+            (scramble, None) => Ok(scramble),
+            (_, end) => Err(unexpected(end, "", "")),
+        }
+    }
 
-        let mut text = text.to_string();
+    // Set with "#! puzzle:", or named in the set's path
+    pub fn puzzle(&self) -> Puzzle {
+        self.meta.puzzle.or_else(|| Puzzle::in_path(&self.path)).unwrap_or(Puzzle::Cube(3))
+    }
 
-        // TODO: Add proper parenthesis support
-        text.retain(|c| c != '(' && c != ')');
+    pub fn is_locked(&self) -> bool {
+        self.meta.locked.unwrap_or(false)
+    }
 
-        for tk in text.split(' ').filter(|tk| tk.len() > 0) {
-            match Movement::from_text(tk) {
-                Some(movement) => scramble.push(movement),
-                None => {
-                    return Err(RubiksError::InvalidMovement(tk.to_string()));
-                }
-            }
+    // `root` is the alg directory, the puzzle is read from the path inside it
+    pub fn load_from<P: Into<PathBuf>>(path: P, root: &Path) -> Result<AlgSet, RubiksError> {
+        let path = path.into();
+        let name = file_name(&path);
+        let text = read_file(&path).map_err(RubiksError::IOError)?;
+        let (mut alg_set, mut skipped) = AlgSet::read_text(name, &text, Puzzle::in_path(&inside(&path, root)));
+        if !skipped.is_empty() {
+            return Err(skipped.remove(0).1);
         }
+        alg_set.file = Some(path);
+        Ok(alg_set)
+    }
 
-        Ok(scramble)
+    pub fn from_text(name: String, text: &str) -> Result<AlgSet, RubiksError> {
+        let (alg_set, mut skipped) = AlgSet::read_text(name, text, None);
+        match skipped.is_empty() {
+            true => Ok(alg_set),
+            false => Err(skipped.remove(0).1),
+        }
     }
 
-    pub fn load_from<P: Into<PathBuf>>(path: P) -> Result<AlgSet, RubiksError> {
-        let path = path.into();
-        let name = path.file_name().unwrap().to_str().unwrap().to_string();
-        let text = std::fs::read_to_string(path).map_err(|e| RubiksError::IOError(e))?;
-        let mut scrambles: Vec<Vec<Movement>> = Vec::new();
+    // Reads what it can of a text alg file: lines that don't parse are left out and returned with
+    // their line number (from 1). ALT: and SCRAMBLE: lines of a case that was left out go with it.
+    // Moves are read in the notation of the set's puzzle, or of `puzzle` (the one the file's location names)
+    pub fn read_text(name: String, text: &str, puzzle: Option<Puzzle>) -> (AlgSet, Vec<(usize, RubiksError)>) {
+        let mut algs: Vec<Alg> = Vec::new();
+        let mut meta = SetMeta::default();
+        let mut notes = Vec::new();
+        let mut skipped = Vec::new();
+        let mut skipping_case = false;
 
-        for line in text.lines() {
-            let line = line.split('#').nth(0).unwrap();
+        for (number, line) in text.lines().enumerate() {
+            let number = number + 1;
+            if let Some(directive) = line.trim_start().strip_prefix("#!") {
+                if let Err(err) = meta.parse_line(directive) {
+                    skipped.push((number, err));
+                }
+                continue;
+            }
+            let (line, comment) = line.split_once('#').unwrap_or((line, ""));
+            // Comments are notes for the case above them, or for the set before the first case
+            let comment = comment.trim();
+            let note = |algs: &mut Vec<Alg>, notes: &mut Vec<String>| {
+                if !comment.is_empty() {
+                    algs.last_mut().map(|alg| &mut alg.notes).unwrap_or(notes).push(comment.to_string());
+                }
+            };
             let line: String = line.chars().map(|c| match c {'’' => '\'', c => c}).collect();
             let mut is_whitespace = true;
             for chr in line.chars() {
@@ -172,87 +1344,380 @@ impl AlgSet {
                 }
             }
             if is_whitespace {
+                note(&mut algs, &mut notes);
                 continue;
             }
-            let scramble = AlgSet::parse_scramble(&line)?;
-            scrambles.push(scramble);
+
+            let notation = meta.puzzle.or_else(|| Puzzle::in_path(&name)).or(puzzle).unwrap_or(Puzzle::Cube(3)).notation();
+            let extra = ["ALT:", "SCRAMBLE:"].into_iter().find_map(|prefix| Some((prefix, line.trim_start().strip_prefix(prefix)?)));
+            let parsed = match extra {
+                Some(_) if skipping_case => continue,
+                Some((prefix, moves)) => match (algs.last_mut(), AlgSet::parse_moves(moves, notation)) {
+                    (None, _) => Err(RubiksError::InvalidMetadata(line.trim().to_string())),
+                    (_, Err(err)) => Err(err),
+                    (Some(alg), Ok(moves)) => {
+                        match prefix {
+                            "ALT:" => alg.alternatives.push(moves),
+                            _ => alg.scrambles.push(moves),
+                        }
+                        Ok(())
+                    }
+                },
+                None => Alg::parse(&line, notation).map(|alg| algs.push(alg)),
+            };
+            match parsed {
+                Ok(()) => {
+                    skipping_case = false;
+                    note(&mut algs, &mut notes);
+                }
+                Err(err) => {
+                    skipping_case = extra.is_none();
+                    skipped.push((number, err));
+                }
+            }
         }
 
-        Ok(AlgSet {
-            name: name,
-            algs: scrambles,
+        let alg_set = AlgSet {
+            path: name.clone(),
+            name,
+            algs,
+            meta,
             enabled: true,
-        })
+            file: None,
+            notes,
+        };
+        (alg_set, skipped)
+    }
+
+    // The set as a text alg file, with its notes as comments
+    pub fn to_text(&self) -> String {
+        let mut lines: Vec<String> = self.meta.lines().iter().map(|line| format!("#! {}", line)).collect();
+        lines.extend(self.notes.iter().map(|note| format!("# {}", note)));
+        for alg in self.algs.iter() {
+            lines.push(alg.line());
+            lines.extend(alg.alternatives.iter().map(|moves| format!("ALT: {}", moves_text(moves))));
+            lines.extend(alg.scrambles.iter().map(|moves| format!("SCRAMBLE: {}", moves_text(moves))));
+            lines.extend(alg.notes.iter().map(|note| format!("# {}", note)));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&EntryFile::of(self)).unwrap() + "\n"
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(&EntryFile::of(self)).unwrap()
     }
 }
 
-fn handle_rubiks_error(err: RubiksError) -> ! {
-    match err {
-        RubiksError::IOError(err) => {
-            eprintln!("IO Error: {:?}", err);
-            std::process::exit(1);
+// '*' matches anything inside a path segment, "**" anything at all and '?' a single character
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            ['*', '*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+            ['*', rest @ ..] => {
+                let segment = text.iter().position(|c| *c == '/').unwrap_or(text.len());
+                (0..=segment).any(|i| matches(rest, &text[i..]))
+            }
+            ['?', rest @ ..] => !text.is_empty() && text[0] != '/' && matches(rest, &text[1..]),
+            [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    // Nothing in the file or directory was loaded
+    SkippedFile,
+    // A line left out of a file that was loaded
+    SkippedLine,
+    // A move written another way than the trainer writes it, like "Rw" for "r"
+    Alias,
+    // A "#! generator:" line that isn't run, since the config doesn't allow commands
+    Generator,
+}
+
+impl DiagnosticKind {
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            DiagnosticKind::SkippedFile => "skipped file",
+            DiagnosticKind::SkippedLine => "skipped line",
+            DiagnosticKind::Alias => "alias",
+            DiagnosticKind::Generator => "generator off",
+        }
+    }
+}
+
+// Something loading the alg directory had to leave out or read differently than written
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub file: PathBuf,
+    // From 1, None when it's about the whole file
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+// Moves of a text alg file written another way than usual, by line number
+fn aliases(text: &str, notation: &dyn Notation) -> Vec<(usize, String)> {
+    let mut found = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with("#!") {
+            continue;
+        }
+        let code = line.split('#').next().unwrap();
+        let (_, moves) = split_name(code);
+        let mut substitutions: Vec<String> = Vec::new();
+        if code.contains('’') {
+            substitutions.push("’ read as '".to_string());
+        }
+        for token in moves.replace('’', "'").split(' ') {
+            // Brackets and commas around a move are only left out when the move isn't written with
+            // them, as a Square-1 turn like (1,0) is
+            let token = match notation.parse(token) {
+                Some(_) => token,
+                None => token.trim_matches(|c| "()[],:".contains(c)),
+            };
+            if let Some(movement) = notation.parse(token) && movement.as_text() != token {
+                substitutions.push(format!("{} read as {}", token, movement.as_text()));
+            }
         }
-        RubiksError::InvalidMovement(movement) => {
-            eprintln!("Invalid movement: {}", movement);
-            std::process::exit(1);
+        if !substitutions.is_empty() {
+            found.push((number + 1, substitutions.join(", ")));
         }
     }
+    found
 }
 
 #[derive(Debug)]
 pub struct AlgDB {
     pub entries: Vec<AlgEntry>,
+    // What loading left out or read differently, shown on the diagnostics page
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl AlgDB {
-    fn parse_entry(path: PathBuf) -> AlgEntry {
-        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+    // None when nothing could be loaded from the path. Text alg sets keep the lines that parse
+    fn parse_entry(path: PathBuf, root: &Path, diagnostics: &mut Vec<Diagnostic>) -> Option<AlgEntry> {
+        let name = file_name(&path);
+        let skipped = |diagnostics: &mut Vec<Diagnostic>, message: String| {
+            diagnostics.push(Diagnostic { kind: DiagnosticKind::SkippedFile, file: path.clone(), line: None, message });
+        };
         if path.is_dir() {
-            let paths = fs::read_dir(path).unwrap();
-            let mut entries: Vec<AlgEntry> = Vec::new();
-            for path in paths {
-                let path = path.unwrap().path();
-                let entry = AlgDB::parse_entry(path);
-                entries.push(entry);
+            let paths = match read_dir(&path) {
+                Ok(paths) => paths,
+                Err(err) => {
+                    skipped(diagnostics, err.to_string());
+                    return None;
+                }
+            };
+            let entries = paths.into_iter().flatten().filter_map(|entry| AlgDB::parse_entry(entry, root, diagnostics)).collect();
+            return Some(AlgEntry::Group(name, entries));
+        }
+        if matches!(path.extension().and_then(|ext| ext.to_str()), Some("json" | "toml" | "md")) {
+            return AlgDB::load_file(&path, root).map_err(|err| skipped(diagnostics, err.to_string())).ok();
+        }
+
+        let text = match read_file(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                skipped(diagnostics, err.to_string());
+                return None;
             }
-            AlgEntry::Group(name, entries)
-        } else {
-            let alg_set = AlgSet::load_from(path).unwrap();
+        };
+        let puzzle = Puzzle::in_path(&inside(&path, root));
+        let (mut alg_set, lines) = AlgSet::read_text(name.clone(), &text, puzzle);
+        alg_set.file = Some(path.clone());
+        for (line, err) in lines {
+            diagnostics.push(Diagnostic { kind: DiagnosticKind::SkippedLine, file: path.clone(), line: Some(line), message: err.to_string() });
+        }
+        let notation = alg_set.meta.puzzle.or(puzzle).unwrap_or(Puzzle::Cube(3)).notation();
+        for (line, message) in aliases(&text, notation) {
+            diagnostics.push(Diagnostic { kind: DiagnosticKind::Alias, file: path.clone(), line: Some(line), message });
+        }
+        Some(AlgEntry::Algs(name, alg_set))
+    }
 
-            AlgEntry::Algs(name, alg_set)
+    // Plain text alg sets, Markdown alg sets, or a structured .json/.toml file that can also hold
+    // a whole group. `root` is the alg directory the file is in
+    pub fn load_file(path: &Path, root: &Path) -> Result<AlgEntry, RubiksError> {
+        let name = file_name(path);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json" | "toml") => {
+                let text = read_file(path).map_err(RubiksError::IOError)?;
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(|| file_name(path));
+                let mut entry = EntryFile::parse(path, &text)?.into_entry(stem, Puzzle::in_path(&inside(path, root)))?;
+                entry.set_file(path);
+                Ok(entry)
+            }
+            Some("md") => {
+                let text = read_file(path).map_err(RubiksError::IOError)?;
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(|| file_name(path));
+                let mut alg_set = markdown::parse(stem.clone(), &text)?;
+                alg_set.file = Some(path.to_path_buf());
+                Ok(AlgEntry::Algs(stem, alg_set))
+            }
+            _ => Ok(AlgEntry::Algs(name, AlgSet::load_from(path, root)?)),
         }
     }
     
-    pub fn load(path: PathBuf) -> AlgDB {
-        let mut entries = Vec::new();
-        let paths: ReadDir = fs::read_dir(path).unwrap();
-        for path in paths {
-            let path: PathBuf = path.unwrap().path();
-            let alg_entry = AlgDB::parse_entry(path);
-            entries.push(alg_entry);
+    pub fn demo() -> AlgDB {
+        fn algs(name: &str, text: &str) -> AlgEntry {
+            let alg_set = AlgSet::from_text(name.to_string(), text).unwrap();
+            AlgEntry::Algs(name.to_string(), alg_set)
         }
-        AlgDB { entries }
+
+        let cll = vec![
+            algs("sune", include_str!("../default_algs/2x2/cll/sune")),
+            algs("antisune", include_str!("../default_algs/2x2/cll/antisune")),
+            algs("T", include_str!("../default_algs/2x2/cll/T")),
+            algs("U", include_str!("../default_algs/2x2/cll/U")),
+            algs("L", include_str!("../default_algs/2x2/cll/L")),
+            algs("pi", include_str!("../default_algs/2x2/cll/pi")),
+            algs("H", include_str!("../default_algs/2x2/cll/H")),
+        ];
+        let entries = vec![AlgEntry::Group("2x2".to_string(), vec![
+            algs("PBL", include_str!("../default_algs/2x2/PBL")),
+            AlgEntry::Group("cll".to_string(), cll),
+        ])];
+
+        let mut db = AlgDB { entries, diagnostics: Vec::new() };
+        db.assign_paths();
+        db
     }
 
-    fn add_entries<'a>(vec: &mut Vec<&'a [Movement]>, entries: &'a [AlgEntry]) {
-        for entry in entries {
+    // Only fails when the directory itself can't be read, anything inside it that doesn't load is
+    // left out and listed in the diagnostics
+    pub fn load(path: PathBuf) -> Result<AlgDB, RubiksError> {
+        let mut entries = Vec::new();
+        let mut diagnostics = Vec::new();
+        let paths = read_dir(&path).map_err(|err| RubiksError::AlgDir(path.clone(), err))?;
+        for entry in paths {
             match entry {
-                AlgEntry::Algs(_, alg_set) => {
-                    for algs in alg_set.algs.iter() {
-                        vec.push(&algs);
+                Ok(entry) => entries.extend(AlgDB::parse_entry(entry, &path, &mut diagnostics)),
+                Err(err) => diagnostics.push(Diagnostic { kind: DiagnosticKind::SkippedFile, file: path.clone(), line: None, message: err.to_string() }),
+            }
+        }
+        diagnostics.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        sort_by_order(&mut entries);
+        let mut db = AlgDB { entries, diagnostics };
+        db.assign_paths();
+        Ok(db)
+    }
+
+    fn assign_paths(&mut self) {
+        fn assign(entries: &mut [AlgEntry], prefix: &str) {
+            for entry in entries {
+                match entry {
+                    AlgEntry::Group(name, entries) => assign(entries, &format!("{}{}/", prefix, name)),
+                    AlgEntry::Algs(name, alg_set) => alg_set.path = format!("{}{}", prefix, name),
+                }
+            }
+        }
+        assign(&mut self.entries, "");
+    }
+
+    // `root` is the alg directory, which is `path` itself when the whole directory is validated
+    pub fn validate(path: &Path, root: &Path) -> Vec<(PathBuf, RubiksError)> {
+        fn check(path: PathBuf, root: &Path, errors: &mut Vec<(PathBuf, RubiksError)>) {
+            if path.is_dir() {
+                match read_dir(&path) {
+                    Ok(paths) => {
+                        for entry in paths.into_iter().flatten() {
+                            check(entry, root, errors);
+                        }
                     }
+                    Err(err) => errors.push((path, RubiksError::IOError(err))),
                 }
-                AlgEntry::Group(_, entries) => {
-                    AlgDB::add_entries(vec, entries);
+            } else if let Err(err) = AlgDB::load_file(&path, root) {
+                errors.push((path, err));
+            }
+        }
+
+        let mut errors = Vec::new();
+        check(path.to_path_buf(), root, &mut errors);
+        errors
+    }
+
+    // Looks up an alg set by name or by its path inside the db (e.g. "2x2/cll/T")
+    pub fn find(&self, name: &str) -> Option<&AlgSet> {
+        fn find_in<'a>(entries: &'a [AlgEntry], prefix: &str, name: &str) -> Option<&'a AlgSet> {
+            for entry in entries {
+                match entry {
+                    AlgEntry::Algs(entry_name, alg_set) => {
+                        if entry_name == name || format!("{}{}", prefix, entry_name) == name {
+                            return Some(alg_set);
+                        }
+                    }
+                    AlgEntry::Group(group_name, entries) => {
+                        let prefix = format!("{}{}/", prefix, group_name);
+                        if let Some(alg_set) = find_in(entries, &prefix, name) {
+                            return Some(alg_set);
+                        }
+                    }
                 }
             }
+            None
         }
+
+        find_in(&self.entries, "", name)
     }
-    pub fn get_rand<'a>(&'a self) -> &'a [Movement] {
-        let mut possibilities: Vec<&[Movement]> = Vec::new();
-        AlgDB::add_entries(&mut possibilities, &self.entries);
-        
-        possibilities.choose(&mut rng()).unwrap()
+
+    // Puts sets named with the same prefix ("OLL_01", "OLL-02", "OLL 03" or "OLL04") in a group of
+    // that name, for alg directories kept flat
+    pub fn group_by_prefix(&mut self) {
+        self.entries = group_by_prefix(std::mem::take(&mut self.entries));
+        self.assign_paths();
+    }
+
+    // Alg files can come from anyone, their commands only run when the config says so. The sets
+    // get their scrambles the usual way instead
+    pub fn drop_generators(&mut self) {
+        fn drop(entries: &mut [AlgEntry], diagnostics: &mut Vec<Diagnostic>) {
+            for entry in entries {
+                match entry {
+                    AlgEntry::Algs(_, alg_set) => {
+                        if let Some(generator) = alg_set.meta.generator.take() {
+                            diagnostics.push(Diagnostic {
+                                kind: DiagnosticKind::Generator,
+                                file: alg_set.file.clone().unwrap_or_default(),
+                                line: None,
+                                message: format!("{} isn't run, set training.generators to allow it", generator),
+                            });
+                        }
+                    }
+                    AlgEntry::Group(_, entries) => drop(entries, diagnostics),
+                }
+            }
+        }
+        drop(&mut self.entries, &mut self.diagnostics);
+    }
+
+    // Alg sets whose path matches a glob pattern like "ZBLL/T/*".
+    // A pattern without a '/' is also tried against the set names
+    pub fn matching(&self, pattern: &str) -> Vec<&AlgSet> {
+        fn collect<'a>(entries: &'a [AlgEntry], pattern: &str, found: &mut Vec<&'a AlgSet>) {
+            for entry in entries {
+                match entry {
+                    AlgEntry::Algs(name, alg_set) => {
+                        if glob_match(pattern, &alg_set.path) || (!pattern.contains('/') && glob_match(pattern, name)) {
+                            found.push(alg_set);
+                        }
+                    }
+                    AlgEntry::Group(_, entries) => collect(entries, pattern, found),
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(&self.entries, pattern, &mut found);
+        found
     }
 }
 
@@ -262,3 +1727,293 @@ pub enum AlgEntry {
     Algs(String, AlgSet),
 }
 
+// Splits a set name into the prefix it's grouped by and the rest, its name inside the group
+fn name_prefix(name: &str) -> Option<(String, String)> {
+    let (prefix, rest) = match name.find(['_', '-', ' ']) {
+        Some(idx) => (&name[..idx], &name[idx + 1..]),
+        None => name.split_at(name.find(|c: char| c.is_ascii_digit())?),
+    };
+    (!prefix.is_empty() && !rest.is_empty()).then(|| (prefix.to_string(), rest.to_string()))
+}
+
+// Only prefixes shared by several sets make a group
+fn group_by_prefix(entries: Vec<AlgEntry>) -> Vec<AlgEntry> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for entry in entries.iter() {
+        if let AlgEntry::Algs(name, _) = entry && let Some((prefix, _)) = name_prefix(name) {
+            *counts.entry(prefix).or_default() += 1;
+        }
+    }
+
+    let mut grouped: Vec<AlgEntry> = Vec::new();
+    for entry in entries {
+        let (name, alg_set) = match entry {
+            AlgEntry::Group(name, entries) => {
+                grouped.push(AlgEntry::Group(name, group_by_prefix(entries)));
+                continue;
+            }
+            AlgEntry::Algs(name, alg_set) => (name, alg_set),
+        };
+        let Some((prefix, rest)) = name_prefix(&name).filter(|(prefix, _)| counts[prefix] > 1) else {
+            grouped.push(AlgEntry::Algs(name, alg_set));
+            continue;
+        };
+        let group = grouped.iter_mut().find_map(|entry| match entry {
+            AlgEntry::Group(name, entries) if *name == prefix => Some(entries),
+            _ => None,
+        });
+        match group {
+            Some(entries) => entries.push(AlgEntry::Algs(rest, alg_set)),
+            None => grouped.push(AlgEntry::Group(prefix, vec![AlgEntry::Algs(rest, alg_set)])),
+        }
+    }
+    grouped
+}
+
+// Puts the sets with an "#! order:" line first, in that order, and groups by their first set. The
+// rest keep the order they were read in
+fn sort_by_order(entries: &mut [AlgEntry]) {
+    fn first(entry: &AlgEntry) -> Option<usize> {
+        match entry {
+            AlgEntry::Group(_, entries) => entries.iter().filter_map(first).min(),
+            AlgEntry::Algs(_, alg_set) => alg_set.meta.order,
+        }
+    }
+
+    entries.sort_by_key(|entry| first(entry).map_or((1, 0), |order| (0, order)));
+    for entry in entries {
+        if let AlgEntry::Group(_, entries) = entry {
+            sort_by_order(entries);
+        }
+    }
+}
+
+impl AlgEntry {
+    fn set_file(&mut self, path: &Path) {
+        match self {
+            AlgEntry::Group(_, entries) => entries.iter_mut().for_each(|entry| entry.set_file(path)),
+            AlgEntry::Algs(_, alg_set) => alg_set.file = Some(path.to_path_buf()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<String, RubiksError> {
+        AlgSet::parse_scramble(text).map(|moves| moves_text(&moves))
+    }
+
+    #[test]
+    fn nested_groups() {
+        assert_eq!(parse("(R (U R')2)").unwrap(), "R U R' U R'");
+        assert_eq!(parse("((R U)2 D)'").unwrap(), "D' U' R' U' R'");
+        // Nesting is capped, brackets count the same as parentheses
+        let deep = |depth: usize| format!("{}R{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(parse(&deep(MAX_DEPTH)).unwrap(), "R");
+        assert!(parse(&deep(MAX_DEPTH + 1)).is_err());
+        assert!(parse(&format!("[{}: U]", deep(MAX_DEPTH))).is_err());
+        assert!(parse(&"(".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        // Usable as any other error, with the IO error underneath as its source
+        let err: Box<dyn std::error::Error> = Box::new(RubiksError::AlgDir(PathBuf::from("algs"), io::ErrorKind::NotFound.into()));
+        assert_eq!(err.to_string(), "Can't read the alg directory algs: entity not found");
+        assert!(err.source().is_some());
+        let err: RubiksError = io::Error::from(io::ErrorKind::NotFound).into();
+        assert_eq!(err.to_string(), "IO Error: entity not found");
+        assert_eq!(parse("R Q").unwrap_err().to_string(), "Invalid movement: Q");
+    }
+
+    #[test]
+    fn repeat_counts() {
+        assert_eq!(parse("(R U)3").unwrap(), "R U R U R U");
+        assert_eq!(parse("(R U)2'").unwrap(), "U' R' U' R'");
+        assert_eq!(AlgSet::parse_scramble("(R)99").unwrap().len(), 99);
+        assert!(parse("(R U)100").is_err());
+        assert!(parse("(R U)99999999999999999999").is_err());
+        assert!(parse("(R U)0").is_err());
+    }
+
+    #[test]
+    fn bad_input() {
+        assert!(parse("(R U").is_err());
+        assert!(parse("R U)").is_err());
+        assert!(parse("[R U]").is_err());
+        assert!(parse("[R, U").is_err());
+        assert!(parse("R Q").is_err());
+    }
+
+    #[test]
+    fn commutators_and_conjugates() {
+        assert_eq!(parse("[R, U]").unwrap(), "R U R' U'");
+        assert_eq!(parse("[F: [R, U]]").unwrap(), "F R U R' U' F'");
+        assert_eq!(parse("[R U R': D]").unwrap(), "R U R' D R U' R'");
+        assert_eq!(parse("[R, U]2").unwrap(), "R U R' U' R U R' U'");
+        assert_eq!(parse("[R, U]'").unwrap(), "U R U' R'");
+        assert!(parse("[R: U").is_err());
+        assert!(parse("R, U").is_err());
+    }
+
+    #[test]
+    fn names_before_colons() {
+        assert_eq!(split_name("T-perm: R U R'"), (Some("T-perm"), " R U R'"));
+        assert_eq!(split_name("[F: [R, U]]"), (None, "[F: [R, U]]"));
+        assert_eq!(split_name("UF-UB: [M2: U]"), (Some("UF-UB"), " [M2: U]"));
+        assert_eq!(split_name("R U R'"), (None, "R U R'"));
+    }
+
+    fn read(text: &str) -> String {
+        moves_text(&[Movement::from_text(text).unwrap()])
+    }
+
+    #[test]
+    fn tolerant_moves() {
+        assert_eq!(read("U3"), "U'");
+        assert_eq!(read("U3'"), "U");
+        assert_eq!(read("R’"), "R'");
+        assert_eq!(read("X"), "x");
+        assert_eq!(read("Y2"), "y2");
+        assert_eq!(read("Rw"), "r");
+        assert_eq!(read("rw'"), "r'");
+        assert_eq!(read("M2'"), "M2");
+        assert!(Movement::from_text("U4").is_none());
+        assert!(Movement::from_text("Mw").is_none());
+        assert!(Movement::from_text("Q").is_none());
+    }
+
+    #[test]
+    fn unknown_moves_skip_their_line() {
+        let (alg_set, skipped) = AlgSet::read_text("test".to_string(), "A: R U R'\nB: R Q R'\nC: U3 x", None);
+        let algs: Vec<String> = alg_set.algs.iter().map(|alg| moves_text(&alg.moves)).collect();
+        assert_eq!(algs, ["R U R'", "U' x"]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, 2);
+    }
+
+    #[test]
+    fn layered_moves() {
+        assert_eq!(read("3Rw"), "3Rw");
+        assert_eq!(read("3Rw2"), "3Rw2");
+        assert_eq!(read("2-3Lw'"), "2-3Lw'");
+        assert_eq!(read("2R"), "2R");
+        // The outer two layers are a wide move
+        assert_eq!(read("2Rw"), "r");
+        assert_eq!(read("1R'"), "R'");
+        assert!(Movement::from_text("2-3R").is_none());
+        assert!(Movement::from_text("3-2Rw").is_none());
+        assert!(Movement::from_text("0R").is_none());
+        assert_eq!(moves_text(&[Movement::from_text("2-3Lw'").unwrap().inv()]), "2-3Lw");
+    }
+
+    fn turned(text: &str) -> crate::cube::CubeState {
+        let mut state = crate::cube::CubeState::solved();
+        state.apply(&AlgSet::parse_scramble(text).unwrap());
+        state
+    }
+
+    #[test]
+    fn layered_move_meaning() {
+        // "2R" is the one layer next to R on any cube, not M' as before big cube moves were read.
+        // On a 3x3 that's the same turn
+        assert!(matches!(Movement::from_text("2R"), Some(Movement::Layered { face: Face::R, from: 2, to: 2, turns: 1 })));
+        assert_eq!(turned("2R"), turned("M'"));
+        assert_eq!(turned("2R2"), turned("M2"));
+        assert_eq!(turned("2U'"), turned("E"));
+        assert!(matches!(Movement::from_text("3Rw"), Some(Movement::Layered { face: Face::R, from: 1, to: 3, turns: 1 })));
+        assert_eq!(turned("3Rw"), turned("x"));
+        assert!(matches!(Movement::from_text("2-3Lw'"), Some(Movement::Layered { face: Face::L, from: 2, to: 3, turns: 3 })));
+        assert_eq!(turned("2-3Lw'"), turned("M' R"));
+    }
+
+    #[test]
+    fn puzzle_sizes() {
+        assert_eq!(Puzzle::from_text("4x4"), Some(Puzzle::Cube(4)));
+        assert_eq!(Puzzle::from_text("4x5"), None);
+        assert_eq!(Puzzle::in_path("big/5x5/edges.txt"), Some(Puzzle::Cube(5)));
+        assert_eq!(Puzzle::in_path("parity.4x4.txt"), Some(Puzzle::Cube(4)));
+    }
+
+    #[test]
+    fn puzzles_inside_the_alg_dir() {
+        // Directories above the alg directory are named like a puzzle, which mustn't count
+        let root = std::env::temp_dir().join(format!("rubiks_trainer-db-{}", std::process::id())).join("pyraminx");
+        let dir = root.join("algs");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(dir.join("oll")).unwrap();
+        std::fs::create_dir_all(dir.join("pyraminx")).unwrap();
+        std::fs::write(dir.join("oll/T"), "F R U R' U' F'\n").unwrap();
+        std::fs::write(dir.join("pyraminx/l4e"), "R U' R' U\n").unwrap();
+
+        let db = AlgDB::load(dir.clone()).unwrap();
+        assert!(db.diagnostics.is_empty(), "{:?}", db.diagnostics);
+        assert_eq!(db.find("oll/T").unwrap().algs.len(), 1);
+        assert_eq!(db.find("oll/T").unwrap().puzzle(), Puzzle::Cube(3));
+        assert_eq!(db.find("pyraminx/l4e").unwrap().puzzle(), Puzzle::Pyraminx);
+        assert!(AlgDB::validate(&dir, &dir).is_empty());
+        assert!(AlgDB::load_file(&dir.join("oll/T"), &dir).is_ok());
+        // Outside the alg directory only the file's name is read
+        assert_eq!(inside(&dir.join("pyraminx/l4e"), &dir), "pyraminx/l4e");
+        assert_eq!(inside(&dir.join("oll/T"), &dir.join("pyraminx")), "T");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn parse_as(puzzle: Puzzle, text: &str) -> Result<String, RubiksError> {
+        let notation = puzzle.notation();
+        AlgSet::parse_moves(text, notation).map(|moves| moves.iter().map(|movement| notation.text(movement)).collect::<Vec<_>>().join(" "))
+    }
+
+    fn inverse_as(puzzle: Puzzle, text: &str) -> String {
+        let notation = puzzle.notation();
+        let moves = AlgSet::parse_moves(text, notation).unwrap();
+        moves.iter().rev().map(|movement| notation.text(&notation.inverse(movement))).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn twist_notations() {
+        assert_eq!(parse_as(Puzzle::Pyraminx, "R U' l b2").unwrap(), "R U' l b'");
+        assert_eq!(inverse_as(Puzzle::Pyraminx, "R U' l"), "l' U R'");
+        assert_eq!(parse_as(Puzzle::Skewb, "(R L')2 x").unwrap(), "R L' R L' x");
+        assert_eq!(inverse_as(Puzzle::Skewb, "R y"), "y' R'");
+        // The longest face name wins, and turning back is shorter past halfway
+        assert_eq!(parse_as(Puzzle::Megaminx, "BR2 R3 U4").unwrap(), "BR2 R2' U'");
+        assert_eq!(inverse_as(Puzzle::Megaminx, "R++ D-- BL2"), "BL2' D++ R--");
+        assert!(parse_as(Puzzle::Pyraminx, "F").is_err());
+        assert!(parse_as(Puzzle::Skewb, "M").is_err());
+        assert!(parse_as(Puzzle::Megaminx, "R+").is_err());
+    }
+
+    #[test]
+    fn alias_lines() {
+        let text = "#! puzzle: 3x3\nSune: R U R’ U R U2' R'\n[R, U] (R U)2\n";
+        assert_eq!(aliases(text, &CubeNotation), vec![(2, "’ read as ', U2' read as U2".to_string())]);
+        // Square-1 turns are written in brackets, only ones written another way are aliases
+        let text = "/ (3,0) / (1,0) / (0,-3) /\n(7,-6) /\n";
+        assert_eq!(aliases(text, &Square1Notation), vec![(2, "(7,-6) read as (-5,6)".to_string())]);
+    }
+
+    #[test]
+    fn square1_notation() {
+        assert_eq!(parse_as(Puzzle::Square1, "/ (3,0) / (1,0) / (0,-3) /").unwrap(), "/ (3,0) / (1,0) / (0,-3) /");
+        assert_eq!(parse_as(Puzzle::Square1, "1,0/-1,-1/").unwrap(), "(1,0) / (-1,-1) /");
+        // Turns are kept between -5 and 6 and a turn of nothing is dropped
+        assert_eq!(parse_as(Puzzle::Square1, "(7,-6) / (0,0) /").unwrap(), "(-5,6) / /");
+        assert_eq!(inverse_as(Puzzle::Square1, "(1,0) / (-1,6) /"), "/ (1,6) / (-1,0)");
+        assert!(parse_as(Puzzle::Square1, "(1,0").is_err());
+        assert!(parse_as(Puzzle::Square1, "(1) /").is_err());
+        assert!(parse_as(Puzzle::Square1, "R U").is_err());
+    }
+
+    #[test]
+    fn square1_turn_amounts() {
+        // Any whole number of twelfths is read, however far past a full turn
+        assert_eq!(parse_as(Puzzle::Square1, "(125,0) / (127,-127) /").unwrap(), "(5,0) / (-5,5) /");
+        assert_eq!(parse_as(Puzzle::Square1, "(-6,-13) / (-17,24)").unwrap(), "(6,-1) / (-5,0)");
+        assert_eq!(parse_as(Puzzle::Square1, "(2147483647,-2147483648)").unwrap(), "(-5,4)");
+        assert_eq!(inverse_as(Puzzle::Square1, "(6,-5) /"), "/ (6,5)");
+        assert!(parse_as(Puzzle::Square1, "(99999999999,0)").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use ratatui::{
+    crossterm::event::KeyEvent, layout::Constraint, style::{Style, Stylize}, widgets::{Block, Row, Table}, Frame
+};
+
+use crate::app::{App, Nav};
+use crate::config::Config;
+use crate::db::{AlgDB, AlgEntry};
+use crate::profile::timings;
+
+// Hidden page with the timings from `profile`, to spot slowdowns with large alg directories
+#[derive(Debug)]
+pub struct DebugPage {
+    algsets: usize,
+    cases: usize,
+}
+
+impl DebugPage {
+    pub fn new(db: &AlgDB) -> DebugPage {
+        fn count(entries: &[AlgEntry], algsets: &mut usize, cases: &mut usize) {
+            for entry in entries {
+                match entry {
+                    AlgEntry::Group(_, entries) => count(entries, algsets, cases),
+                    AlgEntry::Algs(_, algset) => {
+                        *algsets += 1;
+                        *cases += algset.algs.len();
+                    }
+                }
+            }
+        }
+
+        let (mut algsets, mut cases) = (0, 0);
+        count(&db.entries, &mut algsets, &mut cases);
+        DebugPage { algsets, cases }
+    }
+
+    pub fn handle_key<'a>(&mut self, app: &mut App<'a>, key: KeyEvent) -> Nav<'a> {
+        let keys = &app.config.keys;
+        if keys.back.matches(key.code) || keys.quit.matches(key.code) {
+            return Nav::Pop;
+        }
+        Nav::Stay
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, config: &Config) {
+        let micros = |duration: std::time::Duration| format!("{} µs", duration.as_micros());
+        let rows: Vec<Row> = timings().into_iter()
+            .map(|(name, timing)| Row::new(vec![name.to_string(), timing.count.to_string(), micros(timing.last), micros(timing.mean()), micros(timing.max)]))
+            .collect();
+        let header = Row::new(vec!["", "Count", "Last", "Mean", "Max"]).style(Style::new().bold().underlined());
+        let widths = [Constraint::Min(24), Constraint::Length(8), Constraint::Length(12), Constraint::Length(12), Constraint::Length(12)];
+        let title = format!(" Debug · {} alg sets, {} cases ", self.algsets, self.cases);
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(title).title_bottom(format!(" {}: back ", config.keys.back.label())).border_style(Style::new().fg(config.colors.highlight)));
+        frame.render_widget(table, frame.area());
+    }
+}
@@ -0,0 +1,74 @@
+use ratatui::{
+    crossterm::event::KeyEvent, layout::Constraint, style::{Style, Stylize}, widgets::{Block, Row, Table, TableState}, Frame
+};
+
+use crate::app::{App, Nav};
+use crate::config::Config;
+use crate::db::{Diagnostic, DiagnosticKind};
+
+// What loading the alg directory left out or read differently, with the file and line to fix it at
+#[derive(Debug)]
+pub struct DiagnosticsPage {
+    diagnostics: Vec<Diagnostic>,
+    table: TableState,
+}
+
+impl DiagnosticsPage {
+    pub fn new(diagnostics: &[Diagnostic]) -> DiagnosticsPage {
+        DiagnosticsPage {
+            diagnostics: diagnostics.to_vec(),
+            table: TableState::default().with_selected(Some(0)),
+        }
+    }
+
+    pub fn handle_key<'a>(&mut self, app: &mut App<'a>, key: KeyEvent) -> Nav<'a> {
+        let keys = &app.config.keys;
+        match key.code {
+            code if keys.up.matches(code) => self.table.select_previous(),
+            code if keys.down.matches(code) => {
+                let last = self.diagnostics.len().saturating_sub(1);
+                self.table.select(Some((self.table.selected().unwrap_or(0) + 1).min(last)));
+            }
+            code if keys.edit.matches(code) => {
+                if let Some(diagnostic) = self.table.selected().and_then(|idx| self.diagnostics.get(idx)) && diagnostic.file.is_file() {
+                    return Nav::Edit(diagnostic.file.clone());
+                }
+            }
+            code if keys.back.matches(code) || keys.quit.matches(code) || keys.diagnostics.matches(code) => {
+                return Nav::Pop;
+            }
+            _ => {}
+        }
+        Nav::Stay
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, config: &Config) {
+        let rows: Vec<Row> = self.diagnostics.iter()
+            .map(|diagnostic| {
+                // "file:line" is what editors and terminals open at the line
+                let place = match diagnostic.line {
+                    Some(line) => format!("{}:{}", diagnostic.file.display(), line),
+                    None => diagnostic.file.display().to_string(),
+                };
+                let row = Row::new(vec![diagnostic.kind.as_text().to_string(), place, diagnostic.message.clone()]);
+                match diagnostic.kind {
+                    DiagnosticKind::Alias => row.dim(),
+                    _ => row,
+                }
+            })
+            .collect();
+
+        let skipped = self.diagnostics.iter().filter(|diagnostic| diagnostic.kind != DiagnosticKind::Alias).count();
+        let title = format!(" Diagnostics · {} skipped, {} aliases ", skipped, self.diagnostics.len() - skipped);
+        let header = Row::new(vec!["", "Where", "What"]).style(Style::new().bold().underlined());
+        let widths = [Constraint::Length(13), Constraint::Percentage(45), Constraint::Fill(1)];
+        let keys = &config.keys;
+        let hint = format!(" {}/{}: move  {}: edit the file  {}: back ", keys.down.label(), keys.up.label(), keys.edit.label(), keys.back.label());
+        let table = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::new().fg(config.colors.highlight))
+            .highlight_symbol("> ")
+            .block(Block::bordered().title(title).title_bottom(hint));
+        frame.render_stateful_widget(table, frame.area(), &mut self.table);
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::{Mistake, Solve};
+
+// A periodic mastery check: some random cases of the enabled sets, each attempted once and graded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExamResult {
+    pub date: SystemTime,
+    // Paths of the alg sets the cases were drawn from, sorted
+    pub algsets: Vec<String>,
+    pub cases: usize,
+    // Cases attempted without being marked as misrecognized
+    pub recognized: usize,
+    // Cases solved within their target time
+    pub on_target: usize,
+    // Percentage, recognition and time counting half each
+    pub grade: f64,
+}
+
+impl ExamResult {
+    // `attempts` has the solve of every case drawn, None if it was skipped, with the case's target time.
+    // `date` is when the exam ended, the caller reads the clock
+    pub fn grade(mut algsets: Vec<String>, attempts: &[(Option<&Solve>, Duration)], date: SystemTime) -> ExamResult {
+        algsets.sort();
+        let recognized = attempts.iter()
+            .filter(|(solve, _)| solve.is_some_and(|solve| solve.mistake != Some(Mistake::Misrecognition)))
+            .count();
+        let on_target = attempts.iter()
+            .filter(|(solve, target)| solve.and_then(Solve::result).is_some_and(|time| time <= *target))
+            .count();
+        let grade = if attempts.is_empty() {
+            0.0
+        } else {
+            (recognized + on_target) as f64 * 50.0 / attempts.len() as f64
+        };
+        ExamResult { date, algsets, cases: attempts.len(), recognized, on_target, grade }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Penalty;
+
+    fn solve(millis: u64, penalty: Penalty, mistake: Option<Mistake>) -> Solve {
+        Solve {
+            date: SystemTime::UNIX_EPOCH,
+            scramble: "R U R'".to_string(),
+            algset: "3x3/pll".to_string(),
+            alg: 0,
+            time: Duration::from_millis(millis),
+            penalty,
+            tags: Vec::new(),
+            comment: String::new(),
+            mistake,
+            records: Vec::new(),
+            moves: None,
+            turns: None,
+        }
+    }
+
+    fn graded(attempts: &[(Option<&Solve>, Duration)]) -> ExamResult {
+        ExamResult::grade(vec!["3x3/pll".to_string(), "3x3/oll".to_string()], attempts, SystemTime::UNIX_EPOCH)
+    }
+
+    #[test]
+    fn full_marks() {
+        let fast = solve(1500, Penalty::None, None);
+        let target = Duration::from_secs(2);
+        let result = graded(&[(Some(&fast), target), (Some(&fast), target)]);
+        assert_eq!(result.algsets, ["3x3/oll", "3x3/pll"]);
+        assert_eq!((result.cases, result.recognized, result.on_target), (2, 2, 2));
+        assert_eq!(result.grade, 100.0);
+        // The target itself is still on target
+        let exact = solve(2000, Penalty::None, None);
+        assert_eq!(graded(&[(Some(&exact), target)]).on_target, 1);
+    }
+
+    #[test]
+    fn missed_cases() {
+        let fast = solve(1500, Penalty::None, None);
+        let misrecognized = solve(1500, Penalty::None, Some(Mistake::Misrecognition));
+        let slip = solve(1500, Penalty::None, Some(Mistake::ExecutionSlip));
+        let target = Duration::from_secs(2);
+        // A skipped case counts for neither, a misrecognized one is only on target, other mistakes still recognize it
+        let result = graded(&[(Some(&fast), target), (None, target), (Some(&misrecognized), target), (Some(&slip), target)]);
+        assert_eq!((result.cases, result.recognized, result.on_target), (4, 2, 3));
+        assert_eq!(result.grade, 62.5);
+        assert_eq!(graded(&[]).grade, 0.0);
+    }
+
+    #[test]
+    fn penalties() {
+        let target = Duration::from_secs(3);
+        // A DNF is recognized but never on target, +2 counts against the target
+        let dnf = solve(1000, Penalty::Dnf, None);
+        let plus_two = solve(1500, Penalty::PlusTwo, None);
+        let plus_two_in_time = solve(900, Penalty::PlusTwo, None);
+        let result = graded(&[(Some(&dnf), target), (Some(&plus_two), target), (Some(&plus_two_in_time), target)]);
+        assert_eq!((result.recognized, result.on_target), (3, 1));
+        assert_eq!(result.grade, 4.0 * 50.0 / 3.0);
+    }
+}
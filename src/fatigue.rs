@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local, Timelike};
+
+use crate::session::Solve;
+use crate::timer::format_time;
+
+// Buckets with fewer solves are too noisy to say anything
+const MIN_SOLVES: usize = 5;
+const BAR_WIDTH: usize = 30;
+
+// Solves grouped by the hour they were done at or by how far into a session they came
+#[derive(Debug)]
+pub struct Bucket {
+    pub label: String,
+    pub solves: usize,
+    pub dnfs: usize,
+    pub mean: Option<Duration>,
+    // Mean of each solve's time over the mean of its case, so sessions of harder cases don't look
+    // like fatigue. 1.05 is 5% slower than usual
+    pub relative: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct Fatigue {
+    pub by_hour: Vec<Bucket>,
+    // Every session fills these from the first, so none are empty
+    pub by_position: Vec<Bucket>,
+    // Solves per position bucket
+    pub size: usize,
+}
+
+// Each solve's time over its case's mean, None for DNFs
+fn relative_times(solves: &[&Solve]) -> Vec<Option<f64>> {
+    let mut cases: HashMap<(&str, usize), (f64, usize)> = HashMap::new();
+    for solve in solves {
+        if let Some(result) = solve.result() {
+            let (sum, count) = cases.entry(solve.case()).or_default();
+            *sum += result.as_secs_f64();
+            *count += 1;
+        }
+    }
+    solves.iter()
+        .map(|solve| {
+            let (sum, count) = cases.get(&solve.case())?;
+            Some(solve.result()?.as_secs_f64() / (sum / *count as f64))
+        })
+        .collect()
+}
+
+fn bucket(label: String, solves: &[(&Solve, Option<f64>)]) -> Bucket {
+    let results: Vec<Duration> = solves.iter().filter_map(|(solve, _)| solve.result()).collect();
+    let relative: Vec<f64> = solves.iter().filter_map(|(_, relative)| *relative).collect();
+    Bucket {
+        label,
+        solves: solves.len(),
+        dnfs: solves.len() - results.len(),
+        mean: (!results.is_empty()).then(|| results.iter().sum::<Duration>() / results.len() as u32),
+        relative: (!relative.is_empty()).then(|| relative.iter().sum::<f64>() / relative.len() as f64),
+    }
+}
+
+impl Fatigue {
+    // Sessions end at a break longer than `gap`, positions are grouped `size` solves at a time
+    pub fn of(solves: &[Solve], gap: Duration, size: usize) -> Fatigue {
+        let size = size.max(1);
+        let mut solves: Vec<&Solve> = solves.iter().collect();
+        solves.sort_by_key(|solve| solve.date);
+        let relative = relative_times(&solves);
+        let solves: Vec<(&Solve, Option<f64>)> = solves.into_iter().zip(relative).collect();
+
+        let mut hours: Vec<Vec<(&Solve, Option<f64>)>> = vec![Vec::new(); 24];
+        let mut positions: Vec<Vec<(&Solve, Option<f64>)>> = Vec::new();
+        let mut position = 0;
+        let mut last: Option<SystemTime> = None;
+        for (solve, relative) in solves {
+            let local: DateTime<Local> = solve.date.into();
+            hours[local.hour() as usize].push((solve, relative));
+
+            let pause = last.and_then(|last| solve.date.duration_since(last).ok());
+            if last.is_none() || pause.is_some_and(|pause| pause > gap) {
+                position = 0;
+            }
+            last = Some(solve.date);
+            let idx = position / size;
+            if positions.len() <= idx {
+                positions.resize(idx + 1, Vec::new());
+            }
+            positions[idx].push((solve, relative));
+            position += 1;
+        }
+
+        Fatigue {
+            by_hour: hours.iter().enumerate()
+                .filter(|(_, solves)| !solves.is_empty())
+                .map(|(hour, solves)| bucket(format!("{:02}:00", hour), solves))
+                .collect(),
+            by_position: positions.iter().enumerate()
+                .map(|(idx, solves)| bucket(format!("#{}-{}", idx * size + 1, (idx + 1) * size), solves))
+                .collect(),
+            size,
+        }
+    }
+
+    // The first solve of the first position bucket that's clearly slower than the start of a
+    // session, and how much slower
+    fn slowdown(&self) -> Option<(usize, f64)> {
+        let mut buckets = self.by_position.iter().enumerate().filter(|(_, bucket)| bucket.solves >= MIN_SOLVES);
+        let start = buckets.next()?.1.relative?;
+        buckets
+            .filter_map(|(idx, bucket)| Some((idx * self.size + 1, bucket.relative? / start - 1.0)))
+            .find(|(_, slower)| *slower >= 0.05)
+    }
+
+    pub fn text(&self) -> String {
+        let mut lines = vec!["By time of day".to_string()];
+        lines.extend(chart(&self.by_hour));
+        lines.push(String::new());
+        lines.push("By position in the session".to_string());
+        lines.extend(chart(&self.by_position));
+        lines.push(String::new());
+        lines.push(match self.slowdown() {
+            Some((solve, slower)) => format!("From solve {} on, times are {:.0}% slower than at the start of a session", solve, slower * 100.0),
+            None => "Times don't get slower later in a session".to_string(),
+        });
+        lines.join("\n")
+    }
+}
+
+// A row per bucket with a bar for its relative time, the slowest bucket getting the longest bar
+fn chart(buckets: &[Bucket]) -> Vec<String> {
+    let shown: Vec<&Bucket> = buckets.iter().filter(|bucket| bucket.solves >= MIN_SOLVES).collect();
+    let values: Vec<f64> = shown.iter().filter_map(|bucket| bucket.relative).collect();
+    if values.is_empty() {
+        return vec![format!("  Not enough solves yet, at least {} per row are needed", MIN_SOLVES)];
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    shown.iter()
+        .map(|bucket| {
+            let (bar, relative) = match bucket.relative {
+                Some(relative) => {
+                    let length = 1 + ((relative - min) / (max - min).max(f64::EPSILON) * (BAR_WIDTH - 1) as f64).round() as usize;
+                    ("█".repeat(length), format!("{:+.0}%", (relative - 1.0) * 100.0))
+                }
+                None => (String::new(), "-".to_string()),
+            };
+            let mean = bucket.mean.map(format_time).unwrap_or("-".to_string());
+            format!("  {:<9} {:>5} solves {:>3} DNF  mean {:>7} {:>5}  {}", bucket.label, bucket.solves, bucket.dnfs, mean, relative, bar)
+        })
+        .collect()
+}
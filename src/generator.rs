@@ -0,0 +1,164 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::cube::same_case;
+use crate::db::{moves_text, AlgSet, Movement, RubiksError};
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut text);
+        }
+        text
+    })
+}
+
+// Longest a generator gets for a scramble before it's stopped
+const GENERATOR_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Runs a set's "#! generator:" command for a scramble of a case. It gets the case in RUBIKS_SET,
+// RUBIKS_CASE and RUBIKS_ALG and prints the scramble on its first line, which has to set up the case
+// when the cube model can tell
+pub fn generate(command: &str, algset: &AlgSet, alg: usize) -> Result<Vec<Movement>, RubiksError> {
+    let case = &algset.algs[alg];
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command)
+        .env("RUBIKS_SET", &algset.path)
+        .env("RUBIKS_CASE", case.name.as_deref().unwrap_or_default())
+        .env("RUBIKS_ALG", moves_text(&case.moves))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = algset.file.as_ref().and_then(|file| file.parent()) {
+        shell.current_dir(dir);
+    }
+    let mut child = shell.spawn().map_err(RubiksError::IOError)?;
+    // Read on their own threads so a generator writing a lot can't fill a pipe and stall
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(RubiksError::IOError)? {
+            break status;
+        }
+        if started.elapsed() >= GENERATOR_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(RubiksError::InvalidFile(format!("{} took longer than {} seconds", command, GENERATOR_TIMEOUT.as_secs())));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    if !status.success() {
+        let stderr = stderr.join().unwrap_or_default();
+        return Err(RubiksError::InvalidFile(format!("{} failed: {}", command, stderr.trim())));
+    }
+    let stdout = stdout.join().unwrap_or_default();
+    let scramble = AlgSet::parse_moves(stdout.lines().next().unwrap_or_default().trim(), algset.puzzle().notation())?;
+    let inverse: Vec<Movement> = scramble.iter().rev().map(Movement::inv).collect();
+    if let Some(size) = algset.puzzle().modeled_size() && !same_case(&case.moves, &inverse, size) {
+        return Err(RubiksError::InvalidFile(format!("{} gave {}, which doesn't set up the case", command, moves_text(&scramble))));
+    }
+    Ok(scramble)
+}
+
+// A generator making a scramble on its own thread, so the trainer isn't held up while it runs
+#[derive(Debug)]
+pub struct Generating {
+    // The case the scramble is for
+    pub algset: String,
+    pub alg: usize,
+    result: Receiver<Result<Vec<Movement>, RubiksError>>,
+}
+
+impl Generating {
+    // None when the set has no generator
+    pub fn start(algset: &AlgSet, alg: usize) -> Option<Generating> {
+        let command = algset.meta.generator.clone()?;
+        let set = algset.clone();
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(generate(&command, &set, alg));
+        });
+        Some(Generating { algset: algset.path.clone(), alg, result })
+    }
+
+    // The scramble or why there's none, once the generator is done
+    pub fn result(&self) -> Option<Result<Vec<Movement>, RubiksError>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(RubiksError::InvalidFile("the generator stopped without a scramble".to_string()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(generator: &str) -> AlgSet {
+        set_in("oll.txt", generator)
+    }
+
+    fn set_in(path: &str, generator: &str) -> AlgSet {
+        let text = format!("#! generator: {}\nSune: R U R' U R U2 R'", generator);
+        let (algset, skipped) = AlgSet::read_text(path.to_string(), &text, None);
+        assert!(skipped.is_empty());
+        algset
+    }
+
+    #[test]
+    fn generated_scrambles() {
+        let algset = set("echo \"R U2 R' U' R U' R'\"");
+        let scramble = generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).unwrap();
+        assert_eq!(moves_text(&scramble), "R U2 R' U' R U' R'");
+        // The case comes in the environment
+        let algset = set("test \"$RUBIKS_CASE\" = Sune && echo \"U2 R U2 R' U' R U' R'\"");
+        assert!(generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).is_ok());
+        // A 2x2 scramble only has to set up the corners
+        let algset = set_in("2x2/oll.txt", "echo \"R U2 R' U' R U' R' R2 U2 R2 U2 R2 U2\"");
+        assert!(generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).is_ok());
+        let algset = set("echo \"R U2 R' U' R U' R' R2 U2 R2 U2 R2 U2\"");
+        assert!(generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).is_err());
+    }
+
+    #[test]
+    fn bad_generators() {
+        // Another case's scramble
+        let algset = set("echo R U R\\' U\\'");
+        assert!(generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).is_err());
+        let algset = set("echo oops; exit 1");
+        assert!(generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).is_err());
+        let algset = set("echo Q");
+        assert!(generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).is_err());
+    }
+
+    #[test]
+    fn slow_generator() {
+        let algset = set("sleep 30");
+        let started = Instant::now();
+        assert!(generate(algset.meta.generator.as_ref().unwrap(), &algset, 0).is_err());
+        assert!(started.elapsed() < GENERATOR_TIMEOUT + Duration::from_secs(2));
+    }
+
+    #[test]
+    fn generating_in_the_background() {
+        let started = Instant::now();
+        let generating = Generating::start(&set("sleep 1; echo \"R U2 R' U' R U' R'\""), 0).unwrap();
+        // It's started without waiting for the scramble
+        assert!(generating.result().is_none());
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let result = loop {
+            if let Some(result) = generating.result() {
+                break result;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(moves_text(&result.unwrap()), "R U2 R' U' R U' R'");
+        assert!(Generating::start(&AlgSet::from_text("oll.txt".to_string(), "R U R' U'").unwrap(), 0).is_none());
+    }
+}
@@ -0,0 +1,272 @@
+use std::{collections::HashSet, time::Duration};
+
+use eframe::egui::{self, Color32, Event, Key, Pos2, Rect, RichText, Sense, Vec2};
+use rand::rngs::StdRng;
+use ratatui::style::Color;
+
+use crate::config::{rgb, Config, FaceColors};
+use crate::cube::{CubeState, Face};
+use crate::db::{AlgDB, AlgSet};
+use crate::generator::Generating;
+use crate::history::{History, HistoryError};
+use crate::lock::InstanceLock;
+use crate::pb;
+use crate::scramble::{get_scramble, Scramble};
+use crate::session::{Penalty, Session};
+use crate::timer::{format_result, Timer, TimerState};
+
+const STICKER: f32 = 18.0;
+
+// Window with the scramble, the scrambled cube and a timer, for people who don't live in a
+// terminal. Alg sets are picked from a side panel instead of the Setup tree
+pub struct Gui<'a> {
+    algsets: Vec<&'a AlgSet>,
+    // Paths of the enabled sets
+    enabled: HashSet<String>,
+    config: &'a Config,
+    history: History,
+    faces: FaceColors,
+    scramble: Option<Scramble>,
+    // The set's generator making the scramble, which is waited for
+    generating: Option<Generating>,
+    // Why the generator's scramble couldn't be used, the case's alg sets it up instead
+    generator_error: Option<String>,
+    timer: Timer,
+    session: Session,
+    save_error: Option<HistoryError>,
+    rng: StdRng,
+    // Lock on the data files, None when another session owns them
+    pub lock: Option<InstanceLock>,
+    // Another session owns the data files, so solves aren't saved
+    pub read_only: bool,
+}
+
+impl<'a> Gui<'a> {
+    pub fn new(db: &'a AlgDB, enabled: &[&AlgSet], config: &'a Config, history: History, rng: StdRng) -> Gui<'a> {
+        let mut gui = Gui {
+            algsets: db.matching("**"),
+            enabled: enabled.iter().map(|algset| algset.path.clone()).collect(),
+            config,
+            history,
+            faces: config.cube.face_colors().unwrap_or_default(),
+            scramble: None,
+            generating: None,
+            generator_error: None,
+            timer: Timer::new(Duration::from_millis(config.training.hold_ms), config.training.inspection),
+            session: Session::default(),
+            save_error: None,
+            rng,
+            lock: None,
+            read_only: false,
+        };
+        gui.next();
+        gui
+    }
+
+    pub fn run(self) -> Result<(), eframe::Error> {
+        let options = eframe::NativeOptions::default();
+        eframe::run_native("Rubik's Trainer", options, Box::new(|_| Ok(Box::new(self))))
+    }
+
+    fn next(&mut self) {
+        let algs: Vec<&AlgSet> = self.algsets.iter().copied().filter(|algset| self.enabled.contains(&algset.path)).collect();
+        self.scramble = get_scramble(&algs, self.config.training.variation(), &mut self.rng);
+        self.generating = self.scramble.as_ref().and_then(|scramble| {
+            let algset = algs.iter().find(|algset| algset.path == scramble.algset)?;
+            Generating::start(algset, scramble.alg)
+        });
+        self.generator_error = None;
+    }
+
+    // Swaps in the generator's scramble once it's made
+    fn generated(&mut self) {
+        let Some(result) = self.generating.as_ref().and_then(Generating::result) else {
+            return;
+        };
+        let Some(generating) = self.generating.take() else {
+            return;
+        };
+        match result {
+            Ok(moves) => {
+                if let Some(algset) = self.algsets.iter().find(|algset| algset.path == generating.algset) {
+                    self.scramble = Some(Scramble::generated(algset, generating.alg, moves, self.config.training.variation(), &mut self.rng));
+                }
+            }
+            Err(err) => self.generator_error = Some(err.to_string()),
+        }
+    }
+
+    fn record(&mut self, time: Duration, penalty: Penalty) {
+        let Some(scramble) = &self.scramble else {
+            return;
+        };
+        self.session.add(scramble, time, penalty);
+        let records = pb::records(&self.session.solves, &self.history.solves);
+        let solve = self.session.last_mut().unwrap();
+        solve.records = records;
+        self.save_error = self.history.add(solve.clone()).err();
+    }
+
+    // Space works like in the terminal: hold, release to start, any key stops
+    fn handle_input(&mut self, ctx: &egui::Context) {
+        let (pressed, released, any) = ctx.input(|input| {
+            let any = input.events.iter().any(|event| matches!(event, Event::Key { pressed: true, repeat: false, .. }));
+            (input.key_pressed(Key::Space), input.key_released(Key::Space), any)
+        });
+
+        if self.timer.is_running() {
+            if any && let Some((time, penalty)) = self.timer.press() {
+                self.record(time, penalty);
+                self.next();
+            }
+            return;
+        }
+        if self.scramble.is_none() || self.generating.is_some() {
+            return;
+        }
+        if pressed {
+            self.timer.press();
+        }
+        if released {
+            self.timer.release();
+        }
+    }
+
+    fn side_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("algsets").show(ctx, |ui| {
+            ui.heading("Alg sets");
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let mut changed = false;
+                for algset in self.algsets.iter() {
+                    let mut enabled = self.enabled.contains(&algset.path);
+                    if ui.checkbox(&mut enabled, &algset.path).changed() {
+                        changed = true;
+                        if enabled {
+                            self.enabled.insert(algset.path.clone());
+                        } else {
+                            self.enabled.remove(&algset.path);
+                        }
+                    }
+                }
+                if changed && !self.timer.is_running() {
+                    self.next();
+                }
+            });
+        });
+    }
+}
+
+impl eframe::App for Gui<'_> {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(lock) = &mut self.lock {
+            if !lock.beat() {
+                self.lock = None;
+                self.history.detach();
+                self.read_only = true;
+            }
+            // Keeps the lock beating while nothing happens in the window
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+        self.generated();
+        if self.generating.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+        self.handle_input(ctx);
+        self.side_panel(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                let Some(scramble) = &self.scramble else {
+                    ui.label("Enable some alg sets to start training");
+                    return;
+                };
+
+                // The scramble is hidden while solving so it doesn't distract
+                if self.generating.is_some() {
+                    ui.label(RichText::new("Generating a scramble").size(24.0).weak());
+                } else if !self.timer.is_running() {
+                    ui.label(RichText::new(&scramble.text).size(24.0));
+                    let mut state = CubeState::solved();
+                    if let Ok(movements) = AlgSet::parse_scramble(&scramble.text) {
+                        state.apply(&movements);
+                    }
+                    let puzzle = self.algsets.iter().find(|algset| algset.path == scramble.algset).map(|algset| algset.puzzle());
+                    if let Some(size) = puzzle.map_or(Some(3), |puzzle| puzzle.modeled_size()) {
+                        draw_net(ui, &state, size, &self.faces);
+                    }
+                }
+
+                let color = if self.timer.is_ready() { color32(self.config.colors.enabled) } else { ui.visuals().text_color() };
+                ui.label(RichText::new(self.timer.display()).size(64.0).monospace().color(color));
+
+                if self.read_only {
+                    ui.colored_label(Color32::YELLOW, "Read-only: another session owns the data files, nothing is saved");
+                }
+                if let Some(err) = &self.generator_error {
+                    ui.colored_label(Color32::RED, format!("Scrambled from the alg, the generator failed: {}", err));
+                }
+                if let Some(err) = &self.save_error {
+                    ui.colored_label(Color32::RED, format!("Couldn't save the solve: {}", err));
+                }
+                let results: Vec<String> = self.session.solves.iter().rev().take(5)
+                    .map(|solve| format_result(solve.time, solve.penalty))
+                    .collect();
+                ui.label(RichText::new(results.join("  ")).weak());
+            });
+        });
+
+        // Keeps the timer moving
+        if !matches!(self.timer.state, TimerState::Idle | TimerState::Stopped(..)) {
+            ctx.request_repaint();
+        }
+    }
+}
+
+// Same layout as the terminal net: U on top of F, L R and B around it and D below
+fn draw_net(ui: &mut egui::Ui, state: &CubeState, size: usize, colors: &FaceColors) {
+    let gap = STICKER / 3.0;
+    let face_size = STICKER * size as f32 + gap;
+    let (response, painter) = ui.allocate_painter(Vec2::new(face_size * 4.0, face_size * 3.0), Sense::hover());
+    let origin = response.rect.min;
+
+    let layout = [(Face::U, 1.0, 0.0), (Face::L, 0.0, 1.0), (Face::F, 1.0, 1.0), (Face::R, 2.0, 1.0), (Face::B, 3.0, 1.0), (Face::D, 1.0, 2.0)];
+    for (face, x, y) in layout {
+        for (row, stickers) in state.stickers(face, size).iter().enumerate() {
+            for (col, facelet) in stickers.iter().enumerate() {
+                let min = Pos2::new(origin.x + x * face_size + col as f32 * STICKER, origin.y + y * face_size + row as f32 * STICKER);
+                let rect = Rect::from_min_size(min, Vec2::splat(STICKER - 1.0));
+                painter.rect_filled(rect, 2.0, color32(colors.color(*facelet)));
+            }
+        }
+    }
+}
+
+fn color32(color: Color) -> Color32 {
+    let (r, g, b) = rgb(color);
+    Color32::from_rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn scrambles(db: &AlgDB, config: &Config, seed: u64) -> Vec<String> {
+        let enabled = db.matching("**");
+        let mut gui = Gui::new(db, &enabled, config, History::default(), StdRng::seed_from_u64(seed));
+        (0..10)
+            .map(|_| {
+                gui.next();
+                gui.scramble.as_ref().unwrap().text.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_scrambles() {
+        let db = AlgDB::demo();
+        let config = Config::default();
+        assert_eq!(scrambles(&db, &config, 7), scrambles(&db, &config, 7));
+        assert_ne!(scrambles(&db, &config, 7), scrambles(&db, &config, 8));
+    }
+}
@@ -0,0 +1,283 @@
+use std::{collections::HashSet, fmt, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::exam::ExamResult;
+use crate::session::Solve;
+use crate::snapshot::{self, Snapshot};
+
+#[derive(Debug)]
+pub enum HistoryError {
+    IOError(std::io::Error),
+    ParseError(serde_json::Error),
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryError::IOError(err) => write!(f, "IO Error: {}", err),
+            HistoryError::ParseError(err) => write!(f, "Invalid history file: {}", err),
+        }
+    }
+}
+
+// Every solve ever recorded, oldest first
+#[derive(Debug, Default)]
+pub struct History {
+    // Where the solves are saved, None keeps them in memory only (demo mode)
+    path: Option<PathBuf>,
+    pub solves: Vec<Solve>,
+    pub snapshots: Vec<Snapshot>,
+    pub exams: Vec<ExamResult>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn read<T: DeserializeOwned + Default>(path: &Path) -> Result<T, HistoryError> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let text = fs::read_to_string(path).map_err(HistoryError::IOError)?;
+    serde_json::from_str(&text).map_err(HistoryError::ParseError)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn write<T: Serialize>(path: &Path, value: &T) -> Result<(), HistoryError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(HistoryError::IOError)?;
+    }
+    let text = serde_json::to_string(value).map_err(HistoryError::ParseError)?;
+    // Written to a temporary file first so a crash can't leave a half written file
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, text).map_err(HistoryError::IOError)?;
+    fs::rename(&tmp, path).map_err(HistoryError::IOError)
+}
+
+// The browser has no files, loading and saving there fails
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn read<T: DeserializeOwned + Default>(_path: &Path) -> Result<T, HistoryError> {
+    Err(HistoryError::IOError(std::io::ErrorKind::Unsupported.into()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn write<T: Serialize>(_path: &Path, _value: &T) -> Result<(), HistoryError> {
+    Err(HistoryError::IOError(std::io::ErrorKind::Unsupported.into()))
+}
+
+impl History {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rubiks_trainer").join("history.json"))
+    }
+
+    // Weekly snapshots are kept next to the solves
+    fn snapshots_path(path: &Path) -> PathBuf {
+        path.with_file_name("snapshots.json")
+    }
+
+    fn exams_path(path: &Path) -> PathBuf {
+        path.with_file_name("exams.json")
+    }
+
+    // A missing file just means nothing was recorded yet
+    pub fn load(path: &Path) -> Result<History, HistoryError> {
+        Ok(History {
+            path: Some(path.to_path_buf()),
+            solves: read(path)?,
+            snapshots: read(&History::snapshots_path(path))?,
+            exams: read(&History::exams_path(path))?,
+        })
+    }
+
+    // New solves stay in memory only, for a session that doesn't own the data files
+    pub fn detach(&mut self) {
+        self.path = None;
+    }
+
+    pub fn save(&self) -> Result<(), HistoryError> {
+        match &self.path {
+            Some(path) => write(path, &self.solves),
+            None => Ok(()),
+        }
+    }
+
+    pub fn save_snapshots(&self) -> Result<(), HistoryError> {
+        match &self.path {
+            Some(path) => write(&History::snapshots_path(path), &self.snapshots),
+            None => Ok(()),
+        }
+    }
+
+    // Snapshots the weeks finished by `now` that don't have one yet, returns how many were taken
+    pub fn snapshot(&mut self, now: SystemTime, day_start_hour: u32) -> usize {
+        let new = snapshot::take(&self.solves, &self.snapshots, now, day_start_hour);
+        let count = new.len();
+        self.snapshots.extend(new);
+        self.snapshots.sort_by_key(|snapshot| snapshot.week);
+        count
+    }
+
+    // Drops solves more than `days` older than `now`, returns how many. Only solves from weeks that
+    // have a snapshot are dropped, so call `snapshot` first
+    pub fn prune(&mut self, days: u64, now: SystemTime, day_start_hour: u32) -> usize {
+        // A cutoff too far back to count means nothing is that old
+        let Some(cutoff) = days.checked_mul(24 * 60 * 60).and_then(|secs| now.checked_sub(Duration::from_secs(secs))) else {
+            return 0;
+        };
+        let count = self.solves.len();
+        let snapshots = &self.snapshots;
+        self.solves.retain(|solve| {
+            let week = snapshot::week_of(solve.date, day_start_hour);
+            solve.date >= cutoff || !snapshots.iter().any(|taken| taken.week == week)
+        });
+        count - self.solves.len()
+    }
+
+    // Removes solves recorded twice (same date, scramble and time), returns how many
+    pub fn dedup(&mut self) -> usize {
+        let count = self.solves.len();
+        let mut seen = HashSet::new();
+        self.solves.retain(|solve| seen.insert((solve.date, solve.scramble.clone(), solve.time)));
+        count - self.solves.len()
+    }
+
+    // Sorts solves by date and keeps a single snapshot per week, returns how many snapshots were dropped
+    pub fn compact(&mut self) -> usize {
+        self.solves.sort_by_key(|solve| solve.date);
+        let count = self.snapshots.len();
+        self.snapshots.sort_by_key(|snapshot| snapshot.week);
+        self.snapshots.dedup_by_key(|snapshot| snapshot.week);
+        count - self.snapshots.len()
+    }
+
+    // Size in bytes the solves and snapshots take on disk
+    pub fn size(&self) -> usize {
+        let solves = serde_json::to_string(&self.solves).map(|text| text.len()).unwrap_or(0);
+        let snapshots = serde_json::to_string(&self.snapshots).map(|text| text.len()).unwrap_or(0);
+        solves + snapshots
+    }
+
+    // Snapshots every finished week, then drops solves older than `keep_days` (0 keeps them all)
+    pub fn maintain(&mut self, keep_days: u64, now: SystemTime, day_start_hour: u32) -> Result<(), HistoryError> {
+        if self.snapshot(now, day_start_hour) > 0 {
+            self.save_snapshots()?;
+        }
+        if keep_days > 0 && self.prune(keep_days, now, day_start_hour) > 0 {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, solve: Solve) -> Result<(), HistoryError> {
+        self.solves.push(solve);
+        self.save()
+    }
+
+    pub fn add_exam(&mut self, exam: ExamResult) -> Result<(), HistoryError> {
+        self.exams.push(exam);
+        match &self.path {
+            Some(path) => write(&History::exams_path(path), &self.exams),
+            None => Ok(()),
+        }
+    }
+
+    // Used when the last solve gets tagged or classified after it was recorded
+    pub fn update_last(&mut self, solve: Solve) -> Result<(), HistoryError> {
+        if let Some(last) = self.solves.last_mut() {
+            *last = solve;
+        }
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{process, time::UNIX_EPOCH};
+
+    use super::*;
+    use crate::session::Penalty;
+
+    fn history_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rubiks_trainer-history-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("history.json")
+    }
+
+    // A solve from `days_ago`, in whole seconds as the file keeps them
+    fn solve(days_ago: u64, scramble: &str, millis: u64) -> Solve {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        Solve {
+            date: UNIX_EPOCH + Duration::from_secs(now - days_ago * 24 * 60 * 60),
+            scramble: scramble.to_string(),
+            algset: "3x3/pll".to_string(),
+            alg: 0,
+            time: Duration::from_millis(millis),
+            penalty: Penalty::None,
+            tags: Vec::new(),
+            comment: String::new(),
+            mistake: None,
+            records: Vec::new(),
+            moves: None,
+            turns: None,
+        }
+    }
+
+    fn scrambles(history: &History) -> Vec<&str> {
+        history.solves.iter().map(|solve| solve.scramble.as_str()).collect()
+    }
+
+    #[test]
+    fn prune_round_trip() {
+        let path = history_path("prune");
+        let mut history = History::load(&path).unwrap();
+        assert!(history.solves.is_empty());
+        for solve in [solve(100, "old", 1500), solve(60, "older than a month", 1800), solve(0, "today", 1200)] {
+            history.add(solve).unwrap();
+        }
+        let today = history.solves[2].clone();
+
+        // Nothing goes before its week has a snapshot
+        let mut history = History::load(&path).unwrap();
+        assert_eq!(scrambles(&history), ["old", "older than a month", "today"]);
+        assert_eq!(history.prune(30, SystemTime::now(), 4), 0);
+
+        history.maintain(30, SystemTime::now(), 4).unwrap();
+        assert_eq!(scrambles(&history), ["today"]);
+        let weeks = history.snapshots.len();
+        assert!(weeks >= 2);
+
+        let mut history = History::load(&path).unwrap();
+        assert_eq!(scrambles(&history), ["today"]);
+        assert_eq!((history.solves[0].date, history.solves[0].time), (today.date, today.time));
+        assert_eq!(history.snapshots.len(), weeks);
+        assert_eq!(history.prune(0, SystemTime::now(), 4), 0);
+        assert_eq!(history.prune(u64::MAX, SystemTime::now(), 4), 0);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn dedup() {
+        let path = history_path("dedup");
+        let mut history = History::load(&path).unwrap();
+        let first = solve(3, "R U R'", 1500);
+        history.solves = vec![
+            first.clone(),
+            // Another time or scramble at the same moment is a different solve
+            Solve { time: Duration::from_millis(1600), ..first.clone() },
+            Solve { scramble: "R' U' R".to_string(), ..first.clone() },
+            solve(2, "R U R'", 1500),
+            first.clone(),
+            Solve { comment: "edited".to_string(), ..first.clone() },
+        ];
+        assert_eq!(history.dedup(), 2);
+        assert_eq!(history.solves.len(), 4);
+        assert_eq!(history.solves[0].comment, "");
+        assert_eq!(history.dedup(), 0);
+
+        history.save().unwrap();
+        let mut history = History::load(&path).unwrap();
+        assert_eq!(history.solves.len(), 4);
+        assert_eq!(history.dedup(), 0);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -1,3 +1,74 @@
 pub mod db;
+pub mod markdown;
+pub mod bundle;
+#[cfg(feature = "tui")]
 pub mod app;
+#[cfg(feature = "tui")]
+pub mod tour;
+#[cfg(feature = "tui")]
+pub mod cli;
+#[cfg(feature = "tui")]
+pub mod config;
+pub mod timer;
+#[cfg(feature = "tui")]
+pub mod train;
+pub mod session;
+pub mod history;
+pub mod exam;
+// Files and processes, which the browser has neither of
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lock;
+pub mod selection;
+pub mod dates;
+pub mod snapshot;
+pub mod srs;
+pub mod pb;
+#[cfg(feature = "tui")]
+pub mod celebration;
+pub mod scramble;
+// Runs programs, which the browser can't
+#[cfg(not(target_arch = "wasm32"))]
+pub mod generator;
+pub mod cube;
+pub mod solver;
+pub mod metrics;
+pub mod coverage;
+pub mod cubing;
+pub mod cstimer;
+pub mod csv;
+#[cfg(feature = "tui")]
+pub mod net;
+pub mod stats;
+#[cfg(feature = "tui")]
+pub mod stats_page;
+pub mod cast;
+pub mod triggers;
+#[cfg(feature = "tui")]
+pub mod triggers_page;
+#[cfg(feature = "tui")]
+pub mod review_page;
+#[cfg(feature = "tui")]
+pub mod sheet;
+// Works from today's date, and the browser has no clock
+#[cfg(not(target_arch = "wasm32"))]
+pub mod status;
+pub mod fatigue;
+#[cfg(feature = "tui")]
+pub mod dashboard;
+#[cfg(feature = "tui")]
+pub mod live;
+#[cfg(feature = "tui")]
+pub mod stackmat;
+#[cfg(feature = "tui")]
+pub mod smart_cube;
+#[cfg(feature = "bluetooth")]
+pub mod bluetooth;
+#[cfg(feature = "tui")]
+pub mod profile;
+#[cfg(feature = "tui")]
+pub mod debug_page;
+#[cfg(feature = "tui")]
+pub mod diagnostics_page;
 
+#[cfg(feature = "gui")]
+pub mod gui;
@@ -0,0 +1,150 @@
+use std::{io::{self, BufRead, BufReader, Write}, net::{Ipv4Addr, TcpListener, TcpStream}, sync::mpsc::{self, Receiver, TryRecvError}, thread, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+
+// How often the state is sent again even when it didn't change, so a running timer shown from
+// it doesn't drift
+const RESEND: Duration = Duration::from_secs(1);
+
+// What the trainer is doing right now, for dashboards following it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Live {
+    // The case being trained, as "alg set · name"
+    pub case: Option<String>,
+    // How long the timer has been running, None while it's stopped
+    pub running: Option<Duration>,
+}
+
+impl Live {
+    // Whether a dashboard needs to be told, a running timer it keeps counting up by itself
+    fn differs(&self, other: &Live) -> bool {
+        self.case != other.case || self.running.is_some() != other.running.is_some()
+    }
+}
+
+// A socket on the loopback that dashboards connect to, sent a line of JSON whenever the state
+// changes. Its port is shared in the lock file
+#[derive(Debug)]
+pub struct LiveServer {
+    listener: TcpListener,
+    dashboards: Vec<TcpStream>,
+    // What was sent last and when
+    sent: Option<(Live, Instant)>,
+}
+
+impl LiveServer {
+    pub fn open() -> io::Result<LiveServer> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        listener.set_nonblocking(true)?;
+        Ok(LiveServer { listener, dashboards: Vec::new(), sent: None })
+    }
+
+    pub fn port(&self) -> io::Result<u16> {
+        self.listener.local_addr().map(|addr| addr.port())
+    }
+
+    // Takes in dashboards that connected since the last call and tells them the state. A dashboard
+    // that can't keep up or went away is dropped
+    pub fn publish(&mut self, live: &Live) {
+        let mut joined = Vec::new();
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                joined.push(stream);
+            }
+        }
+        let due = self.sent.as_ref().is_none_or(|(sent, at)| sent.differs(live) || at.elapsed() >= RESEND);
+        let Ok(mut line) = serde_json::to_string(live) else {
+            return;
+        };
+        line.push('\n');
+        if due {
+            self.dashboards.retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+            self.sent = Some((live.clone(), Instant::now()));
+        }
+        joined.retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+        self.dashboards.extend(joined);
+    }
+}
+
+// A dashboard's connection to the trainer's live state, read on a thread of its own
+#[derive(Debug)]
+pub struct LiveClient {
+    updates: Receiver<Live>,
+    pub live: Live,
+    // When `live` came in
+    at: Instant,
+}
+
+impl LiveClient {
+    pub fn connect(port: u16) -> io::Result<LiveClient> {
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+        let (sender, updates) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Ok(live) = serde_json::from_str(&line) && sender.send(live).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(LiveClient { updates, live: Live::default(), at: Instant::now() })
+    }
+
+    // Takes in what the trainer sent since the last call, false once it's gone
+    pub fn update(&mut self) -> bool {
+        loop {
+            match self.updates.try_recv() {
+                Ok(live) => {
+                    self.live = live;
+                    self.at = Instant::now();
+                }
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    // The trainer's timer, counted on from the last time it was sent
+    pub fn running(&self) -> Option<Duration> {
+        self.live.running.map(|running| running + self.at.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Waits for the next state the trainer sends
+    fn next(client: &mut LiveClient, server: &mut LiveServer, live: &Live) -> Live {
+        let start = Instant::now();
+        let before = client.at;
+        while client.at == before && start.elapsed() < Duration::from_secs(5) {
+            server.publish(live);
+            assert!(client.update());
+            thread::sleep(Duration::from_millis(10));
+        }
+        client.live.clone()
+    }
+
+    #[test]
+    fn live_state() {
+        let mut server = LiveServer::open().unwrap();
+        let mut client = LiveClient::connect(server.port().unwrap()).unwrap();
+        let training = Live { case: Some("3x3/pll · T".to_string()), running: None };
+        assert_eq!(next(&mut client, &mut server, &training), training);
+        assert_eq!(client.running(), None);
+
+        let timing = Live { case: training.case.clone(), running: Some(Duration::from_secs(2)) };
+        assert_eq!(next(&mut client, &mut server, &timing), timing);
+        assert!(client.running().is_some_and(|running| running >= Duration::from_secs(2)));
+
+        drop(server);
+        let start = Instant::now();
+        while client.update() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!client.update());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{fs::{self, OpenOptions}, io::{self, Write}, path::{Path, PathBuf}, process, thread, time::{Duration, SystemTime}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::{read, write, HistoryError};
+
+// A lock older than this belongs to a session that crashed or was killed
+const STALE: Duration = Duration::from_secs(30);
+const BEAT_EVERY: Duration = Duration::from_secs(5);
+// Times to try creating the lock file before giving up on a file that keeps getting in the way
+const CREATE_TRIES: usize = 20;
+
+fn modified_within(path: &Path, age: Duration) -> bool {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).is_ok_and(|modified| modified.elapsed().is_ok_and(|elapsed| elapsed < age))
+}
+
+// Who holds the lock. The holder rewrites `beat` every few seconds while it runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub started: SystemTime,
+    pub beat: SystemTime,
+    // Loopback port dashboards connect to for the session's live state
+    #[serde(default)]
+    pub port: Option<u16>,
+}
+
+impl LockOwner {
+    fn new() -> LockOwner {
+        let now = SystemTime::now();
+        LockOwner { pid: process::id(), started: now, beat: now, port: None }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.beat.elapsed().map_or(true, |age| age < STALE)
+    }
+
+    fn same_session(&self, other: &LockOwner) -> bool {
+        self.pid == other.pid && self.started == other.started
+    }
+}
+
+// Keeps two trainers from writing the same data files. Taking over a held lock makes the other
+// session notice on its next beat and stop saving
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+    owner: LockOwner,
+}
+
+impl InstanceLock {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rubiks_trainer").join("lock.json"))
+    }
+
+    // Takes the lock if it's free or stale, otherwise returns the session holding it. Creating the
+    // file is what takes it, so two sessions starting at once can't both get it
+    pub fn try_acquire(path: &Path) -> Result<Result<InstanceLock, LockOwner>, HistoryError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(HistoryError::IOError)?;
+        }
+        for _ in 0..CREATE_TRIES {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    let owner = LockOwner::new();
+                    let text = serde_json::to_string(&owner).map_err(HistoryError::ParseError)?;
+                    file.write_all(text.as_bytes()).map_err(HistoryError::IOError)?;
+                    return Ok(Ok(InstanceLock { path: path.to_path_buf(), owner }));
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(HistoryError::IOError(err)),
+            }
+            match read::<Option<LockOwner>>(path) {
+                Ok(Some(owner)) if owner.is_alive() => return Ok(Err(owner)),
+                // A session that is still writing the file it just made
+                Err(_) if modified_within(path, STALE) => thread::sleep(Duration::from_millis(50)),
+                _ => match fs::remove_file(path) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(HistoryError::IOError(err)),
+                },
+            }
+        }
+        Err(HistoryError::IOError(io::Error::new(io::ErrorKind::WouldBlock, "the lock file can't be read")))
+    }
+
+    // The session holding the lock, if it's still running
+    pub fn holder(path: &Path) -> Option<LockOwner> {
+        read::<Option<LockOwner>>(path).ok().flatten().filter(LockOwner::is_alive)
+    }
+
+    // Tells dashboards which port to follow the session on
+    pub fn share(&mut self, port: u16) -> Result<(), HistoryError> {
+        self.owner.port = Some(port);
+        write(&self.path, &self.owner)
+    }
+
+    // Takes the lock over from the session holding it
+    pub fn take_over(path: &Path) -> Result<InstanceLock, HistoryError> {
+        let owner = LockOwner::new();
+        write(path, &owner)?;
+        Ok(InstanceLock { path: path.to_path_buf(), owner })
+    }
+
+    // Keeps the lock fresh, returns false once another session took it over
+    pub fn beat(&mut self) -> bool {
+        if self.owner.beat.elapsed().is_ok_and(|age| age < BEAT_EVERY) {
+            return true;
+        }
+        let current: Option<LockOwner> = read(&self.path).ok().flatten();
+        if current.is_some_and(|current| !current.same_session(&self.owner)) {
+            return false;
+        }
+        self.owner.beat = SystemTime::now();
+        // A missed beat only matters after several in a row
+        let _ = write(&self.path, &self.owner);
+        true
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let current: Option<LockOwner> = read(&self.path).ok().flatten();
+        if current.is_some_and(|current| current.same_session(&self.owner)) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rubiks_trainer-lock-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("lock.json")
+    }
+
+    #[test]
+    fn held_lock() {
+        let path = lock_path("held");
+        let lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+        let owner = InstanceLock::try_acquire(&path).unwrap().unwrap_err();
+        assert!(owner.same_session(&lock.owner));
+        drop(lock);
+        assert!(!path.exists());
+        assert!(InstanceLock::try_acquire(&path).unwrap().is_ok());
+    }
+
+    #[test]
+    fn shared_port() {
+        let path = lock_path("shared");
+        let mut lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+        assert_eq!(InstanceLock::holder(&path).unwrap().port, None);
+        lock.share(4242).unwrap();
+        assert_eq!(InstanceLock::holder(&path).unwrap().port, Some(4242));
+        drop(lock);
+        assert_eq!(InstanceLock::holder(&path), None);
+    }
+
+    #[test]
+    fn stale_lock() {
+        let path = lock_path("stale");
+        let mut owner = LockOwner::new();
+        owner.beat -= STALE * 2;
+        write(&path, &owner).unwrap();
+        let lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+        assert!(!lock.owner.same_session(&owner));
+    }
+
+    #[test]
+    fn taken_over_lock() {
+        let path = lock_path("taken");
+        let mut lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+        // A later start, as if from another session
+        thread::sleep(Duration::from_millis(10));
+        let other = InstanceLock::take_over(&path).unwrap();
+        lock.owner.beat -= BEAT_EVERY;
+        assert!(!lock.beat());
+        drop(lock);
+        assert!(path.exists());
+        drop(other);
+    }
+}
@@ -1,15 +1,851 @@
-use std::env;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, Local, NaiveDate};
+use clap::{CommandFactory, Parser};
+use rand::{rngs::StdRng, SeedableRng};
 use rubiks_trainer::app::App;
-use rubiks_trainer::db::AlgDB;
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let alg_dir: String = args.get(1).map(|str| str.clone()).unwrap_or(".".to_string());
-    let db = AlgDB::load(PathBuf::from(alg_dir));
+use rubiks_trainer::cast::{default_name, session_cast};
+use rubiks_trainer::bundle::Bundle;
+use rubiks_trainer::cli::{AlgFormat, BundleCommand, Cli, Command, HistoryCommand, ImportCommand, TrainArgs};
+use rubiks_trainer::config::Config;
+use rubiks_trainer::cube::{check_alg, same_case};
+use rubiks_trainer::dashboard::Dashboard;
+use rubiks_trainer::cstimer::CsTimerSession;
+use rubiks_trainer::csv::{self, Columns, Sheet};
+use rubiks_trainer::cubing::AlgJson;
+use rubiks_trainer::coverage::{CaseRef, Coverage, Duplicates};
+use rubiks_trainer::dates::day_of;
+use rubiks_trainer::fatigue::Fatigue;
+use rubiks_trainer::db::{AlgDB, AlgEntry, AlgSet, DiagnosticKind, Movement, Puzzle, RubiksError};
+#[cfg(feature = "gui")]
+use rubiks_trainer::gui::Gui;
+use rubiks_trainer::history::History;
+use rubiks_trainer::live::LiveServer;
+use rubiks_trainer::lock::InstanceLock;
+use rubiks_trainer::markdown;
+use rubiks_trainer::metrics::MoveCount;
+use rubiks_trainer::profile;
+use rubiks_trainer::selection::Selection;
+use rubiks_trainer::generator::generate;
+use rubiks_trainer::scramble::{get_scramble_avoiding, Scramble, Variation};
+use rubiks_trainer::session::Solve;
+use rubiks_trainer::sheet;
+use rubiks_trainer::smart_cube::SmartCube;
+use rubiks_trainer::stackmat::Stackmat;
+use rubiks_trainer::srs::Scheduler;
+use rubiks_trainer::status::Status;
+use rubiks_trainer::tour::Tour;
+use rubiks_trainer::triggers::TriggerLog;
+
+fn alg_dir(dir: Option<PathBuf>, config: &Config) -> PathBuf {
+    dir.or(config.alg_dir.clone()).unwrap_or(PathBuf::from("."))
+}
+
+fn read_alg_dir(path: PathBuf) -> AlgDB {
+    AlgDB::load(path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    })
+}
+
+// The alg directory as the config says to read it
+fn load_algs(dir: Option<PathBuf>, config: &Config) -> AlgDB {
+    let mut db = profile::time("load alg files", || read_alg_dir(alg_dir(dir, config)));
+    if config.group_prefixes {
+        db.group_by_prefix();
+    }
+    if !config.training.generators {
+        db.drop_generators();
+    }
+    db
+}
+
+fn load_db(args: &TrainArgs, config: &Config) -> AlgDB {
+    if args.demo {
+        AlgDB::demo()
+    } else {
+        load_algs(args.dir.clone(), config)
+    }
+}
+
+// The demo doesn't touch the history so trying it out doesn't mix into real stats
+fn load_history(args: &TrainArgs, config: &Config, read_only: bool) -> History {
+    let Some(path) = History::default_path().filter(|_| !args.in_memory()) else {
+        return History::default();
+    };
+    let mut history = History::load(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path.display(), err);
+        std::process::exit(1);
+    });
+    if read_only {
+        history.detach();
+    }
+    if let Err(err) = history.maintain(config.history.keep_days, SystemTime::now(), config.history.day_start_hour) {
+        eprintln!("{}: {}", path.display(), err);
+    }
+    history
+}
+
+// Alg sets picked with --enable and --preset
+fn enabled<'a>(db: &'a AlgDB, args: &TrainArgs, config: &Config) -> Vec<&'a AlgSet> {
+    let mut patterns = args.enable.clone();
+    if let Some(name) = &args.preset {
+        match config.presets.get(name) {
+            Some(preset) => patterns.extend(preset.iter().cloned()),
+            None => {
+                eprintln!("No preset named {}", name);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut enabled = Vec::new();
+    for pattern in patterns.iter() {
+        let algsets = db.matching(pattern);
+        if algsets.is_empty() {
+            eprintln!("No alg set matches {}", pattern);
+            std::process::exit(1);
+        }
+        enabled.extend(algsets);
+    }
+    enabled
+}
+
+// Takes the lock on the data files, asking first when another session holds it. Returns whether
+// this session only reads the data, having attached to the other one
+fn instance_lock() -> (Option<InstanceLock>, bool) {
+    let Some(path) = InstanceLock::default_path() else {
+        return (None, false);
+    };
+    let fail = |err| -> ! {
+        eprintln!("{}: {}", path.display(), err);
+        std::process::exit(1);
+    };
+    let owner = match InstanceLock::try_acquire(&path).unwrap_or_else(|err| fail(err)) {
+        Ok(lock) => return (Some(lock), false),
+        Err(owner) => owner,
+    };
+    let since: DateTime<Local> = owner.started.into();
+    print!("Another session is active (pid {}, since {}). Attach read-only, take over, or quit? [a/t/q] ", owner.pid, since.format("%H:%M"));
+    let mut answer = String::new();
+    if let Err(err) = io::stdout().flush().and_then(|_| io::stdin().read_line(&mut answer)) {
+        eprintln!("\n{}", err);
+        std::process::exit(1);
+    }
+    match answer.trim().to_lowercase().as_str() {
+        "a" => (None, true),
+        "t" => (Some(InstanceLock::take_over(&path).unwrap_or_else(|err| fail(err))), false),
+        _ => std::process::exit(0),
+    }
+}
+
+fn train(args: TrainArgs, config: &Config) {
+    let mut db = load_db(&args, config);
+    let (lock, read_only) = if args.in_memory() { (None, false) } else { instance_lock() };
+
+    let mut app = App::new(&db, config);
+    app.history = load_history(&args, config, read_only);
+    app.no_save = args.no_save;
+    if args.demo {
+        app.tour = Some(Tour::default());
+    } else if let Some(path) = Scheduler::default_path().filter(|_| !args.no_save) {
+        app.srs = Scheduler::load(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1);
+        });
+    }
+    if !args.in_memory() && let Some(path) = TriggerLog::default_path() {
+        app.triggers = TriggerLog::load(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1);
+        });
+    }
+
+    app.lock = lock;
+    // Dashboards are extra, a session they can't follow still trains
+    if let Some(lock) = &mut app.lock && let Ok(live) = LiveServer::open() && live.port().is_ok_and(|port| lock.share(port).is_ok()) {
+        app.live = Some(live);
+    }
+    if let Some(command) = &config.stackmat.command {
+        app.stackmat = Some(Stackmat::open(command, config.stackmat.sample_rate).unwrap_or_else(|err| {
+            eprintln!("{}: {}", command, err);
+            std::process::exit(1);
+        }));
+    }
+    if let Some(command) = &config.smart_cube.command {
+        app.smart_cube = Some(SmartCube::open(command).unwrap_or_else(|err| {
+            eprintln!("{}: {}", command, err);
+            std::process::exit(1);
+        }));
+    } else if let Some(name) = &config.smart_cube.bluetooth {
+        app.smart_cube = Some(SmartCube::bluetooth(name).unwrap_or_else(|err| {
+            eprintln!("{}: {}", name, err);
+            std::process::exit(1);
+        }));
+    }
+    if let Some(seed) = args.seed {
+        app.rng = StdRng::seed_from_u64(seed);
+    }
+    if read_only {
+        app.detach();
+    }
+
+    // The sets enabled last time, unless others were asked for
+    let mut selection = match Selection::default_path().filter(|_| !args.in_memory()) {
+        Some(path) => Selection::load(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1);
+        }),
+        None => Selection::default(),
+    };
+    if args.enable.is_empty() && args.preset.is_none() {
+        let saved: Vec<&AlgSet> = selection.algsets.iter().filter_map(|path| db.find(path)).collect();
+        app.enable(&saved);
+    }
+    app.enable(&enabled(&db, &args, config));
+    if let Some(minutes) = args.quick {
+        app.quick(minutes);
+    }
+    if (args.start || args.quick.is_some()) && let Err(err) = app.start() {
+        eprintln!("{} to start training with, use --enable or --preset", err);
+        std::process::exit(1);
+    }
 
-    let mut app = App::new(&db);
     color_eyre::install().unwrap();
     let mut term = ratatui::init();
-    let _result = app.run(&mut term);
+    loop {
+        if let Err(err) = app.run(&mut term) {
+            ratatui::restore();
+            eprintln!("Terminal error: {}", err);
+            std::process::exit(1);
+        }
+        let Some(file) = app.edit.take() else {
+            break;
+        };
+        let mut reload = app.into_reload();
+
+        ratatui::restore();
+        let edited = edit(&file);
+        term = ratatui::init();
+
+        // A file that doesn't load anymore keeps the sets as they were until it's fixed
+        reload.notice = Some(match edited {
+            Ok(()) => match AlgDB::validate(&file, &alg_dir(args.dir.clone(), config)).into_iter().next() {
+                Some((_, err)) => format!("{}: {}", file.display(), err),
+                None => {
+                    db = load_db(&args, config);
+                    format!("Reloaded {}", file.display())
+                }
+            },
+            Err(err) => err,
+        });
+        app = App::new(&db, config);
+        app.reload(reload);
+    }
     ratatui::restore();
+
+    if !app.read_only {
+        selection.algsets = app.enabled();
+        if let Err(err) = selection.save() {
+            eprintln!("Couldn't save the enabled alg sets: {}", err);
+        }
+    }
+}
+
+// Opens a file in $VISUAL or $EDITOR (vi without either) and waits for it to close
+fn edit(file: &Path) -> Result<(), String> {
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or("vi".to_string());
+    // The variable can hold arguments too, like "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    match std::process::Command::new(program).args(words).arg(file).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("{} exited with {}", editor, status)),
+        Err(err) => Err(format!("Couldn't run {}: {}", editor, err)),
+    }
+}
+
+#[cfg(feature = "gui")]
+fn gui(args: TrainArgs, config: &Config) {
+    let db = load_db(&args, config);
+    let (lock, read_only) = if args.in_memory() { (None, false) } else { instance_lock() };
+    let rng = args.seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64);
+    let mut gui = Gui::new(&db, &enabled(&db, &args, config), config, load_history(&args, config, read_only), rng);
+    gui.lock = lock;
+    gui.read_only = read_only;
+    if let Err(err) = gui.run() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn saved_history() -> (PathBuf, History) {
+    let Some(path) = History::default_path() else {
+        eprintln!("No data directory to keep the history in");
+        std::process::exit(1);
+    };
+    let history = History::load(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path.display(), err);
+        std::process::exit(1);
+    });
+    (path, history)
+}
+
+// Holds the lock on the data files while the history is rewritten. A running session keeps every
+// solve in memory and would write them all back on its next save, so it has to be closed first
+fn history_lock() -> Option<InstanceLock> {
+    let path = InstanceLock::default_path()?;
+    match InstanceLock::try_acquire(&path) {
+        Ok(Ok(lock)) => Some(lock),
+        Ok(Err(owner)) => {
+            eprintln!("Another session is active (pid {}), quit it before changing the history", owner.pid);
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn history(command: HistoryCommand, config: &Config) {
+    let dry_run = match command {
+        HistoryCommand::Export { output, date, dir } => {
+            export(output, date, dir, config);
+            return;
+        }
+        HistoryCommand::Prune { dry_run, .. } | HistoryCommand::Dedup { dry_run } | HistoryCommand::Compact { dry_run } => dry_run,
+    };
+    let _lock = if dry_run { None } else { history_lock() };
+    let (path, mut history) = saved_history();
+    let size = history.size();
+
+    let report = match command {
+        HistoryCommand::Prune { older_than, .. } => {
+            let day_start = config.history.day_start_hour;
+            let weeks = history.snapshot(SystemTime::now(), day_start);
+            let solves = history.prune(older_than, SystemTime::now(), day_start);
+            format!("{} solves older than {} days deleted, {} weeks archived first", solves, older_than, weeks)
+        }
+        HistoryCommand::Dedup { .. } => format!("{} duplicate solves removed", history.dedup()),
+        HistoryCommand::Compact { .. } => {
+            let weeks = history.snapshot(SystemTime::now(), config.history.day_start_hour);
+            let snapshots = history.compact();
+            format!("{} weeks archived, {} repeated snapshots dropped", weeks, snapshots)
+        }
+        HistoryCommand::Export { .. } => unreachable!(),
+    };
+
+    println!("{}", report);
+    println!("{} solves left, {} KB -> {} KB", history.solves.len(), size / 1024, history.size() / 1024);
+    if dry_run {
+        println!("Dry run, nothing was changed");
+        return;
+    }
+    if let Err(err) = history.save().and_then(|_| history.save_snapshots()) {
+        eprintln!("{}: {}", path.display(), err);
+        std::process::exit(1);
+    }
+}
+
+// Writes the solves of one day as an asciinema cast
+fn export(output: Option<PathBuf>, date: Option<NaiveDate>, dir: Option<PathBuf>, config: &Config) {
+    let (_, history) = saved_history();
+    let day = |solve: &Solve| day_of(solve.date, config.history.day_start_hour);
+    let Some(date) = date.or_else(|| history.solves.iter().map(day).max()) else {
+        eprintln!("No solves to export");
+        std::process::exit(1);
+    };
+    let solves: Vec<Solve> = history.solves.iter().filter(|solve| day(solve) == date).cloned().collect();
+    if solves.is_empty() {
+        eprintln!("No solves on {}", date);
+        std::process::exit(1);
+    }
+
+    // Case names need the alg files, without them the set and case number are shown
+    let db = dir.or(config.alg_dir.clone()).map(|dir| load_algs(Some(dir), config));
+    let cases: Vec<String> = solves.iter()
+        .map(|solve| {
+            let alg = db.as_ref().and_then(|db| db.find(&solve.algset)).and_then(|algset| algset.algs.get(solve.alg));
+            match alg.and_then(|alg| alg.name.clone()) {
+                Some(name) => format!("{} · {}", solve.algset, name),
+                None => format!("{} case {}", solve.algset, solve.alg + 1),
+            }
+        })
+        .collect();
+
+    let output = output.unwrap_or_else(|| PathBuf::from(default_name(&solves)));
+    let cast = session_cast(&format!("Session of {}", date), &solves, &cases);
+    if let Err(err) = std::fs::write(&output, cast) {
+        eprintln!("{}: {}", output.display(), err);
+        std::process::exit(1);
+    }
+    println!("{} solves written to {}", solves.len(), output.display());
+}
+
+fn read_import<T>(file: &Path, parse: impl FnOnce(&str) -> Result<T, RubiksError>) -> T {
+    std::fs::read_to_string(file)
+        .map_err(RubiksError::IOError)
+        .and_then(|text| parse(&text))
+        .unwrap_or_else(|err| {
+            eprintln!("{}: {}", file.display(), err);
+            std::process::exit(1);
+        })
+}
+
+// Alg files already there are left alone, so importing the same file again doesn't clobber edits
+fn write_alg_file(path: &Path, text: &str, cases: usize) {
+    if path.exists() {
+        println!("Skipped {}: it already exists", path.display());
+        return;
+    }
+    if let Some(dir) = path.parent() && let Err(err) = std::fs::create_dir_all(dir) {
+        eprintln!("{}: {}", dir.display(), err);
+        std::process::exit(1);
+    }
+    if let Err(err) = std::fs::write(path, text) {
+        eprintln!("{}: {}", path.display(), err);
+        std::process::exit(1);
+    }
+    println!("{}: {} cases", path.display(), cases);
+}
+
+// Writes each alg file in the new format next to it, then removes it unless it's kept. The new
+// file is read back first, so nothing is lost to a format it doesn't fit in
+fn convert(files: Vec<PathBuf>, to: AlgFormat, keep: bool, dir: &Path) {
+    let mut failed = false;
+    for file in files {
+        let algset = match AlgDB::load_file(&file, dir) {
+            Ok(AlgEntry::Algs(_, algset)) if algset.is_locked() => Err("it's locked".to_string()),
+            Ok(AlgEntry::Algs(_, algset)) => Ok(algset),
+            Ok(AlgEntry::Group(..)) => Err("it holds a group, only alg sets can be converted".to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        let algset = match algset {
+            Ok(algset) => algset,
+            Err(err) => {
+                eprintln!("Skipped {}: {}", file.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+
+        let (text, extension) = match to {
+            AlgFormat::Text => (algset.to_text(), None),
+            AlgFormat::Json => (algset.to_json(), Some("json")),
+            AlgFormat::Toml => (algset.to_toml(), Some("toml")),
+            AlgFormat::Markdown => (markdown::write(&algset), Some("md")),
+        };
+        // Text alg files have no extension of their own
+        let name = match file.extension().and_then(|ext| ext.to_str()) {
+            Some("json" | "toml" | "md") => file.file_stem(),
+            _ => file.file_name(),
+        };
+        let Some(name) = name.map(|name| name.to_string_lossy().to_string()) else {
+            eprintln!("Skipped {}: it has no file name", file.display());
+            failed = true;
+            continue;
+        };
+        let output = file.with_file_name(match extension {
+            Some(extension) => format!("{}.{}", name, extension),
+            None => name,
+        });
+        if output == file {
+            println!("Skipped {}: it's already in that format", file.display());
+            continue;
+        }
+        if output.exists() {
+            eprintln!("Skipped {}: {} already exists", file.display(), output.display());
+            failed = true;
+            continue;
+        }
+
+        if let Err(err) = std::fs::write(&output, text) {
+            eprintln!("{}: {}", output.display(), err);
+            std::process::exit(1);
+        }
+        match AlgDB::load_file(&output, dir) {
+            Ok(AlgEntry::Algs(_, converted)) if converted.algs == algset.algs && converted.meta == algset.meta => {}
+            _ => {
+                let _ = std::fs::remove_file(&output);
+                eprintln!("Skipped {}: it doesn't read back the same as {}", file.display(), output.display());
+                failed = true;
+                continue;
+            }
+        }
+        if !keep && let Err(err) = std::fs::remove_file(&file) {
+            eprintln!("{}: {}", file.display(), err);
+            failed = true;
+        }
+        println!("{} -> {}: {} cases", file.display(), output.display(), algset.algs.len());
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn import(command: ImportCommand, config: &Config) {
+    match command {
+        ImportCommand::Cstimer { file, dir } => {
+            let sessions = read_import(&file, CsTimerSession::parse_export);
+            let group = alg_dir(dir, config).join("cstimer");
+            for session in sessions.iter() {
+                match session.alg_file().filter(|_| !session.scrambles.is_empty()) {
+                    Some(text) => write_alg_file(&group.join(session.file_name()), &text, session.scrambles.len()),
+                    None => println!("Skipped {}: no scrambles the trainer can read", session.name),
+                }
+            }
+        }
+        ImportCommand::Csv { file, dir, set, name_column, alg_column, group_column } => {
+            let columns = Columns { name: name_column, alg: alg_column, group: group_column };
+            let Some(source) = file.file_name().map(|name| name.to_string_lossy().to_string()) else {
+                eprintln!("{}: not a CSV file", file.display());
+                std::process::exit(1);
+            };
+            let sheet = read_import(&file, |text| Sheet::parse(text, &columns));
+            let set = set.or_else(|| file.file_stem().map(|stem| stem.to_string_lossy().to_string())).unwrap_or_else(|| source.clone());
+            let path = alg_dir(dir, config).join(csv::file_name(&set));
+            for group in sheet.groups.iter() {
+                // Without groups the sheet is a single set
+                let path = if group.name.is_empty() { path.clone() } else { path.join(csv::file_name(&group.name)) };
+                write_alg_file(&path, &group.alg_file(&source), group.cases.len());
+            }
+            if !sheet.skipped.is_empty() {
+                let lines: Vec<String> = sheet.skipped.iter().map(usize::to_string).collect();
+                println!("Skipped lines without an alg the trainer can read: {}", lines.join(", "));
+            }
+        }
+    }
+}
+
+// The alg sets matching any of the patterns, in the order the patterns come, each once
+fn matching_sets<'a>(db: &'a AlgDB, patterns: &[String]) -> Vec<&'a AlgSet> {
+    let mut algsets: Vec<&AlgSet> = Vec::new();
+    for pattern in patterns.iter() {
+        let found = db.matching(pattern);
+        if found.is_empty() {
+            eprintln!("No alg set matches {}", pattern);
+            std::process::exit(1);
+        }
+        for algset in found {
+            if !algsets.iter().any(|other| std::ptr::eq(*other, algset)) {
+                algsets.push(algset);
+            }
+        }
+    }
+    algsets
+}
+
+// Like `matching_sets`, but a name that isn't a set's can also be a group's, for all the sets in it
+fn scramble_sets<'a>(db: &'a AlgDB, patterns: &[String]) -> Vec<&'a AlgSet> {
+    let patterns: Vec<String> = patterns.iter()
+        .map(|pattern| {
+            if !db.matching(pattern).is_empty() {
+                return pattern.clone();
+            }
+            [format!("{}/**", pattern), format!("**/{}/**", pattern)].into_iter()
+                .find(|group| !db.matching(group).is_empty())
+                .unwrap_or(pattern.clone())
+        })
+        .collect();
+    matching_sets(db, &patterns)
+}
+
+fn bundle(command: BundleCommand, config: &Config) {
+    match command {
+        BundleCommand::Export { patterns, name, description, dir, output } => {
+            let db = load_algs(dir, config);
+            let bundle = Bundle::of(name, description, &matching_sets(&db, &patterns));
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.bundle.json", csv::file_name(&bundle.name))));
+            if let Err(err) = std::fs::write(&output, bundle.to_json()) {
+                eprintln!("{}: {}", output.display(), err);
+                std::process::exit(1);
+            }
+            println!("{}: {} alg sets", output.display(), bundle.sets.len());
+        }
+        BundleCommand::Import { file, dir } => {
+            let bundle = read_import(&file, Bundle::parse);
+            let files = bundle.files().unwrap_or_else(|err| {
+                eprintln!("{}: {}", file.display(), err);
+                std::process::exit(1);
+            });
+            if !bundle.description.is_empty() {
+                println!("{}", bundle.description);
+            }
+            let group = alg_dir(dir, config).join(csv::file_name(&bundle.name));
+            for (path, text, cases) in files {
+                write_alg_file(&group.join(path), &text, cases);
+            }
+        }
+    }
+}
+
+// Runs every set's generator once, for its first case
+fn validate_generators(db: &AlgDB) {
+    let mut bad = 0;
+    for algset in db.matching("**") {
+        if let Some(command) = &algset.meta.generator && !algset.algs.is_empty() && let Err(err) = generate(command, algset, 0) {
+            eprintln!("{}: generator: {}", algset.path, err);
+            bad += 1;
+        }
+    }
+    if bad > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn validate_cases(db: &AlgDB) {
+    let mut bad = 0;
+    let mut unmasked = 0;
+    let mut unmodeled = 0;
+    for algset in db.matching("**") {
+        // Only 2x2 and 3x3 algs can be followed on the cube model
+        let Some(size) = algset.puzzle().modeled_size() else {
+            unmodeled += 1;
+            continue;
+        };
+        if algset.meta.mask.is_none() {
+            unmasked += 1;
+        }
+        for (idx, case) in algset.algs.iter().enumerate() {
+            for alg in case.all() {
+                let problem = check_alg(alg, algset.meta.mask, size)
+                    .or_else(|| (!same_case(&case.moves, alg, size)).then(|| "is an alternative for another case".to_string()));
+                if let Some(problem) = problem {
+                    let text: Vec<String> = alg.iter().map(Movement::as_text).collect();
+                    eprintln!("{} case {} ({}): {}", algset.path, idx + 1, text.join(" "), problem);
+                    bad += 1;
+                }
+            }
+            for scramble in case.scrambles.iter() {
+                let inverse: Vec<Movement> = scramble.iter().rev().map(Movement::inv).collect();
+                if !same_case(&case.moves, &inverse, size) {
+                    let text: Vec<String> = scramble.iter().map(Movement::as_text).collect();
+                    eprintln!("{} case {} (scramble {}): doesn't set up the case", algset.path, idx + 1, text.join(" "));
+                    bad += 1;
+                }
+            }
+        }
+    }
+    if unmasked > 0 {
+        println!("{} alg sets have no mask, their algs were only checked for doing something", unmasked);
+    }
+    if unmodeled > 0 {
+        println!("{} alg sets are for other puzzles than the 2x2 and 3x3, their algs weren't checked", unmodeled);
+    }
+    if bad > 0 {
+        std::process::exit(1);
+    }
+    println!("All algs solve their cases");
+}
+
+fn diff(left: PathBuf, right: PathBuf) {
+    let (left_db, right_db) = (read_alg_dir(left.clone()), read_alg_dir(right.clone()));
+    let coverage = Coverage::compare(&left_db, &right_db);
+
+    for (dir, cases) in [(&left, &coverage.only_left), (&right, &coverage.only_right)] {
+        if !cases.is_empty() {
+            println!("Only in {} ({}):", dir.display(), cases.len());
+            for case in cases.iter() {
+                println!("  {}: {}", case.name(), case.algs()[0]);
+            }
+            println!();
+        }
+    }
+    if !coverage.differing.is_empty() {
+        println!("Different algs ({}):", coverage.differing.len());
+        for (case, other) in coverage.differing.iter() {
+            println!("  {} / {}", case.name(), other.name());
+            println!("    {}: {}", left.display(), case.algs().join(" / "));
+            println!("    {}: {}", right.display(), other.algs().join(" / "));
+        }
+        println!();
+    }
+    println!("{} cases in both, {} only in {}, {} only in {}", coverage.shared, coverage.only_left.len(), left.display(), coverage.only_right.len(), right.display());
+}
+
+fn dedupe(db: &AlgDB) {
+    let duplicates = Duplicates::find(db);
+    if !duplicates.algs.is_empty() {
+        println!("Algs written more than once ({}):", duplicates.algs.len());
+        for copies in duplicates.algs.iter() {
+            for (idx, (case, alg)) in copies.iter().enumerate() {
+                println!("{} {}: {}", if idx == 0 { " " } else { "   " }, case.name(), alg);
+            }
+        }
+        println!();
+    }
+    if !duplicates.cases.is_empty() {
+        println!("Cases in more than one place ({}):", duplicates.cases.len());
+        for cases in duplicates.cases.iter() {
+            println!("  {}", cases.iter().map(CaseRef::name).collect::<Vec<_>>().join(" / "));
+        }
+        println!();
+    }
+    println!("{} duplicated algs, {} duplicated cases", duplicates.algs.len(), duplicates.cases.len());
+}
+
+fn list(entries: &[AlgEntry], depth: usize) {
+    for entry in entries {
+        match entry {
+            AlgEntry::Group(name, entries) => {
+                println!("{}{}/", "  ".repeat(depth), name);
+                list(entries, depth + 1);
+            }
+            AlgEntry::Algs(name, alg_set) => {
+                let locked = if alg_set.is_locked() { ", locked" } else { "" };
+                let htm: usize = alg_set.algs.iter().map(|alg| MoveCount::of(&alg.moves).htm).sum();
+                // Face turn metrics only mean something for cubes
+                let average = if alg_set.algs.is_empty() || !alg_set.puzzle().is_cube() {
+                    String::new()
+                } else {
+                    format!(", {:.1} HTM on average", htm as f64 / alg_set.algs.len() as f64)
+                };
+                println!("{}{} ({} algs{}{})", "  ".repeat(depth), name, alg_set.algs.len(), average, locked);
+            }
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let config = match cli.config.or(Config::default_path()) {
+        Some(path) => Config::load(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1);
+        }),
+        None => Config::default(),
+    };
+
+    match cli.command {
+        None => train(cli.train, &config),
+        Some(Command::Train(args)) => train(args, &config),
+        #[cfg(feature = "gui")]
+        Some(Command::Gui(args)) => gui(args, &config),
+        Some(Command::History { command }) => history(command, &config),
+        Some(Command::Import { command }) => import(command, &config),
+        Some(Command::Status { json }) => {
+            let (_, history) = saved_history();
+            let srs = match Scheduler::default_path() {
+                Some(path) => Scheduler::load(&path).unwrap_or_else(|err| {
+                    eprintln!("{}: {}", path.display(), err);
+                    std::process::exit(1);
+                }),
+                None => Scheduler::default(),
+            };
+            let status = Status::new(&history, &srs, config.history.day_start_hour);
+            if json {
+                println!("{}", serde_json::to_string(&status).unwrap());
+            } else {
+                println!("{}", status.text);
+            }
+        }
+        Some(Command::Fatigue { gap, size }) => {
+            let (_, history) = saved_history();
+            println!("{}", Fatigue::of(&history.solves, Duration::from_secs(gap * 60), size).text());
+        }
+        Some(Command::Dashboard { dir, gap }) => {
+            let (path, _) = saved_history();
+            let db = load_algs(dir, &config);
+            let mut term = ratatui::init();
+            let result = Dashboard::new(&path, InstanceLock::default_path(), &db, &config, Duration::from_secs(gap * 60)).run(&mut term);
+            ratatui::restore();
+            if let Err(err) = result {
+                eprintln!("Terminal error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        }
+        Some(Command::Man) => {
+            if let Err(err) = clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout()) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Export { patterns, dir, format, diagrams, output }) => {
+            let db = load_algs(dir, &config);
+            let algsets = matching_sets(&db, &patterns);
+            let sheet = sheet::render(&algsets, format, diagrams, &config.cube);
+            match output {
+                Some(path) => {
+                    if let Err(err) = std::fs::write(&path, sheet) {
+                        eprintln!("{}: {}", path.display(), err);
+                        std::process::exit(1);
+                    }
+                }
+                None => print!("{}", sheet),
+            }
+        }
+        Some(Command::Bundle { command }) => bundle(command, &config),
+        Some(Command::Diff { left, right }) => diff(left, right),
+        Some(Command::Dedupe { dir }) => dedupe(&load_algs(dir, &config)),
+        Some(Command::Convert { files, to, keep }) => convert(files, to, keep, &alg_dir(None, &config)),
+        Some(Command::List { dir }) => {
+            let db = load_algs(dir, &config);
+            list(&db.entries, 0);
+            for diagnostic in db.diagnostics.iter().filter(|diagnostic| diagnostic.kind != DiagnosticKind::Alias) {
+                let line = diagnostic.line.map(|line| format!(":{}", line)).unwrap_or_default();
+                eprintln!("{} {}{}: {}", diagnostic.kind.as_text(), diagnostic.file.display(), line, diagnostic.message);
+            }
+        }
+        Some(Command::Validate { dir, cases }) => {
+            let dir = alg_dir(dir, &config);
+            let errors = AlgDB::validate(&dir, &dir);
+            for (path, err) in errors.iter() {
+                eprintln!("{}: {}", path.display(), err);
+            }
+            if !errors.is_empty() {
+                std::process::exit(1);
+            }
+            println!("All alg files are valid");
+            let db = read_alg_dir(dir);
+            if config.training.generators {
+                validate_generators(&db);
+            }
+            if cases {
+                validate_cases(&db);
+            }
+        }
+        Some(Command::Scramble { mut algsets, sets, count, seed, dir, json, random_state }) => {
+            algsets.extend(sets);
+            if algsets.is_empty() {
+                eprintln!("No alg set given");
+                std::process::exit(1);
+            }
+            let db = load_algs(dir, &config);
+            let algsets = scramble_sets(&db, &algsets);
+            let mut rng = seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64);
+            let variation = Variation { random_state: random_state || config.training.random_state, ..config.training.variation() };
+            let mut scrambles: Vec<Scramble> = Vec::new();
+            for _ in 0..count {
+                let Some(mut scramble) = get_scramble_avoiding(&algsets, variation, &scrambles, &mut rng) else {
+                    eprintln!("No cases in the selected sets");
+                    std::process::exit(1);
+                };
+                // A generator that fails is reported and the case's alg sets it up instead
+                if let Some(algset) = algsets.iter().find(|algset| algset.path == scramble.algset) && let Some(command) = &algset.meta.generator {
+                    match generate(command, algset, scramble.alg) {
+                        Ok(moves) => scramble = Scramble::generated(algset, scramble.alg, moves, variation, &mut rng),
+                        Err(err) => eprintln!("{}: {}", algset.path, err),
+                    }
+                }
+                if json {
+                    let puzzle = algsets.iter().find(|algset| algset.path == scramble.algset).map_or(Puzzle::Cube(3), |algset| algset.puzzle());
+                    let alg = AlgJson::from_text(&scramble.text, puzzle.notation()).unwrap_or_else(|err| {
+                        eprintln!("{}: {}", scramble.algset, err);
+                        std::process::exit(1);
+                    });
+                    println!("{}", serde_json::to_string(&alg).unwrap());
+                } else {
+                    println!("{}", scramble.text);
+                }
+                scrambles.push(scramble);
+            }
+        }
+    }
 }
@@ -0,0 +1,204 @@
+use crate::db::{moves_text, Alg, AlgSet, RubiksError};
+
+// Alg sets as Markdown files (.md), readable on a git host or in a notes app:
+//
+// # PLL
+// - mask: pll
+// Notes about the set
+// ## T-perm
+// - `R U R' U' R' F R2 U' R' U' R U R' F'`
+// - scramble: `...`
+// Tags: @easy
+// Weight: 2
+// Notes about the case
+//
+// The first alg of a case is the main one and the rest alternatives. "- key: value" lines before the
+// first case are the same settings as "#!" lines, other text is kept as notes. A note that would read
+// as something else, like "- not a setting", is written with a backslash in front the way Markdown escapes it
+
+fn escape(note: &str) -> String {
+    if note.starts_with(['-', '#', '\\']) || note.starts_with("Tags:") || note.starts_with("Weight:") {
+        format!("\\{}", note)
+    } else {
+        note.to_string()
+    }
+}
+
+fn unescape(note: &str) -> String {
+    note.strip_prefix('\\').unwrap_or(note).to_string()
+}
+
+fn code(text: &str) -> Option<&str> {
+    text.trim().strip_prefix('`')?.strip_suffix('`')
+}
+
+// Adds the case that was being read, which needs an alg
+fn finish(case: Option<(Alg, bool)>, algset: &mut AlgSet) -> Result<(), RubiksError> {
+    match case {
+        Some((alg, false)) => Err(RubiksError::InvalidFile(format!("case {} has no algs", alg.name.unwrap_or_default()))),
+        Some((alg, true)) => {
+            algset.algs.push(alg);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+pub fn parse(name: String, text: &str) -> Result<AlgSet, RubiksError> {
+    let mut algset = AlgSet::from_text(name, "")?;
+    // The case being read and whether its main alg was found yet
+    let mut case: Option<(Alg, bool)> = None;
+
+    for line in text.lines() {
+        let line = line.replace('’', "'");
+        let line = line.trim();
+        if let Some(heading) = line.strip_prefix("##") {
+            finish(case.take(), &mut algset)?;
+            let name = Some(heading.trim().to_string()).filter(|name| !name.is_empty());
+            let alg = Alg { name, moves: Vec::new(), alternatives: Vec::new(), scrambles: Vec::new(), tags: Vec::new(), weight: 1.0, notes: Vec::new() };
+            case = Some((alg, false));
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some((alg, has_alg)) = case.as_mut() else {
+            // The title is the set's name, which comes from the file name like for other alg files
+            if line.starts_with("# ") && algset.notes.is_empty() {
+                continue;
+            }
+            match line.strip_prefix("- ") {
+                Some(setting) => algset.meta.parse_line(setting)?,
+                None => algset.notes.push(unescape(line)),
+            }
+            continue;
+        };
+        let notation = algset.puzzle().notation();
+        if let Some(moves) = line.strip_prefix("- scramble:").and_then(code) {
+            alg.scrambles.push(AlgSet::parse_moves(moves, notation)?);
+        } else if let Some(moves) = line.strip_prefix("- ").and_then(code) {
+            let moves = AlgSet::parse_moves(moves, notation)?;
+            if *has_alg {
+                alg.alternatives.push(moves);
+            } else {
+                alg.moves = moves;
+                *has_alg = true;
+            }
+        } else if let Some(tags) = line.strip_prefix("Tags:") {
+            alg.tags.extend(tags.split_whitespace().map(|tag| tag.trim_start_matches('@').to_string()));
+        } else if let Some(weight) = line.strip_prefix("Weight:") {
+            alg.weight = weight.trim().parse().ok().filter(|weight: &f64| weight.is_finite() && *weight >= 0.0)
+                .ok_or_else(|| RubiksError::InvalidMetadata(line.to_string()))?;
+        } else {
+            alg.notes.push(unescape(line));
+        }
+    }
+    finish(case, &mut algset)?;
+    Ok(algset)
+}
+
+pub fn write(algset: &AlgSet) -> String {
+    let mut lines = vec![format!("# {}", algset.name), String::new()];
+    if !algset.meta.is_empty() {
+        lines.extend(algset.meta.lines().iter().map(|line| format!("- {}", line)));
+        lines.push(String::new());
+    }
+    if !algset.notes.is_empty() {
+        lines.extend(algset.notes.iter().map(|note| escape(note)));
+        lines.push(String::new());
+    }
+
+    for alg in algset.algs.iter() {
+        lines.push(format!("## {}", alg.name.as_deref().unwrap_or_default()).trim_end().to_string());
+        lines.push(String::new());
+        lines.extend(alg.all().map(|moves| format!("- `{}`", moves_text(moves))));
+        lines.extend(alg.scrambles.iter().map(|moves| format!("- scramble: `{}`", moves_text(moves))));
+        lines.push(String::new());
+        // Two trailing spaces break the line without starting a new paragraph
+        if !alg.tags.is_empty() {
+            let tags: Vec<String> = alg.tags.iter().map(|tag| format!("@{}", tag)).collect();
+            lines.push(format!("Tags: {}  ", tags.join(" ")));
+        }
+        if alg.weight != 1.0 {
+            lines.push(format!("Weight: {}  ", alg.weight));
+        }
+        lines.extend(alg.notes.iter().map(|note| format!("{}  ", escape(note))));
+        if lines.last().is_some_and(|line| !line.is_empty()) {
+            lines.push(String::new());
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Mask;
+
+    const PLL: &str = "# PLL
+
+- mask: pll
+- reps: 3
+
+Learn these first
+\\- not a setting
+
+## T-perm
+
+- `R U R' U' R' F R2 U' R' U' R U R' F'`
+- `R2 U R2 U' R2 U' D R2 U' R2 U R2 D'`
+- scramble: `F R U' R' U' R U R' F' R U R' U' R' F R F'`
+
+Tags: @easy @edges  
+Weight: 2  
+Headlights on the left  
+\\- keep the F' tight  
+
+## Aa
+
+- `x R' U R' D2 R U' R' D2 R2 x'`
+";
+
+    #[test]
+    fn parse_sets() {
+        let algset = parse("pll".to_string(), PLL).unwrap();
+        // The title isn't a note, and the settings are read like "#!" lines
+        assert_eq!(algset.notes, ["Learn these first", "- not a setting"]);
+        assert_eq!(algset.meta.mask, Some(Mask::PLL));
+        assert_eq!(algset.meta.reps, Some(3));
+
+        assert_eq!(algset.algs.len(), 2);
+        let t = &algset.algs[0];
+        assert_eq!(t.name.as_deref(), Some("T-perm"));
+        assert_eq!(moves_text(&t.moves), "R U R' U' R' F R2 U' R' U' R U R' F'");
+        assert_eq!(t.alternatives.len(), 1);
+        assert_eq!(t.scrambles.len(), 1);
+        assert_eq!(t.tags, ["easy", "edges"]);
+        assert_eq!(t.weight, 2.0);
+        assert_eq!(t.notes, ["Headlights on the left", "- keep the F' tight"]);
+        assert!(algset.algs[1].notes.is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut algset = parse("pll".to_string(), PLL).unwrap();
+        // Notes that look like headings, settings or case fields come back as notes
+        algset.notes.extend(["# not a title", "## not a case", "\\ backslash"].map(String::from));
+        algset.algs[1].notes.extend(["Tags: not tags", "Weight: heavy", "- `R U R'`"].map(String::from));
+
+        let text = write(&algset);
+        let again = parse("pll".to_string(), &text).unwrap();
+        assert_eq!(again.notes, algset.notes);
+        assert_eq!(again.meta.lines(), algset.meta.lines());
+        assert_eq!(again.to_text(), algset.to_text());
+        assert_eq!(write(&again), text);
+    }
+
+    #[test]
+    fn bad_files() {
+        assert!(parse("pll".to_string(), "- mask: nothing\n").is_err());
+        assert!(parse("pll".to_string(), "## T-perm\nno algs here\n").is_err());
+        assert!(parse("pll".to_string(), "## T-perm\n- `R U R'`\nWeight: -1\n").is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::db::Movement;
+
+// How a move counts in the metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    // Whole cube rotations are free in every metric
+    Rotation,
+    // Outer layers, alone or with the ones next to them (R, Rw, 3Rw)
+    Face,
+    // Inner layers without the outer one (M, 2R, 2-3Lw), two face turns in HTM and QTM
+    Slice,
+    // Moves of other puzzles count once
+    Other,
+}
+
+fn kind(movement: &Movement) -> Kind {
+    use Movement::*;
+    match movement {
+        X | XP | X2 | Y | YP | Y2 | Z | ZP | Z2 => Kind::Rotation,
+        M | MP | M2 | E | EP | E2 | S | SP | S2 => Kind::Slice,
+        Layered { from, .. } if *from > 1 => Kind::Slice,
+        Twist { .. } | Square1Turn { .. } | Square1Slash => Kind::Other,
+        _ => Kind::Face,
+    }
+}
+
+// Length of an alg or scramble in half turns (HTM), quarter turns (QTM) and slice turns (STM)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveCount {
+    pub htm: usize,
+    pub qtm: usize,
+    pub stm: usize,
+}
+
+impl MoveCount {
+    pub fn of(movements: &[Movement]) -> MoveCount {
+        let mut count = MoveCount::default();
+        for movement in movements {
+            // Cube moves written with a 2 are half turns, two quarters
+            let quarters = if movement.as_text().ends_with('2') { 2 } else { 1 };
+            let (htm, qtm, stm) = match kind(movement) {
+                Kind::Rotation => (0, 0, 0),
+                Kind::Face => (1, quarters, 1),
+                Kind::Slice => (2, 2 * quarters, 1),
+                Kind::Other => (1, 1, 1),
+            };
+            count.htm += htm;
+            count.qtm += qtm;
+            count.stm += stm;
+        }
+        count
+    }
+
+    // "11 HTM, 13 QTM, 11 STM"
+    pub fn text(&self) -> String {
+        format!("{} HTM, {} QTM, {} STM", self.htm, self.qtm, self.stm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{AlgSet, Puzzle};
+
+    fn count(text: &str) -> MoveCount {
+        MoveCount::of(&AlgSet::parse_scramble(text).unwrap())
+    }
+
+    #[test]
+    fn face_turns() {
+        // T-perm
+        assert_eq!(count("R U R' U' R' F R2 U' R' U' R U R' F'"), MoveCount { htm: 14, qtm: 15, stm: 14 });
+        assert_eq!(count("r U2 Rw'"), MoveCount { htm: 3, qtm: 4, stm: 3 });
+        assert_eq!(count("3Rw2 2-3Lw"), MoveCount { htm: 3, qtm: 4, stm: 2 });
+    }
+
+    #[test]
+    fn slices_and_rotations() {
+        // H-perm with slices
+        assert_eq!(count("M2 U M2 U2 M2 U M2"), MoveCount { htm: 11, qtm: 20, stm: 7 });
+        assert_eq!(count("x y2 z'"), MoveCount::default());
+        assert_eq!(count("2R' E"), MoveCount { htm: 4, qtm: 4, stm: 2 });
+    }
+
+    #[test]
+    fn other_puzzles() {
+        let moves = AlgSet::parse_moves("/ (3,0) / (-1,-1)", Puzzle::Square1.notation()).unwrap();
+        assert_eq!(MoveCount::of(&moves), MoveCount { htm: 4, qtm: 4, stm: 4 });
+        let moves = AlgSet::parse_moves("R2 BR'", Puzzle::Megaminx.notation()).unwrap();
+        assert_eq!(MoveCount::of(&moves), MoveCount { htm: 2, qtm: 2, stm: 2 });
+        assert_eq!(MoveCount { htm: 11, qtm: 13, stm: 11 }.text(), "11 HTM, 13 QTM, 11 STM");
+    }
+}
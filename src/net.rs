@@ -0,0 +1,96 @@
+use ratatui::{
+    style::Style,
+    text::{Line, Span, Text},
+};
+
+use crate::config::FaceColors;
+use crate::cube::{CubeState, Face};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetView {
+    Full,
+    // Only the U face and the side stickers around it, for OLL and PLL
+    LastLayer,
+}
+
+impl NetView {
+    // Stickers are two cells wide so they look square, faces are one cell apart. `size` is the
+    // number of stickers along a face's edge
+    pub fn size(&self, size: usize) -> (u16, u16) {
+        let size = size as u16;
+        match self {
+            NetView::Full => (4 * 2 * size + 3, 3 * size),
+            NetView::LastLayer => (2 + 1 + 2 * size + 1 + 2, size + 2),
+        }
+    }
+
+    pub fn draw(&self, state: &CubeState, size: usize, colors: &FaceColors) -> Text<'static> {
+        match self {
+            NetView::Full => net(state, size, colors),
+            NetView::LastLayer => last_layer(state, size, colors),
+        }
+    }
+}
+
+fn sticker(face: Face, colors: &FaceColors) -> Span<'static> {
+    Span::styled("██", Style::new().fg(colors.color(face)))
+}
+
+// Unfolded cube with U on top of F, L R and B around it and D below:
+//        U
+//      L F R B
+//        D
+pub fn net(state: &CubeState, size: usize, colors: &FaceColors) -> Text<'static> {
+    let indented = |face: Face| -> Vec<Line<'static>> {
+        state.stickers(face, size).iter().map(|row| {
+            let mut spans = vec![Span::raw(" ".repeat(2 * size + 1))];
+            spans.extend(row.iter().map(|facelet| sticker(*facelet, colors)));
+            Line::from(spans)
+        })
+        .collect()
+    };
+
+    let mut lines = indented(Face::U);
+    let middle = [Face::L, Face::F, Face::R, Face::B].map(|face| state.stickers(face, size));
+    for row in 0..size {
+        let mut spans = Vec::new();
+        for (i, stickers) in middle.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(" "));
+            }
+            spans.extend(stickers[row].iter().map(|facelet| sticker(*facelet, colors)));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines.extend(indented(Face::D));
+
+    Text::from(lines)
+}
+
+// The U face seen from above with the top row of each side around it:
+//     B
+//   L U R
+//     F
+pub fn last_layer(state: &CubeState, size: usize, colors: &FaceColors) -> Text<'static> {
+    let u = state.stickers(Face::U, size);
+    let [f, r, b, l] = [Face::F, Face::R, Face::B, Face::L].map(|face| state.stickers(face, size).swap_remove(0));
+
+    let side_row = |stickers: Vec<Face>| {
+        let mut spans = vec![Span::raw("   ")];
+        spans.extend(stickers.iter().map(|facelet| sticker(*facelet, colors)));
+        Line::from(spans)
+    };
+
+    // B is read from behind and R from the right, so both run backwards here
+    let mut lines = vec![side_row(b.iter().rev().copied().collect())];
+    for row in 0..size {
+        let mut spans = vec![sticker(l[row], colors), Span::raw(" ")];
+        spans.extend(u[row].iter().map(|facelet| sticker(*facelet, colors)));
+        spans.push(Span::raw(" "));
+        spans.push(sticker(r[size - 1 - row], colors));
+        lines.push(Line::from(spans));
+    }
+    lines.push(side_row(f));
+
+    Text::from(lines)
+}
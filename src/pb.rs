@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::Solve;
+use crate::stats::{average_of, best_average};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    Session,
+    AllTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kind {
+    // Best single on the case that was just solved
+    Case,
+    Single,
+    Ao5,
+    Ao12,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [Kind::Case, Kind::Single, Kind::Ao5, Kind::Ao12];
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Kind::Case => "single on this case",
+            Kind::Single => "single",
+            Kind::Ao5 => "ao5",
+            Kind::Ao12 => "ao12",
+        }
+    }
+}
+
+// A PB set by a solve, saved with it so the PB history survives in the solve history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub kind: Kind,
+    pub scope: Scope,
+    pub time: Duration,
+}
+
+impl Record {
+    pub fn as_text(&self) -> String {
+        let scope = match self.scope {
+            Scope::Session => "Session",
+            Scope::AllTime => "All-time",
+        };
+        format!("{} PB {}", scope, self.kind.as_text())
+    }
+}
+
+// The new PB of a kind, if `solve` set one over the `previous` solves.
+// There has to be something to beat, so the first solve of a case isn't a PB
+fn beaten(kind: Kind, solve: &Solve, previous: &[Solve]) -> Option<Duration> {
+    let mut results: Vec<Option<Duration>> = previous.iter()
+        .filter(|other| kind != Kind::Case || other.case() == solve.case())
+        .map(|other| other.result())
+        .collect();
+
+    let (old, new) = match kind {
+        Kind::Case | Kind::Single => (results.iter().flatten().min().copied(), solve.result()),
+        Kind::Ao5 | Kind::Ao12 => {
+            let n = if kind == Kind::Ao5 { 5 } else { 12 };
+            let old = best_average(&results, n);
+            results.push(solve.result());
+            (old, average_of(&results, n).flatten())
+        }
+    };
+
+    match (old, new) {
+        (Some(old), Some(new)) if new < old => Some(new),
+        _ => None,
+    }
+}
+
+// PBs set by the last solve of the session. `history` holds every earlier solve, this
+// session's included. A session PB isn't reported when it is an all-time PB too
+pub fn records(session: &[Solve], history: &[Solve]) -> Vec<Record> {
+    let Some((solve, previous)) = session.split_last() else {
+        return Vec::new();
+    };
+
+    let mut records = Vec::new();
+    for kind in Kind::ALL {
+        if let Some(time) = beaten(kind, solve, history) {
+            records.push(Record { kind, scope: Scope::AllTime, time });
+        } else if let Some(time) = beaten(kind, solve, previous) {
+            records.push(Record { kind, scope: Scope::Session, time });
+        }
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::session::Penalty;
+
+    fn solve(alg: usize, millis: u64) -> Solve {
+        Solve {
+            date: SystemTime::UNIX_EPOCH,
+            scramble: String::new(),
+            algset: "3x3/pll".to_string(),
+            alg,
+            time: Duration::from_millis(millis),
+            penalty: Penalty::None,
+            tags: Vec::new(),
+            comment: String::new(),
+            mistake: None,
+            records: Vec::new(),
+            moves: None,
+            turns: None,
+        }
+    }
+
+    fn record(kind: Kind, scope: Scope, millis: u64) -> Record {
+        Record { kind, scope, time: Duration::from_millis(millis) }
+    }
+
+    #[test]
+    fn first_solves() {
+        assert!(records(&[], &[]).is_empty());
+        assert!(records(&[solve(0, 1000)], &[]).is_empty());
+        // The first solve of a case can still be the best single
+        let history = [solve(1, 2000)];
+        assert_eq!(records(&[solve(0, 1500)], &history), [record(Kind::Single, Scope::AllTime, 1500)]);
+        assert!(records(&[solve(0, 2500)], &history).is_empty());
+    }
+
+    #[test]
+    fn session_records() {
+        // Faster than earlier in the session, but not than before it
+        let before = solve(0, 1000);
+        let session = [solve(0, 2000), solve(0, 1500)];
+        let history = [before, session[0].clone()];
+        assert_eq!(records(&session, &history), [record(Kind::Case, Scope::Session, 1500), record(Kind::Single, Scope::Session, 1500)]);
+
+        // A DNF beats nothing
+        let dnf = Solve { penalty: Penalty::Dnf, ..solve(0, 500) };
+        assert!(records(&[session[0].clone(), dnf], &history).is_empty());
+    }
+
+    #[test]
+    fn all_time_records() {
+        let session: Vec<Solve> = [3000, 3000, 2000, 2000, 2000, 1000].iter().map(|millis| solve(0, *millis)).collect();
+        let history = &session[..5];
+        // The best ao5 before was 2.333, now the 1 and a 3 are dropped. All-time PBs aren't
+        // reported again as session ones
+        assert_eq!(records(&session, history), [
+            record(Kind::Case, Scope::AllTime, 1000),
+            record(Kind::Single, Scope::AllTime, 1000),
+            record(Kind::Ao5, Scope::AllTime, 2000),
+        ]);
+    }
+}
@@ -0,0 +1,40 @@
+use std::{collections::BTreeMap, sync::Mutex, time::{Duration, Instant}};
+
+// How long the work that grows with the alg directory takes, shown on the debug page (F12 in Setup)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timing {
+    pub count: u32,
+    pub total: Duration,
+    pub max: Duration,
+    pub last: Duration,
+}
+
+impl Timing {
+    pub fn mean(&self) -> Duration {
+        self.total / self.count.max(1)
+    }
+}
+
+// Global so the timed code doesn't need a handle to the app to record into
+static TIMINGS: Mutex<BTreeMap<&'static str, Timing>> = Mutex::new(BTreeMap::new());
+
+pub fn time<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let value = f();
+    record(name, start.elapsed());
+    value
+}
+
+pub fn record(name: &'static str, elapsed: Duration) {
+    let mut timings = TIMINGS.lock().unwrap_or_else(|err| err.into_inner());
+    let timing = timings.entry(name).or_default();
+    timing.count += 1;
+    timing.total += elapsed;
+    timing.max = timing.max.max(elapsed);
+    timing.last = elapsed;
+}
+
+pub fn timings() -> Vec<(&'static str, Timing)> {
+    let timings = TIMINGS.lock().unwrap_or_else(|err| err.into_inner());
+    timings.iter().map(|(name, timing)| (*name, *timing)).collect()
+}
@@ -0,0 +1,109 @@
+use std::time::SystemTime;
+
+use ratatui::{
+    crossterm::event::KeyEvent, layout::Constraint, style::{Style, Stylize}, widgets::{Block, Row, Table, TableState}, Frame
+};
+
+use crate::app::{App, Nav};
+use crate::config::Config;
+use crate::dates::{day_of, format_date, locale, today};
+use crate::history::HistoryError;
+
+#[derive(Debug)]
+struct ReviewRow {
+    name: String,
+    algset: String,
+    alg: usize,
+    due: SystemTime,
+}
+
+// Shown when leaving a spaced repetition session: the cases graded in it and when each comes up
+// next, which can still be moved a day at a time before the schedule is saved
+#[derive(Debug)]
+pub struct ReviewPage {
+    rows: Vec<ReviewRow>,
+    table: TableState,
+    save_error: Option<HistoryError>,
+}
+
+impl ReviewPage {
+    // `cases` has the name, alg set path and alg index of every case graded
+    pub fn new(cases: Vec<(String, String, usize)>, app: &App) -> ReviewPage {
+        let rows = cases.into_iter()
+            .filter_map(|(name, algset, alg)| {
+                let due = app.srs.due(&algset, alg)?;
+                Some(ReviewRow { name, algset, alg, due })
+            })
+            .collect();
+        ReviewPage {
+            rows,
+            table: TableState::default().with_selected(Some(0)),
+            save_error: None,
+        }
+    }
+
+    fn shift(&mut self, app: &mut App, days: i32) {
+        let Some(row) = self.table.selected().and_then(|idx| self.rows.get_mut(idx)) else {
+            return;
+        };
+        app.srs.shift(&row.algset, row.alg, days);
+        row.due = app.srs.due(&row.algset, row.alg).unwrap_or(row.due);
+    }
+
+    pub fn handle_key<'a>(&mut self, app: &mut App<'a>, key: KeyEvent) -> Nav<'a> {
+        let keys = &app.config.keys;
+        match key.code {
+            code if keys.up.matches(code) => self.table.select_previous(),
+            code if keys.down.matches(code) => {
+                let last = self.rows.len().saturating_sub(1);
+                self.table.select(Some((self.table.selected().unwrap_or(0) + 1).min(last)));
+            }
+            code if keys.later.matches(code) => self.shift(app, 1),
+            code if keys.sooner.matches(code) => self.shift(app, -1),
+            code if keys.select.matches(code) || keys.back.matches(code) || keys.quit.matches(code) => {
+                // A second try leaves even if saving fails again, the error was already shown
+                match app.srs.save() {
+                    Err(err) if self.save_error.is_none() => self.save_error = Some(err),
+                    _ => return Nav::Pop,
+                }
+            }
+            _ => {}
+        }
+        Nav::Stay
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, config: &Config) {
+        let day_start = config.history.day_start_hour;
+        let today = today(day_start);
+        let locale = locale(config.locale.as_deref());
+        let rows: Vec<Row> = self.rows.iter()
+            .map(|row| {
+                let day = day_of(row.due, day_start);
+                let when = match (day - today).num_days() {
+                    ..=0 => "today".to_string(),
+                    1 => "tomorrow".to_string(),
+                    days => format!("in {} days", days),
+                };
+                Row::new(vec![row.name.clone(), format_date(day, locale), when])
+            })
+            .collect();
+
+        let title = match &self.save_error {
+            Some(err) => format!(" Couldn't save the schedule: {} ", err),
+            None => " Session review ".to_string(),
+        };
+        let header = Row::new(vec!["Case", "Next review", ""]).style(Style::new().bold().underlined());
+        let widths = [Constraint::Min(20), Constraint::Length(14), Constraint::Length(14)];
+        let keys = &config.keys;
+        let hint = format!(
+            " {}/{}: case  {}/{}: a day later/sooner  {}: save ",
+            keys.down.label(), keys.up.label(), keys.later.label(), keys.sooner.label(), keys.select.label(),
+        );
+        let table = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::new().fg(config.colors.highlight))
+            .highlight_symbol("> ")
+            .block(Block::bordered().title(title).title_bottom(hint));
+        frame.render_stateful_widget(table, frame.area(), &mut self.table);
+    }
+}
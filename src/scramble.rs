@@ -0,0 +1,479 @@
+use std::time::Duration;
+
+use rand::{seq::IndexedRandom, Rng};
+use serde::Deserialize;
+
+use crate::cube::{cancel_moves, mirror_moves, rotate_moves, trim_rotations};
+use crate::db::{AlgSet, Movement, ScrambleSource};
+use crate::metrics::MoveCount;
+use crate::solver::random_state;
+use crate::session::Solve;
+use crate::stats::mean;
+
+#[derive(Debug, Clone)]
+pub struct Scramble {
+    pub text: String,
+    // Path of the alg set inside the db and index of the alg the scramble was made from
+    pub algset: String,
+    pub alg: usize,
+    // Slice turns of the case's alg, what solving it takes for the TPS
+    pub turns: usize,
+}
+
+// Random changes to the scramble so cases aren't always seen from the angle they were written in
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Variation {
+    // U turn to do after the alg
+    pub auf: bool,
+    // U turn before the case is shown, changing the angle it shows up at
+    pub pre_auf: bool,
+    pub rotation: Rotation,
+    // Train the left hand version of every case
+    pub mirror: bool,
+    // Leave out rotations at the start and end of scrambles made from algs with rotations
+    pub trim_rotations: bool,
+    // Scrambles found by the solver instead of undoing an alg, for 2x2 and 3x3 sets
+    pub random_state: bool,
+}
+
+// Whole cube rotation the scramble is done from, so the case shows up on another side
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rotation {
+    #[default]
+    Off,
+    Y,
+    // Any of the 24 orientations
+    Any,
+}
+
+impl Rotation {
+    fn random(&self, rng: &mut impl Rng) -> Vec<Movement> {
+        let tilts: &[&str] = match self {
+            Rotation::Off => return Vec::new(),
+            Rotation::Y => &[""],
+            Rotation::Any => &["", "x", "x2", "x'", "z", "z'"],
+        };
+        let text = format!("{} {}", tilts.choose(rng).unwrap(), ["", "y", "y2", "y'"].choose(rng).unwrap());
+        AlgSet::parse_scramble(&text).unwrap()
+    }
+}
+
+impl Variation {
+    // An alg file's "#! scrambles" or "#! auf" setting overrides the config. Listed scrambles are
+    // used as written, only the angle they're done from changes
+    fn for_set(&self, algset: &AlgSet) -> Variation {
+        // U turns, mirroring and rotations are cube moves, other puzzles get the inverse as it is
+        if !algset.puzzle().is_cube() {
+            return Variation::default();
+        }
+        if algset.meta.generator.is_some() {
+            return Variation { auf: false, pre_auf: false, ..*self };
+        }
+        match (algset.meta.scrambles, algset.meta.auf) {
+            (Some(ScrambleSource::Auf), _) => Variation { auf: true, pre_auf: true, ..*self },
+            (Some(ScrambleSource::Inverse | ScrambleSource::Listed), _) | (None, Some(false)) => Variation { auf: false, pre_auf: false, ..*self },
+            (None, Some(true)) => Variation { auf: true, ..*self },
+            (None, None) => *self,
+        }
+    }
+}
+
+fn random_u(rng: &mut impl Rng) -> Option<Movement> {
+    [None, Some(Movement::U), Some(Movement::UP), Some(Movement::U2)].choose(rng).unwrap().clone()
+}
+
+impl Scramble {
+    // All the randomness comes from `rng`, the same seed gives the same scrambles. A set's generator
+    // isn't run here, it can take seconds: its scramble goes through `generated` once it's made
+    pub fn of(algset: &AlgSet, alg: usize, variation: Variation, rng: &mut impl Rng) -> Scramble {
+        Scramble::set_up(algset, alg, None, variation, rng)
+    }
+
+    // A scramble from the set's generator, varied like any other
+    pub fn generated(algset: &AlgSet, alg: usize, generated: Vec<Movement>, variation: Variation, rng: &mut impl Rng) -> Scramble {
+        Scramble::set_up(algset, alg, Some(generated), variation, rng)
+    }
+
+    fn set_up(algset: &AlgSet, alg: usize, generated: Option<Vec<Movement>>, variation: Variation, rng: &mut impl Rng) -> Scramble {
+        let variation = variation.for_set(algset);
+        let mut movements: Vec<Movement> = Vec::new();
+        if variation.auf {
+            movements.extend(random_u(rng));
+        }
+        let case = &algset.algs[alg];
+        match (generated, case.scrambles.choose(rng)) {
+            (Some(generated), _) => movements.extend(generated),
+            (None, Some(scramble)) if algset.meta.scrambles == Some(ScrambleSource::Listed) => movements.extend(scramble.iter().cloned()),
+            // Any of the case's algs sets it up, picking one also varies the AUF the case comes with
+            _ => {
+                let algs: Vec<&Vec<Movement>> = case.all().collect();
+                movements.extend(algs.choose(rng).unwrap().iter().rev().map(Movement::inv));
+            }
+        }
+        if variation.pre_auf {
+            movements.extend(random_u(rng));
+        }
+        if variation.mirror {
+            movements = mirror_moves(&movements);
+        }
+        if variation.random_state && let Some(size) = algset.puzzle().modeled_size() && let Some(solved) = random_state(&movements, size, rng) {
+            movements = solved;
+        }
+        let mut movements = rotate_moves(&movements, &variation.rotation.random(rng));
+        if variation.trim_rotations {
+            movements = trim_rotations(&movements);
+        }
+        // The AUF, the alg's inverse and the rotation are put together without looking at each other
+        let movements = cancel_moves(&movements);
+
+        let texts: Vec<String> = movements.iter().map(Movement::as_text).collect();
+        Scramble {
+            text: texts.join(" "),
+            algset: algset.path.clone(),
+            alg,
+            turns: MoveCount::of(&case.moves).stm,
+        }
+    }
+
+    // Made from the same alg, whatever the AUF and rotation
+    pub fn same_case(&self, other: &Scramble) -> bool {
+        self.algset == other.algset && self.alg == other.alg
+    }
+}
+
+pub(crate) fn cases<'a>(algsets: &[&'a AlgSet]) -> Vec<(&'a AlgSet, usize)> {
+    algsets.iter().flat_map(|algset| (0..algset.algs.len()).map(move |alg| (*algset, alg))).collect()
+}
+
+fn pick(cases: &[(&AlgSet, usize)], variation: Variation, rng: &mut impl Rng) -> Option<Scramble> {
+    let weights: Vec<f64> = cases.iter().map(|(algset, alg)| algset.algs[*alg].weight).collect();
+    pick_weighted(cases, &weights, variation, rng)
+}
+
+// Cases come up as often as their weights say, unless every weight is 0. None without any case
+fn pick_weighted(cases: &[(&AlgSet, usize)], weights: &[f64], variation: Variation, rng: &mut impl Rng) -> Option<Scramble> {
+    let indices: Vec<usize> = (0..cases.len()).collect();
+    let idx = match indices.choose_weighted(rng, |idx| weights[*idx]) {
+        Ok(idx) => idx,
+        Err(_) => indices.choose(rng)?,
+    };
+    let (algset, alg) = cases[*idx];
+    Some(Scramble::of(algset, alg, variation, rng))
+}
+
+pub fn get_scramble(algsets: &[&AlgSet], variation: Variation, rng: &mut impl Rng) -> Option<Scramble> {
+    pick(&cases(algsets), variation, rng)
+}
+
+// Drops the cases of the recent scrambles (newest last) from the cases to pick from, newest first,
+// stopping before the last case would go so a small set still cycles through every case
+fn without_recent<'a>(mut cases: Vec<(&'a AlgSet, usize)>, recent: &[Scramble]) -> Vec<(&'a AlgSet, usize)> {
+    for scramble in recent.iter().rev() {
+        let Some(idx) = cases.iter().position(|(algset, alg)| algset.path == scramble.algset && *alg == scramble.alg) else {
+            continue;
+        };
+        if cases.len() == 1 {
+            break;
+        }
+        cases.remove(idx);
+    }
+    cases
+}
+
+// A random case other than the ones of the recent scrambles
+pub fn get_scramble_avoiding(algsets: &[&AlgSet], variation: Variation, recent: &[Scramble], rng: &mut impl Rng) -> Option<Scramble> {
+    pick(&without_recent(cases(algsets), recent), variation, rng)
+}
+
+// Number of recent results a case's difficulty is judged by
+const RECENT_SOLVES: usize = 5;
+
+fn recent_solves<'s>(solves: &'s [Solve], algset: &AlgSet, alg: usize) -> Vec<&'s Solve> {
+    solves.iter().rev()
+        .filter(|solve| solve.case() == (algset.path.as_str(), alg))
+        .take(RECENT_SOLVES)
+        .collect()
+}
+
+// A scramble from the easy or the hard cases. The faster half of the cases by the mean of their
+// last few results are easy, the rest hard, as are cases with a recent DNF or no results yet.
+// Falls back to any case when one side is empty. Cases of the recent scrambles are left out
+pub fn get_scramble_by_difficulty(algsets: &[&AlgSet], variation: Variation, solves: &[Solve], easy: bool, recent: &[Scramble], rng: &mut impl Rng) -> Option<Scramble> {
+    let cases = without_recent(cases(algsets), recent);
+    let means: Vec<Option<Duration>> = cases.iter()
+        .map(|(algset, alg)| {
+            let recent: Vec<Option<Duration>> = recent_solves(solves, algset, *alg).into_iter().map(Solve::result).collect();
+            (!recent.is_empty() && recent.iter().all(Option::is_some)).then(|| mean(&recent)).flatten()
+        })
+        .collect();
+
+    let mut times: Vec<Duration> = means.iter().flatten().copied().collect();
+    times.sort();
+    let Some(median) = times.get(times.len().saturating_sub(1) / 2).copied() else {
+        return pick(&cases, variation, rng);
+    };
+    let side: Vec<(&AlgSet, usize)> = cases.iter().zip(means.iter())
+        .filter(|(_, mean)| mean.is_some_and(|mean| mean <= median) == easy)
+        .map(|(case, _)| *case)
+        .collect();
+    if side.is_empty() {
+        return pick(&cases, variation, rng);
+    }
+    pick(&side, variation, rng)
+}
+
+// A scramble biased toward the cases that go worst by `badness`, the worse the more often it
+// comes up. `exploration` (0 to 1) is how often the best case still comes up compared to the
+// worst one. Cases of the recent scrambles are left out
+pub fn get_scramble_by_performance(algsets: &[&AlgSet], variation: Variation, solves: &[Solve], exploration: f64, recent: &[Scramble], rng: &mut impl Rng) -> Option<Scramble> {
+    let cases = without_recent(cases(algsets), recent);
+    let Some(badness) = badness(&cases, solves) else {
+        return pick(&cases, variation, rng);
+    };
+    // Scaled from the best case at 0 to the worst at 1, all of them 1 when they go the same
+    let best = badness.iter().copied().fold(f64::INFINITY, f64::min);
+    let worst = badness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let scaled = |badness: f64| if worst > best { (badness - best) / (worst - best) } else { 1.0 };
+    let exploration = exploration.clamp(0.0, 1.0);
+    let weights: Vec<f64> = cases.iter().zip(badness.iter())
+        .map(|((algset, alg), badness)| algset.algs[*alg].weight * (exploration + (1.0 - exploration) * scaled(*badness)))
+        .collect();
+    pick_weighted(&cases, &weights, variation, rng)
+}
+
+// How badly each case goes by its last few solves, relative to the slowest case: the slower the
+// mean of its successful results and the more of them were DNFs or had a mistake, the higher.
+// Cases without a time yet count as the slowest. None when no case has a time
+fn badness(cases: &[(&AlgSet, usize)], solves: &[Solve]) -> Option<Vec<f64>> {
+    let performance: Vec<(Option<Duration>, f64)> = cases.iter()
+        .map(|(algset, alg)| {
+            let recent = recent_solves(solves, algset, *alg);
+            let times: Vec<Option<Duration>> = recent.iter().map(|solve| solve.result()).collect();
+            let failed = recent.iter().filter(|solve| solve.result().is_none() || solve.mistake.is_some()).count();
+            (mean(&times), failed as f64 / recent.len().max(1) as f64)
+        })
+        .collect();
+
+    let slowest = performance.iter().filter_map(|(mean, _)| *mean).max()?;
+    // A case failed every time counts three times as bad as one with the same time and no failures
+    Some(performance.iter()
+        .map(|(mean, failed)| mean.unwrap_or(slowest).as_secs_f64() / slowest.as_secs_f64().max(f64::EPSILON) * (1.0 + 2.0 * failed))
+        .collect())
+}
+
+// A scramble from the weakest `percent` of the cases of each set, by `badness`, at least one per
+// set. Every set keeps the same share of its cases, so the sets come up as often as their sizes
+// say. Cases of the recent scrambles are left out
+pub fn get_scramble_from_weakest(algsets: &[&AlgSet], variation: Variation, solves: &[Solve], percent: u32, recent: &[Scramble], rng: &mut impl Rng) -> Option<Scramble> {
+    let mut weakest: Vec<(&AlgSet, usize)> = Vec::new();
+    for algset in algsets {
+        let cases = cases(&[*algset]);
+        let Some(badness) = badness(&cases, solves) else {
+            weakest.extend(cases);
+            continue;
+        };
+        let mut ranked: Vec<((&AlgSet, usize), f64)> = cases.into_iter().zip(badness).collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let keep = (ranked.len() * percent.min(100) as usize).div_ceil(100).max(1);
+        weakest.extend(ranked.into_iter().take(keep).map(|(case, _)| case));
+    }
+    pick(&without_recent(weakest, recent), variation, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::cube::same_case;
+    use crate::db::Puzzle;
+    use crate::session::{Mistake, Penalty};
+
+    fn set(path: &str, text: &str) -> AlgSet {
+        let (algset, skipped) = AlgSet::read_text(path.to_string(), text, None);
+        assert!(skipped.is_empty());
+        algset
+    }
+
+    #[test]
+    fn random_state_scrambles() {
+        let algset = set("oll.txt", "Sune: R U R' U R U2 R'\nH perm: M2 U M2 U2 M2 U M2");
+        let variation = Variation { random_state: true, auf: true, ..Variation::default() };
+        let mut rng = StdRng::seed_from_u64(5);
+        for alg in 0..2 {
+            let scramble = Scramble::of(&algset, alg, variation, &mut rng);
+            let movements = AlgSet::parse_scramble(&scramble.text).unwrap();
+            let inverse: Vec<Movement> = movements.iter().rev().map(Movement::inv).collect();
+            assert!(same_case(&algset.algs[alg].moves, &inverse, 3), "{}", scramble.text);
+            assert!(!scramble.text.contains('M'));
+        }
+    }
+
+    #[test]
+    fn empty_sets() {
+        let empty = set("empty.txt", "# nothing here yet\n");
+        let mut rng = StdRng::seed_from_u64(1);
+        let variation = Variation::default();
+        assert!(get_scramble(&[&empty], variation, &mut rng).is_none());
+        assert!(get_scramble_avoiding(&[&empty], variation, &[], &mut rng).is_none());
+        assert!(get_scramble_by_difficulty(&[&empty], variation, &[], true, &[], &mut rng).is_none());
+        assert!(get_scramble_by_performance(&[&empty], variation, &[], 0.5, &[], &mut rng).is_none());
+        assert!(get_scramble_from_weakest(&[&empty], variation, &[], 20, &[], &mut rng).is_none());
+
+        let oll = set("oll.txt", "Sune: R U R' U R U2 R'");
+        assert_eq!(get_scramble(&[&empty, &oll], variation, &mut rng).unwrap().algset, "oll.txt");
+    }
+
+    #[test]
+    fn seeded_scrambles() {
+        let pll = set("pll.txt", "T: R U R' U' R' F R2 U' R' U' R U R' F'\nJb: R U R' F' R U R' U' R' F R2 U' R'\nH: M2 U M2 U2 M2 U M2\n");
+        let variation = Variation { auf: true, pre_auf: true, rotation: Rotation::Any, ..Variation::default() };
+        let run = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut scrambles: Vec<Scramble> = Vec::new();
+            for _ in 0..20 {
+                let scramble = get_scramble_avoiding(&[&pll], variation, &scrambles, &mut rng).unwrap();
+                scrambles.push(scramble);
+            }
+            scrambles.into_iter().map(|scramble| scramble.text).collect::<Vec<_>>()
+        };
+        // The same seed gives the same scrambles, another seed other ones
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    fn texts(algset: &AlgSet, variation: Variation) -> HashSet<String> {
+        (0..40).map(|seed| Scramble::of(algset, 0, variation, &mut StdRng::seed_from_u64(seed)).text).collect()
+    }
+
+    #[test]
+    fn aufs() {
+        let sune = set("oll.txt", "Sune: R U R' U R U2 R'\n");
+        assert_eq!(texts(&sune, Variation::default()).len(), 1);
+        for variation in [Variation { auf: true, ..Variation::default() }, Variation { pre_auf: true, ..Variation::default() }] {
+            // Either U turn still sets up the case, from other angles
+            let texts = texts(&sune, variation);
+            assert!(texts.len() > 1);
+            for text in texts {
+                let movements = AlgSet::parse_scramble(&text).unwrap();
+                let inverse: Vec<Movement> = movements.iter().rev().map(Movement::inv).collect();
+                assert!(same_case(&sune.algs[0].moves, &inverse, 3), "{}", text);
+            }
+        }
+
+        // An alg file's settings win over the config
+        let variation = Variation { auf: true, pre_auf: true, ..Variation::default() };
+        let inverse = set("oll.txt", "#! scrambles: inverse\nSune: R U R' U R U2 R'\n");
+        assert_eq!(texts(&inverse, variation), HashSet::from(["R U2 R' U' R U' R'".to_string()]));
+        let off = set("oll.txt", "#! auf: off\nSune: R U R' U R U2 R'\n");
+        assert_eq!(texts(&off, variation).len(), 1);
+        let auf = set("oll.txt", "#! scrambles: auf\nSune: R U R' U R U2 R'\n");
+        assert!(texts(&auf, Variation::default()).len() > 1);
+
+        // Other puzzles get the inverse whatever the config says
+        let pyraminx = set("l4e.txt", "#! puzzle: pyraminx\nA: R U' R' U\n");
+        let variation = Variation { auf: true, pre_auf: true, rotation: Rotation::Any, mirror: true, trim_rotations: true, random_state: true };
+        assert_eq!(pyraminx.meta.puzzle, Some(Puzzle::Pyraminx));
+        assert_eq!(texts(&pyraminx, variation), HashSet::from(["U' R U R'".to_string()]));
+    }
+
+    fn solve(algset: &str, alg: usize, millis: u64, penalty: Penalty, mistake: Option<Mistake>) -> Solve {
+        Solve {
+            date: std::time::SystemTime::UNIX_EPOCH,
+            scramble: String::new(),
+            algset: algset.to_string(),
+            alg,
+            time: Duration::from_millis(millis),
+            penalty,
+            tags: Vec::new(),
+            comment: String::new(),
+            mistake,
+            records: Vec::new(),
+            moves: None,
+            turns: None,
+        }
+    }
+
+    fn draws(mut draw: impl FnMut(&mut StdRng) -> Option<Scramble>) -> HashSet<(String, usize)> {
+        let mut rng = StdRng::seed_from_u64(11);
+        (0..200).map(|_| draw(&mut rng).unwrap()).map(|scramble| (scramble.algset, scramble.alg)).collect()
+    }
+
+    #[test]
+    fn weakest_cases() {
+        let oll = set("oll", "A: R U R' U R U2 R'\nB: R U2 R' U' R U' R'\nC: F R U R' U' F'\nD: R U R' U' R' F R F'\n");
+        let pll = set("pll", "T: R U R' U' R' F R2 U' R' U' R U R' F'\nH: M2 U M2 U2 M2 U M2\n");
+        let solves = [
+            solve("oll", 0, 2000, Penalty::None, None),
+            solve("oll", 0, 2000, Penalty::None, None),
+            solve("oll", 1, 2500, Penalty::None, None),
+            solve("oll", 1, 2500, Penalty::None, None),
+            // As fast as A, but the DNF makes it the weakest
+            solve("oll", 2, 2000, Penalty::None, None),
+            solve("oll", 2, 2000, Penalty::Dnf, None),
+            solve("oll", 3, 1000, Penalty::None, None),
+            // Faster than H, but always with a mistake
+            solve("pll", 0, 2000, Penalty::None, Some(Mistake::ExecutionSlip)),
+            solve("pll", 0, 2000, Penalty::None, Some(Mistake::AufError)),
+            solve("pll", 1, 3000, Penalty::None, None),
+        ];
+        let variation = Variation::default();
+        let case = |algset: &str, alg: usize| (algset.to_string(), alg);
+
+        // A small percent still keeps a case of each set
+        for percent in [0, 10] {
+            let drawn = draws(|rng| get_scramble_from_weakest(&[&oll, &pll], variation, &solves, percent, &[], rng));
+            assert_eq!(drawn, HashSet::from([case("oll", 2), case("pll", 0)]));
+        }
+        let drawn = draws(|rng| get_scramble_from_weakest(&[&oll], variation, &solves, 50, &[], rng));
+        assert_eq!(drawn, HashSet::from([case("oll", 2), case("oll", 1)]));
+        // Without any time every case is weak
+        let drawn = draws(|rng| get_scramble_from_weakest(&[&oll], variation, &[], 10, &[], rng));
+        assert_eq!(drawn.len(), 4);
+    }
+
+    #[test]
+    fn weighted_by_performance() {
+        let pll = set("pll", "T: R U R' U' R' F R2 U' R' U' R U R' F'\nH: M2 U M2 U2 M2 U M2\nZ: M' U M2 U M2 U M' U2 M2\n");
+        let solves = [
+            solve("pll", 0, 1000, Penalty::None, None),
+            solve("pll", 1, 2000, Penalty::None, None),
+            solve("pll", 2, 3000, Penalty::None, None),
+        ];
+        let variation = Variation::default();
+        // Without exploration the fastest case never comes up
+        let drawn = draws(|rng| get_scramble_by_performance(&[&pll], variation, &solves, 0.0, &[], rng));
+        assert_eq!(drawn, HashSet::from([("pll".to_string(), 1), ("pll".to_string(), 2)]));
+        let drawn = draws(|rng| get_scramble_by_performance(&[&pll], variation, &solves, 0.5, &[], rng));
+        assert_eq!(drawn.len(), 3);
+        // Cases that go the same all come up
+        let even = [solve("pll", 0, 1000, Penalty::None, None), solve("pll", 1, 1000, Penalty::None, None), solve("pll", 2, 1000, Penalty::None, None)];
+        assert_eq!(draws(|rng| get_scramble_by_performance(&[&pll], variation, &even, 0.0, &[], rng)).len(), 3);
+
+        // Cases weighted 0 are drawn evenly rather than not at all
+        let zero = set("pll", "T: R U R' U' R' F R2 U' R' U' R U R' F' *0\nH: M2 U M2 U2 M2 U M2 *0\n");
+        assert_eq!(draws(|rng| get_scramble_by_performance(&[&zero], variation, &solves, 0.0, &[], rng)).len(), 2);
+        assert_eq!(draws(|rng| get_scramble(&[&zero], variation, rng)).len(), 2);
+    }
+
+    #[test]
+    fn recent_cases() {
+        let pll = set("pll.txt", "T: R U R' U' R' F R2 U' R' U' R U R' F'\nJb: R U R' F' R U R' U' R' F R2 U' R'\nH: M2 U M2 U2 M2 U M2\n");
+        let variation = Variation::default();
+        let mut rng = StdRng::seed_from_u64(3);
+        let recent = [Scramble::of(&pll, 0, variation, &mut rng), Scramble::of(&pll, 2, variation, &mut rng)];
+        assert_eq!(without_recent(cases(&[&pll]), &recent).iter().map(|(_, alg)| *alg).collect::<Vec<_>>(), [1]);
+        for _ in 0..50 {
+            assert_eq!(get_scramble_avoiding(&[&pll], variation, &recent, &mut rng).unwrap().alg, 1);
+        }
+
+        // A set's last case is still drawn, even right after itself
+        let oll = set("oll.txt", "Sune: R U R' U R U2 R'\n");
+        let recent = [Scramble::of(&oll, 0, variation, &mut rng)];
+        assert_eq!(without_recent(cases(&[&oll]), &recent).len(), 1);
+        assert_eq!(get_scramble_avoiding(&[&oll], variation, &recent, &mut rng).unwrap().alg, 0);
+    }
+}
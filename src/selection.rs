@@ -0,0 +1,31 @@
+use std::path::{Path, PathBuf};
+
+use crate::history::{read, write, HistoryError};
+
+// The alg sets enabled when the trainer was last closed, by their path in the alg directory,
+// so Setup starts with them ticked again
+#[derive(Debug, Default)]
+pub struct Selection {
+    path: Option<PathBuf>,
+    pub algsets: Vec<String>,
+}
+
+impl Selection {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rubiks_trainer").join("selection.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<Selection, HistoryError> {
+        Ok(Selection {
+            path: Some(path.to_path_buf()),
+            algsets: read(path)?,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), HistoryError> {
+        match &self.path {
+            Some(path) => write(path, &self.algsets),
+            None => Ok(()),
+        }
+    }
+}
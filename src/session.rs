@@ -0,0 +1,185 @@
+use std::{collections::HashMap, time::{Duration, SystemTime}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::AlgSet;
+use crate::metrics::MoveCount;
+use crate::pb::Record;
+use crate::scramble::Scramble;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Penalty {
+    None,
+    PlusTwo,
+    Dnf,
+}
+
+impl Penalty {
+    // WCA rules: up to 15 seconds of inspection are free, 2 more cost +2, anything longer is a DNF
+    pub fn from_inspection(inspection: Duration) -> Penalty {
+        if inspection <= Duration::from_secs(15) {
+            Penalty::None
+        } else if inspection <= Duration::from_secs(17) {
+            Penalty::PlusTwo
+        } else {
+            Penalty::Dnf
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Mistake {
+    Misrecognition,
+    WrongAlg,
+    ExecutionSlip,
+    AufError,
+}
+
+impl Mistake {
+    pub const ALL: [Mistake; 4] = [Mistake::Misrecognition, Mistake::WrongAlg, Mistake::ExecutionSlip, Mistake::AufError];
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Mistake::Misrecognition => "misrecognition",
+            Mistake::WrongAlg => "wrong alg",
+            Mistake::ExecutionSlip => "execution slip",
+            Mistake::AufError => "AUF error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Solve {
+    // Saved as UTC seconds since the epoch
+    pub date: SystemTime,
+    pub scramble: String,
+    pub algset: String,
+    pub alg: usize,
+    pub time: Duration,
+    pub penalty: Penalty,
+    pub tags: Vec<String>,
+    pub comment: String,
+    pub mistake: Option<Mistake>,
+    // PBs this solve set when it was done
+    #[serde(default)]
+    pub records: Vec<Record>,
+    // The moves turned during the solve, when a connected cube reported them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moves: Option<String>,
+    // Slice turns of the case's alg, solves from before it was saved don't have it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turns: Option<usize>,
+}
+
+impl Solve {
+    // Identifies the case by the alg the scramble was generated from
+    pub fn case(&self) -> (&str, usize) {
+        (&self.algset, self.alg)
+    }
+
+    // The time counted for stats, None for a DNF
+    pub fn result(&self) -> Option<Duration> {
+        match self.penalty {
+            Penalty::None => Some(self.time),
+            Penalty::PlusTwo => Some(self.time + Duration::from_secs(2)),
+            Penalty::Dnf => None,
+        }
+    }
+
+    // Words starting with '@' become tags, everything else is the comment
+    pub fn set_notes(&mut self, text: &str) {
+        let mut tags = Vec::new();
+        let mut comment = Vec::new();
+        for word in text.split_whitespace() {
+            match word.strip_prefix('@') {
+                Some(tag) if !tag.is_empty() => tags.push(tag.to_string()),
+                _ => comment.push(word),
+            }
+        }
+        self.tags = tags;
+        self.comment = comment.join(" ");
+    }
+
+    pub fn notes(&self) -> String {
+        let mut words: Vec<String> = self.tags.iter().map(|tag| format!("@{}", tag)).collect();
+        if !self.comment.is_empty() {
+            words.push(self.comment.clone());
+        }
+        words.join(" ")
+    }
+
+    // Turns per second, counting the moves a connected cube reported or else the case's alg, over
+    // the time without penalties. The alg is a guess at the moves done, it's the same for every solve
+    pub fn tps(&self) -> Option<f64> {
+        if self.penalty == Penalty::Dnf || self.time.is_zero() {
+            return None;
+        }
+        let turns = match &self.moves {
+            Some(moves) => MoveCount::of(&AlgSet::parse_scramble(moves).ok()?).stm,
+            None => self.turns?,
+        };
+        Some(turns as f64 / self.time.as_secs_f64())
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Session {
+    pub solves: Vec<Solve>,
+    // Scrambles that were turned wrong on a physical cube and replaced
+    pub misscrambles: Vec<Scramble>,
+}
+
+impl Session {
+    // Dated now, so not in the browser where there's no clock to read
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add(&mut self, scramble: &Scramble, time: Duration, penalty: Penalty) {
+        self.solves.push(Solve {
+            date: SystemTime::now(),
+            scramble: scramble.text.clone(),
+            algset: scramble.algset.clone(),
+            alg: scramble.alg,
+            time,
+            penalty,
+            tags: Vec::new(),
+            comment: String::new(),
+            mistake: None,
+            records: Vec::new(),
+            moves: None,
+            turns: Some(scramble.turns),
+        });
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut Solve> {
+        self.solves.last_mut()
+    }
+
+    // Mistake counts for every case
+    pub fn mistakes_by_case(&self) -> HashMap<(&str, usize), HashMap<Mistake, usize>> {
+        let mut cases: HashMap<(&str, usize), HashMap<Mistake, usize>> = HashMap::new();
+        for solve in self.solves.iter() {
+            if let Some(mistake) = solve.mistake {
+                *cases.entry(solve.case()).or_default().entry(mistake).or_default() += 1;
+            }
+        }
+        cases
+    }
+
+    // How many solves of each case ended with a wrong AUF, and how many solves it had
+    pub fn auf_errors_by_case(&self) -> HashMap<(&str, usize), (usize, usize)> {
+        let mut cases: HashMap<(&str, usize), (usize, usize)> = HashMap::new();
+        for solve in self.solves.iter() {
+            let (errors, solves) = cases.entry(solve.case()).or_default();
+            *errors += (solve.mistake == Some(Mistake::AufError)) as usize;
+            *solves += 1;
+        }
+        cases
+    }
+
+    pub fn with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Solve> {
+        self.solves.iter().filter(move |solve| solve.has_tag(tag))
+    }
+}
@@ -0,0 +1,329 @@
+use clap::ValueEnum;
+
+use crate::config::{rgb, CubeColor, CubeSettings};
+use crate::cube::{CubeState, Face};
+use crate::db::{moves_text, AlgSet, Mask, Movement};
+
+// Printable alg sheets to share alg sets, e.g. with a cubing club
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Markdown,
+    Html,
+    // Tab separated notes for Anki's importer: the case on the front, its algs on the back
+    Anki,
+}
+
+// Sticker colors by face (U R F D L B), as CSS colors and as the colored squares used in Markdown
+struct Colors {
+    css: [String; 6],
+    squares: [&'static str; 6],
+}
+
+impl Colors {
+    fn new(cube: &CubeSettings) -> Colors {
+        let held = cube.held_colors().unwrap_or_else(|_| CubeSettings::default().held_colors().unwrap());
+        Colors {
+            css: held.map(|color| {
+                let (r, g, b) = rgb(cube.palette.color(color));
+                format!("#{:02x}{:02x}{:02x}", r, g, b)
+            }),
+            squares: held.map(|color| match color {
+                CubeColor::White => "⬜",
+                CubeColor::Yellow => "🟨",
+                CubeColor::Green => "🟩",
+                CubeColor::Blue => "🟦",
+                CubeColor::Red => "🟥",
+                CubeColor::Orange => "🟧",
+            }),
+        }
+    }
+
+    fn index(face: Face) -> usize {
+        Face::ALL.iter().position(|other| *other == face).unwrap()
+    }
+}
+
+// Stickers of the case laid out like the terminal net, None being a gap. Last layer sets only
+// get the U face with the top row of the sides around it
+fn diagram(alg: &[Movement], mask: Option<Mask>, size: usize) -> Vec<Vec<Option<Face>>> {
+    let mut state = CubeState::solved();
+    state.apply(&alg.iter().rev().map(Movement::inv).collect::<Vec<_>>());
+
+    if matches!(mask, Some(Mask::OLL | Mask::PLL | Mask::LL | Mask::CLL)) {
+        let u = state.stickers(Face::U, size);
+        let [f, r, b, l] = [Face::F, Face::R, Face::B, Face::L].map(|face| state.stickers(face, size).swap_remove(0));
+        let side = |stickers: Vec<Face>| [vec![None], stickers.into_iter().map(Some).collect(), vec![None]].concat();
+        let mut rows = vec![side(b.into_iter().rev().collect())];
+        for row in 0..size {
+            let mut cells = vec![Some(l[row])];
+            cells.extend(u[row].iter().copied().map(Some));
+            cells.push(Some(r[size - 1 - row]));
+            rows.push(cells);
+        }
+        rows.push(side(f));
+        return rows;
+    }
+
+    let indented = |face: Face| state.stickers(face, size).into_iter().map(|row| [vec![None; size], row.into_iter().map(Some).collect()].concat());
+    let mut rows: Vec<Vec<Option<Face>>> = indented(Face::U).collect();
+    let middle = [Face::L, Face::F, Face::R, Face::B].map(|face| state.stickers(face, size));
+    rows.extend((0..size).map(|row| middle.iter().flat_map(|stickers| stickers[row].iter().copied().map(Some)).collect()));
+    rows.extend(indented(Face::D));
+    rows
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Anki splits the fields of a note on tabs and the notes on line breaks, so a field can't keep either
+fn anki_field(html: &str) -> String {
+    html.replace('\t', "&#9;").replace("\r\n", "<br>").replace(['\r', '\n'], "<br>")
+}
+
+// Anki tags are separated by whitespace, whatever's in one is joined with underscores
+fn anki_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+pub fn render(algsets: &[&AlgSet], format: Format, diagrams: bool, cube: &CubeSettings) -> String {
+    let colors = Colors::new(cube);
+    match format {
+        Format::Markdown => markdown(algsets, diagrams, &colors),
+        Format::Html => html(algsets, diagrams, &colors),
+        Format::Anki => anki(algsets, diagrams, &colors),
+    }
+}
+
+fn case_name(algset: &AlgSet, idx: usize) -> String {
+    algset.algs[idx].name.clone().unwrap_or_else(|| format!("Case {}", idx + 1))
+}
+
+fn markdown(algsets: &[&AlgSet], diagrams: bool, colors: &Colors) -> String {
+    let mut lines = Vec::new();
+    for algset in algsets {
+        lines.push(format!("## {}\n", algset.path));
+        for (idx, alg) in algset.algs.iter().enumerate() {
+            lines.push(format!("### {}\n", case_name(algset, idx)));
+            if diagrams && let Some(size) = algset.puzzle().modeled_size() {
+                // Two trailing spaces break the line without starting a new paragraph
+                for row in diagram(&alg.moves, algset.meta.mask, size) {
+                    let cells: String = row.iter()
+                        .map(|cell| cell.map(|face| colors.squares[Colors::index(face)]).unwrap_or("⬛"))
+                        .collect();
+                    lines.push(format!("{}  ", cells));
+                }
+                lines.push(String::new());
+            }
+            lines.extend(alg.all().map(|moves| format!("- `{}`", moves_text(moves))));
+            if !alg.notes.is_empty() {
+                lines.push(String::new());
+                lines.extend(alg.notes.iter().map(|note| format!("> {}  ", note)));
+            }
+            if !alg.tags.is_empty() {
+                let tags: Vec<String> = alg.tags.iter().map(|tag| format!("@{}", tag)).collect();
+                lines.push(format!("\n*{}*", tags.join(" ")));
+            }
+            lines.push(String::new());
+        }
+    }
+    lines.join("\n")
+}
+
+// Styles are inline so the diagram also shows up where the sheet's CSS doesn't go, like Anki cards
+fn diagram_html(alg: &[Movement], mask: Option<Mask>, size: usize, colors: &Colors) -> String {
+    let rows: Vec<String> = diagram(alg, mask, size).iter()
+        .map(|cells| {
+            let cells: String = cells.iter()
+                .map(|cell| match cell {
+                    Some(face) => format!("<td style=\"background:{};width:14px;height:14px;padding:0;border:1px solid #333\"></td>", colors.css[Colors::index(*face)]),
+                    None => "<td style=\"width:14px;height:14px;padding:0\"></td>".to_string(),
+                })
+                .collect();
+            format!("<tr>{}</tr>", cells)
+        })
+        .collect();
+    format!("<table style=\"border-collapse:collapse;display:inline-table\">{}</table>", rows.join(""))
+}
+
+fn anki(algsets: &[&AlgSet], diagrams: bool, colors: &Colors) -> String {
+    // Header lines Anki reads to set up the import
+    let mut lines = vec!["#separator:tab".to_string(), "#html:true".to_string(), "#tags column:3".to_string()];
+    for algset in algsets {
+        for (idx, alg) in algset.algs.iter().enumerate() {
+            let scramble = moves_text(&alg.moves.iter().rev().map(Movement::inv).collect::<Vec<_>>());
+            let front = if diagrams && let Some(size) = algset.puzzle().modeled_size() {
+                diagram_html(&alg.moves, algset.meta.mask, size, colors)
+            } else {
+                format!("Scramble: {}", escape(&scramble))
+            };
+            let algs: Vec<String> = alg.all().map(|moves| escape(&moves_text(moves))).collect();
+            let mut back = format!("<b>{}</b><br>{}", escape(&case_name(algset, idx)), algs.join("<br>"));
+            if !alg.notes.is_empty() {
+                let notes: Vec<String> = alg.notes.iter().map(|note| escape(note)).collect();
+                back.push_str(&format!("<br><i>{}</i>", notes.join("<br>")));
+            }
+            // "::" nests tags like the alg set's directories
+            let mut tags = vec![anki_tag(&algset.path).replace('/', "::")];
+            tags.extend(alg.tags.iter().map(|tag| anki_tag(tag)));
+            lines.push(format!("{}\t{}\t{}", anki_field(&front), anki_field(&back), tags.join(" ")));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn html(algsets: &[&AlgSet], diagrams: bool, colors: &Colors) -> String {
+    let mut body = Vec::new();
+    for algset in algsets {
+        body.push(format!("<h2>{}</h2>\n<table>", escape(&algset.path)));
+        for (idx, alg) in algset.algs.iter().enumerate() {
+            let mut row = String::from("<tr>");
+            if diagrams && let Some(size) = algset.puzzle().modeled_size() {
+                row.push_str(&format!("<td>{}</td>", diagram_html(&alg.moves, algset.meta.mask, size, colors)));
+            }
+            let algs: Vec<String> = alg.all().map(|moves| format!("<div class=\"alg\">{}</div>", escape(&moves_text(moves)))).collect();
+            let notes: Vec<String> = alg.notes.iter().map(|note| format!("<div class=\"note\">{}</div>", escape(note))).collect();
+            let tags: Vec<String> = alg.tags.iter().map(|tag| format!("@{}", escape(tag))).collect();
+            row.push_str(&format!(
+                "<th>{}</th><td>{}{}<div class=\"tags\">{}</div></td></tr>",
+                escape(&case_name(algset, idx)), algs.join(""), notes.join(""), tags.join(" "),
+            ));
+            body.push(row);
+        }
+        body.push("</table>".to_string());
+    }
+
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Alg sheet</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; }}
+tr {{ break-inside: avoid; }}
+th, td {{ padding: 6px 12px; text-align: left; vertical-align: middle; }}
+.alg {{ font-family: monospace; font-size: 1.1em; }}
+.note {{ font-style: italic; }}
+.tags {{ color: #777; }}
+</style>
+</head>
+<body>
+{}
+</body>
+</html>
+"#, body.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(path: &str, text: &str) -> AlgSet {
+        let (algset, skipped) = AlgSet::read_text(path.to_string(), text, None);
+        assert!(skipped.is_empty());
+        algset
+    }
+
+    #[test]
+    fn anki_notes() {
+        let algset = set("oll/dot cases", "#! mask: oll\nSune\tA: R U R' U R U2 R' @fish @two\nALT: y' L' U' L U' L' U2 L\n# tab\there\n");
+        let text = render(&[&algset], Format::Anki, false, &CubeSettings::default());
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[..3], ["#separator:tab", "#html:true", "#tags column:3"]);
+        assert_eq!(lines.len(), 4);
+        // A tab in the name or a note stays in its field
+        let fields: Vec<&str> = lines[3].split('\t').collect();
+        assert_eq!(fields, [
+            "Scramble: R U2 R' U' R U' R'",
+            "<b>Sune&#9;A</b><br>R U R' U R U2 R'<br>y' L' U' L U' L' U2 L<br><i>tab&#9;here</i>",
+            "oll::dot_cases fish two",
+        ]);
+
+        // With diagrams the front is the case's drawing
+        let text = render(&[&algset], Format::Anki, true, &CubeSettings::default());
+        let front = text.lines().nth(3).unwrap().split('\t').next().unwrap();
+        assert!(front.starts_with("<table"));
+        assert_eq!(front.matches("<tr>").count(), 5);
+    }
+
+    #[test]
+    fn anki_fields() {
+        assert_eq!(anki_field("a\tb"), "a&#9;b");
+        assert_eq!(anki_field("one\ntwo\r\nthree\rfour"), "one<br>two<br>three<br>four");
+        assert_eq!(anki_tag("two  words\there"), "two_words_here");
+        // Names are escaped before they're put in a field
+        let mut algset = set("pll", "T: R U R' U' R' F R2 U' R' U' R U R' F'\n");
+        algset.algs[0].name = Some("<T>\nperm".to_string());
+        let text = render(&[&algset], Format::Anki, false, &CubeSettings::default());
+        assert!(text.lines().nth(3).unwrap().contains("<b>&lt;T&gt;<br>perm</b>"));
+        assert_eq!(text.lines().count(), 4);
+    }
+
+    #[test]
+    fn markdown_layout() {
+        let algset = set("oll", "Sune: R U R' U R U2 R' @fish\nALT: y' L' U' L U' L' U2 L\n# the one with the fish\nR U2 R' U' R U' R'\n");
+        let text = render(&[&algset], Format::Markdown, false, &CubeSettings::default());
+        assert_eq!(text, [
+            "## oll\n",
+            "### Sune\n",
+            "- `R U R' U R U2 R'`",
+            "- `y' L' U' L U' L' U2 L`",
+            "",
+            "> the one with the fish  ",
+            "\n*@fish*",
+            "",
+            "### Case 2\n",
+            "- `R U2 R' U' R U' R'`",
+            "",
+        ].join("\n"));
+
+        // A last layer case is drawn as its U face with the sides' top row around it, a 2x2 one smaller
+        let oll = set("oll", "#! mask: oll\nSune: R U R' U R U2 R'\n");
+        let text = render(&[&oll], Format::Markdown, true, &CubeSettings::default());
+        let rows: Vec<&str> = text.lines().filter(|line| line.ends_with("  ") && !line.starts_with('>')).collect();
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().all(|row| row.trim_end().chars().count() == 5));
+        assert!(rows[0].starts_with('⬛') && rows[0].trim_end().ends_with('⬛'));
+        let cll = set("2x2/cll", "#! mask: cll\nSune: R U R' U R U2 R'\n");
+        let text = render(&[&cll], Format::Markdown, true, &CubeSettings::default());
+        assert_eq!(text.lines().filter(|line| line.ends_with("  ")).count(), 4);
+        // Puzzles the cube model doesn't follow get no drawing
+        let pyraminx = set("pyraminx/l4e", "#! puzzle: pyraminx\nR U' R' U\n");
+        let text = render(&[&pyraminx], Format::Markdown, true, &CubeSettings::default());
+        assert!(!text.contains('⬛'));
+    }
+
+    #[test]
+    fn html_escaping() {
+        let mut algset = set("<b>oll</b>", "Sune: R U R' U R U2 R' @a&b\n# say \"hi\" <script>\n");
+        algset.algs[0].name = Some("<i>Sune</i> & co".to_string());
+        let text = render(&[&algset], Format::Html, false, &CubeSettings::default());
+        assert!(text.contains("<h2>&lt;b&gt;oll&lt;/b&gt;</h2>"));
+        assert!(text.contains("<th>&lt;i&gt;Sune&lt;/i&gt; &amp; co</th>"));
+        assert!(text.contains("<div class=\"alg\">R U R' U R U2 R'</div>"));
+        assert!(text.contains("<div class=\"note\">say &quot;hi&quot; &lt;script&gt;</div>"));
+        assert!(text.contains("<div class=\"tags\">@a&amp;b</div>"));
+        assert!(!text.contains("<script>"));
+    }
+
+    #[test]
+    fn html_layout() {
+        let oll = set("oll", "#! mask: oll\nSune: R U R' U R U2 R'\nAntisune: R U2 R' U' R U' R'\n");
+        let pll = set("pll", "T: R U R' U' R' F R2 U' R' U' R U R' F'\n");
+        let text = render(&[&oll, &pll], Format::Html, true, &CubeSettings::default());
+        assert!(text.starts_with("<!DOCTYPE html>"));
+        // A table per set and a row per case, each with its drawing: 5 rows for the last layer, the
+        // whole net of 9 for a set without a mask
+        assert_eq!(text.matches("<h2>").count(), 2);
+        assert_eq!(text.matches("<tr><td><table").count(), 3);
+        let drawings: Vec<usize> = text.split("<table style").skip(1).map(|table| table.split("</table>").next().unwrap().matches("<tr>").count()).collect();
+        assert_eq!(drawings, [5, 5, 9]);
+        // Stickers in the cube's colors
+        assert!(text.contains("background:#"));
+
+        let text = render(&[&oll], Format::Html, false, &CubeSettings::default());
+        assert!(!text.contains("background:"));
+        assert_eq!(text.matches("<tr><th>").count(), 2);
+    }
+}
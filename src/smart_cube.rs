@@ -0,0 +1,602 @@
+use std::io::{self, BufRead, BufReader};
+use std::process::Child;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::Aes128;
+
+use crate::cube::{is_face_turn, off_by_u, CubeState};
+use crate::db::Movement;
+use crate::stackmat::spawn_reader;
+
+// How long the cube can stay a U turn away from solved before the solve is taken to be over with
+// the AUF left undone
+pub const AUF_WAIT: Duration = Duration::from_secs(1);
+
+// Where a smart cube's turns come from
+#[derive(Debug)]
+enum Source {
+    // A program that connects to the cube and prints a `move R'` line for each face turn as it happens
+    Bridge(Child),
+    #[cfg(feature = "bluetooth")]
+    Bluetooth(crate::bluetooth::Connection),
+}
+
+// A smart cube, connected over Bluetooth or through a bridge command. The cube is taken to be
+// solved when it's connected
+#[derive(Debug)]
+pub struct SmartCube {
+    source: Source,
+    turns: Receiver<Movement>,
+    // Where the cube's stickers are, following every turn
+    pub state: CubeState,
+}
+
+impl SmartCube {
+    pub fn open(command: &str) -> io::Result<SmartCube> {
+        let mut bridge = spawn_reader(command)?;
+        let output = BufReader::new(bridge.stdout.take().unwrap());
+
+        let (sender, turns) = mpsc::channel();
+        thread::spawn(move || {
+            for line in output.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Some(movement) = parse_line(&line) && sender.send(movement).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(SmartCube { source: Source::Bridge(bridge), turns, state: CubeState::solved() })
+    }
+
+    // Connects to the Giiker, GAN or MoYu cube whose Bluetooth name starts with `name`
+    #[cfg(feature = "bluetooth")]
+    pub fn bluetooth(name: &str) -> io::Result<SmartCube> {
+        let (sender, turns) = mpsc::channel();
+        let connection = crate::bluetooth::Connection::open(name, sender)?;
+        Ok(SmartCube { source: Source::Bluetooth(connection), turns, state: CubeState::solved() })
+    }
+
+    #[cfg(not(feature = "bluetooth"))]
+    pub fn bluetooth(_name: &str) -> io::Result<SmartCube> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "built without the bluetooth feature"))
+    }
+
+    // The turns since the last call, each with the state it left the cube in
+    pub fn turns(&mut self) -> Vec<(Movement, CubeState)> {
+        let turns: Vec<Movement> = self.turns.try_iter().collect();
+        turns.into_iter()
+            .map(|movement| {
+                self.state.apply(std::slice::from_ref(&movement));
+                (movement, self.state.clone())
+            })
+            .collect()
+    }
+}
+
+impl Drop for SmartCube {
+    fn drop(&mut self) {
+        match &mut self.source {
+            Source::Bridge(bridge) => {
+                let _ = bridge.kill();
+                let _ = bridge.wait();
+            }
+            #[cfg(feature = "bluetooth")]
+            Source::Bluetooth(connection) => connection.close(),
+        }
+    }
+}
+
+// The turn a line of the bridge reports. Anything else it prints, like its connection status or
+// a slice or rotation the cube can't turn, is None
+fn parse_line(line: &str) -> Option<Movement> {
+    let turn = line.trim().strip_prefix("move ")?;
+    Movement::from_text(turn.trim()).filter(is_face_turn)
+}
+
+// Giiker cubes scramble their state with this table when byte 18 is 0xa7, offset by the two
+// halves of byte 19
+const GIIKER_KEY: [u8; 36] = [
+    176, 81, 104, 224, 86, 137, 237, 119, 38, 26, 193, 161, 210, 126, 150, 81, 93, 13,
+    236, 249, 89, 235, 88, 24, 113, 81, 214, 131, 130, 199, 2, 169, 39, 165, 171, 41,
+];
+
+// The last turn in a Giiker cube's state, which it sends after every turn: 20 bytes, with the
+// last turns from byte 16 on as a face (BDLURF from 1) and an amount (1 clockwise, 3
+// counterclockwise, 2 or 9 a half turn) in the two halves of each byte
+pub fn giiker_turn(packet: &[u8]) -> Option<Movement> {
+    let mut packet: Vec<u8> = packet.get(..20)?.to_vec();
+    if packet[18] == 0xa7 {
+        let (k1, k2) = (usize::from(packet[19] >> 4), usize::from(packet[19] & 0xf));
+        for (idx, byte) in packet[..18].iter_mut().enumerate() {
+            *byte = byte.wrapping_add(GIIKER_KEY[idx + k1]).wrapping_add(GIIKER_KEY[idx + k2]);
+        }
+    }
+    let face = match packet[16] >> 4 {
+        1 => "B",
+        2 => "D",
+        3 => "L",
+        4 => "U",
+        5 => "R",
+        6 => "F",
+        _ => return None,
+    };
+    let amount = match packet[16] & 0xf {
+        1 => "",
+        2 | 9 => "2",
+        3 => "'",
+        _ => return None,
+    };
+    Movement::from_text(&format!("{}{}", face, amount))
+}
+
+// The protocols of the cubes read over Bluetooth. GAN's second generation (GAN Mini, 356 i Carry, 11 and 12
+// ui) is also spoken by the MoYu AI 2023 with a key of its own, WCU_MY32 is the one of MoYu's later AI cubes like the WeiLong V10 AI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Giiker,
+    Gan2,
+    Gan3,
+    Gan4,
+    Moyu32,
+}
+
+// AES keys and IVs, the first 6 bytes of each made the cube's own by adding its MAC address
+const GAN_KEY: ([u8; 16], [u8; 16]) = (
+    [0x01, 0x02, 0x42, 0x28, 0x31, 0x91, 0x16, 0x07, 0x20, 0x05, 0x18, 0x54, 0x42, 0x11, 0x12, 0x53],
+    [0x11, 0x03, 0x32, 0x28, 0x21, 0x01, 0x76, 0x27, 0x20, 0x95, 0x78, 0x14, 0x32, 0x12, 0x02, 0x43],
+);
+const MOYU_AI_KEY: ([u8; 16], [u8; 16]) = (
+    [0x05, 0x12, 0x02, 0x45, 0x02, 0x01, 0x29, 0x56, 0x12, 0x78, 0x12, 0x76, 0x81, 0x01, 0x08, 0x03],
+    [0x01, 0x44, 0x28, 0x06, 0x86, 0x21, 0x22, 0x28, 0x51, 0x05, 0x08, 0x31, 0x82, 0x02, 0x21, 0x06],
+);
+const MOYU32_KEY: ([u8; 16], [u8; 16]) = (
+    [0x15, 0x77, 0x3a, 0x5c, 0x67, 0x0e, 0x2d, 0x1f, 0x17, 0x67, 0x2a, 0x13, 0x9b, 0x67, 0x52, 0x57],
+    [0x11, 0x23, 0x26, 0x25, 0x86, 0x2a, 0x2c, 0x3b, 0x55, 0x06, 0x7f, 0x31, 0x7e, 0x67, 0x21, 0x57],
+);
+
+// How GAN and MoYu cubes encrypt their messages: each 16 bytes at the start and at the end of a
+// message XORed with the IV and run through AES, the start first
+pub struct Cipher {
+    aes: Aes128,
+    iv: [u8; 16],
+}
+
+impl Cipher {
+    // `mac` as it's written, "AB:12:..." being [0xab, 0x12, ...]
+    fn new((mut key, mut iv): ([u8; 16], [u8; 16]), mac: [u8; 6]) -> Cipher {
+        for idx in 0..6 {
+            key[idx] = ((u16::from(key[idx]) + u16::from(mac[5 - idx])) % 0xff) as u8;
+            iv[idx] = ((u16::from(iv[idx]) + u16::from(mac[5 - idx])) % 0xff) as u8;
+        }
+        Cipher { aes: Aes128::new(GenericArray::from_slice(&key)), iv }
+    }
+
+    // Where the blocks of a message are, in the order they're encrypted. Messages are at least a
+    // block long, shorter ones are left as they are
+    fn blocks(len: usize) -> Vec<usize> {
+        match len {
+            0..16 => Vec::new(),
+            16 => vec![0],
+            len => vec![0, len - 16],
+        }
+    }
+
+    pub fn encrypt(&self, message: &[u8]) -> Vec<u8> {
+        let mut message = message.to_vec();
+        for start in Cipher::blocks(message.len()) {
+            let block = &mut message[start..start + 16];
+            block.iter_mut().zip(self.iv).for_each(|(byte, iv)| *byte ^= iv);
+            self.aes.encrypt_block(GenericArray::from_mut_slice(block));
+        }
+        message
+    }
+
+    pub fn decrypt(&self, message: &[u8]) -> Vec<u8> {
+        let mut message = message.to_vec();
+        for start in Cipher::blocks(message.len()).into_iter().rev() {
+            let block = &mut message[start..start + 16];
+            self.aes.decrypt_block(GenericArray::from_mut_slice(block));
+            block.iter_mut().zip(self.iv).for_each(|(byte, iv)| *byte ^= iv);
+        }
+        message
+    }
+}
+
+// `len` bits from bit `start` on, the first bit of each byte first
+fn bits(message: &[u8], start: usize, len: usize) -> usize {
+    (start..start + len).fold(0, |word, bit| word << 1 | usize::from(message[bit / 8] >> (7 - bit % 8) & 1))
+}
+
+// `len` bytes from byte `start` on, the lowest first
+fn little_endian(message: &[u8], start: usize, len: usize) -> usize {
+    message[start..start + len].iter().rev().fold(0, |word, byte| word << 8 | usize::from(*byte))
+}
+
+// A face from its number in a protocol's order, turned clockwise or back
+fn face_turn(faces: &str, face: usize, counterclockwise: bool) -> Option<Movement> {
+    let face = faces.get(face..face + 1)?;
+    Movement::from_text(&format!("{}{}", face, if counterclockwise { "'" } else { "" }))
+}
+
+// Reads the messages of a cube connected over Bluetooth, keeping track of which turns it already
+// reported. GAN's second generation and MoYu's cubes number their turns and send the last few in
+// every message, so a message that got lost is made up by the next one
+pub struct Decoder {
+    protocol: Protocol,
+    cipher: Option<Cipher>,
+    // The Giiker state last read, or the number of the turn reported last
+    last_state: Option<Vec<u8>>,
+    last_turn: Option<usize>,
+}
+
+impl Decoder {
+    // The MoYu AI 2023 is told apart from GAN's cubes by its name
+    pub fn new(protocol: Protocol, name: &str, mac: [u8; 6]) -> Decoder {
+        let key = match protocol {
+            Protocol::Giiker => None,
+            Protocol::Gan2 if name.starts_with("AiCube") => Some(MOYU_AI_KEY),
+            Protocol::Gan2 | Protocol::Gan3 | Protocol::Gan4 => Some(GAN_KEY),
+            Protocol::Moyu32 => Some(MOYU32_KEY),
+        };
+        Decoder { protocol, cipher: key.map(|key| Cipher::new(key, mac)), last_state: None, last_turn: None }
+    }
+
+    // What to send the cube once connected, for its state and with it the number of its last turn
+    pub fn request(&self) -> Option<Vec<u8>> {
+        let opcode = match self.protocol {
+            Protocol::Gan2 => 0x04,
+            Protocol::Moyu32 => 0xa3,
+            _ => return None,
+        };
+        let mut request = vec![0; 20];
+        request[0] = opcode;
+        self.cipher.as_ref().map(|cipher| cipher.encrypt(&request))
+    }
+
+    // The state a Giiker cube is in when it's connected, so the turn it was left with isn't taken for a new one
+    pub fn start(&mut self, state: Vec<u8>) {
+        self.last_state = Some(state);
+    }
+
+    // The new turns in a message, oldest first
+    pub fn turns(&mut self, message: &[u8]) -> Vec<Movement> {
+        let message = match &self.cipher {
+            Some(cipher) => cipher.decrypt(message),
+            None => message.to_vec(),
+        };
+        match self.protocol {
+            Protocol::Giiker => {
+                if self.last_state.as_ref() == Some(&message) {
+                    return Vec::new();
+                }
+                let turn = giiker_turn(&message);
+                self.last_state = Some(message);
+                turn.into_iter().collect()
+            }
+            Protocol::Gan2 if message.len() >= 20 => match bits(&message, 0, 4) {
+                // A move, with the number of the last and the 7 last moves from bit 12 on, the last first
+                0x02 => self.numbered(bits(&message, 4, 8), 7, |turn| {
+                    face_turn("URFDLB", bits(&message, 12 + 5 * turn, 4), bits(&message, 16 + 5 * turn, 1) == 1)
+                }),
+                // The state, numbered like the moves
+                0x04 => {
+                    self.last_turn.get_or_insert(bits(&message, 4, 8));
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            },
+            // A single move, with its number and the face as a bit of its own
+            Protocol::Gan3 if message.len() >= 16 && message[0] == 0x55 && message[1] == 0x01 => {
+                self.single(little_endian(&message, 7, 2), bits(&message, 72, 2), bits(&message, 74, 6))
+            }
+            Protocol::Gan4 if message.len() >= 16 && message[0] == 0x01 => {
+                self.single(little_endian(&message, 6, 2), bits(&message, 64, 2), bits(&message, 66, 6))
+            }
+            Protocol::Moyu32 if message.len() >= 20 => match message[0] {
+                // A move, with the 5 last moves from bit 96 on
+                0xa5 => self.numbered(bits(&message, 88, 8), 5, |turn| {
+                    let movement = bits(&message, 96 + 5 * turn, 5);
+                    face_turn("FBUDLR", movement >> 1, movement & 1 == 1)
+                }),
+                0xa3 => {
+                    self.last_turn.get_or_insert(bits(&message, 152, 8));
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    // The moves since the last one reported, out of a message numbering its moves with a byte and
+    // holding the `kept` last ones. Without a number to go by yet only the last move is new
+    fn numbered(&mut self, number: usize, kept: usize, turn: impl Fn(usize) -> Option<Movement>) -> Vec<Movement> {
+        let new = self.last_turn.map_or(1, |last| number.wrapping_sub(last) & 0xff).min(kept);
+        self.last_turn = Some(number);
+        (0..new).rev().filter_map(turn).collect()
+    }
+
+    // GAN's later cubes send a message per move, the face as a single bit in the order U R F D L B goes
+    fn single(&mut self, number: usize, direction: usize, face: usize) -> Vec<Movement> {
+        if self.last_turn == Some(number) {
+            return Vec::new();
+        }
+        self.last_turn = Some(number);
+        let face = [2, 32, 8, 1, 16, 4].iter().position(|bit| *bit == face);
+        face.and_then(|face| face_turn("URFDLB", face, direction == 1)).into_iter().collect()
+    }
+}
+
+// How a solve on the smart cube ended, with its time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finish {
+    Solved(Duration),
+    // Left a U turn away from solved, timed to when it got there
+    AufError(Duration),
+}
+
+// Follows a timed solve on the smart cube. Solving the cube ends it, and so does the cube staying
+// a U turn away from solved for `AUF_WAIT`, which is a solve with the AUF wrong. Times are from the
+// start of the solve
+#[derive(Debug, Default)]
+pub struct Execution {
+    // When the cube got a U turn away from solved, while it still is
+    off_by_u: Option<Duration>,
+}
+
+impl Execution {
+    // A turn `at` into the solve that left the cube in `state`
+    pub fn turn(&mut self, state: &CubeState, at: Duration) -> Option<Finish> {
+        if state.is_solved() {
+            self.off_by_u = None;
+            return Some(Finish::Solved(at));
+        }
+        // A wrong AUF turned again, like U after U, is still a U turn away
+        self.off_by_u = off_by_u(state).then(|| self.off_by_u.unwrap_or(at));
+        None
+    }
+
+    // No turn has come by `now` into the solve
+    pub fn wait(&mut self, now: Duration) -> Option<Finish> {
+        let at = self.off_by_u.filter(|at| now >= *at + AUF_WAIT)?;
+        self.off_by_u = None;
+        Some(Finish::AufError(at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::AlgSet;
+
+    use super::*;
+
+    #[test]
+    fn bridge_lines() {
+        assert_eq!(parse_line("move R'").map(|movement| movement.as_text()), Some("R'".to_string()));
+        assert_eq!(parse_line("  move U2 ").map(|movement| movement.as_text()), Some("U2".to_string()));
+        for line in ["R'", "connected to GAN-1234", "move M", "move x", "move Rw", "move", "battery 80%"] {
+            assert!(parse_line(line).is_none(), "{}", line);
+        }
+    }
+
+    // A Giiker state with `turn` as its last turn
+    fn giiker_packet(turn: u8) -> [u8; 20] {
+        let mut packet = [0x12; 20];
+        packet[16] = turn;
+        packet[17] = 0x41;
+        packet[18] = 0;
+        packet
+    }
+
+    #[test]
+    fn giiker_turns() {
+        let turn = |packet: &[u8]| giiker_turn(packet).map(|movement| movement.as_text());
+        assert_eq!(turn(&giiker_packet(0x41)), Some("U".to_string()));
+        assert_eq!(turn(&giiker_packet(0x53)), Some("R'".to_string()));
+        assert_eq!(turn(&giiker_packet(0x22)), Some("D2".to_string()));
+        assert_eq!(turn(&giiker_packet(0x69)), Some("F2".to_string()));
+        assert_eq!(turn(&giiker_packet(0x13)), Some("B'".to_string()));
+        for packet in [giiker_packet(0x00), giiker_packet(0x71), giiker_packet(0x34)] {
+            assert!(giiker_turn(&packet).is_none());
+        }
+        assert!(giiker_turn(&giiker_packet(0x41)[..19]).is_none());
+    }
+
+    #[test]
+    fn scrambled_giiker_turns() {
+        // Scrambled the way the cube does it, by taking off what reading it adds back
+        let mut packet = giiker_packet(0x33);
+        packet[18] = 0xa7;
+        packet[19] = 0x5c;
+        for (idx, byte) in packet[..18].iter_mut().enumerate() {
+            *byte = byte.wrapping_sub(GIIKER_KEY[idx + 5]).wrapping_sub(GIIKER_KEY[idx + 12]);
+        }
+        assert_eq!(giiker_turn(&packet).map(|movement| movement.as_text()), Some("L'".to_string()));
+    }
+
+    const MAC: [u8; 6] = [0xab, 0x12, 0x34, 0x56, 0x78, 0x9a];
+
+    // Writes `value` in `len` bits from bit `start` on, the first bit of each byte first
+    fn put(message: &mut [u8], start: usize, len: usize, value: usize) {
+        for bit in 0..len {
+            let at = start + bit;
+            let set = value >> (len - 1 - bit) & 1 == 1;
+            message[at / 8] = message[at / 8] & !(0x80 >> (at % 8)) | if set { 0x80 >> (at % 8) } else { 0 };
+        }
+    }
+
+    fn texts(turns: Vec<Movement>) -> Vec<String> {
+        turns.iter().map(Movement::as_text).collect()
+    }
+
+    #[test]
+    fn cipher() {
+        let cipher = Cipher::new(GAN_KEY, MAC);
+        for len in [16, 19, 20] {
+            let message: Vec<u8> = (0..len as u8).collect();
+            let encrypted = cipher.encrypt(&message);
+            assert_ne!(encrypted, message);
+            assert_eq!(cipher.decrypt(&encrypted), message);
+        }
+        // Another cube's address is another key
+        let message = [7; 20];
+        assert_ne!(Cipher::new(GAN_KEY, [0; 6]).decrypt(&cipher.encrypt(&message)), message);
+        assert_eq!(cipher.decrypt(&[1, 2, 3]), vec![1, 2, 3]);
+    }
+
+    // A GAN Gen2 move message with the moves newest first as face and direction
+    fn gan2_moves(number: usize, moves: &[(usize, usize)]) -> Vec<u8> {
+        let mut message = vec![0; 20];
+        put(&mut message, 0, 4, 0x02);
+        put(&mut message, 4, 8, number);
+        for (idx, (face, direction)) in moves.iter().enumerate() {
+            put(&mut message, 12 + 5 * idx, 4, *face);
+            put(&mut message, 16 + 5 * idx, 1, *direction);
+        }
+        Cipher::new(GAN_KEY, MAC).encrypt(&message)
+    }
+
+    #[test]
+    fn gan2_turns() {
+        let mut decoder = Decoder::new(Protocol::Gan2, "GANicXS", MAC);
+        // Asked for the state first, which numbers the turns
+        assert_eq!(Cipher::new(GAN_KEY, MAC).decrypt(&decoder.request().unwrap())[0], 0x04);
+        let mut state = vec![0; 20];
+        put(&mut state, 0, 4, 0x04);
+        put(&mut state, 4, 8, 254);
+        assert!(decoder.turns(&Cipher::new(GAN_KEY, MAC).encrypt(&state)).is_empty());
+
+        // Two turns since, one message lost, counting over 255
+        assert_eq!(texts(decoder.turns(&gan2_moves(0, &[(1, 1), (0, 0), (5, 0)]))), ["U", "R'"]);
+        assert!(decoder.turns(&gan2_moves(0, &[(1, 1), (0, 0)])).is_empty());
+        assert_eq!(texts(decoder.turns(&gan2_moves(1, &[(3, 0), (1, 1)]))), ["D"]);
+
+        // Without the state, the last turn is the new one
+        let mut decoder = Decoder::new(Protocol::Gan2, "GANicXS", MAC);
+        assert_eq!(texts(decoder.turns(&gan2_moves(9, &[(4, 1), (2, 0)]))), ["L'"]);
+    }
+
+    #[test]
+    fn moyu_ai_turns() {
+        // The MoYu AI 2023 speaks GAN's protocol with a key of its own
+        let mut message = vec![0; 20];
+        put(&mut message, 0, 4, 0x02);
+        put(&mut message, 4, 8, 3);
+        put(&mut message, 12, 4, 2);
+        let message = Cipher::new(MOYU_AI_KEY, MAC).encrypt(&message);
+        assert_eq!(texts(Decoder::new(Protocol::Gan2, "AiCube 2023", MAC).turns(&message)), ["F"]);
+    }
+
+    #[test]
+    fn gan3_and_gan4_turns() {
+        let cipher = Cipher::new(GAN_KEY, MAC);
+        let gan3 = |number: usize, face: usize, direction: usize| {
+            let mut message = vec![0; 16];
+            message[..3].copy_from_slice(&[0x55, 0x01, 0x09]);
+            message[7..9].copy_from_slice(&(number as u16).to_le_bytes());
+            put(&mut message, 72, 2, direction);
+            put(&mut message, 74, 6, face);
+            cipher.encrypt(&message)
+        };
+        let mut decoder = Decoder::new(Protocol::Gan3, "GAN12uiM", MAC);
+        assert!(decoder.request().is_none());
+        assert_eq!(texts(decoder.turns(&gan3(0x0102, 32, 1))), ["R'"]);
+        // The same move sent again
+        assert!(decoder.turns(&gan3(0x0102, 32, 1)).is_empty());
+        assert_eq!(texts(decoder.turns(&gan3(0x0103, 4, 0))), ["B"]);
+        assert!(decoder.turns(&gan3(0x0104, 3, 0)).is_empty());
+
+        let gan4 = |number: usize, face: usize, direction: usize| {
+            let mut message = vec![0; 20];
+            message[..2].copy_from_slice(&[0x01, 0x0e]);
+            message[6] = number as u8;
+            put(&mut message, 64, 2, direction);
+            put(&mut message, 66, 6, face);
+            cipher.encrypt(&message)
+        };
+        let mut decoder = Decoder::new(Protocol::Gan4, "GAN14", MAC);
+        assert_eq!(texts(decoder.turns(&gan4(5, 1, 0))), ["D"]);
+        assert_eq!(texts(decoder.turns(&gan4(6, 2, 1))), ["U'"]);
+    }
+
+    #[test]
+    fn moyu32_turns() {
+        let cipher = Cipher::new(MOYU32_KEY, MAC);
+        // The 5 last moves newest first, a face from F B U D L R and whether it went back
+        let moves = |number: usize, moves: &[usize]| {
+            let mut message = vec![0; 20];
+            message[0] = 0xa5;
+            put(&mut message, 88, 8, number);
+            for (idx, movement) in moves.iter().enumerate() {
+                put(&mut message, 96 + 5 * idx, 5, *movement);
+            }
+            cipher.encrypt(&message)
+        };
+        let mut decoder = Decoder::new(Protocol::Moyu32, "WCU_MY32_1A2B", MAC);
+        assert_eq!(cipher.decrypt(&decoder.request().unwrap())[0], 0xa3);
+        let mut state = vec![0; 20];
+        state[0] = 0xa3;
+        put(&mut state, 152, 8, 40);
+        assert!(decoder.turns(&cipher.encrypt(&state)).is_empty());
+        assert_eq!(texts(decoder.turns(&moves(42, &[11, 4, 0]))), ["U", "R'"]);
+        assert!(decoder.turns(&moves(42, &[11, 4])).is_empty());
+        // More turns than a message holds only get the ones it has
+        assert_eq!(decoder.turns(&moves(52, &[0, 2, 4, 6, 8])).len(), 5);
+    }
+
+    #[test]
+    fn giiker_states() {
+        let mut decoder = Decoder::new(Protocol::Giiker, "GiC1234", [0; 6]);
+        assert!(decoder.request().is_none());
+        // The turn the cube was left with isn't a new one
+        decoder.start(giiker_packet(0x41).to_vec());
+        assert!(decoder.turns(&giiker_packet(0x41)).is_empty());
+        assert_eq!(texts(decoder.turns(&giiker_packet(0x53))), ["R'"]);
+    }
+
+    // Turns of a solve a second apart, each with when it was done
+    fn solve(scramble: &str, turns: &str) -> (Execution, CubeState, Vec<Option<Finish>>) {
+        let mut state = CubeState::solved();
+        state.apply(&AlgSet::parse_scramble(scramble).unwrap());
+        let mut execution = Execution::default();
+        let finishes = AlgSet::parse_scramble(turns).unwrap().iter().enumerate()
+            .map(|(idx, movement)| {
+                state.apply(std::slice::from_ref(movement));
+                execution.turn(&state, Duration::from_secs(idx as u64 + 1))
+            })
+            .collect();
+        (execution, state, finishes)
+    }
+
+    #[test]
+    fn solved_execution() {
+        let (_, _, finishes) = solve("R U2 R' U' R U' R'", "R U R' U R U2 R'");
+        assert_eq!(finishes.last(), Some(&Some(Finish::Solved(Duration::from_secs(7)))));
+        assert!(finishes[..6].iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn auf_errors() {
+        // Sune done, with the U turn after it left out
+        let (mut execution, state, finishes) = solve("U' R U2 R' U' R U' R'", "R U R' U R U2 R'");
+        assert!(finishes.iter().all(Option::is_none));
+        assert!(off_by_u(&state));
+        assert_eq!(execution.wait(Duration::from_millis(7500)), None);
+        assert_eq!(execution.wait(Duration::from_secs(8)), Some(Finish::AufError(Duration::from_secs(7))));
+
+        // Turning the AUF in time makes it a normal solve
+        let (mut execution, _, finishes) = solve("U' R U2 R' U' R U' R'", "R U R' U R U2 R' U");
+        assert_eq!(finishes.last(), Some(&Some(Finish::Solved(Duration::from_secs(8)))));
+        assert_eq!(execution.wait(Duration::from_secs(20)), None);
+
+        // The wrong way around is still a U turn off, timed from the first time it was
+        let (mut execution, _, _) = solve("U' R U2 R' U' R U' R'", "R U R' U R U2 R' U'");
+        assert_eq!(execution.wait(Duration::from_secs(9)), Some(Finish::AufError(Duration::from_secs(7))));
+    }
+}
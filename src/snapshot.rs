@@ -0,0 +1,155 @@
+use std::{collections::BTreeMap, time::{Duration, SystemTime}};
+
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::dates::day_of;
+use crate::pb::Record;
+use crate::session::Solve;
+use crate::stats::{best, best_average, mean};
+
+// The Monday before the unix epoch, which was a Thursday
+const FIRST_MONDAY: NaiveDate = NaiveDate::from_ymd_opt(1969, 12, 29).unwrap();
+
+// Weeks are numbered from FIRST_MONDAY and start on the local Monday at `day_start_hour`, the way
+// days are cut for the rest of the stats
+pub fn week_of(date: SystemTime, day_start_hour: u32) -> u64 {
+    let days = (day_of(date, day_start_hour) - FIRST_MONDAY).num_days();
+    (days.max(0) / 7) as u64
+}
+
+// The Monday a week starts on
+pub fn week_start(week: u64) -> NaiveDate {
+    FIRST_MONDAY + Days::new(week * 7)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekStats {
+    pub count: usize,
+    pub dnfs: usize,
+    pub mean: Option<Duration>,
+    pub best: Option<Duration>,
+    pub best_ao5: Option<Duration>,
+    pub best_ao12: Option<Duration>,
+}
+
+impl WeekStats {
+    fn of(solves: &[&Solve]) -> WeekStats {
+        let results: Vec<Option<Duration>> = solves.iter().map(|solve| solve.result()).collect();
+        WeekStats {
+            count: results.len(),
+            dnfs: results.iter().filter(|result| result.is_none()).count(),
+            mean: mean(&results),
+            best: best(&results),
+            best_ao5: best_average(&results, 5),
+            best_ao12: best_average(&results, 12),
+        }
+    }
+}
+
+// Aggregate stats of one week, kept after the solves themselves are pruned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub week: u64,
+    pub overall: WeekStats,
+    // Keyed by alg set path
+    pub algsets: BTreeMap<String, WeekStats>,
+    // PBs set during the week
+    pub records: Vec<Record>,
+}
+
+impl Snapshot {
+    fn of(week: u64, solves: &[&Solve]) -> Snapshot {
+        let mut by_algset: BTreeMap<String, Vec<&Solve>> = BTreeMap::new();
+        for solve in solves {
+            by_algset.entry(solve.algset.clone()).or_default().push(solve);
+        }
+
+        Snapshot {
+            week,
+            overall: WeekStats::of(solves),
+            algsets: by_algset.into_iter().map(|(algset, solves)| (algset, WeekStats::of(&solves))).collect(),
+            records: solves.iter().flat_map(|solve| solve.records.iter().copied()).collect(),
+        }
+    }
+}
+
+// Snapshots of the finished weeks that have solves but no snapshot yet.
+// The current week is left alone since solves can still be added to it
+pub fn take(solves: &[Solve], snapshots: &[Snapshot], now: SystemTime, day_start_hour: u32) -> Vec<Snapshot> {
+    let current = week_of(now, day_start_hour);
+    let mut weeks: BTreeMap<u64, Vec<&Solve>> = BTreeMap::new();
+    for solve in solves {
+        let week = week_of(solve.date, day_start_hour);
+        if week < current && !snapshots.iter().any(|snapshot| snapshot.week == week) {
+            weeks.entry(week).or_default().push(solve);
+        }
+    }
+
+    weeks.into_iter().map(|(week, solves)| Snapshot::of(week, &solves)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use super::*;
+    use crate::session::Penalty;
+
+    // A local time in June 2026, which starts on a Monday
+    fn june(day: u32, hour: u32) -> SystemTime {
+        Local.with_ymd_and_hms(2026, 6, day, hour, 30, 0).unwrap().into()
+    }
+
+    fn solve(date: SystemTime, millis: u64) -> Solve {
+        Solve {
+            date,
+            scramble: String::new(),
+            algset: "3x3/pll".to_string(),
+            alg: 0,
+            time: Duration::from_millis(millis),
+            penalty: Penalty::None,
+            tags: Vec::new(),
+            comment: String::new(),
+            mistake: None,
+            records: Vec::new(),
+            moves: None,
+            turns: None,
+        }
+    }
+
+    #[test]
+    fn weeks() {
+        let week = week_of(june(1, 12), 4);
+        assert_eq!(week_start(week), NaiveDate::from_ymd_opt(2026, 6, 1).unwrap());
+        assert_eq!(week_of(june(7, 23), 4), week);
+        // A session going past midnight on Sunday still counts for that week
+        assert_eq!(week_of(june(8, 2), 4), week);
+        assert_eq!(week_of(june(8, 2), 0), week + 1);
+        assert_eq!(week_of(june(8, 5), 4), week + 1);
+    }
+
+    #[test]
+    fn taking_snapshots() {
+        let solves = [
+            solve(june(1, 12), 2000),
+            solve(june(7, 23), 1000),
+            solve(june(8, 2), 3000),
+            solve(june(9, 12), 1500),
+            solve(june(16, 12), 1200),
+            solve(june(22, 12), 900),
+        ];
+        let first = week_of(june(1, 12), 4);
+        let taken = [Snapshot::of(first + 1, &[&solves[3]])];
+
+        // Not the week that already has one, nor the current week
+        let new = take(&solves, &taken, june(23, 12), 4);
+        let weeks: Vec<u64> = new.iter().map(|snapshot| snapshot.week).collect();
+        assert_eq!(weeks, [first, first + 2]);
+        assert_eq!(new[0].overall.count, 3);
+        assert_eq!(new[0].overall.best, Some(Duration::from_millis(1000)));
+        assert_eq!(new[0].algsets["3x3/pll"].count, 3);
+        assert_eq!(new[1].overall.count, 1);
+        assert!(take(&solves, &new, june(9, 12), 4).is_empty());
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::cube::{orientations, CubeState, Face};
+use crate::db::Movement;
+
+// Corners in the order their coordinates use, URF UFL ULB UBR DFR DLF DBL DRB, each with the faces
+// of its stickers from the U or D one going clockwise
+const CORNERS: [([i8; 3], [Face; 3]); 8] = [
+    ([1, 1, 1], [Face::U, Face::R, Face::F]),
+    ([-1, 1, 1], [Face::U, Face::F, Face::L]),
+    ([-1, 1, -1], [Face::U, Face::L, Face::B]),
+    ([1, 1, -1], [Face::U, Face::B, Face::R]),
+    ([1, -1, 1], [Face::D, Face::F, Face::R]),
+    ([-1, -1, 1], [Face::D, Face::L, Face::F]),
+    ([-1, -1, -1], [Face::D, Face::B, Face::L]),
+    ([1, -1, -1], [Face::D, Face::R, Face::B]),
+];
+
+// Edges in the order their coordinates use, UR UF UL UB DR DF DL DB FR FL BL BR, each with the faces
+// of its stickers, the U or D one first and the F or B one for the middle layer
+const EDGES: [([i8; 3], [Face; 2]); 12] = [
+    ([1, 1, 0], [Face::U, Face::R]),
+    ([0, 1, 1], [Face::U, Face::F]),
+    ([-1, 1, 0], [Face::U, Face::L]),
+    ([0, 1, -1], [Face::U, Face::B]),
+    ([1, -1, 0], [Face::D, Face::R]),
+    ([0, -1, 1], [Face::D, Face::F]),
+    ([-1, -1, 0], [Face::D, Face::L]),
+    ([0, -1, -1], [Face::D, Face::B]),
+    ([1, 0, 1], [Face::F, Face::R]),
+    ([-1, 0, 1], [Face::F, Face::L]),
+    ([-1, 0, -1], [Face::B, Face::L]),
+    ([1, 0, -1], [Face::B, Face::R]),
+];
+
+// Face turns by face, U R F D L B so that a face's opposite comes three faces later. The 2x2 only
+// turns the first three
+const FACE_TURNS: [Movement; 18] = {
+    use Movement::*;
+    [U, U2, UP, R, R2, RP, F, F2, FP, D, D2, DP, L, L2, LP, B, B2, BP]
+};
+
+// The turns that keep the twist, flip and middle layer edges solved: U, D and half turns of the rest
+const PHASE2: [usize; 10] = [0, 1, 2, 4, 7, 9, 10, 11, 13, 16];
+
+// The corner U, R and F leave alone, so the search never has to turn the whole cube
+const DBL: usize = 6;
+
+// God's number of the 2x2 in half turns
+const MAX_DEPTH: usize = 11;
+
+// Longest 3x3 scramble the two-phase search settles for. Every state has a solution this short
+// that it finds, most within a few moves of the first phase
+const MAX_LENGTH: usize = 22;
+
+static CORNER_TABLES: OnceLock<Tables> = OnceLock::new();
+static TWO_PHASE: OnceLock<TwoPhase> = OnceLock::new();
+
+// The corners of a cube: which corner is at each place and how it's twisted there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Corners {
+    permutation: [u8; 8],
+    orientation: [u8; 8],
+}
+
+impl Corners {
+    const SOLVED: Corners = Corners { permutation: [0, 1, 2, 3, 4, 5, 6, 7], orientation: [0; 8] };
+
+    // None when the stickers don't make up real corners
+    fn of(state: &CubeState) -> Option<Corners> {
+        let mut corners = Corners::SOLVED;
+        for (place, (position, faces)) in CORNERS.iter().enumerate() {
+            let colors = faces.map(|face| state.sticker(*position, face));
+            let twist = colors.iter().position(|color| matches!(color, Face::U | Face::D))?;
+            let piece = [0, 1, 2].map(|k| colors[(twist + k) % 3]);
+            corners.permutation[place] = CORNERS.iter().position(|(_, faces)| *faces == piece)? as u8;
+            corners.orientation[place] = twist as u8;
+        }
+        Some(corners)
+    }
+
+    // These corners with a move done after them
+    fn then(&self, movement: &Corners) -> Corners {
+        let mut result = Corners::SOLVED;
+        for place in 0..8 {
+            let from = movement.permutation[place] as usize;
+            result.permutation[place] = self.permutation[from];
+            result.orientation[place] = (self.orientation[from] + movement.orientation[place]) % 3;
+        }
+        result
+    }
+
+    fn permutation_index(&self) -> usize {
+        permutation_index(&self.permutation)
+    }
+
+    fn orientation_index(&self) -> usize {
+        self.orientation[..7].iter().fold(0, |index, twist| index * 3 + *twist as usize)
+    }
+}
+
+// The edges of a 3x3: which edge is at each place and whether it's flipped there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Edges {
+    permutation: [u8; 12],
+    orientation: [u8; 12],
+}
+
+impl Edges {
+    const SOLVED: Edges = Edges { permutation: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], orientation: [0; 12] };
+
+    // None when the stickers don't make up real edges
+    fn of(state: &CubeState) -> Option<Edges> {
+        let mut edges = Edges::SOLVED;
+        for (place, (position, faces)) in EDGES.iter().enumerate() {
+            let colors = faces.map(|face| state.sticker(*position, face));
+            let (piece, flip) = EDGES.iter().enumerate().find_map(|(piece, (_, faces))| match colors {
+                colors if colors == *faces => Some((piece, 0)),
+                [first, second] if [second, first] == *faces => Some((piece, 1)),
+                _ => None,
+            })?;
+            edges.permutation[place] = piece as u8;
+            edges.orientation[place] = flip;
+        }
+        Some(edges)
+    }
+
+    fn then(&self, movement: &Edges) -> Edges {
+        let mut result = Edges::SOLVED;
+        for place in 0..12 {
+            let from = movement.permutation[place] as usize;
+            result.permutation[place] = self.permutation[from];
+            result.orientation[place] = (self.orientation[from] + movement.orientation[place]) % 2;
+        }
+        result
+    }
+
+    fn orientation_index(&self) -> usize {
+        self.orientation[..11].iter().fold(0, |index, flip| index * 2 + *flip as usize)
+    }
+
+    // Which four places the middle layer edges are at, whatever their order. 0 when they're at
+    // their own places
+    fn slice_index(&self) -> usize {
+        let mut index = 0;
+        let mut found = 0;
+        for place in (0..12).rev() {
+            if self.permutation[place] >= 8 {
+                found += 1;
+                index += binomial(11 - place, found);
+            }
+        }
+        index
+    }
+
+    // The order of the U and D layer edges, once they're in those layers
+    fn layers_index(&self) -> usize {
+        permutation_index(&self.permutation[..8])
+    }
+
+    // The order of the middle layer edges, once they're in it
+    fn slice_order_index(&self) -> usize {
+        permutation_index(&self.permutation[8..])
+    }
+}
+
+// Position of a permutation in the lexicographic order of all of them, 0 for the sorted one
+fn permutation_index(permutation: &[u8]) -> usize {
+    (0..permutation.len()).fold(0, |index, place| {
+        let smaller = permutation[place + 1..].iter().filter(|other| **other < permutation[place]).count();
+        index * (permutation.len() - place) + smaller
+    })
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+}
+
+// Move indices with the faces in a random order and each face's turns together, so the same face
+// check still works and the same case gets different scrambles
+fn shuffled_turns(faces: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..faces).collect();
+    order.shuffle(rng);
+    order.into_iter().flat_map(|face| face * 3..face * 3 + 3).collect()
+}
+
+// Fewest moves to solve each permutation and each orientation of the corners, which the search
+// never goes below
+struct Tables {
+    moves: Vec<Corners>,
+    permutation: Vec<u8>,
+    orientation: Vec<u8>,
+}
+
+impl Tables {
+    fn get() -> &'static Tables {
+        CORNER_TABLES.get_or_init(Tables::new)
+    }
+
+    fn new() -> Tables {
+        let moves: Vec<Corners> = FACE_TURNS[..9].iter()
+            .map(|movement| {
+                let mut state = CubeState::solved();
+                state.apply(std::slice::from_ref(movement));
+                Corners::of(&state).unwrap()
+            })
+            .collect();
+        let permutation = distances(&moves, 40320, Corners::permutation_index);
+        let orientation = distances(&moves, 2187, Corners::orientation_index);
+        Tables { moves, permutation, orientation }
+    }
+
+    fn bound(&self, corners: &Corners) -> usize {
+        self.permutation[corners.permutation_index()].max(self.orientation[corners.orientation_index()]) as usize
+    }
+
+    // Iterative deepening: every depth is searched in full before the next, so the solution is a
+    // shortest one. Moves are tried in `order`
+    fn solve(&self, corners: Corners, order: &[usize]) -> Option<Vec<Movement>> {
+        let mut path = Vec::new();
+        (0..=MAX_DEPTH).find(|depth| self.search(corners, *depth, None, order, &mut path))?;
+        Some(path.into_iter().map(|idx| FACE_TURNS[idx].clone()).collect())
+    }
+
+    fn search(&self, corners: Corners, depth: usize, last_face: Option<usize>, order: &[usize], path: &mut Vec<usize>) -> bool {
+        if corners == Corners::SOLVED {
+            return true;
+        }
+        if self.bound(&corners) > depth {
+            return false;
+        }
+        for &idx in order {
+            // Turning the same face twice in a row is never shortest
+            if last_face == Some(idx / 3) {
+                continue;
+            }
+            path.push(idx);
+            if self.search(corners.then(&self.moves[idx]), depth - 1, Some(idx / 3), order, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+}
+
+// Breadth first from solved, by the part of the corners `index` looks at
+fn distances(moves: &[Corners], size: usize, index: fn(&Corners) -> usize) -> Vec<u8> {
+    let mut table = vec![u8::MAX; size];
+    table[index(&Corners::SOLVED)] = 0;
+    let mut queue = VecDeque::from([Corners::SOLVED]);
+    while let Some(corners) = queue.pop_front() {
+        let distance = table[index(&corners)];
+        for movement in moves {
+            let next = corners.then(movement);
+            if table[index(&next)] == u8::MAX {
+                table[index(&next)] = distance + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    table
+}
+
+// Kociemba's two-phase search for the 3x3. Phase 1 gets the corner twist, the edge flip and the
+// middle layer edges into their layer solved; phase 2 solves the rest with the turns that keep them
+// solved. Every part of the cube is a coordinate, 0 when solved, with a table of where each turn
+// takes it and tables of the fewest moves to solve pairs of them
+struct TwoPhase {
+    moves: Vec<(Corners, Edges)>,
+    twist: Vec<[u16; 18]>,
+    flip: Vec<[u16; 18]>,
+    slice: Vec<[u16; 18]>,
+    // Phase 2 coordinates, only for its turns
+    corner_order: Vec<[u16; 18]>,
+    layers_order: Vec<[u16; 18]>,
+    slice_order: Vec<[u16; 18]>,
+    slice_twist: Vec<u8>,
+    slice_flip: Vec<u8>,
+    corners_left: Vec<u8>,
+    edges_left: Vec<u8>,
+}
+
+impl TwoPhase {
+    fn get() -> &'static TwoPhase {
+        TWO_PHASE.get_or_init(TwoPhase::new)
+    }
+
+    fn new() -> TwoPhase {
+        let moves: Vec<(Corners, Edges)> = FACE_TURNS.iter()
+            .map(|movement| {
+                let mut state = CubeState::solved();
+                state.apply(std::slice::from_ref(movement));
+                (Corners::of(&state).unwrap(), Edges::of(&state).unwrap())
+            })
+            .collect();
+        let corner_moves: Vec<(usize, Corners)> = moves.iter().map(|(corners, _)| *corners).enumerate().collect();
+        let edge_moves: Vec<(usize, Edges)> = moves.iter().map(|(_, edges)| *edges).enumerate().collect();
+        let phase2_corners: Vec<(usize, Corners)> = PHASE2.iter().map(|turn| corner_moves[*turn]).collect();
+        let phase2_edges: Vec<(usize, Edges)> = PHASE2.iter().map(|turn| edge_moves[*turn]).collect();
+
+        let twist = move_table(2187, Corners::SOLVED, &corner_moves, Corners::then, Corners::orientation_index);
+        let flip = move_table(2048, Edges::SOLVED, &edge_moves, Edges::then, Edges::orientation_index);
+        let slice = move_table(495, Edges::SOLVED, &edge_moves, Edges::then, Edges::slice_index);
+        let corner_order = move_table(40320, Corners::SOLVED, &phase2_corners, Corners::then, Corners::permutation_index);
+        let layers_order = move_table(40320, Edges::SOLVED, &phase2_edges, Edges::then, Edges::layers_index);
+        let slice_order = move_table(24, Edges::SOLVED, &phase2_edges, Edges::then, Edges::slice_order_index);
+
+        let all: Vec<usize> = (0..18).collect();
+        TwoPhase {
+            slice_twist: pair_distances(&slice, &twist, &all),
+            slice_flip: pair_distances(&slice, &flip, &all),
+            corners_left: pair_distances(&slice_order, &corner_order, &PHASE2),
+            edges_left: pair_distances(&slice_order, &layers_order, &PHASE2),
+            moves,
+            twist,
+            flip,
+            slice,
+            corner_order,
+            layers_order,
+            slice_order,
+        }
+    }
+
+    // Phase 1 depths go up one at a time, and each way through it is finished by phase 2 in what's
+    // left of the longest scramble. Moves are tried in `order`
+    fn solve(&self, corners: Corners, edges: Edges, order: &[usize]) -> Option<Vec<Movement>> {
+        let start = (corners.orientation_index(), edges.orientation_index(), edges.slice_index());
+        let mut path = Vec::new();
+        (0..=MAX_LENGTH).find(|depth| self.phase1(start, *depth, (corners, edges), order, &mut path))?;
+        Some(path.into_iter().map(|idx| FACE_TURNS[idx].clone()).collect())
+    }
+
+    fn phase1(&self, (twist, flip, slice): (usize, usize, usize), depth: usize, cube: (Corners, Edges), order: &[usize], path: &mut Vec<usize>) -> bool {
+        if depth == 0 {
+            // A phase 1 ending in a phase 2 turn was already tried a turn shorter
+            let ends_in_phase2 = path.last().is_some_and(|turn| PHASE2.contains(turn));
+            return twist == 0 && flip == 0 && slice == 0 && !ends_in_phase2 && self.finish(cube, order, path);
+        }
+        let bound = self.slice_twist[slice * 2187 + twist].max(self.slice_flip[slice * 2048 + flip]);
+        if bound as usize > depth {
+            return false;
+        }
+        for &turn in order {
+            if redundant(path.last(), turn) {
+                continue;
+            }
+            path.push(turn);
+            let next = (self.twist[twist][turn] as usize, self.flip[flip][turn] as usize, self.slice[slice][turn] as usize);
+            if self.phase1(next, depth - 1, cube, order, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    // Phase 2 from where phase 1's moves left the cube, pushed onto them when it fits
+    fn finish(&self, (corners, edges): (Corners, Edges), order: &[usize], path: &mut Vec<usize>) -> bool {
+        let (corners, edges) = path.iter().fold((corners, edges), |(corners, edges), turn| {
+            let (turn_corners, turn_edges) = &self.moves[*turn];
+            (corners.then(turn_corners), edges.then(turn_edges))
+        });
+        let start = (corners.permutation_index(), edges.layers_index(), edges.slice_order_index());
+        let order: Vec<usize> = order.iter().copied().filter(|turn| PHASE2.contains(turn)).collect();
+        let left = MAX_LENGTH - path.len();
+        (0..=left).any(|depth| self.phase2(start, depth, &order, path))
+    }
+
+    fn phase2(&self, (corners, layers, slice): (usize, usize, usize), depth: usize, order: &[usize], path: &mut Vec<usize>) -> bool {
+        let bound = self.corners_left[slice * 40320 + corners].max(self.edges_left[slice * 40320 + layers]);
+        if bound as usize > depth {
+            return false;
+        }
+        if depth == 0 {
+            return true;
+        }
+        for &turn in order {
+            if redundant(path.last(), turn) {
+                continue;
+            }
+            path.push(turn);
+            let next = (self.corner_order[corners][turn] as usize, self.layers_order[layers][turn] as usize, self.slice_order[slice][turn] as usize);
+            if self.phase2(next, depth - 1, order, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+}
+
+// Turning the same face twice in a row is never shortest, and opposite faces turn the same either
+// way around, so only one order of them is tried
+fn redundant(last: Option<&usize>, turn: usize) -> bool {
+    last.is_some_and(|last| last / 3 == turn / 3 || last / 3 == turn / 3 + 3)
+}
+
+// Where each value of a coordinate goes with each of `moves`, breadth first from solved so that
+// every value comes with pieces that have it
+fn move_table<P: Copy>(size: usize, solved: P, moves: &[(usize, P)], then: fn(&P, &P) -> P, index: fn(&P) -> usize) -> Vec<[u16; 18]> {
+    let mut table = vec![[0; 18]; size];
+    let mut seen = vec![false; size];
+    seen[index(&solved)] = true;
+    let mut queue = VecDeque::from([solved]);
+    while let Some(pieces) = queue.pop_front() {
+        let from = index(&pieces);
+        for (turn, movement) in moves {
+            let next = then(&pieces, movement);
+            let to = index(&next);
+            table[from][*turn] = to as u16;
+            if !seen[to] {
+                seen[to] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    table
+}
+
+// Fewest of `turns` to solve each pair of coordinates, breadth first from both being 0
+fn pair_distances(first: &[[u16; 18]], second: &[[u16; 18]], turns: &[usize]) -> Vec<u8> {
+    let width = second.len();
+    let mut table = vec![u8::MAX; first.len() * width];
+    table[0] = 0;
+    let mut queue = VecDeque::from([0]);
+    while let Some(pair) = queue.pop_front() {
+        let (a, b) = (pair / width, pair % width);
+        for &turn in turns {
+            let next = first[a][turn] as usize * width + second[b][turn] as usize;
+            if table[next] == u8::MAX {
+                table[next] = table[pair] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    table
+}
+
+// A scramble leaving the cube the way `movements` do, found by a search instead of undoing an alg,
+// so the scramble doesn't give the alg away. A 2x2 gets the fewest moves in U, R and F for its
+// corners, a 3x3 face turns from the two-phase search, at most 22 of them. Faces are tried in a
+// random order so the same case gets different scrambles, and a rotation ends the scramble when the
+// case isn't held the usual way after it. None for other sizes, or when the moves don't leave real
+// pieces behind
+pub fn random_state(movements: &[Movement], size: usize, rng: &mut impl Rng) -> Option<Vec<Movement>> {
+    let mut state = CubeState::solved();
+    state.apply(movements);
+
+    let (rotation, solution) = match size {
+        2 => {
+            // Held so that DBL is solved, the search only turns U, R and F
+            let (rotation, held) = orientations().into_iter().find_map(|rotation| {
+                let mut held = state.clone();
+                held.apply(&rotation);
+                Corners::of(&held)
+                    .filter(|corners| corners.permutation[DBL] == DBL as u8 && corners.orientation[DBL] == 0)
+                    .map(|corners| (rotation, corners))
+            })?;
+            (rotation, Tables::get().solve(held, &shuffled_turns(3, rng))?)
+        }
+        3 => {
+            // Held with the centers where they go, the search only turns faces
+            let (rotation, held) = orientations().into_iter().find_map(|rotation| {
+                let mut held = state.clone();
+                held.apply(&rotation);
+                let centered = held.sticker([0, 1, 0], Face::U) == Face::U && held.sticker([0, 0, 1], Face::F) == Face::F;
+                centered.then_some((rotation, held))
+            })?;
+            let (corners, edges) = (Corners::of(&held)?, Edges::of(&held)?);
+            (rotation, TwoPhase::get().solve(corners, edges, &shuffled_turns(6, rng))?)
+        }
+        _ => return None,
+    };
+    let mut scramble: Vec<Movement> = solution.iter().rev().map(Movement::inv).collect();
+    scramble.extend(rotation.iter().rev().map(Movement::inv));
+
+    let mut check = CubeState::solved();
+    check.apply(&scramble);
+    let same = match size {
+        2 => Corners::of(&check)? == Corners::of(&state)?,
+        _ => check == state,
+    };
+    same.then_some(scramble)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::db::{moves_text, AlgSet};
+
+    use super::*;
+
+    // The scramble sets up exactly what the moves do, held the same way after it
+    fn sets_up(moves: &str, size: usize, seed: u64) -> Vec<Movement> {
+        let movements = AlgSet::parse_scramble(moves).unwrap();
+        let scramble = random_state(&movements, size, &mut StdRng::seed_from_u64(seed)).unwrap();
+        let (mut expected, mut got) = (CubeState::solved(), CubeState::solved());
+        expected.apply(&movements);
+        got.apply(&scramble);
+        match size {
+            2 => assert!(Corners::of(&expected) == Corners::of(&got), "{} for {}", moves_text(&scramble), moves),
+            _ => assert!(expected == got, "{} for {}", moves_text(&scramble), moves),
+        }
+        scramble
+    }
+
+    fn face_turns(scramble: &[Movement]) -> usize {
+        scramble.iter().filter(|movement| !matches!(movement, Movement::X | Movement::XP | Movement::X2 | Movement::Y | Movement::YP | Movement::Y2 | Movement::Z | Movement::ZP | Movement::Z2)).count()
+    }
+
+    #[test]
+    fn pocket_cube() {
+        // Sune's inverse, and a case turning the DBL corner that needs the cube held another way
+        for (moves, seed) in [("R U2 R' U' R U' R'", 1), ("L' U' L U' L' U2 L", 2), ("R U R' U R U2 R' B2", 3)] {
+            let scramble = sets_up(moves, 2, seed);
+            assert!(face_turns(&scramble) <= MAX_DEPTH);
+            assert!(scramble.iter().all(|movement| !matches!(movement, Movement::D | Movement::L | Movement::B)));
+        }
+        assert_eq!(face_turns(&sets_up("R U2 R' U' R U' R'", 2, 4)), 7);
+    }
+
+    #[test]
+    fn two_phase() {
+        // T-perm, an M slice H-perm and an A-perm with rotations, then a superflip
+        let cases = [
+            "R U R' U' R' F R2 U' R' U' R U R' F'",
+            "M2 U M2 U2 M2 U M2",
+            "x R' U R' D2 R U' R' D2 R2 x'",
+            "U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2",
+        ];
+        for (seed, moves) in cases.iter().enumerate() {
+            let scramble = sets_up(moves, 3, seed as u64);
+            assert!(face_turns(&scramble) <= MAX_LENGTH, "{}", moves_text(&scramble));
+        }
+        // A case leaving the centers moved ends in the rotation holding the cube like the case does
+        let scramble = sets_up("R U R' U' M'", 3, 7);
+        assert!(face_turns(&scramble) < scramble.len());
+    }
+
+    #[test]
+    fn shuffled_searches() {
+        let scrambles: Vec<String> = (0..8).map(|seed| moves_text(&sets_up("R U R' U' R' F R2 U' R' U' R U R' F'", 3, seed))).collect();
+        assert!(scrambles.iter().any(|scramble| *scramble != scrambles[0]));
+        assert!(random_state(&[], 4, &mut StdRng::seed_from_u64(0)).is_none());
+    }
+}
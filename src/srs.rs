@@ -0,0 +1,278 @@
+use std::{path::{Path, PathBuf}, time::{Duration, SystemTime}};
+
+use rand::{seq::IndexedRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::db::AlgSet;
+use crate::history::{read, write, HistoryError};
+use crate::scramble::{cases, Scramble, Variation};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+const MIN_EASE: f64 = 1.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    pub const ALL: [Grade; 4] = [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy];
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Grade::Again => "again",
+            Grade::Hard => "hard",
+            Grade::Good => "good",
+            Grade::Easy => "easy",
+        }
+    }
+
+    // Quality of the answer on SM-2's 0-5 scale
+    fn quality(&self) -> f64 {
+        match self {
+            Grade::Again => 0.0,
+            Grade::Hard => 3.0,
+            Grade::Good => 4.0,
+            Grade::Easy => 5.0,
+        }
+    }
+}
+
+// Scheduling state of one case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Card {
+    pub algset: String,
+    pub alg: usize,
+    pub ease: f64,
+    pub interval_days: f64,
+    // Successful reviews in a row
+    pub reps: u32,
+    pub due: SystemTime,
+}
+
+impl Card {
+    fn new(algset: &str, alg: usize, now: SystemTime) -> Card {
+        Card {
+            algset: algset.to_string(),
+            alg,
+            ease: 2.5,
+            interval_days: 0.0,
+            reps: 0,
+            due: now,
+        }
+    }
+
+    // SM-2: a failed case starts over and is due right away, a passed one is shown again
+    // after 1 day, then 6, then a growing interval depending on how easy it was
+    fn grade(&mut self, grade: Grade, now: SystemTime) {
+        let quality = grade.quality();
+        if grade == Grade::Again {
+            self.reps = 0;
+            self.interval_days = 0.0;
+        } else {
+            self.reps += 1;
+            self.interval_days = match self.reps {
+                1 => 1.0,
+                2 => 6.0,
+                _ => self.interval_days * self.ease,
+            };
+            self.ease = (self.ease + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)).max(MIN_EASE);
+        }
+        self.due = now + DAY.mul_f64(self.interval_days);
+    }
+}
+
+// What's due goes by `now`, which the caller reads: the browser has no clock to read here
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    // None keeps the cards in memory only (demo mode)
+    path: Option<PathBuf>,
+    pub cards: Vec<Card>,
+}
+
+impl Scheduler {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rubiks_trainer").join("srs.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<Scheduler, HistoryError> {
+        Ok(Scheduler {
+            path: Some(path.to_path_buf()),
+            cards: read(path)?,
+        })
+    }
+
+    pub fn detach(&mut self) {
+        self.path = None;
+    }
+
+    pub fn save(&self) -> Result<(), HistoryError> {
+        match &self.path {
+            Some(path) => write(path, &self.cards),
+            None => Ok(()),
+        }
+    }
+
+    fn card(&self, algset: &str, alg: usize) -> Option<&Card> {
+        self.cards.iter().find(|card| card.algset == algset && card.alg == alg)
+    }
+
+    pub fn grade(&mut self, scramble: &Scramble, grade: Grade, now: SystemTime) {
+        let idx = match self.cards.iter().position(|card| card.algset == scramble.algset && card.alg == scramble.alg) {
+            Some(idx) => idx,
+            None => {
+                self.cards.push(Card::new(&scramble.algset, scramble.alg, now));
+                self.cards.len() - 1
+            }
+        };
+        self.cards[idx].grade(grade, now);
+    }
+
+    pub fn due(&self, algset: &str, alg: usize) -> Option<SystemTime> {
+        self.card(algset, alg).map(|card| card.due)
+    }
+
+    // Moves a case's next review by whole days, later for positive days and sooner for negative ones
+    pub fn shift(&mut self, algset: &str, alg: usize, days: i32) {
+        if let Some(card) = self.cards.iter_mut().find(|card| card.algset == algset && card.alg == alg) {
+            let shift = DAY * days.unsigned_abs();
+            card.due = if days >= 0 { card.due + shift } else { card.due.checked_sub(shift).unwrap_or(card.due) };
+        }
+    }
+
+    pub fn due_count(&self, algsets: &[&AlgSet], now: SystemTime) -> usize {
+        cases(algsets).iter()
+            .filter(|(algset, alg)| self.card(&algset.path, *alg).is_some_and(|card| card.due <= now))
+            .count()
+    }
+
+    // Every case due now, whether or not its alg set still exists
+    pub fn due_total(&self, now: SystemTime) -> usize {
+        self.cards.iter().filter(|card| card.due <= now).count()
+    }
+
+    // The most overdue case, else a random case that was never graded, else the one due soonest.
+    // `avoid` is skipped when there is another case to show. None when the sets have no cases
+    pub fn pick(&self, algsets: &[&AlgSet], avoid: Option<&Scramble>, variation: Variation, now: SystemTime, rng: &mut impl Rng) -> Option<Scramble> {
+        let mut cases = cases(algsets);
+        if let Some(avoid) = avoid && cases.len() > 1 {
+            cases.retain(|(algset, alg)| algset.path != avoid.algset || *alg != avoid.alg);
+        }
+
+        let (new, graded): (Vec<_>, Vec<_>) = cases.into_iter()
+            .map(|(algset, alg)| (algset, alg, self.card(&algset.path, alg)))
+            .partition(|(_, _, card)| card.is_none());
+
+        let soonest = graded.iter().min_by_key(|(_, _, card)| card.unwrap().due);
+        let (algset, alg, _) = match soonest {
+            Some(case) if case.2.unwrap().due <= now || new.is_empty() => case,
+            _ => new.choose(rng)?,
+        };
+        Some(Scramble::of(algset, *alg, variation, rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, NaiveDate, TimeZone};
+
+    use super::*;
+    use crate::dates::day_of;
+
+    fn scramble(alg: usize) -> Scramble {
+        Scramble { text: "R U R'".to_string(), algset: "3x3/pll".to_string(), alg, turns: 3 }
+    }
+
+    fn now() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000)
+    }
+
+    fn graded(grades: &[Grade]) -> Card {
+        let mut card = Card::new("3x3/pll", 0, now());
+        for grade in grades {
+            card.grade(*grade, now());
+        }
+        card
+    }
+
+    #[test]
+    fn intervals() {
+        assert_eq!(graded(&[Grade::Good]).interval_days, 1.0);
+        assert_eq!(graded(&[Grade::Good, Grade::Good]).interval_days, 6.0);
+        let third = graded(&[Grade::Good, Grade::Good, Grade::Good]);
+        assert_eq!(third.reps, 3);
+        assert!((third.interval_days - 6.0 * 2.5).abs() < 1e-9);
+
+        let again = graded(&[Grade::Good, Grade::Good, Grade::Again]);
+        assert_eq!((again.reps, again.interval_days), (0, 0.0));
+        assert_eq!(again.due, now());
+        assert_eq!(graded(&[Grade::Good, Grade::Again, Grade::Good]).interval_days, 1.0);
+    }
+
+    #[test]
+    fn ease() {
+        assert!((graded(&[Grade::Good]).ease - 2.5).abs() < 1e-9);
+        assert!((graded(&[Grade::Easy]).ease - 2.6).abs() < 1e-9);
+        assert!((graded(&[Grade::Hard]).ease - 2.36).abs() < 1e-9);
+        // Failing doesn't touch the ease, and hard answers never take it below the floor
+        assert!((graded(&[Grade::Again]).ease - 2.5).abs() < 1e-9);
+        assert_eq!(graded(&[Grade::Hard; 20]).ease, MIN_EASE);
+    }
+
+    #[test]
+    fn due() {
+        let mut srs = Scheduler::default();
+        assert_eq!(srs.due("3x3/pll", 1), None);
+        srs.grade(&scramble(1), Grade::Again, now());
+        srs.grade(&scramble(2), Grade::Good, now());
+        assert_eq!(srs.cards.len(), 2);
+        assert_eq!(srs.due_total(now()), 1);
+
+        let before = srs.due("3x3/pll", 2).unwrap();
+        assert_eq!(before, now() + DAY);
+        assert_eq!(srs.due_total(now() + DAY), 2);
+        srs.shift("3x3/pll", 2, -1);
+        assert_eq!(srs.due_total(now()), 2);
+        srs.shift("3x3/pll", 2, 2);
+        assert_eq!(srs.due("3x3/pll", 2), Some(before + DAY));
+    }
+
+    #[test]
+    fn due_day() {
+        let day = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        let at = |hour| -> SystemTime { Local.with_ymd_and_hms(2026, 3, 10, hour, 30, 0).unwrap().into() };
+        let mut srs = Scheduler::default();
+        srs.grade(&scramble(0), Grade::Good, at(12));
+
+        // Before the day starts a review still counts towards the day before
+        srs.cards[0].due = at(3);
+        let due = srs.due("3x3/pll", 0).unwrap();
+        assert_eq!(day_of(due, 0), day);
+        assert_eq!(day_of(due, 4), day.pred_opt().unwrap());
+
+        srs.cards[0].due = at(4);
+        assert_eq!(day_of(srs.due("3x3/pll", 0).unwrap(), 4), day);
+        srs.shift("3x3/pll", 0, 1);
+        assert_eq!(day_of(srs.due("3x3/pll", 0).unwrap(), 4), day.succ_opt().unwrap());
+    }
+
+    #[test]
+    fn pick() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let (empty, _) = AlgSet::read_text("empty.txt".to_string(), "# nothing here yet\n", None);
+        let mut srs = Scheduler::default();
+        assert!(srs.pick(&[&empty], None, Variation::default(), now(), &mut rng).is_none());
+
+        // The case graded again is due before the new one comes up
+        let (oll, _) = AlgSet::read_text("oll.txt".to_string(), "Sune: R U R' U R U2 R'\nAntisune: R U2 R' U' R U' R'\n", None);
+        let sune = Scramble { algset: "oll.txt".to_string(), ..scramble(0) };
+        srs.grade(&sune, Grade::Again, now());
+        assert!(srs.pick(&[&empty, &oll], None, Variation::default(), now(), &mut rng).unwrap().same_case(&sune));
+        assert_eq!(srs.pick(&[&oll], Some(&sune), Variation::default(), now(), &mut rng).unwrap().alg, 1);
+    }
+}
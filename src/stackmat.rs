@@ -0,0 +1,382 @@
+use std::io::{self, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+// Stackmat timers send their display as 1200 baud serial over the audio cable, 8 data bits after a
+// start bit, least significant first
+const BAUD: f64 = 1200.0;
+// A gap this long between bytes ends a packet
+const GAP_BITS: f64 = 20.0;
+// More bits than two packets without a gap are noise, or the signal read the wrong way up
+const MAX_BITS: usize = 200;
+// Packets that don't check out before the signal is read the other way up, as sound cards differ
+const BAD_PACKETS: usize = 4;
+
+// What the timer is doing, from the first byte of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackmatState {
+    // Reset to zero
+    Idle,
+    // Hands on the pads, the timer starts when they're lifted
+    Hands,
+    Running,
+    Stopped,
+}
+
+impl StackmatState {
+    fn from_status(status: u8) -> Option<StackmatState> {
+        match status {
+            b'I' => Some(StackmatState::Idle),
+            b'L' | b'R' | b'C' | b'A' => Some(StackmatState::Hands),
+            b' ' => Some(StackmatState::Running),
+            b'S' => Some(StackmatState::Stopped),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub state: StackmatState,
+    // The time on the display
+    pub time: Duration,
+}
+
+impl Packet {
+    // "S0123456\n\r" from a Gen4 or Gen5 timer with thousandths, or "S012345\n\r" from a Gen3, with
+    // the state, minutes, seconds, hundredths and maybe thousandths, then 64 plus the sum of the digits
+    fn parse(bytes: &[u8]) -> Option<Packet> {
+        let (status, rest) = bytes.split_first()?;
+        let rest = rest.strip_suffix(b"\n\r")?;
+        let (checksum, digits) = rest.split_last()?;
+        if !(digits.len() == 5 || digits.len() == 6) || !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let digits: Vec<u64> = digits.iter().map(|digit| (digit - b'0') as u64).collect();
+        if 64 + digits.iter().sum::<u64>() != *checksum as u64 {
+            return None;
+        }
+        let millis = digits[5..].first().copied().unwrap_or(0);
+        let time = Duration::from_millis(digits[0] * 60_000 + (digits[1] * 10 + digits[2]) * 1000 + (digits[3] * 10 + digits[4]) * 10 + millis);
+        Some(Packet { state: StackmatState::from_status(*status)?, time })
+    }
+}
+
+// Turns audio samples into packets: the signal's level is read as bits by how long it stays the same
+#[derive(Debug)]
+pub struct Decoder {
+    // Samples per bit
+    period: f64,
+    inverted: bool,
+    level: bool,
+    // Samples the level has stayed the same for
+    run: usize,
+    bits: Vec<bool>,
+    bad_packets: usize,
+}
+
+impl Decoder {
+    pub fn new(sample_rate: u32) -> Decoder {
+        Decoder { period: sample_rate as f64 / BAUD, inverted: false, level: true, run: 0, bits: Vec::new(), bad_packets: 0 }
+    }
+
+    pub fn push(&mut self, sample: i16) -> Option<Packet> {
+        let level = (sample > 0) != self.inverted;
+        if level == self.level {
+            self.run += 1;
+            // The line stays high between packets
+            if level && self.run as f64 == (self.period * GAP_BITS).round() {
+                return self.end_packet();
+            }
+            return None;
+        }
+        let bits = (self.run as f64 / self.period).round() as usize;
+        // Long idle stretches were handled when they got long enough
+        if !(self.level && self.run as f64 >= self.period * GAP_BITS) {
+            self.bits.extend(std::iter::repeat_n(self.level, bits.min(GAP_BITS as usize)));
+        }
+        self.level = level;
+        self.run = 1;
+        if self.bits.len() > MAX_BITS {
+            return self.end_packet();
+        }
+        None
+    }
+
+    fn end_packet(&mut self) -> Option<Packet> {
+        let bits = std::mem::take(&mut self.bits);
+        if bits.is_empty() {
+            return None;
+        }
+        let mut bytes = Vec::new();
+        let mut idx = 0;
+        while idx + 9 <= bits.len() {
+            // Waiting for a start bit
+            if bits[idx] {
+                idx += 1;
+                continue;
+            }
+            let byte = (0..8).fold(0u8, |byte, bit| byte | ((bits[idx + 1 + bit] as u8) << bit));
+            bytes.push(byte);
+            idx += 10;
+        }
+        let packet = Packet::parse(&bytes);
+        if packet.is_some() {
+            self.bad_packets = 0;
+        } else {
+            self.bad_packets += 1;
+            if self.bad_packets >= BAD_PACKETS {
+                self.inverted = !self.inverted;
+                self.level = !self.level;
+                self.bad_packets = 0;
+            }
+        }
+        packet
+    }
+}
+
+// The words of a command line split the way a shell would: quotes keep spaces in a word, and a
+// backslash outside single quotes takes the next character as it is
+fn command_words(command: &str) -> io::Result<Vec<String>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{} in `{}`", message, command));
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\\' => word.get_or_insert_default().push(chars.next().ok_or_else(|| invalid("trailing backslash"))?),
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next().ok_or_else(|| invalid("unclosed quote"))? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next().ok_or_else(|| invalid("unclosed quote"))? {
+                        '"' => break,
+                        '\\' => word.push(chars.next().ok_or_else(|| invalid("unclosed quote"))?),
+                        c => word.push(c),
+                    }
+                }
+            }
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// Starts a command line whose output is read by the trainer, without going through a shell
+pub(crate) fn spawn_reader(command: &str) -> io::Result<Child> {
+    let words = command_words(command)?;
+    let (program, args) = words.split_first().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+    Command::new(program).args(args).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null()).spawn()
+}
+
+// A Stackmat timer on the line in, recorded by a command that writes raw signed 16 bit little endian
+// mono samples, like `arecord -q -t raw -f S16_LE -c 1 -r 44100`
+#[derive(Debug)]
+pub struct Stackmat {
+    recorder: Child,
+    packets: Receiver<Packet>,
+}
+
+impl Stackmat {
+    pub fn open(command: &str, sample_rate: u32) -> io::Result<Stackmat> {
+        let mut recorder = spawn_reader(command)?;
+        let mut audio = BufReader::new(recorder.stdout.take().unwrap());
+
+        let (sender, packets) = mpsc::channel();
+        thread::spawn(move || {
+            let mut decoder = Decoder::new(sample_rate);
+            let mut last: Option<StackmatState> = None;
+            let mut sample = [0u8; 2];
+            while audio.read_exact(&mut sample).is_ok() {
+                let Some(packet) = decoder.push(i16::from_le_bytes(sample)) else {
+                    continue;
+                };
+                // Timers repeat their display all the time, only changes are passed on
+                if last == Some(packet.state) {
+                    continue;
+                }
+                last = Some(packet.state);
+                if sender.send(packet).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Stackmat { recorder, packets })
+    }
+
+    // Changes of the timer's state since the last call
+    pub fn packets(&self) -> Vec<Packet> {
+        self.packets.try_iter().collect()
+    }
+}
+
+impl Drop for Stackmat {
+    fn drop(&mut self) {
+        let _ = self.recorder.kill();
+        let _ = self.recorder.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The bits of a packet on the line: idle high, then a low start bit, the byte and a high stop
+    // bit for each byte, then idle again
+    fn line_bits(packet: &[u8]) -> Vec<bool> {
+        let mut bits = vec![true; 30];
+        for byte in packet {
+            bits.push(false);
+            bits.extend((0..8).map(|bit| byte & (1 << bit) != 0));
+            bits.push(true);
+        }
+        bits.extend([true; 30]);
+        bits
+    }
+
+    // The audio of bits at `sample_rate`, which needn't be a whole number of samples a bit
+    fn audio(bits: &[bool], inverted: bool, sample_rate: u32) -> Vec<i16> {
+        let period = sample_rate as f64 / BAUD;
+        let samples = (bits.len() as f64 * period) as usize;
+        (0..samples)
+            .map(|idx| if bits[(idx as f64 / period) as usize] != inverted { 8000 } else { -8000 })
+            .collect()
+    }
+
+    // A packet at 10 samples a bit
+    fn signal(packet: &[u8], inverted: bool) -> Vec<i16> {
+        audio(&line_bits(packet), inverted, 12_000)
+    }
+
+    fn decode(decoder: &mut Decoder, samples: &[i16]) -> Vec<Packet> {
+        samples.iter().filter_map(|sample| decoder.push(*sample)).collect()
+    }
+
+    #[test]
+    fn packets() {
+        // 1:23.45 on a Gen3, digits adding up to 15 and 64 + 15 = 'O'
+        let packet = Packet::parse(b" 12345O\n\r").unwrap();
+        assert_eq!(packet, Packet { state: StackmatState::Running, time: Duration::from_millis(83_450) });
+        // 0:09.876 on a Gen4, 64 + 30 = '^'
+        let packet = Packet::parse(b"S009876^\n\r").unwrap();
+        assert_eq!(packet, Packet { state: StackmatState::Stopped, time: Duration::from_millis(9_876) });
+        assert_eq!(Packet::parse(b"I000000@\n\r").unwrap().state, StackmatState::Idle);
+        assert_eq!(Packet::parse(b"A000000@\n\r").unwrap().state, StackmatState::Hands);
+    }
+
+    #[test]
+    fn bad_packets() {
+        // Wrong checksum
+        assert_eq!(Packet::parse(b" 12345P\n\r"), None);
+        // Unknown state, missing end, too few digits
+        assert_eq!(Packet::parse(b"X12345O\n\r"), None);
+        assert_eq!(Packet::parse(b" 12345O"), None);
+        assert_eq!(Packet::parse(b" 1234K\n\r"), None);
+    }
+
+    #[test]
+    fn decoding() {
+        let mut decoder = Decoder::new(12_000);
+        let samples = signal(b"S009876^\n\r", false);
+        assert_eq!(decode(&mut decoder, &samples), [Packet { state: StackmatState::Stopped, time: Duration::from_millis(9_876) }]);
+    }
+
+    #[test]
+    fn decoding_the_wrong_way_up() {
+        let mut decoder = Decoder::new(12_000);
+        // Timers send their display over and over, enough for the decoder to turn it around
+        let samples = signal(b" 12345O\n\r", true).repeat(20);
+        let packets = decode(&mut decoder, &samples);
+        assert!(!packets.is_empty());
+        assert!(packets.iter().all(|packet| packet.time == Duration::from_millis(83_450)));
+    }
+
+    #[test]
+    fn framing() {
+        // Packets come one after another with the line idle between them, at a sample rate that
+        // doesn't fit the baud rate evenly
+        let mut samples = audio(&line_bits(b"A000000@\n\r"), false, 44_100);
+        samples.extend(audio(&line_bits(b" 001234J\n\r"), false, 44_100));
+        let mut decoder = Decoder::new(44_100);
+        assert_eq!(decode(&mut decoder, &samples), [
+            Packet { state: StackmatState::Hands, time: Duration::ZERO },
+            Packet { state: StackmatState::Running, time: Duration::from_millis(1_234) },
+        ]);
+
+        // A packet cut off after a few bytes doesn't make one out of what's left
+        let mut decoder = Decoder::new(12_000);
+        assert!(decode(&mut decoder, &signal(b" 0012", false)).is_empty());
+        // Nor does a line that never goes idle, it's dropped once it's longer than a packet can be
+        let noise: Vec<i16> = (0..MAX_BITS * 30).map(|idx| if idx % 20 < 10 { 8000 } else { -8000 }).collect();
+        assert!(decode(&mut decoder, &noise).is_empty());
+        assert!(decoder.bits.len() <= MAX_BITS + GAP_BITS as usize);
+    }
+
+    #[test]
+    fn checksums() {
+        let mut decoder = Decoder::new(12_000);
+        // Digits adding up to 10 need a 'J', one bit off is rejected
+        assert!(decode(&mut decoder, &signal(b" 001234K\n\r", false)).is_empty());
+        assert_eq!(decoder.bad_packets, 1);
+        // A good packet after it clears the count
+        assert_eq!(decode(&mut decoder, &signal(b" 001234J\n\r", false)).len(), 1);
+        assert_eq!(decoder.bad_packets, 0);
+    }
+
+    // Decodes the samples, checking that the decoder turns the signal around once, after BAD_PACKETS
+    // packets in a row that don't check out and before any packet came through
+    fn decode_turning_around(decoder: &mut Decoder, samples: &[i16]) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        let mut turns = 0;
+        for sample in samples {
+            let (inverted, bad_packets) = (decoder.inverted, decoder.bad_packets);
+            let packet = decoder.push(*sample);
+            if decoder.inverted != inverted {
+                assert_eq!(bad_packets, BAD_PACKETS - 1);
+                assert!(packets.is_empty());
+                assert_eq!(decoder.bad_packets, 0);
+                turns += 1;
+            }
+            packets.extend(packet);
+        }
+        assert_eq!(turns, 1);
+        packets
+    }
+
+    #[test]
+    fn turning_the_signal_around() {
+        let stopped = Packet { state: StackmatState::Stopped, time: Duration::from_millis(9_876) };
+        let mut decoder = Decoder::new(12_000);
+        let packets = decode_turning_around(&mut decoder, &signal(b"S009876^\n\r", true).repeat(20));
+        assert!(decoder.inverted);
+        assert!(packets.len() > 10);
+        assert!(packets.iter().all(|packet| *packet == stopped));
+
+        // And back again when the signal comes the right way up
+        let packets = decode_turning_around(&mut decoder, &signal(b"S009876^\n\r", false).repeat(20));
+        assert!(!decoder.inverted);
+        assert!(packets.len() > 10);
+        assert!(packets.iter().all(|packet| *packet == stopped));
+    }
+
+    #[test]
+    fn command_lines() {
+        assert_eq!(command_words("arecord -q  -r 44100").unwrap(), ["arecord", "-q", "-r", "44100"]);
+        assert_eq!(command_words("rec -t raw '/tmp/my file' \"a \\\"b\\\"\" c\\ d").unwrap(), ["rec", "-t", "raw", "/tmp/my file", "a \"b\"", "c d"]);
+        assert_eq!(command_words("x '' y").unwrap(), ["x", "", "y"]);
+        assert!(command_words("rec 'open").is_err());
+        assert!(command_words("rec \\").is_err());
+    }
+}
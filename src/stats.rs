@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use crate::session::Solve;
+
+// Results are solve times after penalties, None being a DNF
+
+pub fn mean(results: &[Option<Duration>]) -> Option<Duration> {
+    let times: Vec<Duration> = results.iter().flatten().copied().collect();
+    if times.is_empty() {
+        return None;
+    }
+    Some(times.iter().sum::<Duration>() / times.len() as u32)
+}
+
+pub fn best(results: &[Option<Duration>]) -> Option<Duration> {
+    results.iter().flatten().min().copied()
+}
+
+pub fn worst(results: &[Option<Duration>]) -> Option<Duration> {
+    results.iter().flatten().max().copied()
+}
+
+// WCA style average of the last n results: the best and worst 5% (at least one each) are
+// dropped and the rest averaged. DNFs count as the worst results, so too many make it a DNF.
+// Returns None when there aren't n results yet, Some(None) for a DNF average
+pub fn average_of(results: &[Option<Duration>], n: usize) -> Option<Option<Duration>> {
+    if results.len() < n || n < 3 {
+        return None;
+    }
+    let trim = (n as f64 * 0.05).ceil() as usize;
+
+    let mut last: Vec<Option<Duration>> = results[results.len() - n..].to_vec();
+    // Sorts times ascending with DNFs at the end
+    last.sort_by_key(|result| (result.is_none(), *result));
+
+    let counted = &last[trim..n - trim];
+    if counted.iter().any(|result| result.is_none()) {
+        return Some(None);
+    }
+    let sum: Duration = counted.iter().flatten().sum();
+    Some(Some(sum / counted.len() as u32))
+}
+
+// Best average of n over every window of n consecutive results, ignoring DNF averages
+pub fn best_average(results: &[Option<Duration>], n: usize) -> Option<Duration> {
+    (n..=results.len())
+        .filter_map(|end| average_of(&results[..end], n).flatten())
+        .min()
+}
+
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: Option<Duration>,
+    pub best: Option<Duration>,
+    pub worst: Option<Duration>,
+    pub ao5: Option<Option<Duration>>,
+    pub ao12: Option<Option<Duration>>,
+    // Mean turns per second of the solves that have it
+    pub tps: Option<f64>,
+}
+
+impl Summary {
+    pub fn of<'a>(solves: impl Iterator<Item = &'a Solve>) -> Summary {
+        let solves: Vec<&Solve> = solves.collect();
+        let results: Vec<Option<Duration>> = solves.iter().map(|solve| solve.result()).collect();
+        let tps: Vec<f64> = solves.iter().filter_map(|solve| solve.tps()).collect();
+        Summary {
+            tps: (!tps.is_empty()).then(|| tps.iter().sum::<f64>() / tps.len() as f64),
+            count: results.len(),
+            mean: mean(&results),
+            best: best(&results),
+            worst: worst(&results),
+            ao5: average_of(&results, 5),
+            ao12: average_of(&results, 12),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(millis: &[u64]) -> Vec<Option<Duration>> {
+        // 0 stands for a DNF
+        millis.iter().map(|&millis| Some(Duration::from_millis(millis)).filter(|_| millis > 0)).collect()
+    }
+
+    fn ms(millis: u64) -> Option<Option<Duration>> {
+        Some(Some(Duration::from_millis(millis)))
+    }
+
+    #[test]
+    fn trimmed_mean() {
+        // The best and worst of 5 are dropped
+        assert_eq!(average_of(&times(&[1000, 5000, 2000, 3000, 4000]), 5), ms(3000));
+        // Only the last n count
+        assert_eq!(average_of(&times(&[9000, 1000, 5000, 2000, 3000, 4000]), 5), ms(3000));
+        // 5% of 100 is 5 results dropped at each end
+        let hundred: Vec<u64> = (1..=100).map(|i| i * 100).collect();
+        assert_eq!(average_of(&times(&hundred), 100), ms(5050));
+    }
+
+    #[test]
+    fn dnfs() {
+        // One DNF is the dropped worst result
+        assert_eq!(average_of(&times(&[1000, 0, 2000, 3000, 4000]), 5), ms(3000));
+        // Two can't both be dropped
+        assert_eq!(average_of(&times(&[1000, 0, 2000, 0, 4000]), 5), Some(None));
+        assert_eq!(average_of(&times(&[0, 0, 0, 0, 0]), 5), Some(None));
+        // An ao12 drops one at each end too
+        let mut twelve = times(&[1000; 12]);
+        twelve[3] = None;
+        assert_eq!(average_of(&twelve, 12), ms(1000));
+        twelve[7] = None;
+        assert_eq!(average_of(&twelve, 12), Some(None));
+    }
+
+    #[test]
+    fn too_few() {
+        assert_eq!(average_of(&times(&[1000, 2000, 3000, 4000]), 5), None);
+        // Less than 3 leaves nothing after trimming
+        assert_eq!(average_of(&times(&[1000, 2000]), 2), None);
+        assert_eq!(average_of(&times(&[1000]), 1), None);
+        assert_eq!(average_of(&times(&[]), 0), None);
+        assert_eq!(average_of(&times(&[1000, 2000, 6000]), 3), ms(2000));
+        // Best average skips DNF windows
+        assert_eq!(best_average(&times(&[0, 0, 1000, 2000, 3000, 4000]), 3), Some(Duration::from_millis(2000)));
+        assert_eq!(best_average(&times(&[0, 0, 1000]), 3), None);
+    }
+}
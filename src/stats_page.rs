@@ -0,0 +1,152 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent}, layout::{Constraint, Layout}, style::{Style, Stylize}, widgets::{Block, Paragraph, Row, Table}, Frame
+};
+
+use crate::app::{App, Nav};
+use crate::config::Config;
+use crate::dates::{day_of, format_date, locale, streak, today};
+use crate::session::Solve;
+use crate::stats::Summary;
+use crate::timer::format_time;
+
+#[derive(Debug)]
+pub struct StatsPage {
+    session: Vec<Solve>,
+    lifetime: Vec<Solve>,
+    algsets: Vec<String>,
+    // Only solves with this tag are counted when not empty
+    tag: String,
+    editing: bool,
+}
+
+fn format_opt(time: Option<Duration>) -> String {
+    time.map(format_time).unwrap_or("-".to_string())
+}
+
+fn format_avg(avg: Option<Option<Duration>>) -> String {
+    match avg {
+        None => "-".to_string(),
+        Some(None) => "DNF".to_string(),
+        Some(Some(time)) => format_time(time),
+    }
+}
+
+impl StatsPage {
+    pub fn new(session: Vec<Solve>, lifetime: Vec<Solve>, algsets: Vec<String>) -> StatsPage {
+        StatsPage {
+            session,
+            lifetime,
+            algsets,
+            tag: String::new(),
+            editing: false,
+        }
+    }
+
+    pub fn handle_key<'a>(&mut self, app: &mut App<'a>, key: KeyEvent) -> Nav<'a> {
+        let keys = &app.config.keys;
+
+        if self.editing {
+            match key.code {
+                code if keys.save.matches(code) || keys.cancel.matches(code) => self.editing = false,
+                KeyCode::Backspace => {
+                    self.tag.pop();
+                }
+                KeyCode::Char(c) if c != ' ' && c != '@' => self.tag.push(c),
+                _ => {}
+            }
+            return Nav::Stay;
+        }
+
+        match key.code {
+            code if keys.filter.matches(code) => {
+                self.editing = true;
+            }
+            code if keys.back.matches(code) || keys.quit.matches(code) || keys.stats.matches(code) => {
+                return Nav::Pop;
+            }
+            _ => {}
+        }
+        Nav::Stay
+    }
+
+    fn row<'a>(name: &str, solves: impl Iterator<Item = &'a Solve>) -> Row<'static> {
+        let summary = Summary::of(solves);
+        Row::new(vec![
+            name.to_string(),
+            summary.count.to_string(),
+            format_opt(summary.mean),
+            format_opt(summary.best),
+            format_opt(summary.worst),
+            format_avg(summary.ao5),
+            format_avg(summary.ao12),
+            summary.tps.map_or("-".to_string(), |tps| format!("{:.1}", tps)),
+        ])
+    }
+
+    fn table(&self, title: &str, solves: &[Solve]) -> Table<'static> {
+        let solves: Vec<&Solve> = solves.iter()
+            .filter(|solve| self.tag.is_empty() || solve.has_tag(&self.tag))
+            .collect();
+
+        let mut rows = vec![StatsPage::row("All", solves.iter().copied()).bold()];
+        for algset in self.algsets.iter() {
+            let algset_solves = solves.iter().copied().filter(|solve| solve.algset == *algset);
+            rows.push(StatsPage::row(algset, algset_solves));
+        }
+
+        let header = Row::new(vec!["Set", "Solves", "Mean", "Best", "Worst", "ao5", "ao12", "TPS"])
+            .style(Style::new().bold().underlined());
+        let widths = [
+            Constraint::Min(16),
+            Constraint::Length(7),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(5),
+        ];
+        Table::new(rows, widths).header(header).block(Block::bordered().title(format!(" {} ", title)))
+    }
+
+    // Solves today, the current streak and the first day practiced, in local days
+    fn days(&self, config: &Config) -> String {
+        let day_start = config.history.day_start_hour;
+        let days: BTreeSet<_> = self.lifetime.iter().map(|solve| day_of(solve.date, day_start)).collect();
+        let today = today(day_start);
+        let solves_today = self.lifetime.iter().filter(|solve| day_of(solve.date, day_start) == today).count();
+
+        let mut text = format!("Today: {} solves · Streak: {} days", solves_today, streak(&days, today));
+        if let Some(first) = days.first() {
+            text = format!("{} · Practicing since {}", text, format_date(*first, locale(config.locale.as_deref())));
+        }
+        text
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, config: &Config) {
+        let title = match (self.editing, self.tag.is_empty()) {
+            (true, _) => format!(" Stats · tag: @{}_ ", self.tag),
+            (false, false) => format!(" Stats · tag: @{} ", self.tag),
+            (false, true) => " Stats ".to_string(),
+        };
+        let block = Block::bordered()
+            .title(title)
+            .title_bottom(format!(" {}: filter by tag  {}: back ", config.keys.filter.label(), config.keys.back.label()));
+        let area = block.inner(frame.area());
+        frame.render_widget(block, frame.area());
+
+        // Header, "All" and a row per alg set, plus the borders
+        let height = self.algsets.len() as u16 + 4;
+        let [days_area, session_area, lifetime_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(height),
+            Constraint::Length(height),
+        ])
+        .areas(area);
+        frame.render_widget(Paragraph::new(self.days(config)).dim(), days_area);
+        frame.render_widget(self.table("This session", &self.session), session_area);
+        frame.render_widget(self.table("All time", &self.lifetime), lifetime_area);
+    }
+}
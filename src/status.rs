@@ -0,0 +1,51 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+
+use crate::dates::{day_of, streak, today};
+use crate::history::History;
+use crate::srs::Scheduler;
+use crate::stats::average_of;
+use crate::timer::format_time;
+
+// A one line summary for status bars (waybar, polybar, tmux). `text` and `tooltip` are what
+// waybar's custom modules read from JSON, the rest is there for scripts
+#[derive(Debug, Serialize)]
+pub struct Status {
+    // Spaced repetition cases due now
+    pub due: usize,
+    pub streak: usize,
+    pub today: usize,
+    // Average of the last 12 solves in seconds, None with fewer solves or a DNF average
+    pub ao12: Option<f64>,
+    pub text: String,
+    pub tooltip: String,
+}
+
+impl Status {
+    pub fn new(history: &History, srs: &Scheduler, day_start_hour: u32) -> Status {
+        let days: BTreeSet<_> = history.solves.iter().map(|solve| day_of(solve.date, day_start_hour)).collect();
+        let today = today(day_start_hour);
+        let solves_today = history.solves.iter().filter(|solve| day_of(solve.date, day_start_hour) == today).count();
+        let streak = streak(&days, today);
+        let due = srs.due_total(SystemTime::now());
+
+        let results: Vec<Option<Duration>> = history.solves.iter().map(|solve| solve.result()).collect();
+        let ao12 = average_of(&results, 12);
+        let ao12_text = match ao12 {
+            None => "-".to_string(),
+            Some(None) => "DNF".to_string(),
+            Some(Some(time)) => format_time(time),
+        };
+
+        Status {
+            due,
+            streak,
+            today: solves_today,
+            ao12: ao12.flatten().map(|time| time.as_secs_f64()),
+            text: format!("{} today · streak {} · ao12 {} · {} due", solves_today, streak, ao12_text, due),
+            tooltip: format!("Streak: {} days\nToday: {} solves\nLast ao12: {}\nDue for review: {}", streak, solves_today, ao12_text, due),
+        }
+    }
+}
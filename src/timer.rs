@@ -0,0 +1,204 @@
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use crate::session::Penalty;
+
+// The timer reads the clock, which panics in the browser, so only the formatting below is built there
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+pub enum TimerState {
+    Idle,
+    Inspecting(Instant),
+    // The timer key is held down, the timer starts when it is released.
+    // Keeps the inspection start so the penalty can be applied then
+    Ready(Instant, Option<Instant>),
+    Running(Instant, Penalty),
+    Stopped(Duration, Penalty),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct Timer {
+    pub state: TimerState,
+    hold: Duration,
+    inspection: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Timer {
+    pub fn new(hold: Duration, inspection: bool) -> Timer {
+        Timer {
+            state: TimerState::Idle,
+            hold,
+            inspection,
+        }
+    }
+
+    // Returns the solve time and penalty when this press stops the timer
+    pub fn press(&mut self) -> Option<(Duration, Penalty)> {
+        match self.state {
+            TimerState::Idle | TimerState::Stopped(..) => {
+                if self.inspection {
+                    self.state = TimerState::Inspecting(Instant::now());
+                } else {
+                    self.state = TimerState::Ready(Instant::now(), None);
+                }
+                None
+            }
+            TimerState::Inspecting(start) => {
+                self.state = TimerState::Ready(Instant::now(), Some(start));
+                None
+            }
+            TimerState::Ready(..) => None,
+            TimerState::Running(start, penalty) => {
+                let time = start.elapsed();
+                self.state = TimerState::Stopped(time, penalty);
+                Some((time, penalty))
+            }
+        }
+    }
+
+    pub fn release(&mut self) {
+        if let TimerState::Ready(since, inspection) = self.state {
+            if since.elapsed() >= self.hold {
+                self.start();
+            } else if let Some(start) = inspection {
+                self.state = TimerState::Inspecting(start);
+            } else {
+                self.state = TimerState::Idle;
+            }
+        }
+    }
+
+    // Used directly when the terminal can't report key releases: a second press starts the timer
+    pub fn start(&mut self) {
+        if let TimerState::Ready(_, inspection) = self.state {
+            let penalty = match inspection {
+                Some(start) => Penalty::from_inspection(start.elapsed()),
+                None => Penalty::None,
+            };
+            self.state = TimerState::Running(Instant::now(), penalty);
+        }
+    }
+
+    // Started by an external timer, whatever state this one was in. An inspection going on still
+    // gives its penalty
+    pub fn start_now(&mut self) {
+        let penalty = match self.state {
+            TimerState::Inspecting(start) | TimerState::Ready(_, Some(start)) => Penalty::from_inspection(start.elapsed()),
+            _ => Penalty::None,
+        };
+        self.state = TimerState::Running(Instant::now(), penalty);
+    }
+
+    // Stopped by an external timer, with the time it measured instead of this one's
+    pub fn stop_at(&mut self, time: Duration) -> Option<(Duration, Penalty)> {
+        let TimerState::Running(_, penalty) = self.state else {
+            return None;
+        };
+        self.state = TimerState::Stopped(time, penalty);
+        Some((time, penalty))
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, TimerState::Running(..))
+    }
+
+    pub fn is_ready(&self) -> bool {
+        match self.state {
+            TimerState::Ready(since, _) => since.elapsed() >= self.hold,
+            _ => false,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match self.state {
+            TimerState::Idle | TimerState::Inspecting(_) | TimerState::Ready(..) => Duration::ZERO,
+            TimerState::Running(start, _) => start.elapsed(),
+            TimerState::Stopped(time, _) => time,
+        }
+    }
+
+    // What to show on the timer display
+    pub fn display(&self) -> String {
+        let inspection = match self.state {
+            TimerState::Inspecting(start) | TimerState::Ready(_, Some(start)) => start.elapsed(),
+            TimerState::Stopped(time, penalty) => return format_result(time, penalty),
+            _ => return format_time(self.elapsed()),
+        };
+
+        match Penalty::from_inspection(inspection) {
+            Penalty::None => (15 - inspection.as_secs()).to_string(),
+            Penalty::PlusTwo => "+2".to_string(),
+            Penalty::Dnf => "DNF".to_string(),
+        }
+    }
+}
+
+pub fn format_result(time: Duration, penalty: Penalty) -> String {
+    match penalty {
+        Penalty::None => format_time(time),
+        Penalty::PlusTwo => format!("{}+", format_time(time + Duration::from_secs(2))),
+        Penalty::Dnf => "DNF".to_string(),
+    }
+}
+
+pub fn format_time(time: Duration) -> String {
+    let centis = time.as_millis() / 10;
+    let (minutes, seconds, centis) = (centis / 6000, centis / 100 % 60, centis % 100);
+    if minutes > 0 {
+        format!("{}:{:02}.{:02}", minutes, seconds, centis)
+    } else {
+        format!("{}.{:02}", seconds, centis)
+    }
+}
+
+pub const DIGIT_HEIGHT: usize = 5;
+
+// Digits and the letters of times and moves, 3 columns wide. Lowercase letters are drawn as capitals
+pub fn big_glyph(c: char) -> [&'static str; DIGIT_HEIGHT] {
+    match c {
+        '0' => ["███", "█ █", "█ █", "█ █", "███"],
+        '1' => ["  █", "  █", "  █", "  █", "  █"],
+        '2' => ["███", "  █", "███", "█  ", "███"],
+        '3' => ["███", "  █", "███", "  █", "███"],
+        '4' => ["█ █", "█ █", "███", "  █", "  █"],
+        '5' => ["███", "█  ", "███", "  █", "███"],
+        '6' => ["███", "█  ", "███", "█ █", "███"],
+        '7' => ["███", "  █", "  █", "  █", "  █"],
+        '8' => ["███", "█ █", "███", "█ █", "███"],
+        '9' => ["███", "█ █", "███", "  █", "███"],
+        '.' => ["   ", "   ", "   ", "   ", " █ "],
+        ':' => ["   ", " █ ", "   ", " █ ", "   "],
+        '+' => ["   ", " █ ", "███", " █ ", "   "],
+        'D' => ["██ ", "█ █", "█ █", "█ █", "██ "],
+        'N' => ["█ █", "███", "███", "█ █", "█ █"],
+        'F' => ["███", "█  ", "██ ", "█  ", "█  "],
+        'U' => ["█ █", "█ █", "█ █", "█ █", "███"],
+        'R' => ["██ ", "█ █", "██ ", "█ █", "█ █"],
+        'L' => ["█  ", "█  ", "█  ", "█  ", "███"],
+        'B' => ["██ ", "█ █", "██ ", "█ █", "██ "],
+        'M' => ["█ █", "███", "█ █", "█ █", "█ █"],
+        'E' => ["███", "█  ", "██ ", "█  ", "███"],
+        'S' => ["███", "█  ", "███", "  █", "███"],
+        'W' => ["█ █", "█ █", "█ █", "███", "█ █"],
+        'X' => ["█ █", "█ █", " █ ", "█ █", "█ █"],
+        'Y' => ["█ █", "█ █", " █ ", " █ ", " █ "],
+        'Z' => ["███", "  █", " █ ", "█  ", "███"],
+        '\'' => [" █ ", " █ ", "   ", "   ", "   "],
+        c if c.is_ascii_lowercase() => big_glyph(c.to_ascii_uppercase()),
+        _ => ["   "; DIGIT_HEIGHT],
+    }
+}
+
+// Renders times (digits, '.', ':', '+' and DNF) with block characters, 5 lines tall
+pub fn big_text(text: &str) -> String {
+    let lines: Vec<String> = (0..DIGIT_HEIGHT)
+        .map(|row| {
+            let line: Vec<&str> = text.chars().map(|c| big_glyph(c)[row]).collect();
+            line.join(" ")
+        })
+        .collect();
+    lines.join("\n")
+}
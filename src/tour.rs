@@ -0,0 +1,118 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::Stylize,
+    widgets::{Block, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::config::{Config, Keys};
+
+// The text is made from the key bindings, so a remapped key is the one the tour names
+pub struct TourStep {
+    pub title: &'static str,
+    pub text: fn(&Keys) -> String,
+}
+
+const STEPS: &[TourStep] = &[
+    TourStep {
+        title: "Welcome",
+        text: |_| "This is a demo with a few 2x2 alg sets built in. Nothing you do here is saved.".to_string(),
+    },
+    TourStep {
+        title: "Setup",
+        text: |keys| format!(
+            "Move with {}/{}, {} opens a group and {} closes it. {} enables an alg set, or every set in a group.",
+            keys.down.label(), keys.up.label(), keys.open.label(), keys.close.label(), keys.select.label(),
+        ),
+    },
+    TourStep {
+        title: "Setup",
+        text: |_| "Enabled alg sets are marked with |--. Enable a few, then select Start at the bottom.".to_string(),
+    },
+    TourStep {
+        title: "Training",
+        text: |keys| format!(
+            "Each scramble is the inverse of an alg from the enabled sets. {} shows the next one, {} goes back and {} toggles zen mode.",
+            keys.next.label(), keys.previous.label(), keys.zen.label(),
+        ),
+    },
+    TourStep {
+        title: "Timing",
+        text: |keys| format!(
+            "In timed mode, hold {} until the timer turns green and let go to start. Press any key to stop, and the next scramble appears.",
+            keys.timer.label(),
+        ),
+    },
+    TourStep {
+        title: "Stats",
+        text: |keys| format!(
+            "Press {} for the session stats: mean, best, ao5 and ao12 for each alg set. {} filters them by tag.",
+            keys.stats.label(), keys.filter.label(),
+        ),
+    },
+    TourStep {
+        title: "Training",
+        text: |keys| format!("Press {} to go back to Setup and pick other sets. Have fun!", keys.back.label()),
+    },
+];
+
+#[derive(Debug, Default)]
+pub struct Tour {
+    step: usize,
+}
+
+impl Tour {
+    pub fn current(&self) -> &'static TourStep {
+        &STEPS[self.step]
+    }
+
+    // Returns false once the last step has been passed
+    pub fn advance(&mut self) -> bool {
+        self.step += 1;
+        self.step < STEPS.len()
+    }
+
+    pub fn draw(&self, frame: &mut Frame, config: &Config) {
+        let step = self.current();
+
+        let [area] = Layout::vertical([Constraint::Length(6)])
+            .flex(Flex::End)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Max(60)])
+            .flex(Flex::End)
+            .areas(area);
+
+        let block = Block::bordered()
+            .title(format!(" {} ({}/{}) ", step.title, self.step + 1, STEPS.len()))
+            .title_bottom(format!(" {}: next  {}: close tour ", config.keys.tour.label(), config.keys.cancel.label()));
+        let paragraph = Paragraph::new((step.text)(&config.keys))
+            .wrap(Wrap { trim: true })
+            .block(block)
+            .yellow();
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Binding;
+
+    fn bind(key: &str) -> Binding {
+        Binding::try_from(vec![key.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn steps_name_the_bound_keys() {
+        let mut keys = Keys::default();
+        assert!((STEPS[1].text)(&keys).starts_with("Move with j/k, l opens a group and h closes it. enter enables"));
+        keys.up = bind("w");
+        keys.down = bind("s");
+        keys.zen = bind("Z");
+        assert!((STEPS[1].text)(&keys).starts_with("Move with s/w,"));
+        assert!((STEPS[3].text)(&keys).contains("Z toggles zen mode"));
+        assert!((STEPS[6].text)(&keys).starts_with("Press esc to go back"));
+    }
+}
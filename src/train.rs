@@ -0,0 +1,1415 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant, SystemTime};
+
+use rand::{seq::SliceRandom, Rng};
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyEventKind}, layout::{Constraint, Flex, Layout}, style::{Color, Style, Stylize}, text::{Line, Span, Text}, widgets::{Block, Clear, Paragraph, Wrap}, Frame
+};
+
+use crate::app::{center, App, AppPage, Modes, Nav};
+use crate::celebration::Celebration;
+use crate::config::{Config, FaceColors};
+use crate::dates::{day_of, today};
+use crate::exam::ExamResult;
+use crate::generator::Generating;
+use crate::history::HistoryError;
+use crate::live::Live;
+use crate::net::NetView;
+use crate::pb;
+use crate::profile;
+use crate::review_page::ReviewPage;
+use crate::cube::{has_rotation, Face, mirror_moves, same_case, same_state, same_turns, solving_moves, without_rotations, CubeState};
+use crate::db::{moves_text, AlgSet, Mask, Movement, Puzzle, SetMeta, TrainingMode};
+use crate::metrics::MoveCount;
+use crate::scramble::{cases, get_scramble, get_scramble_avoiding, get_scramble_by_difficulty, get_scramble_by_performance, get_scramble_from_weakest, Scramble, Variation};
+use crate::session::{Mistake, Penalty, Session, Solve};
+use crate::stats::mean;
+use crate::smart_cube::{Execution, Finish};
+use crate::stackmat::{Packet, StackmatState};
+use crate::stats_page::StatsPage;
+use crate::timer::{big_glyph, big_text, format_time, Timer, TimerState, DIGIT_HEIGHT};
+
+const DEFAULT_DRILL_REPS: usize = 3;
+// Times a case that was never attempted is repeated before it comes up at random
+const FIRST_SEEN_REPS: usize = 3;
+// Time each move of the alg takes when it's played on the cube for a new case
+const PLAYBACK_STEP: Duration = Duration::from_millis(700);
+
+// Every case of the enabled sets once, in random order, to audit a whole alg set. An exam is
+// the same with only some of the cases, graded at the end
+#[derive(Debug)]
+struct Recap<'a> {
+    // Cases still to come, the next one last
+    queue: Vec<(&'a AlgSet, usize)>,
+    // Every case drawn, in the order they come
+    cases: Vec<(&'a AlgSet, usize)>,
+    total: usize,
+    started: Instant,
+    // Set once the last case is done, until then it's shown like any other case
+    done: Option<Duration>,
+    exam: bool,
+    // Grades of the earlier exams on the same alg sets, oldest first
+    earlier: Vec<f64>,
+}
+
+// A session with a time limit. It ends with the first new case due after the limit, so the attempt
+// going on is still finished and graded
+#[derive(Debug)]
+struct TimeBox {
+    started: Instant,
+    length: Duration,
+    // How long it took, once it ended
+    done: Option<Duration>,
+}
+
+impl<'a> Recap<'a> {
+    // `limit` draws that many cases for an exam
+    fn new(algs: &[&'a AlgSet], limit: Option<usize>, rng: &mut impl Rng) -> Recap<'a> {
+        let mut queue = cases(algs);
+        queue.shuffle(rng);
+        if let Some(limit) = limit {
+            queue.truncate(limit.max(1));
+        }
+        let cases = queue.iter().rev().copied().collect();
+        Recap { total: queue.len(), queue, cases, started: Instant::now(), done: None, exam: limit.is_some(), earlier: Vec::new() }
+    }
+
+    fn next(&mut self, variation: Variation, rng: &mut impl Rng) -> Option<Scramble> {
+        let (algset, alg) = self.queue.pop()?;
+        Some(Scramble::of(algset, alg, variation, rng))
+    }
+
+    // Cases shown so far, the current one included
+    fn progress(&self) -> usize {
+        self.total - self.queue.len()
+    }
+}
+
+#[derive(Debug)]
+pub struct TrainPage<'a> {
+    algs: Vec<&'a AlgSet>,
+    mode: TrainingMode,
+    meta: SetMeta,
+    scrambles: Vec<Scramble>,
+    // AUF, rotation and mirroring of new scrambles
+    variation: Variation,
+    idx: usize,
+    zen: bool,
+    grouped: bool,
+    // Scramble in block letters, to read it from a distance
+    big: bool,
+    // The alg set and case of the scramble are shown under it
+    source: bool,
+    // None hides the cube
+    net: Option<NetView>,
+    faces: FaceColors,
+    shown_at: Instant,
+    revealed: bool,
+    timer: Timer,
+    session: Session,
+    // Text being typed to tag or comment the last solve
+    notes: Option<String>,
+    save_error: Option<HistoryError>,
+    celebration: Option<Celebration>,
+    // Cases are picked by the spaced repetition scheduler and graded after each attempt
+    srs: bool,
+    due: usize,
+    // Cases graded this session, reviewed before the schedule is saved
+    graded: Vec<(String, usize)>,
+    // For recovery mode
+    last_attempt: Option<Instant>,
+    attempts_today: usize,
+    // The cube was compared with the drawing of the scramble
+    checked: bool,
+    // Algs for the case being shown, once asked for
+    solution: Option<Vec<String>>,
+    // The set's generator making the scramble at that index, the case waits for it
+    generating: Option<(usize, Generating)>,
+    // Why the generator's scramble couldn't be used, the case's alg sets it up instead
+    generator_error: Option<String>,
+    // Hard cases shown since the last easy one, when interleaving them
+    hard_streak: usize,
+    recap: Option<Recap<'a>>,
+    quick: Option<TimeBox>,
+    // Cases of the enabled sets without any solve in the history, introduced with their alg before
+    // they come up at random
+    unseen: HashSet<(String, usize)>,
+    // The exam's grade was saved, it's only saved once
+    exam_saved: bool,
+    // The smart cube was turned into the scramble, its next turn starts the timer
+    armed: bool,
+    // Turns of the smart cube since the timer started
+    turned: Vec<Movement>,
+    execution: Execution,
+    // Moves played on the cube for a new case, with the scramble they solve
+    playback: Option<(String, Vec<Movement>)>,
+    // Recognition mode: how long the case being shown took to recognize, once it was
+    recognized: Option<Duration>,
+    // Recognition times this session, with whether the case was misrecognized
+    recognitions: Vec<(Duration, bool)>,
+}
+
+impl<'a> TrainPage<'a> {
+    // None when the sets have no cases to train
+    pub fn new(algs: Vec<&'a AlgSet>, modes: Modes, app: &mut App<'a>) -> Option<TrainPage<'a>> {
+        let config = app.config;
+        let Modes { inspection, srs, mirror, random_state, recap, exam, quick } = modes;
+        // Quick sessions go through the cases due first
+        let srs = srs || quick.is_some();
+        let quick = quick.map(|minutes| TimeBox { started: Instant::now(), length: Duration::from_secs(minutes * 60), done: None });
+        let metas: Vec<&SetMeta> = algs.iter().map(|algset| &algset.meta).collect();
+        let meta = SetMeta::merge(&metas);
+        let variation = Variation { mirror, random_state, ..config.training.variation() };
+        let mut recap = (recap || exam).then(|| Recap::new(&algs, exam.then_some(config.training.exam_cases), &mut app.rng));
+        if let Some(recap) = recap.as_mut().filter(|recap| recap.exam) {
+            let mut paths: Vec<&str> = algs.iter().map(|algset| algset.path.as_str()).collect();
+            paths.sort();
+            recap.earlier = app.history.exams.iter().filter(|exam| exam.algsets == paths).map(|exam| exam.grade).collect();
+        }
+        let scramble = match recap.as_mut().and_then(|recap| recap.next(variation, &mut app.rng)) {
+            Some(scramble) => scramble,
+            None if srs => app.srs.pick(&algs, None, variation, SystemTime::now(), &mut app.rng)?,
+            None => get_scramble(&algs, variation, &mut app.rng)?,
+        };
+        let due = app.srs.due_count(&algs, SystemTime::now());
+        let day_start = config.history.day_start_hour;
+        let attempts_today = app.history.solves.iter().filter(|solve| day_of(solve.date, day_start) == today(day_start)).count();
+        let last_layer = config.training.last_layer
+            || matches!(meta.mask, Some(Mask::OLL | Mask::PLL | Mask::LL | Mask::CLL));
+        let net = match (config.training.net, last_layer) {
+            (false, _) => None,
+            (true, false) => Some(NetView::Full),
+            (true, true) => Some(NetView::LastLayer),
+        };
+
+        let mut unseen = HashSet::new();
+        if config.training.first_seen && !exam {
+            unseen = cases(&algs).into_iter().map(|(algset, alg)| (algset.path.clone(), alg)).collect();
+            for solve in app.history.solves.iter() {
+                unseen.remove(&(solve.algset.clone(), solve.alg));
+            }
+        }
+
+        let mut page = TrainPage {
+            algs,
+            mode: meta.mode.unwrap_or(TrainingMode::Timed),
+            meta,
+            scrambles: vec![scramble],
+            variation,
+            idx: 0,
+            zen: config.training.zen,
+            grouped: config.training.group,
+            big: config.training.big_scramble,
+            source: config.training.show_source,
+            net,
+            faces: config.cube.face_colors().unwrap_or_default(),
+            shown_at: Instant::now(),
+            revealed: false,
+            timer: Timer::new(Duration::from_millis(config.training.hold_ms), inspection),
+            session: Session::default(),
+            notes: None,
+            save_error: None,
+            celebration: None,
+            srs,
+            due,
+            graded: Vec::new(),
+            last_attempt: None,
+            attempts_today,
+            checked: false,
+            solution: None,
+            generating: None,
+            generator_error: None,
+            hard_streak: 0,
+            recap,
+            quick,
+            unseen,
+            exam_saved: false,
+            armed: false,
+            turned: Vec::new(),
+            execution: Execution::default(),
+            playback: None,
+            recognized: None,
+            recognitions: Vec::new(),
+        };
+        page.introduce();
+        page.generate();
+        Some(page)
+    }
+
+    // Starts the set's generator for the scramble being shown, when the set has one
+    fn generate(&mut self) {
+        let scramble = &self.scrambles[self.idx];
+        let generating = self.algs.iter().find(|algset| algset.path == scramble.algset)
+            .and_then(|algset| Generating::start(algset, scramble.alg));
+        self.generating = generating.map(|generating| (self.idx, generating));
+        self.generator_error = None;
+    }
+
+    // The scramble being shown is still being generated
+    fn waiting(&self) -> bool {
+        self.generating.as_ref().is_some_and(|(idx, _)| *idx == self.idx)
+    }
+
+    // A new scramble after the last one
+    fn push(&mut self, scramble: Scramble) {
+        self.scrambles.push(scramble);
+        self.idx += 1;
+        self.generate();
+    }
+
+    // Whether the scramble being shown is of a case being introduced
+    fn first_seen(&self) -> bool {
+        let scramble = &self.scrambles[self.idx];
+        self.unseen.contains(&(scramble.algset.clone(), scramble.alg))
+    }
+
+    // Moves solving the scramble being shown with the case's algs as they're trained, worked out
+    // once per scramble
+    fn playback(&mut self) -> &[Movement] {
+        let scramble = &self.scrambles[self.idx];
+        if self.playback.as_ref().is_none_or(|(text, _)| *text != scramble.text) {
+            let moves = self.algs.iter().find(|algset| algset.path == scramble.algset)
+                .and_then(|algset| {
+                    let size = algset.puzzle().modeled_size()?;
+                    let movements = AlgSet::parse_moves(&scramble.text, algset.puzzle().notation()).ok()?;
+                    let algs: Vec<Vec<Movement>> = algset.algs[scramble.alg].all()
+                        .map(|alg| if self.variation.mirror { mirror_moves(alg) } else { alg.clone() })
+                        .collect();
+                    Some(solving_moves(&movements, &algs, size))
+                })
+                .unwrap_or_default();
+            self.playback = Some((scramble.text.clone(), moves));
+        }
+        &self.playback.as_ref().unwrap().1
+    }
+
+    // New cases are shown with their alg right away
+    fn introduce(&mut self) {
+        if self.first_seen() {
+            self.solution = Some(self.solutions());
+        }
+    }
+
+    fn next(&mut self, app: &mut App<'a>) {
+        if let Some(quick) = &mut self.quick && quick.started.elapsed() >= quick.length {
+            quick.done = Some(quick.started.elapsed());
+            return;
+        }
+        self.shown_at = Instant::now();
+        self.revealed = false;
+        self.armed = false;
+        self.recognized = None;
+        self.checked = false;
+        self.solution = None;
+        self.generator_error = None;
+        self.advance(app);
+        self.introduce();
+    }
+
+    fn advance(&mut self, app: &mut App<'a>) {
+        if self.idx < self.scrambles.len()-1 {
+            self.idx += 1;
+            return;
+        }
+
+        let reps = self.meta.reps.unwrap_or(DEFAULT_DRILL_REPS);
+        if self.mode == TrainingMode::Drill && drill_rep(&self.scrambles, self.idx) < reps {
+            self.scrambles.push(self.scrambles[self.idx].clone());
+            self.idx += 1;
+            return;
+        }
+
+        if self.first_seen() {
+            if drill_rep(&self.scrambles, self.idx) < FIRST_SEEN_REPS {
+                self.scrambles.push(self.scrambles[self.idx].clone());
+                self.idx += 1;
+                return;
+            }
+            let scramble = &self.scrambles[self.idx];
+            self.unseen.remove(&(scramble.algset.clone(), scramble.alg));
+        }
+
+        if let Some(recap) = &mut self.recap {
+            match recap.next(self.variation, &mut app.rng) {
+                Some(scramble) => self.push(scramble),
+                None => recap.done = Some(recap.started.elapsed()),
+            }
+            return;
+        }
+
+        if self.srs {
+            // Stays on the case when there's none to pick
+            if let Some(scramble) = app.srs.pick(&self.algs, self.scrambles.last(), self.variation, SystemTime::now(), &mut app.rng) {
+                self.push(scramble);
+            }
+            return;
+        }
+
+        // An easy case after every `interleave` hard ones
+        let interleave = app.config.training.interleave;
+        let easy = interleave > 0 && self.hard_streak >= interleave;
+        let training = &app.config.training;
+        let avoid = if training.avoid_repeats { training.avoid_last } else { 0 };
+        let recent = &self.scrambles[self.scrambles.len().saturating_sub(avoid)..];
+        let Some(scramble) = profile::time("generate scramble", || match interleave {
+            0 if training.weakest > 0 => get_scramble_from_weakest(&self.algs, self.variation, &app.history.solves, training.weakest, recent, &mut app.rng),
+            0 if training.weighted => get_scramble_by_performance(&self.algs, self.variation, &app.history.solves, training.exploration, recent, &mut app.rng),
+            0 => get_scramble_avoiding(&self.algs, self.variation, recent, &mut app.rng),
+            _ => get_scramble_by_difficulty(&self.algs, self.variation, &app.history.solves, easy, recent, &mut app.rng),
+        }) else {
+            return;
+        };
+
+        self.hard_streak = if easy { 0 } else { self.hard_streak + 1 };
+        self.push(scramble);
+    }
+
+    // Why recovery mode doesn't allow another attempt yet, if it doesn't
+    fn rest_reason(&self, config: &Config) -> Option<String> {
+        let recovery = &config.recovery;
+        if !recovery.enabled {
+            return None;
+        }
+        if recovery.daily_attempts > 0 && self.attempts_today >= recovery.daily_attempts {
+            return Some(format!("Daily limit of {} attempts reached, see you tomorrow", recovery.daily_attempts));
+        }
+        let rest = Duration::from_secs(recovery.rest_secs);
+        let since = self.last_attempt?.elapsed();
+        (since < rest).then(|| format!("Rest for {}s before the next attempt", (rest - since).as_secs() + 1))
+    }
+
+    fn record(&mut self, app: &mut App<'a>, time: Duration, penalty: Penalty) {
+        self.last_attempt = Some(Instant::now());
+        self.attempts_today += 1;
+        self.session.add(&self.scrambles[self.idx], time, penalty);
+        let records = pb::records(&self.session.solves, &app.history.solves);
+        let solve = self.session.last_mut().unwrap();
+        solve.records = records.clone();
+        self.save_error = app.history.add(solve.clone()).err();
+
+        if !records.is_empty() {
+            self.celebration = Some(Celebration::new(records, app.config.training.confetti));
+        }
+    }
+
+    // Replaces a scramble that went wrong on the cube with a new one. A recap keeps the case, it
+    // has to come up exactly once
+    fn rescramble(&mut self, app: &mut App<'a>) {
+        let current = &self.scrambles[self.idx];
+        let scramble = if self.recap.is_some() {
+            self.algs.iter()
+                .find(|algset| algset.path == current.algset)
+                .map(|algset| Scramble::of(algset, current.alg, self.variation, &mut app.rng))
+        } else if self.srs {
+            app.srs.pick(&self.algs, Some(current), self.variation, SystemTime::now(), &mut app.rng)
+        } else {
+            get_scramble(&self.algs, self.variation, &mut app.rng)
+        };
+        let Some(scramble) = scramble else {
+            return;
+        };
+        let old = std::mem::replace(&mut self.scrambles[self.idx], scramble);
+        self.session.misscrambles.push(old);
+        self.shown_at = Instant::now();
+        self.revealed = false;
+        self.armed = false;
+        self.recognized = None;
+        self.solution = None;
+        self.introduce();
+        self.generate();
+    }
+
+    // The algs of the case the scramble was made from, then any other alg in its set for the same case
+    fn solutions(&self) -> Vec<String> {
+        let scramble = &self.scrambles[self.idx];
+        let Some(algset) = self.algs.iter().find(|algset| algset.path == scramble.algset) else {
+            return Vec::new();
+        };
+        let alg = &algset.algs[scramble.alg];
+        // Other puzzles' algs can't be told apart on the cube model
+        let size = algset.puzzle().modeled_size();
+        let others = algset.algs.iter().enumerate()
+            .filter(|(idx, other)| *idx != scramble.alg && size.is_some_and(|size| same_case(&alg.moves, &other.moves, size)))
+            .map(|(_, other)| &other.moves);
+
+        let mut solutions: Vec<String> = alg.all().chain(others)
+            .map(|alg| {
+                let alg = if self.variation.mirror { mirror_moves(alg) } else { alg.clone() };
+                let text = moves_text(&alg);
+                if has_rotation(&alg) {
+                    format!("{} (without rotations: {})", text, moves_text(&without_rotations(&alg)))
+                } else {
+                    text
+                }
+            })
+            .collect();
+
+        // The case name and tags go with the first one
+        if let Some(name) = &alg.name {
+            solutions[0] = format!("{}: {}", name, solutions[0]);
+        }
+        for tag in alg.tags.iter() {
+            solutions[0] = format!("{} @{}", solutions[0], tag);
+        }
+        if algset.puzzle().is_cube() {
+            solutions[0] = format!("{} ({})", solutions[0], MoveCount::of(&alg.moves).text());
+        }
+        solutions
+    }
+
+    fn case_name(&self, path: &str, alg: usize) -> String {
+        let name = self.algs.iter()
+            .find(|algset| algset.path == path)
+            .and_then(|algset| algset.algs[alg].name.clone())
+            .unwrap_or_else(|| format!("case {}", alg + 1));
+        format!("{} · {}", path, name)
+    }
+
+    // The case being shown and the timer, for dashboards
+    pub fn live(&self) -> Live {
+        let scramble = &self.scrambles[self.idx];
+        Live {
+            case: Some(self.case_name(&scramble.algset, scramble.alg)),
+            running: self.timer.is_running().then(|| self.timer.elapsed()),
+        }
+    }
+
+    // Name, alg set and alg of each graded case for the review
+    fn review_cases(&self) -> Vec<(String, String, usize)> {
+        self.graded.iter()
+            .map(|(path, alg)| (self.case_name(path, *alg), path.clone(), *alg))
+            .collect()
+    }
+
+    // When the recap or quick session ended, how long it took
+    fn done(&self) -> Option<Duration> {
+        let recap = self.recap.as_ref().and_then(|recap| recap.done);
+        recap.or(self.quick.as_ref().and_then(|quick| quick.done))
+    }
+
+    // How the recap or quick session went, once it's done
+    fn summary(&self, took: Duration) -> Vec<String> {
+        let results: Vec<Option<Duration>> = self.session.solves.iter().map(|solve| solve.result()).collect();
+        let dnfs = results.iter().filter(|result| result.is_none()).count();
+        let mut lines = match &self.recap {
+            Some(recap) if recap.done.is_some() => vec![format!("{} cases in {}", recap.total, format_time(took))],
+            _ => vec![format!("{} cases reviewed in {}", self.graded.len(), format_time(took))],
+        };
+        if !results.is_empty() {
+            let mean = mean(&results).map(format_time).unwrap_or("-".to_string());
+            lines.push(format!("Mean {} over {} solves, {} DNF", mean, results.len(), dnfs));
+        }
+        let mistakes = self.session.mistakes_by_case();
+        if !mistakes.is_empty() {
+            lines.push(format!("Mistakes on {} cases", mistakes.len()));
+        }
+
+        // Each case's mean, DNFs being the slowest
+        let mut cases: Vec<((&str, usize), Option<Duration>)> = Vec::new();
+        for solve in self.session.solves.iter() {
+            if !cases.iter().any(|(case, _)| *case == solve.case()) {
+                let results: Vec<Option<Duration>> = self.session.solves.iter()
+                    .filter(|other| other.case() == solve.case())
+                    .map(|other| other.result())
+                    .collect();
+                let mean = results.iter().all(Option::is_some).then(|| mean(&results)).flatten();
+                cases.push((solve.case(), mean));
+            }
+        }
+        cases.sort_by_key(|(_, mean)| std::cmp::Reverse(mean.unwrap_or(Duration::MAX)));
+        if !cases.is_empty() {
+            lines.push(String::new());
+            lines.push("Slowest:".to_string());
+        }
+        for ((path, alg), mean) in cases.iter().take(3) {
+            lines.push(format!("{}  {}", self.case_name(path, *alg), mean.map(format_time).unwrap_or("DNF".to_string())));
+        }
+        lines
+    }
+
+    // The grade of a finished exam, each case drawn scored on its first attempt
+    fn exam_result(&self, config: &Config) -> Option<ExamResult> {
+        let recap = self.recap.as_ref().filter(|recap| recap.exam && recap.done.is_some())?;
+        let attempts: Vec<(Option<&Solve>, Duration)> = recap.cases.iter()
+            .map(|(algset, alg)| {
+                let solve = self.session.solves.iter().find(|solve| solve.case() == (algset.path.as_str(), *alg));
+                let target = algset.meta.target.unwrap_or(config.training.exam_target_secs);
+                (solve, Duration::try_from_secs_f64(target).unwrap_or_default())
+            })
+            .collect();
+        let algsets = self.algs.iter().map(|algset| algset.path.clone()).collect();
+        Some(ExamResult::grade(algsets, &attempts, SystemTime::now()))
+    }
+
+    // Waiting for the cube to be compared with the drawing
+    fn checking(&self, config: &Config) -> bool {
+        config.training.check_scramble && !self.checked && !self.timer.is_running() && !self.waiting()
+    }
+
+    fn previous(&mut self) {
+        self.shown_at = Instant::now();
+        self.revealed = false;
+        self.armed = false;
+        self.recognized = None;
+        self.checked = false;
+        self.solution = None;
+        self.generator_error = None;
+        if self.idx > 0 {
+            self.idx -= 1;
+        }
+        self.introduce();
+    }
+
+    pub fn handle_key(&mut self, app: &mut App<'a>, key: KeyEvent) -> Nav<'a> {
+        let keys = &app.config.keys;
+        // A save error stays up until the next key
+        if key.kind == KeyEventKind::Press {
+            self.save_error = None;
+        }
+
+        // Any key stops a running timer
+        if self.timer.is_running() {
+            if key.kind == KeyEventKind::Press {
+                if let Some((time, penalty)) = self.timer.press() {
+                    self.record(app, time, penalty);
+                }
+                // With spaced repetition the case stays until it is graded
+                if !self.srs {
+                    self.next(app);
+                }
+            }
+            return Nav::Stay;
+        }
+
+        if let Some(notes) = &mut self.notes {
+            match key.code {
+                code if keys.save.matches(code) => {
+                    if let Some(solve) = self.session.last_mut() {
+                        solve.set_notes(notes);
+                        self.save_error = app.history.update_last(solve.clone()).err();
+                    }
+                    self.notes = None;
+                }
+                code if keys.cancel.matches(code) => {
+                    self.notes = None;
+                }
+                KeyCode::Backspace => {
+                    notes.pop();
+                }
+                KeyCode::Char(c) => {
+                    notes.push(c);
+                }
+                _ => {}
+            }
+            return Nav::Stay;
+        }
+
+        // Once a recap or quick session is done there's only its summary to leave, or the last solve to mark
+        let allowed = keys.back.matches(key.code) || keys.quit.matches(key.code) || keys.stats.matches(key.code) || keys.mistake(key.code).is_some();
+        if self.done().is_some() && !allowed {
+            return Nav::Stay;
+        }
+
+        match key.code {
+            // Recognition is timed from when the case is shown to the timer key, then its algs are
+            // shown to check it against
+            code if self.mode == TrainingMode::Recognition && self.recognized.is_none() && keys.timer.matches(code) => {
+                if key.kind != KeyEventKind::Press {
+                    return Nav::Stay;
+                }
+                let took = self.shown_at.elapsed();
+                self.recognized = Some(took);
+                self.recognitions.push((took, false));
+                self.solution = Some(self.solutions());
+            }
+            code if self.mode == TrainingMode::Timed && keys.timer.matches(code) => {
+                if key.kind != KeyEventKind::Press {
+                    return Nav::Stay;
+                }
+                let starting = matches!(self.timer.state, TimerState::Idle | TimerState::Stopped(..));
+                if starting && (self.rest_reason(app.config).is_some() || self.checking(app.config) || self.waiting()) {
+                    return Nav::Stay;
+                }
+                if app.release_events {
+                    self.timer.press();
+                } else if let TimerState::Ready(..) = self.timer.state {
+                    self.timer.start();
+                } else {
+                    self.timer.press();
+                }
+            }
+            code if self.checking(app.config) && keys.confirm.matches(code) => {
+                self.checked = true;
+            }
+            code if self.checking(app.config) && keys.mismatch.matches(code) => {
+                self.rescramble(app);
+            }
+            code if keys.back.matches(code) || keys.quit.matches(code) => {
+                // A second try leaves even if saving the grade fails again, the error was already shown
+                if let Some(exam) = self.exam_result(app.config) && !self.exam_saved {
+                    self.exam_saved = true;
+                    if let Err(err) = app.history.add_exam(exam) {
+                        self.save_error = Some(err);
+                        return Nav::Stay;
+                    }
+                }
+                if self.graded.is_empty() {
+                    return Nav::Pop;
+                }
+                return Nav::Replace(AppPage::Review(ReviewPage::new(self.review_cases(), app)));
+            }
+            code if keys.zen.matches(code) => {
+                self.zen = !self.zen;
+            }
+            code if keys.group.matches(code) => {
+                self.grouped = !self.grouped;
+            }
+            code if keys.big.matches(code) => {
+                self.big = !self.big;
+            }
+            code if keys.source.matches(code) => {
+                self.source = !self.source;
+            }
+            // Cycles through the full net, the last layer and no cube
+            code if keys.net.matches(code) => {
+                self.net = match self.net {
+                    Some(NetView::Full) => Some(NetView::LastLayer),
+                    Some(NetView::LastLayer) => None,
+                    None => Some(NetView::Full),
+                };
+            }
+            code if keys.reveal.matches(code) => {
+                self.revealed = true;
+            }
+            code if keys.solution.matches(code) => {
+                self.solution = Some(self.solutions());
+            }
+            // Recognition mode has no solves, only the last case recognized can be marked wrong
+            code if self.mode == TrainingMode::Recognition && keys.mistake(code) == Some(Mistake::Misrecognition) => {
+                if let Some((_, wrong)) = self.recognitions.last_mut() {
+                    *wrong = !*wrong;
+                }
+            }
+            code if keys.mistake(code).is_some() => {
+                let mistake = keys.mistake(code);
+                if let Some(solve) = self.session.last_mut() {
+                    // Pressing the same key again clears the classification
+                    solve.mistake = if solve.mistake == mistake { None } else { mistake };
+                    self.save_error = app.history.update_last(solve.clone()).err();
+                }
+            }
+            code if keys.stats.matches(code) => {
+                let algsets = self.algs.iter().map(|algset| algset.path.clone()).collect();
+                return Nav::Push(AppPage::Stats(StatsPage::new(self.session.solves.clone(), app.history.solves.clone(), algsets)));
+            }
+            code if keys.notes.matches(code) => {
+                if let Some(solve) = self.session.solves.last() {
+                    self.notes = Some(solve.notes());
+                }
+            }
+            code if self.srs && keys.grade(code).is_some() => {
+                let scramble = &self.scrambles[self.idx];
+                app.srs.grade(scramble, keys.grade(code).unwrap(), SystemTime::now());
+                if !self.graded.iter().any(|(algset, alg)| *algset == scramble.algset && *alg == scramble.alg) {
+                    self.graded.push((scramble.algset.clone(), scramble.alg));
+                }
+                self.due = app.srs.due_count(&self.algs, SystemTime::now());
+                self.next(app);
+            }
+            // With spaced repetition only a grade moves on, so no case goes by ungraded
+            code if !self.srs && (keys.next.matches(code) || keys.timer.matches(code)) => {
+                self.next(app);
+            }
+            code if keys.previous.matches(code) => {
+                self.previous();
+            }
+            _ => {}
+        }
+        Nav::Stay
+    }
+
+    // The Stackmat starting and stopping takes the place of the timer key, the time it shows is the one saved
+    pub fn handle_stackmat(&mut self, app: &mut App<'a>, packet: Packet) {
+        if self.mode != TrainingMode::Timed || self.done().is_some() || self.notes.is_some() {
+            return;
+        }
+        match packet.state {
+            StackmatState::Running if !self.timer.is_running() => {
+                if self.rest_reason(app.config).is_some() || self.checking(app.config) || self.waiting() {
+                    return;
+                }
+                self.timer.start_now();
+            }
+            StackmatState::Stopped => {
+                if let Some((time, penalty)) = self.timer.stop_at(packet.time) {
+                    self.record(app, time, penalty);
+                    if !self.srs {
+                        self.next(app);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // A smart cube times hands-free: turning it into the scramble arms the timer, the next turn
+    // starts it and solving the cube stops it
+    pub fn handle_turn(&mut self, app: &mut App<'a>, movement: Movement, state: &CubeState) {
+        if self.mode != TrainingMode::Timed || self.done().is_some() || self.notes.is_some() {
+            return;
+        }
+        if self.timer.is_running() {
+            self.turned.push(movement);
+            if let Some(finish) = self.execution.turn(state, self.timer.elapsed()) {
+                self.finish_execution(app, finish);
+            }
+            return;
+        }
+        if self.armed {
+            if self.rest_reason(app.config).is_some() || self.checking(app.config) || self.waiting() {
+                return;
+            }
+            self.armed = false;
+            self.timer.start_now();
+            self.turned = vec![movement];
+            self.execution = Execution::default();
+            return;
+        }
+        // Smart cubes are 3x3s turned by their faces, so the scramble is matched however the cube is held
+        let scramble = &self.scrambles[self.idx];
+        let puzzle = self.algs.iter().find(|algset| algset.path == scramble.algset).map(|algset| algset.puzzle());
+        if puzzle == Some(Puzzle::Cube(3)) && let Ok(movements) = AlgSet::parse_scramble(&scramble.text) {
+            let mut scrambled = CubeState::solved();
+            scrambled.apply(&movements);
+            self.armed = same_state(state, &scrambled);
+        }
+    }
+
+    // Shows the generator's scramble once it's made. One that failed leaves the scramble the case's
+    // alg sets up, with the error shown
+    pub fn handle_generator(&mut self, app: &mut App<'a>) {
+        let Some(result) = self.generating.as_ref().and_then(|(_, generating)| generating.result()) else {
+            return;
+        };
+        let Some((idx, generating)) = self.generating.take() else {
+            return;
+        };
+        match result {
+            Ok(moves) => {
+                let Some(algset) = self.algs.iter().find(|algset| algset.path == generating.algset) else {
+                    return;
+                };
+                self.scrambles[idx] = Scramble::generated(algset, generating.alg, moves, self.variation, &mut app.rng);
+                if idx == self.idx {
+                    self.shown_at = Instant::now();
+                }
+            }
+            Err(err) if idx == self.idx => self.generator_error = Some(err.to_string()),
+            Err(_) => {}
+        }
+    }
+
+    // Between turns of the smart cube: one left a U turn away from solved for a while ends the solve
+    pub fn handle_cube_wait(&mut self, app: &mut App<'a>) {
+        if self.timer.is_running() && let Some(finish) = self.execution.wait(self.timer.elapsed()) {
+            self.finish_execution(app, finish);
+        }
+    }
+
+    fn finish_execution(&mut self, app: &mut App<'a>, finish: Finish) {
+        let (Finish::Solved(time) | Finish::AufError(time)) = finish;
+        let Some((time, penalty)) = self.timer.stop_at(time) else {
+            return;
+        };
+        let turned = std::mem::take(&mut self.turned);
+        self.record(app, time, penalty);
+        if matches!(finish, Finish::AufError(_)) && let Some(solve) = self.session.last_mut() {
+            solve.mistake = Some(Mistake::AufError);
+        }
+        self.check_execution(app, &turned);
+        if !self.srs {
+            self.next(app);
+        }
+    }
+
+    // Saves the turns with the solve, flagging it when they don't follow any alg of the case
+    fn check_execution(&mut self, app: &mut App<'a>, turned: &[Movement]) {
+        let scramble = &self.scrambles[self.idx];
+        let Some(algset) = self.algs.iter().find(|algset| algset.path == scramble.algset) else {
+            return;
+        };
+        let followed = algset.algs[scramble.alg].all()
+            .map(|alg| if self.variation.mirror { mirror_moves(alg) } else { alg.clone() })
+            .any(|alg| same_turns(&alg, turned));
+        let Some(solve) = self.session.last_mut() else {
+            return;
+        };
+        solve.moves = Some(moves_text(turned));
+        if !followed && solve.mistake.is_none() {
+            solve.mistake = Some(Mistake::WrongAlg);
+        }
+        self.save_error = app.history.update_last(solve.clone()).err();
+    }
+
+    // Mean recognition time of the session, not counting misrecognized cases
+    fn recognition_info(&self) -> Option<String> {
+        let &(last, wrong) = self.recognitions.last()?;
+        let right: Vec<Option<Duration>> = self.recognitions.iter().filter(|(_, wrong)| !wrong).map(|(took, _)| Some(*took)).collect();
+        let last = if wrong { format!("{} (misrecognized)", format_time(last)) } else { format_time(last) };
+        let mean = mean(&right).map(format_time).unwrap_or("-".to_string());
+        Some(format!("Last {}  mean {} of {} recognized, {} misrecognized", last, mean, right.len(), self.recognitions.len() - right.len()))
+    }
+
+    pub fn handle_release(&mut self, app: &App<'a>, key: KeyEvent) {
+        if app.config.keys.timer.matches(key.code) {
+            self.timer.release();
+        }
+    }
+
+    fn timer_text(&self, config: &Config) -> Paragraph<'static> {
+        // Recognition mode shows how long recognizing the case takes instead
+        let time = match self.mode {
+            TrainingMode::Recognition => format_time(self.recognized.unwrap_or_else(|| self.shown_at.elapsed())),
+            _ => self.timer.display(),
+        };
+        let style = if self.timer.is_ready() {
+            Style::new().fg(config.colors.enabled)
+        } else {
+            Style::new()
+        };
+
+        if self.zen {
+            Paragraph::new(time).centered().dim()
+        } else {
+            Paragraph::new(big_text(&time)).centered().style(style)
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, config: &Config) {
+        let training = &config.training;
+        let keys = &config.keys;
+        let first_seen = self.first_seen();
+        let playback = if first_seen { self.playback().to_vec() } else { Vec::new() };
+        let hidden = training.flash_ms > 0
+            && !self.revealed
+            && !first_seen
+            && self.shown_at.elapsed() > Duration::from_millis(training.flash_ms);
+        let scramble = &self.scrambles[self.idx].text;
+        let waiting = self.waiting();
+        // Shown so the scramble can be checked before solving, not while memorizing or solving
+        let checking = self.checking(config);
+        // The case is recognized from the cube, so recognition mode always shows it
+        let net = if checking || first_seen || self.mode == TrainingMode::Recognition { self.net.or(Some(NetView::Full)) } else { self.net };
+        // Bigger puzzles than the cube model's 3x3 can't be shown on it
+        let puzzle = self.algs.iter().find(|algset| algset.path == self.scrambles[self.idx].algset).map(|algset| algset.puzzle());
+        let size = puzzle.map_or(Some(3), |puzzle| puzzle.modeled_size());
+        let net = net.filter(|_| size.is_some() && !self.zen && !hidden && !waiting && !self.timer.is_running());
+        let size = size.unwrap_or(3);
+        let mut state = CubeState::solved();
+        let movements = AlgSet::parse_moves(scramble, puzzle.unwrap_or(Puzzle::Cube(3)).notation()).ok().filter(|_| !waiting);
+        if let Some(movements) = &movements {
+            profile::time("apply scramble to cube", || state.apply(movements));
+        }
+        // A new case's alg is played slowly on the cube, over and over with a pause once it's solved
+        if first_seen && !checking {
+            let step = (self.shown_at.elapsed().as_millis() / PLAYBACK_STEP.as_millis()) as usize % (playback.len() + 3);
+            state.apply(&playback[..step.min(playback.len())]);
+        }
+
+        let chunk_size = if self.grouped { training.chunk_size } else { usize::MAX };
+        let chunks = scramble_chunks(scramble, chunk_size, &self.faces, config);
+        let line = if waiting {
+            Line::from("(generating a scramble)").dim()
+        } else if hidden {
+            Line::from(format!("(hidden, press {} to reveal)", keys.reveal.label())).dim()
+        } else {
+            scramble_line(&chunks)
+        };
+        let mut scramble = Paragraph::new(line)
+            .centered()
+            .wrap(Wrap { trim: true });
+
+        let area = if self.zen {
+            frame.area()
+        } else {
+            let mut title = format!(" Scramble {}/{} · {} ", self.idx + 1, self.scrambles.len(), self.mode.as_text());
+            if let Some(movements) = &movements && puzzle.is_none_or(|puzzle| puzzle.is_cube()) {
+                title = format!("{}· {} HTM ", title, MoveCount::of(movements).htm);
+            }
+            if self.mode == TrainingMode::Drill {
+                let reps = self.meta.reps.unwrap_or(DEFAULT_DRILL_REPS);
+                title = format!("{}{}/{} ", title, drill_rep(&self.scrambles, self.idx), reps);
+            }
+            let mistakes = format!(
+                "{}/{}/{}/{}: mistake",
+                keys.misrecognition.label(), keys.wrong_alg.label(), keys.execution_slip.label(), keys.auf_error.label(),
+            );
+            let grades = format!("{}/{}/{}/{}: again/hard/good/easy", keys.again.label(), keys.hard.label(), keys.good.label(), keys.easy.label());
+            let mut hint = format!(
+                " {}: timer  {}: next  {}: previous  {}: solution  {}: notes  {}  {}: stats  {}: cube  {}: zen  {}: group  {}: big  {}: case  {}: back ",
+                keys.timer.label(), keys.next.label(), keys.previous.label(), keys.solution.label(), keys.notes.label(), mistakes,
+                keys.stats.label(), keys.net.label(), keys.zen.label(), keys.group.label(), keys.big.label(), keys.source.label(), keys.back.label(),
+            );
+            if self.variation.mirror {
+                title = format!("{}· mirrored ", title);
+            }
+            if first_seen {
+                title = format!("{}· new case {}/{} ", title, drill_rep(&self.scrambles, self.idx), FIRST_SEEN_REPS);
+            }
+            if let Some(recap) = &self.recap {
+                let kind = if recap.exam { "exam" } else { "recap" };
+                title = format!("{}· {} {}/{} ", title, kind, recap.progress(), recap.total);
+            }
+            if let Some(quick) = &self.quick {
+                let left = quick.length.saturating_sub(quick.started.elapsed());
+                title = match left.is_zero() {
+                    true => format!("{}· last case ", title),
+                    false => format!("{}· {}:{:02} left ", title, left.as_secs() / 60, left.as_secs() % 60),
+                };
+            }
+            if !self.session.misscrambles.is_empty() {
+                title = format!("{}· {} mis-scrambled ", title, self.session.misscrambles.len());
+            }
+            if self.srs {
+                title = format!("{}· {} due ", title, self.due);
+                hint = format!(
+                    " {}: timer  {}  {}: solution  {}: notes  {}  {}: stats  {}: cube  {}: zen  {}: back ",
+                    keys.timer.label(), grades, keys.solution.label(), keys.notes.label(), mistakes,
+                    keys.stats.label(), keys.net.label(), keys.zen.label(), keys.back.label(),
+                );
+            }
+            if self.mode == TrainingMode::Recognition {
+                let recognized = match self.srs {
+                    true => format!("{}: recognized  {}", keys.timer.label(), grades),
+                    false => format!("{}: recognized, then next  {}: previous", keys.timer.label(), keys.previous.label()),
+                };
+                hint = format!(
+                    " {}  {}: misrecognized  {}: cube  {}: zen  {}: case  {}: back ",
+                    recognized, keys.misrecognition.label(), keys.net.label(), keys.zen.label(), keys.source.label(), keys.back.label(),
+                );
+            }
+            let block = Block::bordered()
+                .title(title)
+                .title_bottom(hint);
+            let inner = block.inner(frame.area());
+            frame.render_widget(block, frame.area());
+            scramble = scramble.bold();
+            inner
+        };
+
+        let area = match net {
+            Some(view) if area.width > view.size(size).0 * 3 => {
+                let (width, height) = view.size(size);
+                let [area, net_area] = Layout::horizontal([Constraint::Min(0), Constraint::Length(width + 2)]).areas(area);
+                let net_area = center(net_area, Constraint::Length(width), Constraint::Length(height));
+                frame.render_widget(Paragraph::new(view.draw(&state, size, &self.faces)), net_area);
+                area
+            }
+            _ => area,
+        };
+        let width = area.width * 4 / 5;
+        let big = (self.big && !hidden && !waiting).then(|| big_scramble(&chunks, self.grouped, width as usize));
+        let height = big.as_ref().map_or(2, |text| text.height() as u16);
+        if let Some(text) = big {
+            scramble = Paragraph::new(text).centered();
+        }
+        let area = center(area, Constraint::Length(width), Constraint::Length(height + 9));
+        let [scramble_area, source_area, solution_area, timer_area, notes_area, summary_area] = Layout::vertical([
+            Constraint::Length(height),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(5),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        // The scramble is hidden while solving so it doesn't distract
+        if !self.timer.is_running() {
+            frame.render_widget(scramble, scramble_area);
+        }
+        if self.source && !hidden && !self.timer.is_running() {
+            let current = &self.scrambles[self.idx];
+            let source = Paragraph::new(self.case_name(&current.algset, current.alg)).centered().dim();
+            frame.render_widget(source, source_area);
+        }
+        if let Some(solution) = &self.solution && !self.timer.is_running() {
+            let solution = Paragraph::new(solution.join("  /  ")).centered().fg(config.colors.highlight);
+            frame.render_widget(solution, solution_area);
+        }
+        if self.mode != TrainingMode::Drill {
+            frame.render_widget(self.timer_text(config), timer_area);
+        }
+
+        if let Some(err) = &self.save_error {
+            let error = Paragraph::new(format!("Couldn't save the solve: {}", err)).centered().red();
+            frame.render_widget(error, notes_area);
+        }
+        if let Some(notes) = &self.notes {
+            let [area] = Layout::vertical([Constraint::Length(3)]).flex(Flex::End).areas(frame.area());
+            let input = Paragraph::new(format!("{}_", notes)).block(
+                Block::bordered()
+                    .title(" Notes for the last solve ")
+                    .title_bottom(format!(" @tag marks a tag, e.g. @lockup @regripped  {}: save  {}: cancel ", keys.save.label(), keys.cancel.label())),
+            );
+            frame.render_widget(Clear, area);
+            frame.render_widget(input, area);
+        } else if let Some(solve) = self.session.solves.last() && self.save_error.is_none() {
+            let mut info = solve.notes();
+            if let Some(mistake) = solve.mistake {
+                info = format!("[{}] {}", mistake.as_text(), info);
+            }
+            // A slow solve with a good TPS lost its time recognizing the case, not turning the alg
+            if let Some(tps) = solve.tps() {
+                info = format!("{:.1} TPS  {}", tps, info);
+            }
+            if !self.timer.is_running() && !info.is_empty() {
+                let notes = Paragraph::new(info).centered().dim();
+                frame.render_widget(notes, notes_area);
+            }
+        } else if let Some(info) = self.recognition_info() && self.save_error.is_none() {
+            frame.render_widget(Paragraph::new(info).centered().dim(), notes_area);
+        }
+
+        // Past mistakes on the case being shown
+        let mistakes = self.session.mistakes_by_case();
+        let current = &self.scrambles[self.idx];
+        if let Some(counts) = mistakes.get(&(current.algset.as_str(), current.alg)) {
+            let mut summary: Vec<String> = Mistake::ALL.iter()
+                .filter_map(|mistake| counts.get(mistake).map(|count| format!("{} {}", count, mistake.as_text())))
+                .collect();
+            // Wrong AUFs have their own fix, so their rate is worth seeing
+            if let Some((errors, solves)) = self.session.auf_errors_by_case().get(&(current.algset.as_str(), current.alg))
+                && *errors > 0
+            {
+                summary.push(format!("AUF wrong in {}% of solves", errors * 100 / solves));
+            }
+            let summary = Paragraph::new(format!("This case: {}", summary.join(", "))).centered().dim();
+            if !self.timer.is_running() {
+                frame.render_widget(summary, summary_area);
+            }
+        }
+
+        if let Some(reason) = self.rest_reason(config) && self.mode == TrainingMode::Timed && !self.timer.is_running() {
+            frame.render_widget(Clear, summary_area);
+            frame.render_widget(Paragraph::new(reason).centered().yellow(), summary_area);
+        } else if checking && !hidden {
+            let prompt = format!("Does your cube look like the drawing? {}: yes  {}: no, new scramble", keys.confirm.label(), keys.mismatch.label());
+            frame.render_widget(Clear, summary_area);
+            frame.render_widget(Paragraph::new(prompt).centered().yellow(), summary_area);
+        } else if let Some(err) = &self.generator_error && !self.timer.is_running() {
+            frame.render_widget(Clear, summary_area);
+            frame.render_widget(Paragraph::new(format!("Scrambled from the alg, the generator failed: {}", err)).centered().red(), summary_area);
+        }
+
+        if let Some(took) = self.done() {
+            let mut lines = self.summary(took);
+            let mut title = match self.recap.as_ref().is_some_and(|recap| recap.done.is_some()) {
+                true => " Recap done ".to_string(),
+                false => " Quick session done ".to_string(),
+            };
+            let mut hint = format!(" {}: stats  {}: back ", keys.stats.label(), keys.back.label());
+            if let Some(exam) = self.exam_result(config) && let Some(recap) = &self.recap {
+                let mut grade = vec![
+                    format!("Grade {:.0}%", exam.grade),
+                    format!("Recognized {}/{}, within the target time {}/{}", exam.recognized, exam.cases, exam.on_target, exam.cases),
+                ];
+                if !recap.earlier.is_empty() {
+                    let earlier: Vec<String> = recap.earlier.iter().rev().take(5).rev().map(|grade| format!("{:.0}%", grade)).collect();
+                    grade.push(format!("Earlier exams: {}", earlier.join(" ")));
+                }
+                grade.push(String::new());
+                lines.splice(0..0, grade);
+                title = match &self.save_error {
+                    Some(err) if self.exam_saved => format!(" Couldn't save the grade: {} ", err),
+                    _ => " Exam done ".to_string(),
+                };
+                hint = format!(" {}: misrecognized  {}: stats  {}: save and back ", keys.misrecognition.label(), keys.stats.label(), keys.back.label());
+            }
+            let width = lines.iter().chain([&title]).map(|line| line.chars().count()).max().unwrap_or(0) as u16 + 4;
+            let area = center(frame.area(), Constraint::Length(width.max(30)), Constraint::Length(lines.len() as u16 + 2));
+            let summary = Paragraph::new(lines.join("\n"))
+                .centered()
+                .block(Block::bordered().title(title).title_bottom(hint));
+            frame.render_widget(Clear, area);
+            frame.render_widget(summary, area);
+        }
+
+        if self.celebration.as_ref().is_some_and(|celebration| celebration.is_over()) {
+            self.celebration = None;
+        }
+        if let Some(celebration) = &self.celebration {
+            celebration.draw(frame, frame.area());
+        }
+    }
+}
+
+// How many times in a row the scramble at idx has been shown, counting itself
+fn drill_rep(scrambles: &[Scramble], idx: usize) -> usize {
+    scrambles[..=idx].iter().rev().take_while(|scramble| scramble.text == scrambles[idx].text).count()
+}
+
+// The face whose color a move is shown in: slices go with the face they turn like and rotations
+// with the face they turn around
+fn move_face(text: &str) -> Option<Face> {
+    // Big cube moves have their layers in front
+    let text = text.trim_start_matches(|c: char| c.is_ascii_digit() || c == '-');
+    match text.chars().next()?.to_ascii_uppercase() {
+        'U' | 'Y' => Some(Face::U),
+        'D' | 'E' => Some(Face::D),
+        'R' | 'X' => Some(Face::R),
+        'L' | 'M' => Some(Face::L),
+        'F' | 'S' | 'Z' => Some(Face::F),
+        'B' => Some(Face::B),
+        _ => None,
+    }
+}
+
+// Splits a scramble into chunks of moves to make it easier to memorize. Moves are colored by the
+// face they turn with `color_moves`, otherwise chunks alternate colors
+fn scramble_chunks<'a>(scramble: &'a str, chunk_size: usize, faces: &FaceColors, config: &Config) -> Vec<Vec<(&'a str, Color)>> {
+    let moves: Vec<&str> = scramble.split(' ').collect();
+    moves.chunks(chunk_size.max(1)).enumerate()
+        .map(|(i, chunk)| {
+            let color = if i % 2 == 0 { config.colors.scramble } else { config.colors.scramble_alt };
+            chunk.iter()
+                .map(|text| match move_face(text) {
+                    Some(face) if config.training.color_moves => (*text, faces.color(face)),
+                    _ => (*text, color),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn scramble_line<'a>(chunks: &[Vec<(&'a str, Color)>]) -> Line<'a> {
+    let mut spans: Vec<Span> = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw("   "));
+        }
+        for (j, (text, color)) in chunk.iter().enumerate() {
+            if j > 0 {
+                spans.push(Span::raw(" "));
+            }
+            spans.push(Span::styled(*text, Style::new().fg(*color)));
+        }
+    }
+    Line::from(spans)
+}
+
+// The scramble in block letters, chunks (or single moves when not grouped) packed into rows no
+// wider than `width`
+fn big_scramble(chunks: &[Vec<(&str, Color)>], grouped: bool, width: usize) -> Text<'static> {
+    // Wide moves get a W, since lowercase letters are drawn as capitals
+    let glyphs = |text: &str| -> Vec<[&'static str; DIGIT_HEIGHT]> {
+        text.chars()
+            .flat_map(|c| if "udlrfb".contains(c) { vec![c, 'w'] } else { vec![c] })
+            .map(big_glyph)
+            .collect()
+    };
+    let units: Vec<Vec<(&str, Color)>> = if grouped {
+        chunks.to_vec()
+    } else {
+        chunks.concat().into_iter().map(|unit| vec![unit]).collect()
+    };
+    let gap = if grouped { "      " } else { "   " };
+
+    let mut rows: Vec<[Vec<Span>; DIGIT_HEIGHT]> = Vec::new();
+    let mut row_width = 0;
+    for unit in units {
+        let moves: Vec<(Vec<[&str; DIGIT_HEIGHT]>, Color)> = unit.iter().map(|(text, color)| (glyphs(text), *color)).collect();
+        let unit_width = moves.iter().map(|(glyphs, _)| glyphs.len() * 4 + 1).sum::<usize>().saturating_sub(2);
+        if rows.is_empty() || row_width + gap.len() + unit_width > width {
+            rows.push(Default::default());
+            row_width = 0;
+        }
+        let lines = rows.last_mut().unwrap();
+        for (line, row) in lines.iter_mut().zip(0..) {
+            if row_width > 0 {
+                line.push(Span::raw(gap));
+            }
+            for (i, (glyphs, color)) in moves.iter().enumerate() {
+                let text: Vec<&str> = glyphs.iter().map(|glyph| glyph[row]).collect();
+                let text = if i > 0 { format!("  {}", text.join(" ")) } else { text.join(" ") };
+                line.push(Span::styled(text, Style::new().fg(*color)));
+            }
+        }
+        row_width += if row_width > 0 { gap.len() } else { 0 } + unit_width;
+    }
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        if i > 0 {
+            lines.push(Line::default());
+        }
+        lines.extend(row.into_iter().map(Line::from));
+    }
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::KeyModifiers;
+
+    use crate::db::AlgDB;
+
+    use super::*;
+
+    fn press<'a>(page: &mut TrainPage<'a>, app: &mut App<'a>, c: char) {
+        page.handle_key(app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+
+    // Leaked so pages can borrow it for the rest of the test
+    fn set(name: &str, text: &str) -> &'static AlgSet {
+        Box::leak(Box::new(AlgSet::from_text(name.to_string(), text).unwrap()))
+    }
+
+    // The app on the demo alg sets with `config`, and an alg set to train
+    fn setup(config: Config, name: &str, text: &str) -> (App<'static>, &'static AlgSet) {
+        let db = Box::leak(Box::new(AlgDB::demo()));
+        (App::new(db, Box::leak(Box::new(config))), set(name, text))
+    }
+
+    #[test]
+    fn recognition_mode() {
+        let (mut app, algset) = setup(Config::default(), "pll", "#! mode: recognition\nT: R U R' U' R' F R2 U' R' U' R U R' F'\n");
+        let mut page = TrainPage::new(vec![algset], Modes::default(), &mut app).unwrap();
+
+        // The timer key stops the recognition clock and shows the alg, nothing is timed or saved as a solve
+        press(&mut page, &mut app, ' ');
+        assert!(page.recognized.is_some());
+        assert!(page.solution.is_some());
+        assert!(!page.timer.is_running());
+        assert!(page.session.solves.is_empty());
+
+        press(&mut page, &mut app, '1');
+        assert!(page.recognitions[0].1);
+        assert!(page.recognition_info().unwrap().contains("1 misrecognized"));
+
+        // Then it moves on to the next case, timed from when it's shown
+        press(&mut page, &mut app, ' ');
+        assert_eq!(page.idx, 1);
+        assert!(page.recognized.is_none());
+        assert!(page.session.solves.is_empty());
+    }
+
+    #[test]
+    fn smart_cube_auf_errors() {
+        let (mut app, algset) = setup(Config::default(), "oll", "Sune: R U R' U R U2 R'\n");
+        let mut page = TrainPage::new(vec![algset], Modes::default(), &mut app).unwrap();
+
+        // Left a U turn off, the solve is timed to when it got there and marked
+        page.timer.start_now();
+        page.turned = AlgSet::parse_scramble("R U R' U R U2 R'").unwrap();
+        page.finish_execution(&mut app, Finish::AufError(Duration::from_secs(3)));
+        let solve = page.session.solves.last().unwrap();
+        assert_eq!(solve.time, Duration::from_secs(3));
+        assert_eq!(solve.mistake, Some(Mistake::AufError));
+        assert_eq!(solve.moves.as_deref(), Some("R U R' U R U2 R'"));
+
+        page.timer.start_now();
+        page.turned = AlgSet::parse_scramble("R U R' U R U2 R' U").unwrap();
+        page.finish_execution(&mut app, Finish::Solved(Duration::from_secs(2)));
+        assert_eq!(page.session.solves.last().unwrap().mistake, None);
+        assert_eq!(page.session.auf_errors_by_case()[&("oll", 0)], (1, 2));
+    }
+
+    #[test]
+    fn move_counts_for_cubes_only() {
+        let (mut app, algset) = setup(Config::default(), "oll", "Sune: R U R' U R U2 R'\n");
+        let page = TrainPage::new(vec![algset], Modes::default(), &mut app).unwrap();
+        assert_eq!(page.solutions(), ["Sune: R U R' U R U2 R' (7 HTM, 8 QTM, 7 STM)"]);
+
+        // Square-1 moves aren't face turns
+        let algset = set("cubeshape", "#! puzzle: square-1\nKite: (1,0) / (-1,-1) / (0,1)\n");
+        let page = TrainPage::new(vec![algset], Modes::default(), &mut app).unwrap();
+        assert_eq!(page.solutions(), ["Kite: (1,0) / (-1,-1) / (0,1)"]);
+    }
+
+    // Lets the generator finish, as the app does between frames
+    fn wait_for_generator<'a>(page: &mut TrainPage<'a>, app: &mut App<'a>) {
+        let started = Instant::now();
+        while page.waiting() && started.elapsed() < Duration::from_secs(10) {
+            page.handle_generator(app);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn generated_scrambles() {
+        let (mut app, algset) = setup(Config::default(), "oll", "#! generator: sleep 1; echo \"R U2 R' U' R U' R'\"\nSune: R U R' U R U2 R'\n");
+        let mut page = TrainPage::new(vec![algset], Modes::default(), &mut app).unwrap();
+
+        // The page is up before the generator is done, and the timer waits for the scramble
+        assert!(page.waiting());
+        press(&mut page, &mut app, ' ');
+        assert!(matches!(page.timer.state, TimerState::Idle));
+        wait_for_generator(&mut page, &mut app);
+        assert!(!page.waiting());
+        assert!(page.generator_error.is_none());
+
+        // One that fails leaves the scramble from the alg, with the error shown
+        let algset = set("oll", "#! generator: echo oops >&2; exit 1\nSune: R U R' U R U2 R'\n");
+        let mut page = TrainPage::new(vec![algset], Modes::default(), &mut app).unwrap();
+        wait_for_generator(&mut page, &mut app);
+        assert!(page.generator_error.as_ref().unwrap().contains("oops"));
+        let movements = AlgSet::parse_scramble(&page.scrambles[0].text).unwrap();
+        let inverse: Vec<Movement> = movements.iter().rev().map(Movement::inv).collect();
+        assert!(same_case(&algset.algs[0].moves, &inverse, 3));
+    }
+
+    #[test]
+    fn quick_session_ends() {
+        let (mut app, algset) = setup(Config::default(), "oll", "Sune: R U R' U R U2 R'\nAntisune: R U2 R' U' R U' R'\n");
+        let mut page = TrainPage::new(vec![algset], Modes { quick: Some(5), ..Modes::default() }, &mut app).unwrap();
+        assert!(page.srs);
+
+        // A solve graded before the time is up moves on to the next case
+        page.timer.start_now();
+        press(&mut page, &mut app, ' ');
+        press(&mut page, &mut app, 'o');
+        assert_eq!((page.idx, page.done()), (1, None));
+
+        // Past the deadline the case graded last is the last one, then only the summary is left
+        page.timer.start_now();
+        press(&mut page, &mut app, ' ');
+        page.quick.as_mut().unwrap().length = Duration::ZERO;
+        press(&mut page, &mut app, 'o');
+        let took = page.done().unwrap();
+        assert_eq!(page.idx, 1);
+        assert_eq!(page.session.solves.len(), 2);
+        assert_eq!(app.history.solves.len(), 2);
+        assert_eq!(page.graded.len(), 2);
+        assert!(page.summary(took).iter().any(|line| line.contains("over 2 solves")));
+
+        press(&mut page, &mut app, ' ');
+        assert!(!page.timer.is_running());
+        assert!(matches!(page.handle_key(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)), Nav::Replace(AppPage::Review(_))));
+    }
+
+    #[test]
+    fn rescramble_in_recap() {
+        let mut config = Config::default();
+        config.training.check_scramble = true;
+        let (mut app, algset) = setup(config, "oll", "Sune: R U R' U R U2 R'\nAntisune: R U2 R' U' R U' R'\nH: R U R' U R U' R' U R U2 R'\n");
+        let mut page = TrainPage::new(vec![algset], Modes { recap: true, ..Modes::default() }, &mut app).unwrap();
+
+        // A scramble that went wrong is done again for the same case, without taking another from the queue
+        let case = page.scrambles[0].alg;
+        for _ in 0..10 {
+            press(&mut page, &mut app, 'm');
+            assert_eq!(page.scrambles[0].alg, case);
+        }
+        assert_eq!(page.session.misscrambles.len(), 10);
+        assert_eq!(page.recap.as_ref().unwrap().progress(), 1);
+    }
+
+    #[test]
+    fn no_cases() {
+        let (mut app, empty) = setup(Config::default(), "empty", "# nothing here yet\n");
+        assert!(TrainPage::new(vec![empty], Modes::default(), &mut app).is_none());
+        assert!(TrainPage::new(vec![empty], Modes { srs: true, ..Modes::default() }, &mut app).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use std::{path::{Path, PathBuf}, time::{Duration, SystemTime}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::AlgSet;
+use crate::history::{read, write, HistoryError};
+
+// Short move sequences that come up in many algs, drilled on their own for speed
+pub struct Trigger {
+    pub name: &'static str,
+    pub moves: &'static str,
+}
+
+pub const TRIGGERS: &[Trigger] = &[
+    Trigger { name: "Sexy move", moves: "R U R' U'" },
+    Trigger { name: "Reverse sexy", moves: "U R U' R'" },
+    Trigger { name: "Left sexy", moves: "L' U' L U" },
+    Trigger { name: "Sledgehammer", moves: "R' F R F'" },
+    Trigger { name: "Hedgeslammer", moves: "F R' F' R" },
+    Trigger { name: "Sune", moves: "R U R' U R U2 R'" },
+    Trigger { name: "Antisune", moves: "R U2 R' U' R U' R'" },
+    Trigger { name: "Left sune", moves: "L' U' L U' L' U2 L" },
+    Trigger { name: "Wide sune", moves: "r U R' U R U2 r'" },
+    Trigger { name: "M flicks", moves: "M' U M' U M' U M' U" },
+    Trigger { name: "M2 U2", moves: "M2 U2 M2 U2" },
+    Trigger { name: "M' U2 M", moves: "M' U2 M U2" },
+];
+
+impl Trigger {
+    pub fn move_count(&self) -> usize {
+        AlgSet::parse_scramble(self.moves).map(|moves| moves.len()).unwrap_or(0)
+    }
+}
+
+// A timed set of repetitions of a trigger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerDrill {
+    pub date: SystemTime,
+    pub trigger: String,
+    pub reps: usize,
+    pub time: Duration,
+    // Moves per rep when it was done, so the TPS stays right if the trigger changes
+    pub moves: usize,
+}
+
+impl TriggerDrill {
+    // Turns per second over all the reps
+    pub fn tps(&self) -> f64 {
+        (self.moves * self.reps) as f64 / self.time.as_secs_f64().max(0.001)
+    }
+}
+
+// Every trigger drill done, kept apart from the solve history so they don't count in case stats
+#[derive(Debug, Default)]
+pub struct TriggerLog {
+    // None keeps the drills in memory only (demo mode)
+    path: Option<PathBuf>,
+    pub drills: Vec<TriggerDrill>,
+}
+
+impl TriggerLog {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rubiks_trainer").join("triggers.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<TriggerLog, HistoryError> {
+        Ok(TriggerLog {
+            path: Some(path.to_path_buf()),
+            drills: read(path)?,
+        })
+    }
+
+    pub fn detach(&mut self) {
+        self.path = None;
+    }
+
+    pub fn add(&mut self, drill: TriggerDrill) -> Result<(), HistoryError> {
+        self.drills.push(drill);
+        match &self.path {
+            Some(path) => write(path, &self.drills),
+            None => Ok(()),
+        }
+    }
+
+    // Drills of one trigger, oldest first
+    pub fn of<'a>(&'a self, trigger: &'a str) -> impl Iterator<Item = &'a TriggerDrill> {
+        self.drills.iter().filter(move |drill| drill.trigger == trigger)
+    }
+
+    pub fn best_tps(&self, trigger: &str) -> Option<f64> {
+        self.of(trigger).map(TriggerDrill::tps).reduce(f64::max)
+    }
+}
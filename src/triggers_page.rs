@@ -0,0 +1,191 @@
+use std::time::{Duration, SystemTime};
+
+use ratatui::{
+    crossterm::event::{KeyEvent, KeyEventKind}, layout::{Constraint, Layout}, style::{Style, Stylize}, symbols::Marker, text::Line, widgets::{Axis, Block, Chart, Dataset, GraphType, List, ListState, Paragraph}, Frame
+};
+
+use crate::app::{App, Nav};
+use crate::config::Config;
+use crate::history::HistoryError;
+use crate::timer::{big_text, format_time, Timer, TimerState};
+use crate::triggers::{TriggerDrill, TriggerLog, TRIGGERS};
+
+const DEFAULT_REPS: usize = 5;
+
+// Speed drills for the built-in triggers: do the trigger a number of times in a row while timed,
+// and see how the TPS of each trigger goes over time
+#[derive(Debug)]
+pub struct TriggersPage {
+    list: ListState,
+    reps: usize,
+    timer: Timer,
+    // TPS of every drill of the selected trigger, oldest first
+    points: Vec<(f64, f64)>,
+    // Best TPS of each trigger
+    best: Vec<Option<f64>>,
+    last: Option<TriggerDrill>,
+    save_error: Option<HistoryError>,
+}
+
+impl TriggersPage {
+    pub fn new(log: &TriggerLog, config: &Config) -> TriggersPage {
+        let mut page = TriggersPage {
+            list: ListState::default().with_selected(Some(0)),
+            reps: DEFAULT_REPS,
+            timer: Timer::new(Duration::from_millis(config.training.hold_ms), false),
+            points: Vec::new(),
+            best: Vec::new(),
+            last: None,
+            save_error: None,
+        };
+        page.refresh(log);
+        page
+    }
+
+    fn selected(&self) -> usize {
+        self.list.selected().unwrap_or(0)
+    }
+
+    fn refresh(&mut self, log: &TriggerLog) {
+        self.best = TRIGGERS.iter().map(|trigger| log.best_tps(trigger.name)).collect();
+        self.points = log.of(TRIGGERS[self.selected()].name)
+            .enumerate()
+            .map(|(idx, drill)| ((idx + 1) as f64, drill.tps()))
+            .collect();
+    }
+
+    fn record(&mut self, app: &mut App, time: Duration) {
+        let trigger = &TRIGGERS[self.selected()];
+        let drill = TriggerDrill {
+            date: SystemTime::now(),
+            trigger: trigger.name.to_string(),
+            reps: self.reps,
+            time,
+            moves: trigger.move_count(),
+        };
+        self.save_error = app.triggers.add(drill.clone()).err();
+        self.last = Some(drill);
+        self.refresh(&app.triggers);
+    }
+
+    pub fn handle_key<'a>(&mut self, app: &mut App<'a>, key: KeyEvent) -> Nav<'a> {
+        let keys = &app.config.keys;
+
+        // Any key stops a running timer
+        if self.timer.is_running() {
+            if key.kind == KeyEventKind::Press && let Some((time, _)) = self.timer.press() {
+                self.record(app, time);
+            }
+            return Nav::Stay;
+        }
+
+        match key.code {
+            code if keys.timer.matches(code) => {
+                if key.kind != KeyEventKind::Press {
+                    return Nav::Stay;
+                }
+                if app.release_events {
+                    self.timer.press();
+                } else if let TimerState::Ready(..) = self.timer.state {
+                    self.timer.start();
+                } else {
+                    self.timer.press();
+                }
+            }
+            code if keys.back.matches(code) || keys.quit.matches(code) || keys.triggers.matches(code) => {
+                return Nav::Pop;
+            }
+            code if keys.up.matches(code) => {
+                self.list.select_previous();
+                self.last = None;
+                self.refresh(&app.triggers);
+            }
+            code if keys.down.matches(code) => {
+                self.list.select(Some((self.selected() + 1).min(TRIGGERS.len() - 1)));
+                self.last = None;
+                self.refresh(&app.triggers);
+            }
+            code if keys.more.matches(code) => {
+                self.reps += 1;
+            }
+            code if keys.fewer.matches(code) => {
+                self.reps = (self.reps - 1).max(1);
+            }
+            _ => {}
+        }
+        Nav::Stay
+    }
+
+    pub fn handle_release(&mut self, app: &App, key: KeyEvent) {
+        if app.config.keys.timer.matches(key.code) {
+            self.timer.release();
+        }
+    }
+
+    fn chart(&self) -> Chart<'_> {
+        let max_tps = self.points.iter().map(|(_, tps)| *tps).fold(1.0, f64::max).ceil();
+        let drills = self.points.len().max(2) as f64;
+        let dataset = Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .data(&self.points);
+        Chart::new(vec![dataset])
+            .block(Block::bordered().title(" TPS over time "))
+            .x_axis(Axis::default().title("drill").bounds([1.0, drills]).labels(["1".to_string(), (drills as usize).to_string()]))
+            .y_axis(Axis::default().bounds([0.0, max_tps]).labels(["0".to_string(), format!("{:.0}", max_tps)]))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, config: &Config) {
+        let keys = &config.keys;
+        let hint = format!(
+            " {}/{}: trigger  {}/{}: reps  {}: timer  {}: back ",
+            keys.down.label(), keys.up.label(), keys.more.label(), keys.fewer.label(), keys.timer.label(), keys.back.label(),
+        );
+        let block = Block::bordered()
+            .title(" Triggers ")
+            .title_bottom(hint);
+        let area = block.inner(frame.area());
+        frame.render_widget(block, frame.area());
+
+        let [list_area, drill_area] = Layout::horizontal([Constraint::Length(34), Constraint::Min(0)]).areas(area);
+        let items: Vec<Line> = TRIGGERS.iter().zip(self.best.iter())
+            .map(|(trigger, best)| match best {
+                Some(best) => Line::from(format!("{:<16} best {:.2}", trigger.name, best)),
+                None => Line::from(trigger.name),
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::new().fg(config.colors.highlight));
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let [moves_area, reps_area, timer_area, result_area, chart_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Length(6),
+            Constraint::Length(2),
+            Constraint::Min(0),
+        ])
+        .areas(drill_area);
+
+        let trigger = &TRIGGERS[self.selected()];
+        frame.render_widget(Paragraph::new(trigger.moves).centered().bold().fg(config.colors.scramble), moves_area);
+        frame.render_widget(Paragraph::new(format!("{} reps in a row", self.reps)).centered().dim(), reps_area);
+
+        let style = if self.timer.is_ready() { Style::new().fg(config.colors.enabled) } else { Style::new() };
+        frame.render_widget(Paragraph::new(big_text(&self.timer.display())).centered().style(style), timer_area);
+
+        if let Some(err) = &self.save_error {
+            frame.render_widget(Paragraph::new(format!("Couldn't save the drill: {}", err)).centered().red(), result_area);
+        } else if let Some(drill) = &self.last && !self.timer.is_running() {
+            let text = format!("{} reps in {} · {:.2} TPS", drill.reps, format_time(drill.time), drill.tps());
+            frame.render_widget(Paragraph::new(text).centered(), result_area);
+        }
+
+        if self.points.is_empty() {
+            frame.render_widget(Paragraph::new("No drills of this trigger yet").centered().dim(), chart_area);
+        } else {
+            frame.render_widget(self.chart(), chart_area);
+        }
+    }
+}